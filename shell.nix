@@ -7,6 +7,7 @@ pkgs.mkShell {
     ffmpeg
     normalize
     cdrkit # Contains wodim
+    cdparanoia
     sqlite
   ];
 
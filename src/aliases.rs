@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::errors::DbError;
+
+/// Browses tracks tagged `spelling` under the artist `canonical` from now on, leaving
+/// their tags as they are. Spellings already merged into `spelling` follow it, and
+/// merging into a spelling that is merged itself merges into its canonical name, so
+/// every spelling stays one step from the name it is browsed under. Returns that name.
+pub fn merge(conn: &mut Connection, spelling: &str, canonical: &str) -> Result<String> {
+    let (spelling, canonical) = (spelling.trim(), canonical.trim());
+    anyhow::ensure!(
+        !spelling.is_empty() && !canonical.is_empty(),
+        "expected an artist spelling and the name to browse it under"
+    );
+    let canonical = canonical_name(conn, canonical)?.unwrap_or_else(|| canonical.to_string());
+    anyhow::ensure!(
+        spelling != canonical,
+        "\"{}\" is already browsed as \"{}\"",
+        spelling,
+        canonical
+    );
+
+    let known = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM tracks WHERE artist = ?1)
+                OR EXISTS (SELECT 1 FROM artist_aliases WHERE canonical = ?1)",
+            [spelling],
+            |row| row.get::<_, bool>(0),
+        )
+        .map_err(DbError::Query)?;
+    if !known {
+        return Err(DbError::ArtistNotFound(spelling.to_string()).into());
+    }
+
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for merging artists")?;
+    tx.execute(
+        "UPDATE artist_aliases SET canonical = ?2 WHERE canonical = ?1",
+        params![spelling, canonical],
+    )
+    .context("failed to move merged spellings")?;
+    tx.execute(
+        "INSERT INTO artist_aliases (alias, canonical) VALUES (?1, ?2)
+        ON CONFLICT (alias) DO UPDATE SET canonical = excluded.canonical",
+        params![spelling, canonical],
+    )
+    .context("failed to merge artist")?;
+    crate::totals::invalidate(&tx)?;
+    tx.commit().context("failed to commit artist merge")?;
+
+    Ok(canonical)
+}
+
+/// Browses `name` as an artist of its own again: the spelling itself when it is
+/// merged into another, or every spelling merged into it otherwise. Returns the
+/// spellings split off, none when there was nothing to undo.
+pub fn unmerge(conn: &Connection, name: &str) -> Result<Vec<String>> {
+    let name = name.trim();
+    if conn
+        .execute("DELETE FROM artist_aliases WHERE alias = ?1", [name])
+        .context("failed to unmerge artist")?
+        > 0
+    {
+        crate::totals::invalidate(conn)?;
+        return Ok(vec![name.to_string()]);
+    }
+
+    let spellings = list(conn)?
+        .into_iter()
+        .filter(|(_, canonical)| canonical == name)
+        .map(|(alias, _)| alias)
+        .collect::<Vec<_>>();
+    conn.execute("DELETE FROM artist_aliases WHERE canonical = ?1", [name])
+        .context("failed to unmerge artist spellings")?;
+    crate::totals::invalidate(conn)?;
+
+    Ok(spellings)
+}
+
+/// Every merged spelling with the name it is browsed under, by that name.
+pub fn list(conn: &Connection) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT alias, canonical FROM artist_aliases
+            ORDER BY canonical COLLATE NATSORT, alias COLLATE NATSORT",
+        )
+        .context("failed to prepare query to list artist aliases")?;
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .context("failed to query database for artist aliases")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to map artist aliases from database to rust types")
+}
+
+/// The name `spelling` is browsed under, if it is merged into one.
+pub fn canonical_name(conn: &Connection, spelling: &str) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT canonical FROM artist_aliases WHERE alias = ?1",
+        [spelling],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|err| DbError::Query(err).into())
+}
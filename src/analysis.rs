@@ -0,0 +1,249 @@
+use anyhow::{Context, Result};
+
+use crate::waveform;
+
+/// Sample rate tracks are decoded at for tempo analysis.
+const ANALYSIS_SAMPLE_RATE: u32 = 11025;
+/// Samples per energy frame (~46ms at the analysis rate).
+const HOP: usize = 512;
+/// Tempo range considered, in beats per minute.
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 180.0;
+
+/// Results of analyzing a track. Fields are `None` when not requested or when the
+/// track was too short or too quiet to tell.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub bpm: Option<f64>,
+    pub key: Option<CamelotKey>,
+    /// Average (RMS) level in dBFS, for telling loud masters from quiet ones.
+    pub loudness_db: Option<f64>,
+}
+
+/// Decodes the track at `path` once and runs the requested analyses on it.
+pub fn analyze(path: &str, bpm: bool, key: bool, loudness: bool) -> Result<Analysis> {
+    let samples = waveform::decode_mono(path, ANALYSIS_SAMPLE_RATE)
+        .context("failed to decode track for analysis")?;
+
+    Ok(Analysis {
+        bpm: bpm
+            .then(|| bpm_from_samples(&samples, ANALYSIS_SAMPLE_RATE))
+            .flatten(),
+        key: key
+            .then(|| key_from_samples(&samples, ANALYSIS_SAMPLE_RATE))
+            .flatten(),
+        loudness_db: loudness.then(|| loudness_from_samples(&samples)).flatten(),
+    })
+}
+
+/// Average level of the decoded track in dBFS, or `None` for silence.
+fn loudness_from_samples(samples: &[i16]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mean_square = samples
+        .iter()
+        .map(|&s| (s as f64 / i16::MAX as f64).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64;
+
+    (mean_square > 0.0).then(|| 10.0 * mean_square.log10())
+}
+
+/// Estimates the tempo from the autocorrelation of the onset strength envelope.
+fn bpm_from_samples(samples: &[i16], sample_rate: u32) -> Option<f64> {
+    let energy = samples
+        .chunks_exact(HOP)
+        .map(|frame| {
+            frame
+                .iter()
+                .map(|&s| (s as f64 / i16::MAX as f64).powi(2))
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+
+    // onsets are where the energy rises; falling energy carries no beat information
+    let onsets = energy
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect::<Vec<_>>();
+
+    let frames_per_sec = sample_rate as f64 / HOP as f64;
+    let min_lag = (frames_per_sec * 60.0 / MAX_BPM).floor() as usize;
+    let max_lag = (frames_per_sec * 60.0 / MIN_BPM).ceil() as usize;
+    if onsets.len() <= max_lag * 2 {
+        return None;
+    }
+
+    let (best_lag, best_score) = (min_lag.max(1)..=max_lag)
+        .map(|lag| {
+            let score = onsets
+                .iter()
+                .zip(&onsets[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f64>();
+            (lag, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    if best_score <= 0.0 {
+        return None;
+    }
+
+    Some(60.0 * frames_per_sec / best_lag as f64)
+}
+
+/// Samples per chroma frame for key detection (~370ms at the analysis rate).
+const KEY_FRAME: usize = 4096;
+/// MIDI notes whose energy is folded into the chroma vector (C3 to B5).
+const KEY_NOTES: std::ops::Range<u8> = 48..84;
+
+/// Krumhansl-Schmuckler key profiles, starting at the tonic.
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// A musical key in Camelot wheel notation (e.g. `8A` for A minor, `8B` for C major),
+/// where neighbouring numbers and the same number in the other letter mix harmonically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CamelotKey {
+    /// Position on the wheel, 1 to 12.
+    pub number: u8,
+    pub minor: bool,
+}
+
+impl CamelotKey {
+    /// Key with the given tonic pitch class (C = 0).
+    pub fn from_pitch_class(pitch_class: u8, minor: bool) -> Self {
+        // minor keys share a number with their relative major, three semitones up
+        let major_tonic = if minor {
+            (pitch_class + 3) % 12
+        } else {
+            pitch_class % 12
+        };
+
+        CamelotKey {
+            number: ((major_tonic as u16 * 7 % 12 + 7) % 12 + 1) as u8,
+            minor,
+        }
+    }
+
+    /// How harsh a transition from `self` to `other` is: 0 for the same key, 1 for a
+    /// neighbour on the wheel or the relative major/minor, growing from there.
+    pub fn distance(&self, other: &CamelotKey) -> u32 {
+        let diff = self.number.abs_diff(other.number) as u32;
+        diff.min(12 - diff) + u32::from(self.minor != other.minor)
+    }
+}
+
+impl std::fmt::Display for CamelotKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.number, if self.minor { 'A' } else { 'B' })
+    }
+}
+
+impl std::str::FromStr for CamelotKey {
+    type Err = anyhow::Error;
+
+    /// Accepts Camelot notation (`8A`) as well as the note names taggers write into
+    /// the initial key field (`Am`, `C#`, `Ebm`, `F minor`).
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        if let Some(number) = s
+            .strip_suffix(['A', 'a', 'B', 'b'])
+            .and_then(|n| n.parse::<u8>().ok())
+            && (1..=12).contains(&number)
+        {
+            let minor = s.ends_with(['A', 'a']);
+            return Ok(CamelotKey { number, minor });
+        }
+
+        let mut chars = s.chars();
+        let tonic = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            Some('C') => 0,
+            Some('D') => 2,
+            Some('E') => 4,
+            Some('F') => 5,
+            Some('G') => 7,
+            Some('A') => 9,
+            Some('B') => 11,
+            _ => anyhow::bail!("unrecognized key \"{}\"", s),
+        };
+        let rest = chars.as_str();
+        let (tonic, rest) = if let Some(rest) = rest.strip_prefix(['#', '♯']) {
+            (tonic + 1, rest)
+        } else if let Some(rest) = rest.strip_prefix(['b', '♭']) {
+            (tonic + 11, rest)
+        } else {
+            (tonic, rest)
+        };
+        let minor = match rest.trim().to_ascii_lowercase().as_str() {
+            "" | "maj" | "major" => false,
+            "m" | "min" | "minor" => true,
+            _ => anyhow::bail!("unrecognized key \"{}\"", s),
+        };
+
+        Ok(CamelotKey::from_pitch_class(tonic % 12, minor))
+    }
+}
+
+/// Estimates the key of the decoded track by correlating its chroma vector against
+/// the major and minor profiles for all twelve tonics.
+fn key_from_samples(samples: &[i16], sample_rate: u32) -> Option<CamelotKey> {
+    let mut chroma = [0f64; 12];
+    for frame in samples.chunks_exact(KEY_FRAME) {
+        for note in KEY_NOTES {
+            let freq = 440.0 * 2f64.powf((note as f64 - 69.0) / 12.0);
+            chroma[note as usize % 12] += goertzel_power(frame, freq, sample_rate);
+        }
+    }
+    if chroma.iter().all(|&c| c == 0.0) {
+        return None;
+    }
+
+    (0..12u8)
+        .flat_map(|tonic| [(tonic, false), (tonic, true)])
+        .map(|(tonic, minor)| {
+            let profile = if minor {
+                &MINOR_PROFILE
+            } else {
+                &MAJOR_PROFILE
+            };
+            let rotated = (0..12)
+                .map(|i| profile[(i + 12 - tonic as usize) % 12])
+                .collect::<Vec<_>>();
+            (tonic, minor, correlation(&chroma, &rotated))
+        })
+        .max_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(tonic, minor, _)| CamelotKey::from_pitch_class(tonic, minor))
+}
+
+/// Power of a single frequency in `frame`.
+fn goertzel_power(frame: &[i16], freq: f64, sample_rate: u32) -> f64 {
+    let coeff = 2.0 * (2.0 * std::f64::consts::PI * freq / sample_rate as f64).cos();
+    let (mut s1, mut s2) = (0f64, 0f64);
+    for &sample in frame {
+        let s0 = sample as f64 / i16::MAX as f64 + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+
+    s1 * s1 + s2 * s2 - coeff * s1 * s2
+}
+
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+
+    cov / (var_a * var_b).sqrt()
+}
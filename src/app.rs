@@ -1,44 +1,94 @@
 use anyhow::{Context, Result};
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
-use std::iter::Peekable;
-use std::sync::mpsc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
-use tempfile::TempDir;
-
-use crate::DB_PATH;
-
-const CD_MAX_DURATION_SECONDS: u64 = 4799; // 79:59
-const CD_WRITER_DEVICE: &str = "/dev/sr0"; // Default Linux CD device
-
-fn temp_dir() -> io::Result<TempDir> {
-    tempfile::tempdir_in("/dev/shm")
-}
-
-pub fn humantime_secs(secs: u64) -> humantime::FormattedDuration {
-    humantime::format_duration(std::time::Duration::from_secs(secs))
+use std::time::{Duration, Instant};
+
+use crate::aliases;
+use crate::backend::{self, Backend};
+use crate::burns;
+use crate::cdtext::{self, CdTextMode};
+use crate::chapters;
+use crate::collate;
+use crate::command::{self, Console};
+use crate::config::Config;
+use crate::discid::DiscId;
+use crate::duration::{self, format_secs};
+use crate::edit::TrackEdit;
+use crate::errors::{self, BurnError, DbError, TranscodeError};
+use crate::events::{self, BurnStage, Emitter, Event, EventBus, LogLine};
+use crate::hooks::{self, Hook};
+use crate::image::Image;
+use crate::journal::Journal;
+use crate::layout::DiscLayout;
+use crate::loudness;
+use crate::mixdown;
+use crate::naming::{self, NameContext};
+use crate::normalize::{NormalizeMode, NormalizeProfile};
+use crate::notify::{self, TaskTitle};
+use crate::order;
+use crate::paths;
+use crate::quality::{self, GateAction, QualityGate, SourceQuality};
+use crate::session::{self, DiscLabels, Entry, UiState};
+use crate::shutdown;
+use crate::split;
+use crate::staging;
+use crate::style;
+use crate::tags::{self, TagChange, TagField};
+use crate::throughput::{self, Throughput};
+use crate::userdata;
+use crate::verify;
+use crate::wodim::{self, BufferStatus};
+
+pub const CD_MAX_DURATION_SECONDS: u64 = 4799; // 79:59
+/// Most tracks the Red Book lets a CD hold.
+pub const CD_MAX_TRACKS: usize = 99;
+/// The disc [`CD_MAX_DURATION_SECONDS`] is sized for, as shown to the user.
+pub const CD_CAPACITY_PROFILE: &str = "80 min CD-R";
+
+/// Staging directory named in printed burn commands before the session has a real one.
+const STAGING_PLACEHOLDER: &str = "STAGING";
+
+/// A temporary directory, removed when dropped or if the process is interrupted unless
+/// it has been kept for a later run.
+struct StagingDir {
+    path: PathBuf,
+    /// Registration for removal on shutdown, until the directory is kept.
+    registered: Option<shutdown::DirGuard>,
 }
 
-pub struct LogLine {
-    pub is_stderr: bool,
-    pub line: String,
-}
+impl StagingDir {
+    fn path(&self) -> &Path {
+        &self.path
+    }
 
-pub enum LogMessage {
-    Line(LogLine),
-    Complete(Result<String>),
+    /// Leaves the directory in place when the process exits, however it does.
+    fn keep(&mut self) {
+        self.registered = None;
+    }
 }
 
-impl From<LogLine> for LogMessage {
-    fn from(line: LogLine) -> Self {
-        LogMessage::Line(line)
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        // removed before it stops being registered
+        if let Some(registered) = self.registered.take() {
+            let _ = std::fs::remove_dir_all(&self.path);
+            drop(registered);
+        }
     }
 }
 
-impl From<Result<String>> for LogMessage {
-    fn from(result: Result<String>) -> Self {
-        LogMessage::Complete(result)
-    }
+fn temp_dir(root: &Path) -> io::Result<StagingDir> {
+    let path = tempfile::tempdir_in(root)?.keep();
+    let registered = shutdown::remove_on_exit(&path);
+    Ok(StagingDir {
+        path,
+        registered: Some(registered),
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -51,9 +101,22 @@ pub struct Song {
     pub track: i64,
     pub year: u32,
     pub duration_sec: u64,
+    /// Trim/fade of this playlist entry; empty for tracks straight from the library.
+    pub edit: TrackEdit,
 }
 
 impl Song {
+    /// What tells tracks apart: the library ID, and for files from outside the library,
+    /// which all have ID 0, the path as well.
+    pub fn key(&self) -> (i64, Option<String>) {
+        (self.id, (self.id == 0).then(|| self.path.clone()))
+    }
+
+    /// Length once the entry's trim is applied.
+    pub fn played_duration_secs(&self) -> u64 {
+        self.edit.duration_secs(self.duration_sec)
+    }
+
     pub fn format(&self) -> &str {
         self.path
             .rsplit_once('.')
@@ -61,15 +124,36 @@ impl Song {
     }
 }
 
-fn playlist_duration_secs(playlist: &[Song]) -> u64 {
-    playlist.iter().fold(0u64, |acc, s| acc + s.duration_sec)
+/// An album as the TUI's album grid shows it.
+#[derive(Debug, Clone)]
+pub struct Album {
+    pub album: String,
+    pub artist: String,
+    /// Latest year among its tracks, 0 when none is tagged.
+    pub year: u32,
+    pub tracks: u64,
+    pub duration_secs: u64,
+    /// One of its tracks, to find the album's art by.
+    pub path: String,
+}
+
+pub fn playlist_duration_secs(playlist: &[Song]) -> u64 {
+    playlist
+        .iter()
+        .fold(0u64, |acc, s| acc + s.played_duration_secs())
 }
 
 // DB Queries
 pub mod queries {
-    use super::Song;
+    use super::{Album, Song};
+    use crate::analysis::CamelotKey;
+    use crate::edit::TrackEdit;
+    use crate::errors::DbError;
+    use crate::quality::{self, SourceQuality};
+    use crate::tags::{self, TagField};
     use anyhow::{Context, Result};
     use rusqlite::{Connection, params};
+    use std::collections::{HashMap, HashSet};
 
     pub fn track_from_row<'a>(row: &rusqlite::Row<'a>) -> rusqlite::Result<Song> {
         Ok(Song {
@@ -81,18 +165,88 @@ pub mod queries {
             track: row.get(5)?,
             year: row.get(6)?,
             duration_sec: row.get(7)?,
+            edit: TrackEdit::default(),
         })
     }
 
     pub fn track_from_id(conn: &Connection, id: i64) -> Result<Song> {
         let sql = "SELECT id, path, title, artist, album, track, year, duration_sec FROM tracks WHERE id = ?1";
         conn.query_row(sql, params![id], track_from_row)
-            .with_context(|| format!("Track ID {} not found in database.", id))
+            .map_err(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => DbError::TrackNotFound(id),
+                err => DbError::Query(err),
+            })
+            .map_err(Into::into)
+    }
+
+    /// The library track whose file is at `path`, if there is one.
+    pub fn track_from_path(conn: &Connection, path: &str) -> Result<Option<Song>> {
+        let sql = "SELECT id, path, title, artist, album, track, year, duration_sec FROM tracks WHERE path = ?1";
+        match conn.query_row(sql, params![path], track_from_row) {
+            Ok(song) => Ok(Some(song)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(DbError::Query(err).into()),
+        }
+    }
+
+    /// How good a source track `id`'s file is, from its format and audio properties.
+    pub fn track_quality(conn: &Connection, id: i64) -> Result<SourceQuality> {
+        let sql = "SELECT path, bit_depth, sample_rate_hz, bitrate_kbps FROM tracks WHERE id = ?1";
+        conn.query_row(sql, params![id], |row| {
+            let path: String = row.get(0)?;
+            Ok(SourceQuality {
+                lossless: path
+                    .rsplit_once('.')
+                    .is_some_and(|(_, ext)| quality::is_lossless(ext)),
+                bit_depth: row.get(1)?,
+                sample_rate_hz: row.get(2)?,
+                bitrate_kbps: row.get(3)?,
+            })
+        })
+        .with_context(|| format!("Track ID {} not found in database.", id))
+    }
+
+    /// Every copy of the track with this artist and title, best source first.
+    pub fn list_versions(
+        conn: &Connection,
+        artist: &str,
+        title: &str,
+    ) -> Result<Vec<(Song, SourceQuality)>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT
+            id, path, title, artist, album, track, year, duration_sec,
+            bit_depth, sample_rate_hz, bitrate_kbps
+            FROM tracks
+            WHERE artist = ?1 AND title = ?2",
+            )
+            .context("failed to prepare query to list versions of a track")?;
+        let mut versions = stmt
+            .query_map([artist, title], |row| {
+                let song = track_from_row(row)?;
+                let quality = SourceQuality {
+                    lossless: quality::is_lossless(song.format()),
+                    bit_depth: row.get(8)?,
+                    sample_rate_hz: row.get(9)?,
+                    bitrate_kbps: row.get(10)?,
+                };
+                Ok((song, quality))
+            })
+            .with_context(|| format!("failed to query versions of \"{}\"", title))?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map tracks from database to rust types")?;
+
+        versions.sort_by_key(|(_, quality)| std::cmp::Reverse(*quality));
+        Ok(versions)
     }
 
     pub fn list_artists(conn: &Connection) -> Result<Vec<String>> {
         let mut stmt = conn
-            .prepare("SELECT DISTINCT artist FROM tracks ORDER BY artist")
+            .prepare(
+                "SELECT DISTINCT COALESCE(canonical, artist) AS browsed
+                FROM tracks LEFT JOIN artist_aliases ON alias = artist
+                ORDER BY browsed COLLATE NATSORT",
+            )
             .context("failed to prepare query to list all artists")?;
         stmt.query_map([], |row| row.get::<_, _>(0))
             .context("failed to query database")?
@@ -100,6 +254,203 @@ pub mod queries {
             .context("failed to map artists from database to strings")
     }
 
+    /// Every artist with how many albums and tracks they have, from one grouped query.
+    /// Merged spellings count towards the artist they are browsed under.
+    pub fn list_artist_counts(conn: &Connection) -> Result<Vec<(String, u64, u64)>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT COALESCE(canonical, artist) AS browsed, COUNT(DISTINCT album), COUNT(*)
+                FROM tracks LEFT JOIN artist_aliases ON alias = artist
+                GROUP BY browsed ORDER BY browsed COLLATE NATSORT",
+            )
+            .context("failed to prepare query to count artist albums and tracks")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .context("failed to query database")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map artist counts from database")
+    }
+
+    pub fn list_all_tracks(conn: &Connection) -> Result<Vec<Song>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT
+            id, path, title, artist, album, track, year, duration_sec
+            FROM tracks
+            ORDER BY artist COLLATE NATSORT, year, album COLLATE NATSORT, track,
+                title COLLATE NATSORT",
+            )
+            .context("failed to prepare query to list all tracks")?;
+        stmt.query_map([], track_from_row)
+            .context("failed to query database for all tracks")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map tracks from database to rust types")
+    }
+
+    /// Every named album as (album, artist, total seconds).
+    pub fn list_album_durations(conn: &Connection) -> Result<Vec<(String, String, u64)>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT album, artist, SUM(duration_sec)
+            FROM tracks
+            WHERE album != ''
+            GROUP BY album, artist
+            HAVING COUNT(unplayable) = 0
+            ORDER BY artist COLLATE NATSORT, album COLLATE NATSORT",
+            )
+            .context("failed to prepare query to list album durations")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .context("failed to query database for album durations")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map albums from database to rust types")
+    }
+
+    /// Every named album by artist, oldest first for each.
+    pub fn list_albums(conn: &Connection) -> Result<Vec<Album>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT album, artist, COALESCE(MAX(year), 0), COUNT(*), SUM(duration_sec),
+                MIN(path)
+            FROM tracks
+            WHERE album != ''
+            GROUP BY album, artist
+            ORDER BY artist COLLATE NATSORT, MAX(year), album COLLATE NATSORT",
+            )
+            .context("failed to prepare query to list albums")?;
+        stmt.query_map([], |row| {
+            Ok(Album {
+                album: row.get(0)?,
+                artist: row.get(1)?,
+                year: row.get(2)?,
+                tracks: row.get(3)?,
+                duration_secs: row.get(4)?,
+                path: row.get(5)?,
+            })
+        })
+        .context("failed to query database for albums")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to map albums from database to rust types")
+    }
+
+    /// Tracks whose genre tag contains `genre`, case-insensitively.
+    pub fn list_genre_tracks(conn: &Connection, genre: &str) -> Result<Vec<Song>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT
+            id, path, title, artist, album, track, year, duration_sec
+            FROM tracks
+            WHERE genre LIKE '%' || ?1 || '%' AND unplayable IS NULL",
+            )
+            .context("failed to prepare query to list tracks in genre")?;
+        stmt.query_map([genre], track_from_row)
+            .with_context(|| format!("failed to query database for genre \"{}\"", genre))?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map tracks from database to rust types")
+    }
+
+    /// Tracks released in the ten years starting at `decade`.
+    pub fn list_decade_tracks(conn: &Connection, decade: u32) -> Result<Vec<Song>> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT
+            id, path, title, artist, album, track, year, duration_sec
+            FROM tracks
+            WHERE year >= ?1 AND year < ?1 + 10 AND unplayable IS NULL",
+            )
+            .context("failed to prepare query to list tracks in decade")?;
+        stmt.query_map([decade], track_from_row)
+            .with_context(|| format!("failed to query database for the {}s", decade))?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map tracks from database to rust types")
+    }
+
+    /// Why each track found unplayable while staging can't be burned.
+    pub fn unplayable_tracks(conn: &Connection) -> Result<HashMap<i64, String>> {
+        let mut stmt = conn
+            .prepare("SELECT id, unplayable FROM tracks WHERE unplayable IS NOT NULL")
+            .context("failed to prepare query to list unplayable tracks")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("failed to query database for unplayable tracks")?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .context("failed to map unplayable tracks from database to rust types")
+    }
+
+    /// Tracks let through the quality gate regardless of their quality.
+    pub fn gate_allowed(conn: &Connection) -> Result<HashSet<i64>> {
+        let mut stmt = conn
+            .prepare("SELECT track_id FROM gate_allowed")
+            .context("failed to prepare query to list tracks allowed past the gate")?;
+        stmt.query_map([], |row| row.get(0))
+            .context("failed to query database for tracks allowed past the gate")?
+            .collect::<Result<HashSet<_>, _>>()
+            .context("failed to map allowed tracks from database to rust types")
+    }
+
+    /// Lets track `id` through the quality gate, or stops doing so.
+    pub fn set_gate_allowed(conn: &Connection, id: i64, allowed: bool) -> Result<()> {
+        let sql = if allowed {
+            "INSERT OR IGNORE INTO gate_allowed (track_id) VALUES (?1)"
+        } else {
+            "DELETE FROM gate_allowed WHERE track_id = ?1"
+        };
+        conn.execute(sql, [id])
+            .with_context(|| format!("failed to update the quality gate for track {}", id))?;
+
+        Ok(())
+    }
+
+    /// Marks track `id` as unplayable for `reason`, or clears the mark when `None`.
+    pub fn set_unplayable(conn: &Connection, id: i64, reason: Option<&str>) -> Result<()> {
+        conn.execute(
+            "UPDATE tracks SET unplayable = ?2 WHERE id = ?1",
+            params![id, reason],
+        )
+        .with_context(|| format!("failed to mark track {} in the library", id))?;
+
+        Ok(())
+    }
+
+    /// Stored tempo of each track that has been analyzed.
+    pub fn track_bpms(conn: &Connection) -> Result<HashMap<i64, f64>> {
+        let mut stmt = conn
+            .prepare("SELECT id, bpm FROM tracks WHERE bpm IS NOT NULL")
+            .context("failed to prepare query to list track tempos")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("failed to query database for track tempos")?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .context("failed to map tempos from database to rust types")
+    }
+
+    /// Measured average level of each track that has one, in dBFS.
+    pub fn track_loudness(conn: &Connection) -> Result<HashMap<i64, f64>> {
+        let mut stmt = conn
+            .prepare("SELECT id, loudness_db FROM tracks WHERE loudness_db IS NOT NULL")
+            .context("failed to prepare query to list track loudness")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .context("failed to query database for track loudness")?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .context("failed to map loudness from database to rust types")
+    }
+
+    /// Stored key of each track that has one.
+    pub fn track_keys(conn: &Connection) -> Result<HashMap<i64, CamelotKey>> {
+        let mut stmt = conn
+            .prepare("SELECT id, musical_key FROM tracks WHERE musical_key IS NOT NULL")
+            .context("failed to prepare query to list track keys")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .context("failed to query database for track keys")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to map keys from database to rust types")?;
+
+        // keys we can't parse are treated the same as missing ones
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, key)| Some((id, key.parse().ok()?)))
+            .collect())
+    }
+
     pub fn list_album(conn: &Connection, album: &str) -> Result<Vec<Song>> {
         let mut stmt = conn
             .prepare(
@@ -107,7 +458,7 @@ pub mod queries {
             id, path, title, artist, album, track, year, duration_sec
             FROM tracks
             WHERE album = ?1
-            ORDER BY track",
+            ORDER BY track, title COLLATE NATSORT",
             )
             .context("failed to prepare query to list all tracks in album")?;
         stmt.query_map([album], track_from_row)
@@ -116,14 +467,15 @@ pub mod queries {
             .context("failed to map tracks from database to rust types")
     }
 
+    /// Tracks browsed under `artist`, whichever of its spellings they are tagged with.
     pub fn list_artist_tracks(conn: &Connection, artist: &str) -> Result<Vec<Song>> {
         let mut stmt = conn
             .prepare(
                 "SELECT
             id, path, title, artist, album, track, year, duration_sec
-            FROM tracks
-            WHERE artist = ?1
-            ORDER BY year, album, track",
+            FROM tracks LEFT JOIN artist_aliases ON alias = artist
+            WHERE COALESCE(canonical, artist) = ?1
+            ORDER BY year, album COLLATE NATSORT, track, title COLLATE NATSORT",
             )
             .context("failed to prepare query to list all artist's tracks")?;
         stmt.query_map([artist], track_from_row)
@@ -132,42 +484,244 @@ pub mod queries {
             .context("failed to map tracks from database to rust types")
     }
 
-    /// Clears the playlist and the staging directory.
+    /// Tracks whose title, artist, album or file path contain every word of `terms`, so
+    /// folder names such as "bootlegs" or "live" can be searched too. A word ending in
+    /// `*` matches any word starting with it, so "radi*" finds "Radiohead". Words such as
+    /// `language:eng` or `instrumental:yes` keep only the tracks tagged so, and can make
+    /// up the whole search, e.g. to save one for an instrumental-only disc.
     pub fn search_group(conn: &Connection, terms: &str) -> anyhow::Result<Vec<Song>> {
-        println!("searching for term \"{}\"", terms);
-        let sql = r#"SELECT
+        let (filters, words) = search_filters(terms)?;
+        let query = fts_query(&words);
+        if query.is_empty() && filters.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut conditions = filters
+            .iter()
+            .enumerate()
+            .map(|(i, (field, _))| format!("{} = ?{} COLLATE NOCASE", field.text_sql(), i + 1))
+            .collect::<Vec<_>>();
+        let mut params = filters
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+        let join = if query.is_empty() {
+            ""
+        } else {
+            params.push(query);
+            conditions.push(format!("tracks_fts MATCH ?{}", params.len()));
+            "INNER JOIN tracks_fts AS f ON f.id = t.id"
+        };
+        let sql = format!(
+            r#"SELECT
             t.id, t.path, t.title, t.artist, t.album, t.track, t.year, t.duration_sec
             FROM tracks AS t
-            INNER JOIN tracks_fts AS f
-            ON f.id = t.id
-            WHERE tracks_fts MATCH '"' || ?1 || '"'
-            LIMIT 50"#;
+            {join}
+            WHERE {}
+            LIMIT 50"#,
+            conditions.join(" AND ")
+        );
 
         let mut stmt = conn
-            .prepare(sql)
+            .prepare(&sql)
             .context("failed to create search statement")?;
 
-        stmt.query_map([terms], track_from_row)
+        stmt.query_map(rusqlite::params_from_iter(params), track_from_row)
             .with_context(|| format!("failed to query database with search term: \"{}\"", terms))?
             .collect::<Result<Vec<_>, _>>()
             .context("failed to map tracks from database to rust types")
     }
+
+    /// Splits the `language:` and `instrumental:` filters off `terms`, leaving the words
+    /// to search for.
+    pub fn search_filters(terms: &str) -> anyhow::Result<(Vec<(TagField, String)>, String)> {
+        let mut filters = vec![];
+        let mut words = vec![];
+        for word in terms.split_whitespace() {
+            match word.split_once(':') {
+                Some(("language", value)) => {
+                    filters.push((TagField::Language, value.to_string()));
+                }
+                Some(("instrumental", value)) => {
+                    let flag = tags::parse_flag(value)?
+                        .context("expected instrumental:yes or instrumental:no")?;
+                    let value = if flag { "yes" } else { "no" };
+                    filters.push((TagField::Instrumental, value.to_string()));
+                }
+                _ => words.push(word),
+            }
+        }
+
+        Ok((filters, words.join(" ")))
+    }
+
+    /// IDs of the tracks that pass every filter of `filters`, as [`search_filters`]
+    /// splits them off.
+    pub fn filtered_ids(
+        conn: &Connection,
+        filters: &[(TagField, String)],
+    ) -> anyhow::Result<HashSet<i64>> {
+        let conditions = filters
+            .iter()
+            .enumerate()
+            .map(|(i, (field, _))| format!("{} = ?{} COLLATE NOCASE", field.text_sql(), i + 1))
+            .collect::<Vec<_>>();
+        let sql = format!("SELECT id FROM tracks WHERE {}", conditions.join(" AND "));
+        let mut stmt = conn
+            .prepare(&sql)
+            .context("failed to prepare query for filtered tracks")?;
+
+        stmt.query_map(
+            rusqlite::params_from_iter(filters.iter().map(|(_, value)| value)),
+            |row| row.get(0),
+        )
+        .context("failed to query database for filtered tracks")?
+        .collect::<Result<HashSet<_>, _>>()
+        .context("failed to read filtered tracks")
+    }
+
+    /// Quotes each word of `terms` as an FTS5 string so punctuation is matched rather
+    /// than parsed, keeping a trailing `*` outside the quotes as a prefix search.
+    fn fts_query(terms: &str) -> String {
+        terms
+            .split_whitespace()
+            .filter_map(|word| {
+                let (word, prefix) = match word.strip_suffix('*') {
+                    Some(word) => (word, "*"),
+                    None => (word, ""),
+                };
+                (!word.is_empty()).then(|| format!("\"{}\"{}", word.replace('"', "\"\""), prefix))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Settings that control how a playlist is written to disc.
+#[derive(Debug, Clone, Default)]
+pub struct BurnOptions {
+    /// Title of the disc, for CD-TEXT, cue sheets, export folders and the burn history.
+    /// When unset one is suggested from the history, see [`AppState::disc_title`].
+    pub title: Option<String>,
+    /// Named series the disc is the next volume of, e.g. "Road Trips".
+    pub series: Option<String>,
+    pub cdtext: CdTextMode,
+    /// Write speed passed to wodim, or the drive's choice when unset.
+    pub speed: Option<u32>,
+    /// Silence between tracks in a mixdown export.
+    pub gap_secs: u32,
+    /// Overlap between tracks in a mixdown export; takes precedence over the gap.
+    pub crossfade_secs: u32,
+    /// Gap or crossfade runs of one album's consecutive tracks like the rest in a
+    /// mixdown export, rather than letting them play straight on.
+    pub gap_runs: bool,
+    pub normalize: NormalizeProfile,
+    /// Album or track gain for this burn, or the profile's usual one when unset.
+    pub normalize_mode: Option<NormalizeMode>,
+    pub quality_gate: QualityGate,
+}
+
+impl BurnOptions {
+    /// The profile and the mode it is used in.
+    pub fn normalization(&self) -> (NormalizeProfile, NormalizeMode) {
+        (
+            self.normalize,
+            self.normalize_mode
+                .unwrap_or_else(|| self.normalize.default_mode()),
+        )
+    }
+}
+
+/// Set by `--read-only`: the library is opened read-only, and commands that would change
+/// it, the saved session or the burner are refused, so the library can be browsed while
+/// another session scans or burns.
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+pub fn read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
 }
 
+/// How long a read-only session waits for another session's write to finish.
+const READ_ONLY_BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct AppState {
     conn: Connection,
     playlist: Vec<Song>,
+    /// Candidates parked to be previewed before they go on the playlist.
+    audition: Vec<Song>,
+    burn_options: BurnOptions,
+    burn_session: Arc<Mutex<BurnSession>>,
+    /// Swap added tracks for a lossless copy of themselves when the library has one.
+    prefer_lossless: bool,
+    /// Progress of every background task, for frontends that want to follow along.
+    events: EventBus,
+    backend: Backend,
+    /// Changes that take back the last bulk tag edit, and whether it wrote to the files.
+    tag_undo: Option<(Vec<TagChange>, bool)>,
+    /// Playlist changes that can be undone, and the playlist as the journal last saw it.
+    journal: Journal,
+    journaled: Vec<Song>,
+    /// Where the TUI was left, saved with the session.
+    ui: UiState,
+    config: Config,
 }
 
 impl AppState {
     pub fn new() -> Result<Self> {
         // Connect to the database
-        let conn = Connection::open(DB_PATH)
-            .context("Failed to open library.db. Ensure it is created and populated.")?;
+        let db_path = paths::db();
+        let open_error = |source| DbError::Open {
+            path: db_path.display().to_string(),
+            source,
+        };
+        let config = Config::load()?;
+        let conn = if read_only() {
+            let conn = Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(open_error)?;
+            conn.busy_timeout(READ_ONLY_BUSY_TIMEOUT)
+                .context("failed to set busy timeout")?;
+            conn
+        } else {
+            let conn = Connection::open(&db_path).map_err(open_error)?;
+            crate::build_db::migrate(&conn, &config.fts).context("failed to migrate library.db")?;
+            conn
+        };
+        collate::register(&conn).context("failed to register the natural sort order")?;
+        duration::set_style(config.durations);
+        let journal = Journal::load(config.undo_depth)?;
+        let ui = session::load_ui()?;
+        let quality_gate = QualityGate {
+            action: config.gate_action,
+            min_lossy_kbps: config.gate_min_kbps,
+            // a read-only library from before the gate was kept there has no table
+            allowed: queries::gate_allowed(&conn).unwrap_or_default(),
+        };
 
         Ok(AppState {
             conn,
             playlist: Vec::new(),
+            audition: Vec::new(),
+            burn_options: BurnOptions {
+                quality_gate,
+                ..BurnOptions::default()
+            },
+            burn_session: Arc::new(Mutex::new(if read_only() {
+                BurnSession::default()
+            } else {
+                BurnSession::resume()
+            })),
+            prefer_lossless: config.prefer_lossless,
+            events: EventBus::default(),
+            backend: Backend::from_env(&config),
+            tag_undo: None,
+            journal,
+            journaled: Vec::new(),
+            ui,
+            config,
         })
     }
 
@@ -175,267 +729,2473 @@ impl AppState {
         &self.conn
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Fails with [`DbError::ReadOnly`] naming `action` in read-only mode.
+    pub fn ensure_writable(&self, action: &'static str) -> Result<()> {
+        if read_only() {
+            return Err(DbError::ReadOnly(action).into());
+        }
+
+        Ok(())
+    }
+
+    /// One line naming what a burn targets: the library, the burner, the disc size and
+    /// where audio is staged.
+    pub fn target_summary(&self) -> String {
+        format!(
+            "Profile {} | DB {}{} | Device {} | {} | Staging {}",
+            paths::profile(),
+            paths::db().display(),
+            if read_only() { " (read-only)" } else { "" },
+            self.backend.device,
+            CD_CAPACITY_PROFILE,
+            self.backend.staging_root.display()
+        )
+    }
+
     pub fn playlist(&self) -> &[Song] {
         &self.playlist
     }
 
-    pub fn playlist_add_by_id(&mut self, id: i64) -> Result<()> {
-        let track = queries::track_from_id(&self.conn, id)?;
+    pub fn burn_options(&self) -> &BurnOptions {
+        &self.burn_options
+    }
 
-        self.playlist_add(track)
+    pub fn burn_options_mut(&mut self) -> &mut BurnOptions {
+        &mut self.burn_options
     }
 
-    pub fn playlist_add(&mut self, song: Song) -> Result<()> {
-        if playlist_duration_secs(&self.playlist[..]) + song.duration_sec > CD_MAX_DURATION_SECONDS
-        {
-            anyhow::bail!(
-                "Track is too long! Adding would exceed the CD Limit of {}",
-                humantime_secs(CD_MAX_DURATION_SECONDS)
-            );
+    /// The disc title set for the playlist, or else the next volume of its series, or
+    /// else one suggested from the burn history.
+    pub fn disc_title(&self) -> Result<String> {
+        match (&self.burn_options.title, self.next_volume()?) {
+            (Some(title), _) => Ok(title.clone()),
+            (None, Some((series, volume))) => Ok(format!("{} Vol. {}", series, volume)),
+            (None, None) => burns::suggest_title(&self.conn),
         }
+    }
 
-        self.playlist.push(song);
+    /// The number `{disc}` stands for in file names: the volume the playlist would be
+    /// burned as in its series, else 1.
+    pub fn disc_number(&self) -> Result<u32> {
+        Ok(self.next_volume()?.map_or(1, |(_, volume)| volume))
+    }
 
-        Ok(())
+    /// The playlist's series and the volume it would be burned as, if it is in one.
+    pub fn next_volume(&self) -> Result<Option<(&str, u32)>> {
+        self.burn_options
+            .series
+            .as_deref()
+            .map(|series| Ok((series, burns::next_volume(&self.conn, series)?)))
+            .transpose()
     }
 
-    pub fn playlist_remove(&mut self, index: usize) -> bool {
-        if index >= self.playlist.len() {
-            return false;
+    /// Playlist entries already burned on an earlier volume of the playlist's series,
+    /// each with the first volume it was on.
+    pub fn series_repeats(&self) -> Result<Vec<(Song, u32)>> {
+        match &self.burn_options.series {
+            Some(series) => burns::series_repeats(&self.conn, series, &self.playlist),
+            None => Ok(vec![]),
         }
-        self.playlist.remove(index);
+    }
 
-        true
+    /// The burn options with the disc title settled, for handing to a background task.
+    fn titled_burn_options(&self) -> Result<BurnOptions> {
+        Ok(BurnOptions {
+            title: Some(self.disc_title()?),
+            ..self.burn_options.clone()
+        })
     }
 
-    pub fn playlist_clear(&mut self) {
-        self.playlist.clear();
+    /// Lets track `id` through the quality gate whatever its quality, from now on, or
+    /// puts it back under the gate.
+    pub fn set_gate_allowed(&mut self, id: i64, allowed: bool) -> Result<()> {
+        self.ensure_writable("changing the quality gate's allowed tracks")?;
+        queries::set_gate_allowed(&self.conn, id, allowed)?;
+        let set = &mut self.burn_options.quality_gate.allowed;
+        if allowed {
+            set.insert(id);
+        } else {
+            set.remove(&id);
+        }
+
+        Ok(())
     }
 
-    pub fn burn(&self) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<LogMessage>)> {
-        let (tx, rx) = mpsc::channel();
-        let playlist = self.playlist().to_vec();
-        let handle = thread::spawn(move || -> Result<()> {
-            playlist_burn(playlist, tx).context("failed to burn playlist")
-        });
+    pub fn prefer_lossless(&self) -> bool {
+        self.prefer_lossless
+    }
 
-        Ok((handle, rx))
+    pub fn set_prefer_lossless(&mut self, prefer_lossless: bool) {
+        self.prefer_lossless = prefer_lossless;
     }
-}
 
-/// Executes the final normalization and burning pipeline.
-// - Downsample + decompress music
-// - Normalize
-// - Burn to CD
-pub fn playlist_burn(playlist: Vec<Song>, msgs: mpsc::Sender<LogMessage>) -> Result<()> {
-    use LogMessage::*;
-    use std::io::{BufRead, BufReader};
-    use std::process::{Command, Stdio};
-    let temp_dir = match temp_dir() {
-        Ok(dir) => dir,
-        Err(err) => {
-            msgs.send(Err(anyhow::anyhow!("failed to setup tempdir: {:?}", err)).into())
-                .context("failed to send")?;
-            return Ok(());
+    /// Applies the lossless policy: returns the best lossless copy of `song` when the
+    /// policy is on and `song` itself is lossy, otherwise `song` unchanged.
+    pub fn preferred_version(&self, song: Song) -> Result<Song> {
+        if !self.prefer_lossless || quality::is_lossless(song.format()) {
+            return Ok(song);
         }
-    };
 
-    if playlist.is_empty() {
-        msgs.send(Complete(Err(anyhow::anyhow!(
-            "playlist is empty. Add songs first"
-        ))))
-        .context("failed to send")?;
-        return Ok(());
+        let best = queries::list_versions(&self.conn, &song.artist, &song.title)?
+            .into_iter()
+            .find(|(_, quality)| quality.lossless);
+
+        Ok(best.map_or(song, |(lossless, _)| lossless))
     }
 
-    let mut downsampled_paths = std::collections::HashMap::new();
+    pub fn playlist_add_by_id(&mut self, id: i64) -> Result<()> {
+        let track = queries::track_from_id(&self.conn, id)?;
 
-    for song in &playlist {
-        if downsampled_paths.contains_key(&song.id) {
-            continue;
-        }
+        self.playlist_add(track)
+    }
 
-        msgs.send(
-            LogLine {
-                is_stderr: false,
-                line: format!("transcoding track {}...", song.title),
+    /// The library's track for the file at `path`, given as it was scanned or any other
+    /// way that leads to the same file.
+    pub fn library_track_at(&self, path: &Path) -> Result<Option<Song>> {
+        let canonical = path.canonicalize().ok();
+        for candidate in std::iter::once(path).chain(canonical.as_deref()) {
+            let song = queries::track_from_path(&self.conn, &candidate.to_string_lossy())?;
+            if song.is_some() {
+                return Ok(song);
             }
-            .into(),
-        )
-        .context("failed to send")?;
-        let song_path = &song.path;
-
-        // 3. Transcode and Downsample (FFmpeg)
-        let output_filename = format!("track_{}.wav", song.id);
-        let output_path = temp_dir.path().join(&output_filename);
-
-        let status = Command::new("ffmpeg")
-            .arg("-i")
-            .arg(song_path)
-            .arg("-y")
-            .arg("-ar")
-            .arg("44100")
-            .arg("-ac")
-            .arg("2")
-            .arg("-sample_fmt")
-            .arg("s16")
-            .arg(&output_path)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .with_context(|| format!("FFmpeg failed for source path: {}", song_path))?;
-
-        if !status.success() {
-            msgs.send(Err(anyhow::anyhow!("ffmpeg failed to transcode track at path {}. Check source file access and validity.",
-                song_path
-            )).into()).context("failed to send")?;
-            return Ok(());
         }
-        downsampled_paths.insert(song.id, output_path);
+
+        Ok(None)
     }
 
-    let wav_files = downsampled_paths.values().cloned().collect::<Vec<_>>();
+    /// Adds the file at `path`: the library's track when the library has it, otherwise
+    /// the file itself, read from its tags.
+    pub fn playlist_add_path(&mut self, path: &Path) -> Result<()> {
+        let song = match self.library_track_at(path)? {
+            Some(song) => song,
+            None => {
+                anyhow::ensure!(path.is_file(), "{} is not a file", path.display());
+                // saved with the session by path, so it has to be found from anywhere
+                crate::build_db::song_from_file(&std::path::absolute(path)?)?
+            }
+        };
+
+        self.playlist_add(song)
+    }
 
-    let mut normalize_command = Command::new("normalize");
-    normalize_command
-        .current_dir(temp_dir.path())
-        .arg("-b")
-        .args(wav_files)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null());
+    pub fn playlist_add(&mut self, song: Song) -> Result<()> {
+        anyhow::ensure!(
+            self.playlist.len() < CD_MAX_TRACKS,
+            "The playlist is full: a CD holds at most {} tracks",
+            CD_MAX_TRACKS
+        );
+        let (needed, remaining) = (song.played_duration_secs(), self.remaining_secs());
+        if needed > remaining {
+            anyhow::bail!(
+                "Track is too long! It takes {} but only {} of the {} CD limit is left",
+                format_secs(needed),
+                format_secs(remaining),
+                format_secs(CD_MAX_DURATION_SECONDS)
+            );
+        }
 
-    let status = normalize_command
-        .status()
-        .context("Failed to execute normalize. Is it installed?")?;
+        self.playlist.push(song);
 
-    if !status.success() {
-        msgs.send(LogMessage::Complete(Err(anyhow::anyhow!(
-            "Audio normalization failed."
-        ))))
-        .context("failed to send")?;
-        return Ok(());
+        Ok(())
     }
 
-    msgs.send(
-        LogLine {
-            is_stderr: false,
-            line: String::from("Normalized playlist volume"),
+    /// Adds all of `songs` in order, or none of them when one doesn't fit.
+    pub fn playlist_add_all(&mut self, songs: Vec<Song>) -> Result<()> {
+        let before = self.playlist.clone();
+        for song in songs {
+            if let Err(err) = self.playlist_add(song) {
+                self.playlist = before;
+                return Err(err);
+            }
         }
-        .into(),
-    )
-    .context("failed to send")?;
 
-    let playlist_files = playlist
-        .iter()
-        .map(|song| downsampled_paths[&song.id].clone())
-        .collect::<Vec<_>>();
+        Ok(())
+    }
 
-    msgs.send(
-        LogLine {
-            is_stderr: false,
-            line: String::from("Burning playlist"),
+    /// How many more tracks the disc can hold.
+    pub fn free_track_slots(&self) -> usize {
+        CD_MAX_TRACKS.saturating_sub(self.playlist.len())
+    }
+
+    /// Adds `song` as [`Self::playlist_add`] does. When it is too long for the disc and
+    /// `split_long_tracks` is set, as much as fits is added instead, split at a quiet
+    /// moment, and the rest is parked in the audition list for the next disc. Returns the
+    /// second of the file it was split at, if it was.
+    pub fn playlist_add_splitting(&mut self, song: Song) -> Result<Option<u64>> {
+        let remaining = self.remaining_secs();
+        if !self.config.split_long_tracks || song.played_duration_secs() <= remaining {
+            return self.playlist_add(song).map(|()| None);
         }
-        .into(),
-    )
-    .context("failed to send")?;
 
-    let mut wodim = Command::new("wodim")
-        .current_dir(temp_dir)
-        .arg("-v")
-        .arg("-eject")
-        .arg("-dao")
-        .arg("-pad")
-        .arg("dev=")
-        .arg(CD_WRITER_DEVICE)
-        .arg("-audio")
-        .args(playlist_files)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .context("Failed to spawn wodim. Check device path and permissions.")?;
-
-    let (stdout, stderr) = (
-        wodim
-            .stdout
-            .take()
-            .context("failed to get handle to stdout")?,
-        wodim
-            .stderr
-            .take()
-            .context("failed to get handle to stderr")?,
-    );
+        let (first, rest, at) = split::split(&song, remaining)?;
+        self.playlist_add(first)?;
+        // not audition_add, which would drop the rest if the whole track were parked
+        self.audition.push(rest);
+
+        Ok(Some(at))
+    }
+
+    /// Adds the chapters of track `id` as entries of their own, all of them or the ones
+    /// numbered `range`, stopping at the first that doesn't fit. Returns how many were
+    /// added.
+    pub fn playlist_add_chapters(
+        &mut self,
+        id: i64,
+        range: Option<(usize, usize)>,
+    ) -> Result<usize> {
+        let song = queries::track_from_id(&self.conn, id)?;
+        let chapters = chapters::probe(&song.path)?;
+        anyhow::ensure!(!chapters.is_empty(), "\"{}\" has no chapters", song.title);
+        let (first, last) = range.unwrap_or((1, chapters.len()));
+        anyhow::ensure!(
+            first <= last && last <= chapters.len(),
+            "\"{}\" has chapters 1 to {}",
+            song.title,
+            chapters.len()
+        );
+
+        for (added, chapter) in chapters[first - 1..last].iter().enumerate() {
+            self.playlist_add(chapter.entry(&song)).with_context(|| {
+                format!(
+                    "added {} chapters, but not chapter {}",
+                    added, chapter.number
+                )
+            })?;
+        }
+
+        Ok(last - first + 1)
+    }
+
+    pub fn playlist_remove(&mut self, index: usize) -> bool {
+        if index >= self.playlist.len() {
+            return false;
+        }
+        self.playlist.remove(index);
+
+        true
+    }
+
+    /// Seconds left on the disc before the playlist hits the CD limit.
+    pub fn remaining_secs(&self) -> u64 {
+        CD_MAX_DURATION_SECONDS.saturating_sub(playlist_duration_secs(&self.playlist))
+    }
+
+    /// "4:31, 12:08 left": the time the last playlist entry takes and what that leaves.
+    pub fn added_delta(&self) -> String {
+        format!(
+            "{}, {} left",
+            format_secs(self.playlist.last().map_or(0, Song::played_duration_secs)),
+            format_secs(self.remaining_secs())
+        )
+    }
+
+    /// Changes the trim/fade of the entry at `index`, refusing edits that would push
+    /// the playlist past the CD limit.
+    pub fn playlist_edit(&mut self, index: usize, edit: TrackEdit) -> Result<()> {
+        let entry = self
+            .playlist
+            .get_mut(index)
+            .with_context(|| format!("no playlist entry at position {}", index + 1))?;
+        let previous = std::mem::replace(&mut entry.edit, edit);
+
+        if playlist_duration_secs(&self.playlist) > CD_MAX_DURATION_SECONDS {
+            self.playlist[index].edit = previous;
+            anyhow::bail!(
+                "Edit would exceed the CD Limit of {}",
+                format_secs(CD_MAX_DURATION_SECONDS)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the entry at `index` with another copy of the track, keeping the entry's
+    /// trim and fade, unless the playlist would no longer fit.
+    pub fn playlist_swap_version(&mut self, index: usize, song: Song) -> Result<()> {
+        let entry = self
+            .playlist
+            .get_mut(index)
+            .with_context(|| format!("no playlist entry at position {}", index + 1))?;
+        let edit = entry.edit;
+        let previous = std::mem::replace(entry, Song { edit, ..song });
+
+        if playlist_duration_secs(&self.playlist) > CD_MAX_DURATION_SECONDS {
+            self.playlist[index] = previous;
+            anyhow::bail!(
+                "That version would exceed the CD Limit of {}",
+                format_secs(CD_MAX_DURATION_SECONDS)
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Moves the entry at `from` to position `to`, shifting the entries in between.
+    pub fn playlist_move(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.playlist.len() || to >= self.playlist.len() {
+            return false;
+        }
+        let song = self.playlist.remove(from);
+        self.playlist.insert(to, song);
+
+        true
+    }
+
+    /// Orders the playlist by rising tempo. Tracks without a detected tempo keep their
+    /// relative order at the end.
+    pub fn playlist_sort_by_tempo(&mut self) -> Result<usize> {
+        let bpms = queries::track_bpms(&self.conn)?;
+        self.playlist
+            .sort_by(|a, b| match (bpms.get(&a.id), bpms.get(&b.id)) {
+                (Some(a), Some(b)) => a.total_cmp(b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            });
+
+        Ok(self
+            .playlist
+            .iter()
+            .filter(|s| !bpms.contains_key(&s.id))
+            .count())
+    }
+
+    /// Reorders the playlist so consecutive tracks are as close as possible on the
+    /// Camelot wheel, greedily walking from the first track. Tracks without a known
+    /// key are kept at the end. Returns the number of harsh (non-adjacent) transitions
+    /// left and the number of tracks without a key.
+    pub fn playlist_sort_harmonic(&mut self) -> Result<(usize, usize)> {
+        let keys = queries::track_keys(&self.conn)?;
+        let (mut remaining, unkeyed): (Vec<Song>, Vec<Song>) = self
+            .playlist
+            .drain(..)
+            .partition(|s| keys.contains_key(&s.id));
+
+        let mut ordered: Vec<Song> = vec![];
+        let mut clashes = 0;
+        while !remaining.is_empty() {
+            let next = match ordered.last() {
+                None => 0,
+                Some(prev) => {
+                    let prev = keys[&prev.id];
+                    let (i, distance) = remaining
+                        .iter()
+                        .map(|s| prev.distance(&keys[&s.id]))
+                        .enumerate()
+                        .min_by_key(|(_, d)| *d)
+                        .unwrap_or((0, 0));
+                    if distance > 1 {
+                        clashes += 1;
+                    }
+                    i
+                }
+            };
+            ordered.push(remaining.remove(next));
+        }
+
+        let missing = unkeyed.len();
+        ordered.extend(unkeyed);
+        self.playlist = ordered;
+
+        Ok((clashes, missing))
+    }
+
+    /// A better running order for the playlist, as worked out by [`order::optimize`]
+    /// from the stored tempos and levels. The playlist itself is left alone.
+    pub fn playlist_optimized_order(&self) -> Result<order::Plan> {
+        let bpms = queries::track_bpms(&self.conn)?;
+        let loudness = queries::track_loudness(&self.conn)?;
+
+        Ok(order::optimize(&self.playlist, &bpms, &loudness))
+    }
+
+    /// Puts the playlist in the order of `plan`, unless it changed since the plan was made.
+    pub fn playlist_apply_order(&mut self, plan: &order::Plan) -> bool {
+        let ids = self.playlist.iter().map(|s| s.id).collect::<Vec<_>>();
+        if !plan.fits(&ids) {
+            return false;
+        }
+        self.playlist = plan
+            .order
+            .iter()
+            .map(|&i| self.playlist[i].clone())
+            .collect();
+
+        true
+    }
+
+    pub fn playlist_clear(&mut self) {
+        self.playlist.clear();
+    }
+
+    /// Writes the playlist to `path` as an extended M3U other players read. Trims and
+    /// fades have no place in one, so only the played length shows they were there.
+    pub fn playlist_save(&self, path: &Path) -> Result<()> {
+        let mut m3u = String::from("#EXTM3U\n");
+        for song in &self.playlist {
+            m3u += &format!(
+                "#EXTINF:{},{} - {}\n{}\n",
+                song.played_duration_secs(),
+                song.artist,
+                song.title,
+                song.path
+            );
+        }
+
+        std::fs::write(path, m3u).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Replaces the playlist with the entries of the M3U playlist at `path`, as
+    /// [`run_burn_m3u`] reads them. When an entry can't be added, the playlist is left
+    /// as it was.
+    pub fn playlist_load(&mut self, path: &Path) -> Result<()> {
+        let entries = read_m3u(path)?;
+        let before = std::mem::take(&mut self.playlist);
+        for entry in entries {
+            if let Err(err) = self.playlist_add_path(&entry) {
+                self.playlist = before;
+                return Err(err).with_context(|| format!("failed to add {}", entry.display()));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn audition(&self) -> &[Song] {
+        &self.audition
+    }
+
+    /// Parks `song` in the audition list, unless it is there already.
+    pub fn audition_add(&mut self, song: Song) -> bool {
+        if self
+            .audition
+            .iter()
+            .any(|parked| parked.key() == song.key())
+        {
+            return false;
+        }
+        self.audition.push(song);
+
+        true
+    }
+
+    pub fn audition_remove(&mut self, index: usize) -> bool {
+        if index >= self.audition.len() {
+            return false;
+        }
+        self.audition.remove(index);
+
+        true
+    }
+
+    pub fn audition_clear(&mut self) {
+        self.audition.clear();
+    }
+
+    /// Moves the audition entry at `index` to the end of the playlist, if it fits.
+    pub fn audition_promote(&mut self, index: usize) -> Result<()> {
+        let song = self
+            .audition
+            .get(index)
+            .with_context(|| format!("no audition entry at position {}", index + 1))?
+            .clone();
+        self.playlist_add(song)?;
+        self.audition.remove(index);
+
+        Ok(())
+    }
+
+    /// Moves the playlist entry at `index` back to the audition list.
+    pub fn playlist_demote(&mut self, index: usize) -> bool {
+        if index >= self.playlist.len() {
+            return false;
+        }
+        let song = self.playlist.remove(index);
+        self.audition_add(song);
+
+        true
+    }
+
+    /// Whether the playlist holds tracks that aren't, in this order, the disc burned last.
+    pub fn playlist_unburned(&self) -> Result<bool> {
+        if self.playlist.is_empty() {
+            return Ok(false);
+        }
+        let burned = burns::last_burned_tracks(&self.conn)?;
+
+        Ok(!self.playlist.iter().map(|song| song.id).eq(burned))
+    }
+
+    /// How the playlist would be laid out on the disc.
+    pub fn disc_layout(&self) -> DiscLayout {
+        DiscLayout::new(&self.playlist, self.burn_options.cdtext)
+    }
+
+    /// Corrects a tag of track `id` as [`tags::set`] does, and refreshes the playlist's
+    /// entries for the track to match.
+    pub fn set_tag(
+        &mut self,
+        id: i64,
+        field: TagField,
+        value: &str,
+        write_file: bool,
+    ) -> Result<()> {
+        self.ensure_writable("editing tags")?;
+        tags::set(&mut self.conn, id, field, value, write_file)?;
+        self.refresh_track(id)
+    }
+
+    /// Rereads track `id` from its file, as a scan would, and refreshes the playlist's
+    /// entries for it.
+    pub fn reprobe(&mut self, id: i64) -> Result<()> {
+        self.ensure_writable("rereading tags")?;
+        crate::build_db::reprobe_track(&mut self.conn, id)?;
+        self.refresh_track(id)
+    }
+
+    /// Adds the file at `path` to the library, as a scan would, and returns its ID.
+    pub fn import_file(&mut self, path: &Path) -> Result<i64> {
+        self.ensure_writable("importing files")?;
+        crate::build_db::import_file(&mut self.conn, path)
+    }
+
+    /// Sets or removes the note on track `id`, as [`tags::set_note`] does.
+    pub fn set_note(&self, id: i64, note: &str) -> Result<()> {
+        self.ensure_writable("editing notes")?;
+        tags::set_note(&self.conn, id, note)
+    }
+
+    /// Browses `spelling` under the artist `canonical`, see [`aliases::merge`].
+    pub fn merge_artist(&mut self, spelling: &str, canonical: &str) -> Result<String> {
+        self.ensure_writable("merging artists")?;
+        aliases::merge(&mut self.conn, spelling, canonical)
+    }
+
+    /// Splits `name` back off the artist it was merged into, or its spellings off it.
+    pub fn unmerge_artist(&self, name: &str) -> Result<Vec<String>> {
+        self.ensure_writable("unmerging artists")?;
+        aliases::unmerge(&self.conn, name)
+    }
+
+    /// Writes the library's user metadata to `path`, see [`userdata::export`].
+    pub fn export_user_data(&self, path: &Path) -> Result<userdata::Counts> {
+        userdata::export(&self.conn, self.config.music_dir.as_deref(), path)
+    }
+
+    /// Applies user metadata exported to `path`, see [`userdata::import`].
+    pub fn import_user_data(&mut self, path: &Path) -> Result<userdata::Counts> {
+        self.ensure_writable("importing user metadata")?;
+        userdata::import(&mut self.conn, self.config.music_dir.as_deref(), path)
+    }
+
+    /// Remembers that track `id` was previewed, unless the library is read-only or the
+    /// track isn't in it.
+    pub fn record_preview(&self, id: i64) -> Result<()> {
+        // a file from outside the library has no row to tie the preview to
+        if read_only() || id == 0 {
+            return Ok(());
+        }
+        crate::preview::record(&self.conn, id)
+    }
+
+    /// Reloads the playlist's entries for track `id` from the library, keeping their edits.
+    fn refresh_track(&mut self, id: i64) -> Result<()> {
+        let updated = queries::track_from_id(&self.conn, id)?;
+        for song in self.playlist.iter_mut().filter(|song| song.id == id) {
+            *song = Song {
+                edit: song.edit,
+                ..updated.clone()
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Applies `changes` as one batch that [`Self::undo_tags`] can take back. When a
+    /// change fails, the ones before it stay applied and can still be undone.
+    pub fn apply_tag_changes(&mut self, changes: &[TagChange], write_file: bool) -> Result<()> {
+        self.ensure_writable("editing tags")?;
+        let mut undo = vec![];
+        let mut result = Ok(());
+        for change in changes {
+            result = self.set_tag(change.id, change.field, &change.new, write_file);
+            if result.is_err() {
+                break;
+            }
+            undo.push(change.reversed());
+        }
+        undo.reverse();
+        self.tag_undo = Some((undo, write_file));
+
+        result
+    }
+
+    /// Reverts the last bulk tag edit, returning how many tags changed back.
+    pub fn undo_tags(&mut self) -> Result<usize> {
+        self.ensure_writable("editing tags")?;
+        let (changes, write_file) = self
+            .tag_undo
+            .take()
+            .context("there is no bulk tag edit to undo")?;
+        if let Err(err) = tags::set_batch(&mut self.conn, &changes, write_file) {
+            // still there to undo once whatever stopped it is fixed
+            self.tag_undo = Some((changes, write_file));
+            return Err(err);
+        }
+        let mut ids = changes.iter().map(|change| change.id).collect::<Vec<_>>();
+        ids.dedup();
+        for id in ids {
+            self.refresh_track(id)?;
+        }
+
+        Ok(changes.len())
+    }
+
+    /// Persists the playlist so it survives a crash or a killed terminal, journalling
+    /// what changed on it since the last save. Read-only mode skips this, as the
+    /// session belongs to whoever has the library open for writing.
+    pub fn save_session(&mut self) -> Result<()> {
+        self.journal_changes();
+        if read_only() {
+            return Ok(());
+        }
+        let labels = DiscLabels {
+            title: self.burn_options.title.clone(),
+            series: self.burn_options.series.clone(),
+        };
+        session::save(&self.playlist, &self.audition, &labels, &self.ui)
+            .context("failed to autosave playlist")?;
+        self.journal
+            .save()
+            .context("failed to autosave undo journal")
+    }
+
+    fn journal_changes(&mut self) {
+        self.journal.record(&self.journaled, &self.playlist);
+        self.journaled.clone_from(&self.playlist);
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// Where the TUI was left by the previous session, or by this one so far.
+    pub fn ui(&self) -> &UiState {
+        &self.ui
+    }
+
+    /// Records where the TUI is, to be saved with the session.
+    pub fn set_ui(&mut self, ui: UiState) {
+        self.ui = ui;
+    }
+
+    /// Takes back the last playlist change, even one from before a restart, and says
+    /// what it was. Refused when an entry it brings back can't be found any more,
+    /// rather than leaving it out.
+    pub fn playlist_undo(&mut self) -> Result<String> {
+        self.journal_changes();
+        let step = self
+            .journal
+            .undo_steps()
+            .last()
+            .context("there is no playlist change to undo")?;
+        let songs = self
+            .songs_from_entries(&step.entries)
+            .with_context(|| format!("can't undo {}", step.label))?;
+        let step = self
+            .journal
+            .undo(&self.playlist)
+            .context("journal changed")?;
+        self.playlist = songs;
+        self.journaled.clone_from(&self.playlist);
+
+        Ok(step.label)
+    }
+
+    /// Makes the last undone playlist change again, and says what it was.
+    pub fn playlist_redo(&mut self) -> Result<String> {
+        self.journal_changes();
+        let step = self
+            .journal
+            .redo_steps()
+            .last()
+            .context("there is no undone playlist change to redo")?;
+        let songs = self
+            .songs_from_entries(&step.entries)
+            .with_context(|| format!("can't redo {}", step.label))?;
+        let step = self
+            .journal
+            .redo(&self.playlist)
+            .context("journal changed")?;
+        self.playlist = songs;
+        self.journaled.clone_from(&self.playlist);
+
+        Ok(step.label)
+    }
+
+    /// The tracks for journaled entries, with their edits: from the library by ID, or
+    /// read again from disk for files from outside it.
+    fn songs_from_entries(&self, entries: &[Entry]) -> Result<Vec<Song>> {
+        entries
+            .iter()
+            .map(|entry| self.song_from_entry(entry))
+            .collect()
+    }
+
+    /// The track of a saved entry with its edit, as for [`Self::songs_from_entries`].
+    fn song_from_entry(&self, entry: &Entry) -> Result<Song> {
+        let mut song = match &entry.path {
+            Some(path) => {
+                anyhow::ensure!(Path::new(path).is_file(), "{} is gone", path);
+                crate::build_db::song_from_file(Path::new(path))?
+            }
+            None => queries::track_from_id(&self.conn, entry.id)
+                .with_context(|| format!("track {} is no longer in the library", entry.id))?,
+        };
+        song.edit = entry.edit;
+        chapters::retitle(&mut song);
+
+        Ok(song)
+    }
+
+    /// Number of tracks saved by the previous session, on the playlist or in the
+    /// audition list, if it left any behind.
+    pub fn saved_session_len(&self) -> Result<usize> {
+        Ok(session::load()?.len() + session::load_audition()?.len())
+    }
+
+    /// Appends the previous session's tracks to the playlist, and takes its disc title
+    /// and series where none are set. Tracks that no longer exist or no longer fit are
+    /// skipped and reported back.
+    pub fn restore_session(&mut self) -> Result<Vec<anyhow::Error>> {
+        let labels = session::load_labels()?;
+        let options = &mut self.burn_options;
+        options.title = options.title.take().or(labels.title);
+        options.series = options.series.take().or(labels.series);
+        let mut errors = vec![];
+        for entry in session::load()? {
+            // files from outside the library are read again, or found in it if they
+            // have been scanned since
+            let added = match &entry.path {
+                Some(path) => self.playlist_add_path(Path::new(path)),
+                None => self.playlist_add_by_id(entry.id),
+            };
+            if let Err(err) = added {
+                errors.push(err);
+            } else if !entry.edit.is_empty() {
+                let index = self.playlist.len() - 1;
+                match self.playlist_edit(index, entry.edit) {
+                    Ok(()) => chapters::retitle(&mut self.playlist[index]),
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        for entry in session::load_audition()? {
+            match self.song_from_entry(&entry) {
+                Ok(song) => self.audition.push(song),
+                Err(err) => errors.push(err),
+            }
+        }
+        // picking the session back up isn't a change to undo
+        self.journaled.clone_from(&self.playlist);
+
+        Ok(errors)
+    }
+
+    /// Why each playlist entry is flagged, if it is: etiquette breaches, and files a
+    /// burn found unplayable and will skip.
+    pub fn playlist_warnings(&self) -> Result<Vec<Option<String>>> {
+        let unplayable = queries::unplayable_tracks(&self.conn)?;
+
+        Ok(self.playlist_warnings_given(&unplayable))
+    }
+
+    /// [`Self::playlist_warnings`] with the unplayable tracks already read, as
+    /// [`queries::unplayable_tracks`] lists them.
+    pub fn playlist_warnings_given(
+        &self,
+        unplayable: &HashMap<i64, String>,
+    ) -> Vec<Option<String>> {
+        let etiquette = self.config.etiquette.check(&self.playlist);
+
+        self.playlist
+            .iter()
+            .zip(etiquette)
+            .map(|(song, warning)| match unplayable.get(&song.id) {
+                Some(reason) => Some(format!("unplayable ({}), skipped when burning", reason)),
+                None => warning,
+            })
+            .collect()
+    }
+
+    /// Playlist entries that fail the quality gate, with their quality.
+    pub fn quality_gate_failures(&self) -> Result<Vec<(Song, SourceQuality)>> {
+        let gate = &self.burn_options.quality_gate;
+        let mut failures: Vec<(Song, SourceQuality)> = vec![];
+        // files from outside the library have no quality on record
+        for song in self.playlist.iter().filter(|song| song.id != 0) {
+            let quality = queries::track_quality(&self.conn, song.id)?;
+            if gate.rejects(song.id, &quality) && !failures.iter().any(|(s, _)| s.id == song.id) {
+                failures.push((song.clone(), quality));
+            }
+        }
+
+        Ok(failures)
+    }
+
+    pub fn burn(&self) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<Event>)> {
+        self.burn_as(self.burn_plan()?)
+    }
+
+    /// What the next burn would be recorded as.
+    pub fn burn_plan(&self) -> Result<BurnPlan> {
+        Ok(BurnPlan {
+            title: self.disc_title()?,
+            series: self
+                .next_volume()?
+                .map(|(series, volume)| (series.to_string(), volume)),
+            repeats: self.series_repeats()?,
+        })
+    }
+
+    /// Burns the playlist as `plan` says, so copies of a disc can all be burned as the
+    /// same volume.
+    pub fn burn_as(
+        &self,
+        plan: BurnPlan,
+    ) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<Event>)> {
+        self.ensure_writable("burning")?;
+        let (tx, rx) = self.events.task();
+        let mut playlist = self.playlist().to_vec();
+        let BurnPlan {
+            title,
+            series,
+            repeats,
+        } = plan;
+        let options = BurnOptions {
+            title: Some(title.clone()),
+            ..self.burn_options.clone()
+        };
+        let session = Arc::clone(&self.burn_session);
+        let backend = self.backend.clone();
+        let failures = self.quality_gate_failures()?;
+        let hooks = self.config.hooks.clone();
+        let handle = thread::spawn(move || -> Result<()> {
+            // kept with the burn in the history, to diagnose it after the fact
+            let (tx, transcript) = tx.transcribed();
+            for (song, quality) in &failures {
+                tx.send(
+                    LogLine {
+                        is_stderr: true,
+                        line: format!(
+                            "Low quality source [{}] \"{}\": {} (below {} kbps)",
+                            song.id,
+                            song.title,
+                            quality.describe(),
+                            options.quality_gate.min_lossy_kbps
+                        ),
+                    }
+                    .into(),
+                )
+                .context("failed to send")?;
+            }
+            for (song, volume) in &repeats {
+                tx.send(
+                    LogLine {
+                        is_stderr: true,
+                        line: format!(
+                            "Already in the series [{}] \"{}\": on volume {}",
+                            song.id, song.title, volume
+                        ),
+                    }
+                    .into(),
+                )
+                .context("failed to send")?;
+            }
+            if !failures.is_empty() && options.quality_gate.action == GateAction::Block {
+                tx.send(Err(BurnError::QualityGate(failures.len()).into()).into())
+                    .context("failed to send")?;
+                return Ok(());
+            }
+
+            let playlist_file = hooks::write_playlist(&playlist, &backend.staging_root)?;
+            let tracks = playlist.len().to_string();
+            let mut vars = vec![
+                (
+                    "PLAYLIST",
+                    playlist_file.path().to_str().unwrap_or_default(),
+                ),
+                ("DEVICE", backend.device.as_str()),
+                ("TRACKS", tracks.as_str()),
+                ("TITLE", title.as_str()),
+            ];
+            let send_output = |is_stderr: bool, lines: Vec<String>| {
+                lines
+                    .into_iter()
+                    .try_for_each(|line| tx.send(LogLine { is_stderr, line }.into()))
+            };
+            match hooks.run(Hook::PreBurn, &vars) {
+                Ok(lines) => send_output(false, lines)?,
+                Err(err) => {
+                    tx.send(Err(err).into()).context("failed to send")?;
+                    return Ok(());
+                }
+            }
+
+            let result =
+                playlist_burn(&mut playlist, options, session, backend.clone(), tx.clone())
+                    .context("failed to burn playlist");
+            let burned = result.as_ref().ok().and_then(Option::as_ref);
+            let disc_id = burned.and_then(|burned| burned.disc_id.as_ref());
+            // a failed burn is kept too, with the error that ended it
+            let mut log = transcript.text();
+            if let Err(err) = &result {
+                log.push_str(&format!("{:?}\n", err));
+            }
+            let recorded = Connection::open(paths::db())
+                .context("failed to open library database")
+                .and_then(|mut conn| {
+                    let series = series
+                        .as_ref()
+                        .map(|(series, volume)| (series.as_str(), *volume));
+                    burns::record(
+                        &mut conn,
+                        &title,
+                        series,
+                        &playlist,
+                        burned.is_some(),
+                        disc_id,
+                        &log,
+                    )
+                });
+            if let Err(err) = recorded {
+                send_output(
+                    true,
+                    vec![format!("failed to record burn history: {:?}", err)],
+                )?;
+            }
+
+            // the hook hears about failed burns too, e.g. to eject the drive
+            vars.push(("RESULT", if burned.is_some() { "ok" } else { "failed" }));
+            match hooks.run(Hook::PostBurn, &vars) {
+                Ok(lines) => send_output(false, lines)?,
+                Err(err) => send_output(true, vec![format!("{:?}", err)])?,
+            }
+
+            result.map(|_| ())
+        });
+
+        Ok((handle, rx))
+    }
+
+    /// Reads copy `copy` of the last burn back on the verify drive and compares it with
+    /// the disc set it was burned from. Runs in the background, so the next copy can
+    /// burn meanwhile.
+    pub fn verify(
+        &self,
+        copy: usize,
+    ) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<Event>)> {
+        let device = self
+            .backend
+            .verify_device
+            .clone()
+            .context("no verify_device is set in the config")?;
+        let burned = self
+            .burn_session
+            .lock()
+            .map_err(|_| anyhow::anyhow!("burn session lock was poisoned"))?
+            .disc
+            .clone();
+        anyhow::ensure!(!burned.is_empty(), "nothing has been burned to verify");
+        let (tx, rx) = self.events.task();
+        let backend = self.backend.clone();
+        let handle = thread::spawn(move || -> Result<()> {
+            tx.send(Event::BurnStage(BurnStage::Verifying))
+                .context("failed to send")?;
+            tx.send(
+                LogLine {
+                    is_stderr: false,
+                    line: format!("Reading copy {} back on {}", copy, device),
+                }
+                .into(),
+            )
+            .context("failed to send")?;
+
+            let problems = temp_dir(&backend.staging_root)
+                .context("failed to set up a directory to rip into")
+                .and_then(|dir| {
+                    let ripped = backend.ripper.rip(dir.path(), &burned)?;
+                    verify::compare(&burned, &ripped)
+                });
+            let result = match problems {
+                Ok(problems) if problems.is_empty() => Ok(format!(
+                    "Copy {} verified: all {} tracks match",
+                    copy,
+                    burned.len()
+                )),
+                Ok(problems) => {
+                    for line in problems {
+                        tx.send(
+                            LogLine {
+                                is_stderr: true,
+                                line,
+                            }
+                            .into(),
+                        )
+                        .context("failed to send")?;
+                    }
+                    Err(anyhow::anyhow!(
+                        "copy {} does not match what was burned",
+                        copy
+                    ))
+                }
+                Err(err) => Err(err.context(format!("failed to verify copy {}", copy))),
+            };
+
+            tx.send(result.into()).context("failed to send")
+        });
+
+        Ok((handle, rx))
+    }
+
+    /// The fully expanded commands a burn of the playlist would run, in order, without
+    /// running them or copying the disc set. Paths point into the session's staging
+    /// directory, or a [`STAGING_PLACEHOLDER`] one under the staging root before anything
+    /// is staged, and stages already completed are left out just as a retried burn would
+    /// skip them.
+    pub fn burn_commands(&self) -> Result<Vec<String>> {
+        if self.playlist.is_empty() {
+            return Err(BurnError::EmptyPlaylist.into());
+        }
+
+        let mut session = self
+            .burn_session
+            .lock()
+            .map_err(|_| anyhow::anyhow!("burn session lock was poisoned"))?;
+        session.prepare_for(&self.playlist, self.burn_options.normalization());
+        // nothing is created for a session that hasn't staged anything yet
+        let placeholder = self.backend.staging_root.join(STAGING_PLACEHOLDER);
+        let root = session
+            .staging
+            .as_ref()
+            .map_or(placeholder.as_path(), |staging| staging.path());
+
+        let mut commands = vec![];
+        let mut planned = HashSet::new();
+        let mut raws = vec![];
+        for song in &self.playlist {
+            let name = staging::raw_name(song);
+            let raw = root.join(&name);
+            if !session.transcoded.contains_key(&name) && planned.insert(name) {
+                commands.push(self.backend.throttle.wrap(backend::transcode_command(
+                    song,
+                    &self.backend.presets,
+                    &raw,
+                )));
+            }
+            raws.push(raw);
+        }
+
+        let disc = staging::disc_paths(root, &raws);
+        if session.normalized.is_none() {
+            let (profile, mode) = self.burn_options.normalization();
+            commands.extend(profile.commands(
+                mode,
+                &staging::disc_dir(root),
+                &disc,
+                &self.backend.throttle,
+            ));
+        }
+
+        commands.push(backend::wodim_command(
+            &self.backend.device,
+            root,
+            &disc,
+            self.burn_options.speed,
+        ));
+
+        Ok(commands.iter().map(backend::command_line).collect())
+    }
+
+    /// Measures the loudness of every entry as staged, before normalization, and
+    /// reports it as a table. The transcodes are kept for the next burn.
+    pub fn loudness_report(
+        &self,
+    ) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<Event>)> {
+        let (tx, rx) = self.events.task();
+        let playlist = self.playlist().to_vec();
+        let normalization = self.burn_options.normalization();
+        let session = Arc::clone(&self.burn_session);
+        let backend = self.backend.clone();
+        let handle = thread::spawn(move || -> Result<()> {
+            playlist_loudness(playlist, normalization, session, backend, tx)
+                .context("failed to measure playlist loudness")
+        });
+
+        Ok((handle, rx))
+    }
+
+    /// Burns `image` in the background, reporting progress like [`Self::burn`]. The
+    /// burn history only keeps playlists, so it isn't recorded there.
+    pub fn burn_image(
+        &self,
+        image: Image,
+    ) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<Event>)> {
+        self.ensure_writable("burning")?;
+        let (tx, rx) = self.events.task();
+        let (speed, cdtext) = (self.burn_options.speed, self.burn_options.cdtext);
+        let backend = self.backend.clone();
+        let handle = thread::spawn(move || -> Result<()> {
+            image_burn(image, speed, cdtext, backend, tx).context("failed to burn image")
+        });
+
+        Ok((handle, rx))
+    }
+
+    /// Runs [`playlist_mixdown`] in the background, reporting progress like [`Self::burn`].
+    pub fn mixdown(
+        &self,
+        output: PathBuf,
+    ) -> Result<(thread::JoinHandle<Result<()>>, mpsc::Receiver<Event>)> {
+        let (tx, rx) = self.events.task();
+        let playlist = self.playlist().to_vec();
+        let options = self.titled_burn_options()?;
+        let session = Arc::clone(&self.burn_session);
+        let backend = self.backend.clone();
+        let handle = thread::spawn(move || -> Result<()> {
+            playlist_mixdown(playlist, options, session, backend, output, tx)
+                .context("failed to mix down playlist")
+        });
+
+        Ok((handle, rx))
+    }
+}
+
+/// Staged audio and per-stage progress for the current playlist. It outlives a single
+/// burn attempt so that a failed burn (wrong disc, open tray) can be retried without
+/// transcoding and normalizing everything again, and is saved to
+/// [`paths::burn_progress`] with the staging directory kept so a retry after quitting
+/// resumes too.
+#[derive(Default)]
+pub struct BurnSession {
+    staging: Option<StagingDir>,
+    /// The playlist the disc set was staged for.
+    entries: Vec<Entry>,
+    /// Raw transcodes by [`staging::raw_name`]. These survive playlist changes until the
+    /// next staging, so a track removed and added back is not transcoded again, but the
+    /// ones the playlist no longer has are deleted then, as staging is often in memory.
+    transcoded: HashMap<String, PathBuf>,
+    /// Profile and mode the disc set was normalized with, if it has been.
+    normalized: Option<(NormalizeProfile, NormalizeMode)>,
+    /// The disc set last staged for a burn, to check burned copies against.
+    disc: Vec<PathBuf>,
+}
+
+impl BurnSession {
+    /// Keeps the normalized disc set if it was made for exactly this playlist, profile
+    /// and mode. Album gain is worked out across the whole set, so any change to these
+    /// means normalizing again; raw transcodes are always kept.
+    fn prepare_for(&mut self, playlist: &[Song], normalization: (NormalizeProfile, NormalizeMode)) {
+        let entries = playlist.iter().map(Entry::of).collect::<Vec<_>>();
+        let other_profile = self.normalized.is_some_and(|done| done != normalization);
+        if entries != self.entries || other_profile {
+            self.entries = entries;
+            self.normalized = None;
+            self.disc.clear();
+        }
+    }
+
+    /// The progress saved by an earlier run, if its staging directory is still there,
+    /// or a fresh session. Staged files that have gone since are staged again.
+    fn resume() -> BurnSession {
+        // a missing or unreadable file just means starting over
+        let Ok(contents) = std::fs::read_to_string(paths::burn_progress()) else {
+            return BurnSession::default();
+        };
+        let mut session = BurnSession::default();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            match key {
+                "staging" => {
+                    session.staging = Some(StagingDir {
+                        path: PathBuf::from(value),
+                        registered: None,
+                    })
+                }
+                "entry" => session.entries.extend(session::parse_entry(value).ok()),
+                "normalized" => {
+                    session.normalized = value.split_once(' ').and_then(|(profile, mode)| {
+                        Some((profile.parse().ok()?, mode.parse().ok()?))
+                    })
+                }
+                "transcoded" => {
+                    if let Some((name, path)) = value.split_once(' ') {
+                        session
+                            .transcoded
+                            .insert(name.to_string(), PathBuf::from(path));
+                    }
+                }
+                "disc" => session.disc.push(PathBuf::from(value)),
+                _ => (),
+            }
+        }
+
+        if !session
+            .staging
+            .as_ref()
+            .is_some_and(|dir| dir.path().is_dir())
+        {
+            return BurnSession::default();
+        }
+        session.transcoded.retain(|_, path| path.is_file());
+        if !session.disc.iter().all(|wav| wav.is_file()) {
+            session.normalized = None;
+            session.disc.clear();
+        }
+
+        session
+    }
+
+    /// Saves how far staging got to [`paths::burn_progress`], keeping the staging
+    /// directory for the next run to resume from.
+    fn save(&mut self) -> Result<()> {
+        if read_only() {
+            return Ok(());
+        }
+        let Some(staging) = self.staging.as_mut() else {
+            return Ok(());
+        };
+        staging.keep();
+
+        let mut contents = format!("staging {}\n", staging.path().display());
+        for entry in &self.entries {
+            contents += &format!("entry {}\n", session::format_entry(entry));
+        }
+        if let Some((profile, mode)) = self.normalized {
+            contents += &format!("normalized {} {}\n", profile.name(), mode.name());
+        }
+        for (name, path) in &self.transcoded {
+            contents += &format!("transcoded {} {}\n", name, path.display());
+        }
+        for wav in &self.disc {
+            contents += &format!("disc {}\n", wav.display());
+        }
+
+        let path = paths::burn_progress();
+        let tmp_path = path.with_extension("progress.tmp");
+        shutdown::critical(|| {
+            std::fs::write(&tmp_path, contents)
+                .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+            std::fs::rename(&tmp_path, &path)
+                .with_context(|| format!("failed to replace {}", path.display()))
+        })
+    }
+}
+
+/// Saves the session's progress, warning instead of failing if it can't.
+fn save_progress(session: &mut BurnSession, msgs: &Emitter) -> Result<()> {
+    if let Err(err) = session.save() {
+        msgs.send(
+            LogLine {
+                is_stderr: true,
+                line: format!("failed to save burn progress: {:?}", err),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+    }
+
+    Ok(())
+}
+
+/// What a burn is recorded as in the history, settled before it starts.
+#[derive(Debug, Clone)]
+pub struct BurnPlan {
+    pub title: String,
+    /// Series and volume number, if the playlist is in a series.
+    pub series: Option<(String, u32)>,
+    /// Entries already on an earlier volume of the series, with the first volume.
+    pub repeats: Vec<(Song, u32)>,
+}
+
+/// Records how long a completed stage took, warning instead of failing if it can't.
+fn record_throughput(
+    history: Option<&Throughput>,
+    stage: BurnStage,
+    audio_secs: u64,
+    started: Instant,
+    msgs: &Emitter,
+) -> Result<()> {
+    let Some(history) = history else {
+        return Ok(());
+    };
+    if let Err(err) = history.record(stage, audio_secs, started.elapsed()) {
+        msgs.send(
+            LogLine {
+                is_stderr: true,
+                line: format!("{:#}", err),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+    }
+
+    Ok(())
+}
+
+/// Staged files by [`staging::raw_name`].
+type StagedFiles = Vec<(String, PathBuf)>;
+/// Tracks by [`staging::raw_name`] that can't be staged, with the reason.
+type UnplayableFiles = Vec<(String, &'static str)>;
+
+/// Transcodes `pending` tracks into `root`, running as many at once as the throttle
+/// allows. Workers take the tracks in the order given, which is playlist order, so the
+/// first tracks of the disc are the first ready. Returns the staged files by name, the
+/// ones whose source turned out to be unplayable, and the first other failure if there
+/// was one, after which no new transcodes are started.
+fn transcode_pending(
+    pending: &[(&Song, &String)],
+    root: &Path,
+    backend: &Backend,
+    rate: Option<f64>,
+    msgs: &Emitter,
+) -> Result<(StagedFiles, UnplayableFiles, Option<TranscodeError>)> {
+    use std::sync::atomic::AtomicUsize;
+
+    let next = AtomicUsize::new(0);
+    let finished = Mutex::new(vec![]);
+    let unplayable = Mutex::new(vec![]);
+    let failure = Mutex::new(None);
+    thread::scope(|scope| -> Result<()> {
+        let workers = (0..backend.throttle.jobs.min(pending.len()))
+            .map(|_| {
+                scope.spawn(|| -> Result<()> {
+                    loop {
+                        let done = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((song, name)) = pending.get(done) else {
+                            return Ok(());
+                        };
+                        if failure.lock().is_ok_and(|failure| failure.is_some()) {
+                            return Ok(());
+                        }
+
+                        msgs.send(Event::TranscodeProgress {
+                            done,
+                            total: pending.len(),
+                            title: song.title.clone(),
+                        })
+                        .context("failed to send")?;
+                        let remaining = pending[done..]
+                            .iter()
+                            .map(|(song, _)| song.played_duration_secs())
+                            .sum();
+                        if let Some(remaining_secs) = throughput::eta_secs(rate, remaining) {
+                            msgs.send(Event::Eta {
+                                stage: BurnStage::Transcoding,
+                                remaining_secs,
+                            })
+                            .context("failed to send")?;
+                        }
+
+                        let output_path = root.join(name);
+                        let result = backend.transcoder.transcode(song, &output_path);
+                        // ffmpeg gets through some broken files without a single sample
+                        let silent =
+                            result.is_ok() && !staging::has_audio(&output_path).unwrap_or(true);
+                        let (Ok(mut finished), Ok(mut unplayable), Ok(mut failure)) =
+                            (finished.lock(), unplayable.lock(), failure.lock())
+                        else {
+                            anyhow::bail!("transcode worker panicked");
+                        };
+                        match result {
+                            Ok(()) if silent => unplayable.push(((*name).clone(), "zero duration")),
+                            Ok(()) => finished.push(((*name).clone(), output_path)),
+                            Err(err) => match err.unplayable_reason() {
+                                Some(reason) => unplayable.push(((*name).clone(), reason)),
+                                None => {
+                                    failure.get_or_insert(err);
+                                }
+                            },
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            match worker.join() {
+                Ok(result) => result?,
+                Err(_) => anyhow::bail!("transcode worker panicked"),
+            }
+        }
+
+        Ok(())
+    })?;
+
+    let finished = finished
+        .into_inner()
+        .map_err(|_| anyhow::anyhow!("transcode worker panicked"))?;
+    let unplayable = unplayable
+        .into_inner()
+        .map_err(|_| anyhow::anyhow!("transcode worker panicked"))?;
+    let failure = failure
+        .into_inner()
+        .map_err(|_| anyhow::anyhow!("transcode worker panicked"))?;
+
+    Ok((finished, unplayable, failure))
+}
+
+/// Brings the library's unplayable marks in line with what staging found: set for
+/// `unplayable` tracks, cleared for `staged` ones that have since been fixed. Left
+/// alone in read-only mode.
+fn mark_unplayable(staged: &[i64], unplayable: &[(i64, &str)]) -> Result<()> {
+    if read_only() || (staged.is_empty() && unplayable.is_empty()) {
+        return Ok(());
+    }
+    let conn = Connection::open(paths::db()).context("failed to open library database")?;
+    let marked = queries::unplayable_tracks(&conn)?;
+    for &id in staged.iter().filter(|id| marked.contains_key(id)) {
+        queries::set_unplayable(&conn, id, None)?;
+    }
+    for &(id, reason) in unplayable {
+        queries::set_unplayable(&conn, id, Some(reason))?;
+    }
+
+    Ok(())
+}
+
+/// Transcodes and normalizes the playlist into the session's staging directory,
+/// skipping stages already completed for this playlist. Returns the staged WAVs in
+/// playlist order, or `None` once a failure has been reported through `msgs`.
+/// Unplayable entries are dropped from `playlist`, see [`transcode_playlist`].
+fn stage_playlist(
+    playlist: &mut Vec<Song>,
+    options: &BurnOptions,
+    session: &mut BurnSession,
+    backend: &Backend,
+    msgs: &Emitter,
+) -> Result<Option<Vec<PathBuf>>> {
+    if playlist.is_empty() {
+        msgs.send(Err(BurnError::EmptyPlaylist.into()).into())
+            .context("failed to send")?;
+        return Ok(None);
+    }
+
+    let (profile, mode) = options.normalization();
+    session.prepare_for(playlist, (profile, mode));
+    let raws = transcode_playlist(playlist, session, backend, msgs)?;
+    // what did get transcoded is kept for a retry, even after quitting
+    save_progress(session, msgs)?;
+    let Some(raws) = raws else {
+        return Ok(None);
+    };
+    // SAFETY: transcoding succeeded, so the staging directory exists
+    let root = session.staging.as_ref().unwrap().path();
+
+    let disc = if session.normalized.is_some() {
+        msgs.send(
+            LogLine {
+                is_stderr: false,
+                line: String::from("Playlist already normalized, resuming at burn"),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+        staging::disc_paths(root, &raws)
+    } else {
+        msgs.send(Event::BurnStage(BurnStage::Normalizing))
+            .context("failed to send")?;
+        let disc = staging::copy_disc_set(root, &raws)?;
+
+        if let Err(err) =
+            backend
+                .transcoder
+                .normalize(profile, mode, &staging::disc_dir(root), &disc)
+        {
+            msgs.send(Err(err.context("Audio normalization failed.")).into())
+                .context("failed to send")?;
+            return Ok(None);
+        }
+        session.normalized = Some((profile, mode));
+
+        msgs.send(
+            LogLine {
+                is_stderr: false,
+                line: format!(
+                    "Normalized playlist volume ({:?} profile, {} gain)",
+                    profile,
+                    mode.name()
+                ),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+        disc
+    };
+
+    // CD-TEXT settings may have changed since the set was staged
+    let disc_title = options.title.as_deref().unwrap_or_default();
+    for (song, wav) in playlist.iter().zip(&disc) {
+        cdtext::write_track_inf(wav, song, disc_title, options.cdtext)?;
+    }
+    staging::write_manifest(root, playlist, &disc)?;
+    session.disc = disc.clone();
+    save_progress(session, msgs)?;
+
+    Ok(Some(disc))
+}
+
+/// Transcodes whatever of `playlist` isn't staged yet into the session's staging
+/// directory. Entries whose file is DRM protected, undecodable or empty are dropped
+/// from `playlist` with a warning and marked unplayable in the library. Returns the
+/// raw WAV of every remaining entry in playlist order, or `None` once a failure has
+/// been reported through `msgs`.
+fn transcode_playlist(
+    playlist: &mut Vec<Song>,
+    session: &mut BurnSession,
+    backend: &Backend,
+    msgs: &Emitter,
+) -> Result<Option<Vec<PathBuf>>> {
+    let BurnSession {
+        staging,
+        transcoded,
+        ..
+    } = session;
+
+    if staging.is_none() {
+        match temp_dir(&backend.staging_root) {
+            Ok(dir) => *staging = Some(dir),
+            Err(source) => {
+                let dir = backend.staging_root.clone();
+                msgs.send(Err(BurnError::Staging { dir, source }.into()).into())
+                    .context("failed to send")?;
+                return Ok(None);
+            }
+        }
+    }
+    // SAFETY: populated just above
+    let root = staging.as_ref().unwrap().path();
+
+    msgs.send(Event::BurnStage(BurnStage::Transcoding))
+        .context("failed to send")?;
+    let names = playlist.iter().map(staging::raw_name).collect::<Vec<_>>();
+    transcoded.retain(|name, path| {
+        let wanted = names.contains(name);
+        if !wanted {
+            let _ = std::fs::remove_file(path);
+        }
+        wanted
+    });
+    // a track in the playlist twice only needs transcoding once, where it first appears
+    let mut pending: Vec<(&Song, &String)> = vec![];
+    for (song, name) in playlist.iter().zip(&names) {
+        if !transcoded.contains_key(name) && !pending.iter().any(|(_, n)| *n == name) {
+            pending.push((song, name));
+        }
+    }
+
+    // 3. Transcode and Downsample (FFmpeg)
+    let history = Throughput::open().ok();
+    let rate = history
+        .as_ref()
+        .and_then(|history| history.rate(BurnStage::Transcoding).ok().flatten());
+    let started = Instant::now();
+    let (finished, unplayable, failure) = transcode_pending(&pending, root, backend, rate, msgs)?;
+    // keep what did finish so a retry picks up where this left off
+    transcoded.extend(finished);
+    if let Some(err) = failure {
+        msgs.send(Err(err.into()).into())
+            .context("failed to send")?;
+        return Ok(None);
+    }
+
+    let mut marks = vec![];
+    for (song, name) in &pending {
+        let Some((_, reason)) = unplayable.iter().find(|(n, _)| n == *name) else {
+            continue;
+        };
+        msgs.send(
+            LogLine {
+                is_stderr: true,
+                line: format!(
+                    "Skipping unplayable [{}] \"{}\": {} ({})",
+                    song.id, song.title, reason, song.path
+                ),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+        marks.push((song.id, *reason));
+    }
+    let staged = pending
+        .iter()
+        .filter(|(_, name)| transcoded.contains_key(*name))
+        .map(|(song, _)| song.id)
+        .collect::<Vec<_>>();
+    if let Err(err) = mark_unplayable(&staged, &marks) {
+        msgs.send(
+            LogLine {
+                is_stderr: true,
+                line: format!("failed to mark unplayable tracks: {:?}", err),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+    }
+    let transcoded_secs = pending
+        .iter()
+        .filter(|(_, name)| transcoded.contains_key(*name))
+        .map(|(s, _)| s.played_duration_secs())
+        .sum();
+    record_throughput(
+        history.as_ref(),
+        BurnStage::Transcoding,
+        transcoded_secs,
+        started,
+        msgs,
+    )?;
+
+    // whatever isn't staged by now is unplayable
+    let mut staged_entries = names.iter().map(|name| transcoded.contains_key(name));
+    playlist.retain(|_| staged_entries.next().unwrap_or(false));
+    if playlist.is_empty() {
+        msgs.send(Err(anyhow::anyhow!("every track in the playlist is unplayable")).into())
+            .context("failed to send")?;
+        return Ok(None);
+    }
+    let raws = names
+        .iter()
+        .filter_map(|name| transcoded.get(name).cloned())
+        .collect::<Vec<_>>();
+
+    Ok(Some(raws))
+}
+
+/// Executes the final normalization and burning pipeline. Failures are reported as
+/// events; the result is the disc burned, or `None` when none came out.
+/// Unplayable entries are dropped from `playlist`, leaving the tracks that went on the
+/// disc.
+// - Downsample + decompress music
+// - Normalize
+// - Burn to CD
+pub fn playlist_burn(
+    playlist: &mut Vec<Song>,
+    options: BurnOptions,
+    session: Arc<Mutex<BurnSession>>,
+    backend: Backend,
+    msgs: Emitter,
+) -> Result<Option<Burned>> {
+    let mut session = session
+        .lock()
+        .map_err(|_| anyhow::anyhow!("burn session lock was poisoned"))?;
+    let Some(playlist_files) = stage_playlist(playlist, &options, &mut session, &backend, &msgs)?
+    else {
+        return Ok(None);
+    };
+    // SAFETY: staging succeeded, so the staging directory exists
+    let temp_dir = session.staging.as_ref().unwrap();
+
+    burn_staged(
+        temp_dir.path(),
+        &playlist_files,
+        playlist_duration_secs(playlist),
+        options.speed,
+        &backend,
+        &msgs,
+    )
+}
+
+/// A disc that came out of a burn.
+pub struct Burned {
+    /// Its disc ID, unless the staged tracks could not be measured for one.
+    pub disc_id: Option<DiscId>,
+}
+
+/// Burns the staged `wavs` in `dir`, `audio_secs` long in all, reporting progress and
+/// failures through `msgs`. Returns the disc burned, or `None` when the burn failed.
+fn burn_staged(
+    dir: &Path,
+    wavs: &[PathBuf],
+    audio_secs: u64,
+    speed: Option<u32>,
+    backend: &Backend,
+    msgs: &Emitter,
+) -> Result<Option<Burned>> {
+    // the disc is worth burning without an ID, which only helps players look it up
+    let disc_id = match DiscId::from_wavs(wavs) {
+        Ok(disc_id) => Some(disc_id),
+        Err(err) => {
+            msgs.send(
+                LogLine {
+                    is_stderr: true,
+                    line: format!("No disc ID for this disc: {:#}", err),
+                }
+                .into(),
+            )
+            .context("failed to send")?;
+            None
+        }
+    };
+    msgs.send(Event::BurnStage(BurnStage::Burning))
+        .context("failed to send")?;
+    let history = Throughput::open().ok();
+    let rate = history
+        .as_ref()
+        .and_then(|history| history.rate(BurnStage::Burning).ok().flatten());
+    if let Some(remaining_secs) = throughput::eta_secs(rate, audio_secs) {
+        msgs.send(Event::Eta {
+            stage: BurnStage::Burning,
+            remaining_secs,
+        })
+        .context("failed to send")?;
+    }
+    let started = Instant::now();
 
-    let (stdout, stderr) = (BufReader::new(stdout), BufReader::new(stderr));
+    // tracks are weighed by size for how much of the disc is written
+    let sizes = wavs
+        .iter()
+        .map(|wav| std::fs::metadata(wav).map_or(0, |meta| meta.len()))
+        .collect::<Vec<_>>();
+    let disc_bytes = sizes.iter().sum::<u64>().max(1);
+    let percent = AtomicU8::new(0);
 
-    let mut handles = vec![];
-    let stdout_sender = msgs.clone();
-    handles.push(thread::spawn(move || -> Result<()> {
-        for line in stdout.lines() {
-            let line = line.context("failed to obtain line from stdout")?;
-            stdout_sender
-                .send(
-                    LogLine {
-                        is_stderr: false,
-                        line,
-                    }
-                    .into(),
-                )
-                .context("failed to send")?;
+    let underrun = AtomicBool::new(false);
+    let result = backend.burner.burn(dir, wavs, speed, &|is_stderr, line| {
+        if wodim::is_underrun(&line) {
+            underrun.store(true, Ordering::Relaxed);
         }
-        Ok(())
-    }));
-
-    let stderr_sender = msgs.clone();
-    handles.push(thread::spawn(move || -> Result<()> {
-        for line in stderr.lines() {
-            let line = line.context("failed to obtain line from stderr")?;
-            stderr_sender
-                .send(
-                    LogLine {
-                        is_stderr: false,
-                        line,
-                    }
-                    .into(),
-                )
-                .context("failed to send")?;
+        if let Some((track, written, total)) = wodim::parse_written(&line)
+            && let Some(size) = sizes.get(track.wrapping_sub(1))
+        {
+            let before = sizes[..track - 1].iter().sum::<u64>();
+            let now = ((before + size * written / total.max(1)) * 100 / disc_bytes) as u8;
+            if percent.swap(now, Ordering::Relaxed) != now {
+                msgs.send(Event::BurnProgress { percent: now })
+                    .context("failed to send")?;
+            }
         }
-        Ok(())
-    }));
+        let msg = match wodim::parse_buffer_status(&line) {
+            Some(status) => Event::Buffer(status),
+            None => LogLine { is_stderr, line }.into(),
+        };
+        msgs.send(msg).context("failed to send")
+    });
+
+    if underrun.load(Ordering::Relaxed) {
+        msgs.send(
+            LogLine {
+                is_stderr: true,
+                line: format!(
+                    "Buffer underruns detected. Try burning again at a lower speed, e.g. speed={}",
+                    wodim::recommended_speed(speed)
+                ),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+    }
+
+    if let Err(err) = result {
+        msgs.send(Err(err).into()).context("failed to send")?;
+        return Ok(None);
+    }
+    record_throughput(
+        history.as_ref(),
+        BurnStage::Burning,
+        audio_secs,
+        started,
+        msgs,
+    )?;
+
+    if let Some(disc_id) = &disc_id {
+        msgs.send(
+            LogLine {
+                is_stderr: false,
+                line: format!(
+                    "Disc ID {} (freedb {})",
+                    disc_id.musicbrainz, disc_id.freedb
+                ),
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+    }
+    msgs.send(Ok(String::from("CD Burning Complete. Disc ejected.")).into())
+        .context("failed to send")?;
+
+    Ok(Some(Burned { disc_id }))
+}
+
+/// Burns a disc image as it is: each track is cut from the image's audio into the
+/// disc format and burned with CD-TEXT from the cue sheet, without normalizing, as the
+/// image was made from a finished disc set. Failures are reported through `msgs`.
+pub fn image_burn(
+    image: Image,
+    speed: Option<u32>,
+    cdtext: CdTextMode,
+    backend: Backend,
+    msgs: Emitter,
+) -> Result<()> {
+    let staging = match temp_dir(&backend.staging_root) {
+        Ok(dir) => dir,
+        Err(source) => {
+            let dir = backend.staging_root.clone();
+            return msgs
+                .send(Err(BurnError::Staging { dir, source }.into()).into())
+                .context("failed to send");
+        }
+    };
+    let root = staging.path();
+
+    msgs.send(Event::BurnStage(BurnStage::Transcoding))
+        .context("failed to send")?;
+    let names = image
+        .tracks
+        .iter()
+        .map(staging::raw_name)
+        .collect::<Vec<_>>();
+    let pending = image.tracks.iter().zip(&names).collect::<Vec<_>>();
+    let (finished, unplayable, failure) = transcode_pending(&pending, root, &backend, None, &msgs)?;
+    if let Some(err) = failure {
+        return msgs.send(Err(err.into()).into()).context("failed to send");
+    }
+    if let Some((name, reason)) = unplayable.first() {
+        let err = anyhow::anyhow!("track {} of the image is unplayable: {}", name, reason);
+        return msgs.send(Err(err).into()).context("failed to send");
+    }
+
+    let wavs = names
+        .iter()
+        .filter_map(|name| finished.iter().find(|(n, _)| n == name))
+        .map(|(_, wav)| wav.clone())
+        .collect::<Vec<_>>();
+    for (song, wav) in image.tracks.iter().zip(&wavs) {
+        cdtext::write_track_inf(wav, song, &image.title, cdtext)?;
+    }
+    burn_staged(
+        root,
+        &wavs,
+        playlist_duration_secs(&image.tracks),
+        speed,
+        &backend,
+        &msgs,
+    )?;
+
+    Ok(())
+}
 
-    let status = wodim.wait().context("failed to wait for wodim to exit")?;
+/// Transcodes the playlist like a burn and measures each track's loudness, reporting
+/// the table for the burn's `normalization` and any failure through `msgs`.
+pub fn playlist_loudness(
+    mut playlist: Vec<Song>,
+    normalization: (NormalizeProfile, NormalizeMode),
+    session: Arc<Mutex<BurnSession>>,
+    backend: Backend,
+    msgs: Emitter,
+) -> Result<()> {
+    if playlist.is_empty() {
+        return msgs
+            .send(Err(BurnError::EmptyPlaylist.into()).into())
+            .context("failed to send");
+    }
+    let mut session = session
+        .lock()
+        .map_err(|_| anyhow::anyhow!("burn session lock was poisoned"))?;
+    let Some(raws) = transcode_playlist(&mut playlist, &mut session, &backend, &msgs)? else {
+        return Ok(());
+    };
+    drop(session);
+
+    let mut measured = vec![];
+    for (song, wav) in playlist.iter().zip(&raws) {
+        match backend.transcoder.loudness(wav) {
+            Ok(loudness) => measured.push(loudness),
+            Err(err) => {
+                let err = err.context(format!("failed to measure \"{}\"", song.title));
+                return msgs.send(Err(err).into()).context("failed to send");
+            }
+        }
+    }
+
+    for line in loudness::report(&playlist, &measured, normalization) {
+        msgs.send(
+            LogLine {
+                is_stderr: false,
+                line,
+            }
+            .into(),
+        )
+        .context("failed to send")?;
+    }
+    msgs.send(Ok(format!("Measured {} tracks", playlist.len())).into())
+        .context("failed to send")
+}
+
+/// Stages the playlist like a burn, then joins the normalized WAVs into a single file
+/// with a cue sheet next to it, for listening to the mix away from a CD player.
+pub fn playlist_mixdown(
+    mut playlist: Vec<Song>,
+    options: BurnOptions,
+    session: Arc<Mutex<BurnSession>>,
+    backend: Backend,
+    output: PathBuf,
+    msgs: Emitter,
+) -> Result<()> {
+    use std::process::Stdio;
+
+    let mut session = session
+        .lock()
+        .map_err(|_| anyhow::anyhow!("burn session lock was poisoned"))?;
+    let Some(wavs) = stage_playlist(&mut playlist, &options, &mut session, &backend, &msgs)? else {
+        return Ok(());
+    };
+
+    msgs.send(Event::BurnStage(BurnStage::MixingDown))
+        .context("failed to send")?;
+    msgs.send(
+        LogLine {
+            is_stderr: false,
+            line: format!("Mixing down to {}", output.display()),
+        }
+        .into(),
+    )
+    .context("failed to send")?;
 
+    let joins = mixdown::joins(&playlist, &options);
+    if !options.gap_runs {
+        for run in mixdown::runs(&playlist) {
+            msgs.send(
+                LogLine {
+                    is_stderr: false,
+                    line: format!(
+                        "Joining tracks {}-{} without a gap, as they run on from \"{}\"",
+                        run.start + 1,
+                        run.end,
+                        playlist[run.start].album
+                    ),
+                }
+                .into(),
+            )
+            .context("failed to send")?;
+        }
+    }
+    let status = shutdown::status(
+        mixdown::ffmpeg_command(&wavs, &joins, &output)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+    )
+    .context("failed to run ffmpeg for mixdown")?;
     if !status.success() {
-        msgs.send(Err(anyhow::anyhow!("failed to burn playlist")).into())
+        msgs.send(Err(anyhow::anyhow!("ffmpeg failed to mix down the playlist")).into())
             .context("failed to send")?;
         return Ok(());
     }
 
-    msgs.send(Ok(String::from("✅ CD Burning Complete. Disc ejected.")).into())
-        .context("failed to send")?;
+    let cue_path = output.with_extension("cue");
+    let samples = wavs
+        .iter()
+        .map(|wav| staging::samples(wav))
+        .collect::<Result<Vec<_>>>()?;
+    let cue = mixdown::cue_sheet(
+        &playlist,
+        &samples,
+        options.title.as_deref().unwrap_or_default(),
+        &joins,
+        &output,
+    );
+    std::fs::write(&cue_path, cue)
+        .with_context(|| format!("failed to write cue sheet {}", cue_path.display()))?;
+
+    msgs.send(
+        Ok(format!(
+            "Mixdown written to {} with cue sheet {}",
+            output.display(),
+            cue_path.display()
+        ))
+        .into(),
+    )
+    .context("failed to send")?;
+
+    Ok(())
+}
+
+/// File format of exported tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Copy the source files untouched.
+    Original,
+    Mp3,
+    Flac,
+}
+
+impl ExportFormat {
+    /// Extension written for `song` in this format.
+    fn extension<'a>(&self, song: &'a Song) -> &'a str {
+        match self {
+            ExportFormat::Original => song.format(),
+            ExportFormat::Mp3 => "mp3",
+            ExportFormat::Flac => "flac",
+        }
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "original" => Ok(ExportFormat::Original),
+            "mp3" => Ok(ExportFormat::Mp3),
+            "flac" => Ok(ExportFormat::Flac),
+            _ => anyhow::bail!(
+                "unknown export format \"{}\": expected one of mp3 / flac / original",
+                s
+            ),
+        }
+    }
+}
+
+/// Copies or transcodes the playlist into a folder of `dir` named after disc `title`
+/// using a filename template, for MP3 data discs, USB sticks and phones, and writes a
+/// matching `playlist.m3u`.
+pub fn playlist_export(
+    playlist: &[Song],
+    dir: &Path,
+    title: &str,
+    template: &str,
+    format: ExportFormat,
+    disc: u32,
+) -> Result<PathBuf> {
+    use std::process::Command;
+
+    naming::ensure_numbered(template)?;
+    let dir = &dir.join(naming::sanitize(title));
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create export directory {}", dir.display()))?;
+
+    let mut m3u = String::from("#EXTM3U\n");
+    for (i, song) in playlist.iter().enumerate() {
+        let name = naming::render(
+            template,
+            &NameContext {
+                disc,
+                track: i + 1,
+                song,
+                ext: format.extension(song),
+            },
+        )?;
+        let target = dir.join(&name);
+
+        // a source already in the target format is copied rather than re-encoded
+        if format == ExportFormat::Original || song.format() == format.extension(song) {
+            std::fs::copy(&song.path, &target)
+                .with_context(|| format!("failed to copy {} to {}", song.path, target.display()))?;
+        } else {
+            let mut ffmpeg = Command::new("ffmpeg");
+            ffmpeg
+                .arg("-v")
+                .arg("error")
+                .arg("-y")
+                .arg("-i")
+                .arg(&song.path);
+            match format {
+                ExportFormat::Mp3 => ffmpeg.args(["-codec:a", "libmp3lame", "-q:a", "0"]),
+                ExportFormat::Flac => ffmpeg.args(["-codec:a", "flac"]),
+                ExportFormat::Original => unreachable!("original files are copied"),
+            };
+            TranscodeError::run("ffmpeg", Path::new(&song.path), ffmpeg.arg(&target))?;
+        }
+
+        m3u += &format!(
+            "#EXTINF:{},{} - {}\n{}\n",
+            song.played_duration_secs(),
+            song.artist,
+            song.title,
+            name
+        );
+    }
+
+    let m3u_path = dir.join("playlist.m3u");
+    std::fs::write(&m3u_path, m3u)
+        .with_context(|| format!("failed to write {}", m3u_path.display()))?;
 
-    for handle in handles {
-        if let Err(_) = handle.join() {
-            anyhow::bail!("pipe failed");
+    Ok(m3u_path)
+}
+
+/// How a failed command's error is shown, followed by how to fix it when we know.
+pub fn error_lines(err: &anyhow::Error) -> Vec<String> {
+    let mut lines = vec![format!("{} {:?}", style::error_label(), err)];
+    if let Some(hint) = errors::hint(err) {
+        lines.push(format!("{} {}", style::hint_label(), hint));
+    }
+
+    lines
+}
+
+/// Takes an error back out of an [`Event::Error`], keeping its type when nobody else
+/// holds on to it so exit codes and hints still apply.
+fn unwrap_error(err: Arc<anyhow::Error>) -> anyhow::Error {
+    Arc::try_unwrap(err).unwrap_or_else(|err| anyhow::anyhow!("{:?}", err))
+}
+
+/// Prints a background task's events as JSON lines until it finishes, failing if the
+/// task did.
+fn print_json_events(
+    handle: thread::JoinHandle<Result<()>>,
+    rx: mpsc::Receiver<Event>,
+) -> Result<()> {
+    let mut failure = None;
+    while let Ok(event) = rx.recv() {
+        println!("{}", event.to_json());
+        if let Event::Error(err) = event {
+            failure = Some(err);
+        }
+    }
+
+    match handle.join() {
+        Ok(result) => result?,
+        Err(_) => anyhow::bail!("failed to join on background task thread"),
+    }
+
+    failure.map_or(Ok(()), |err| Err(unwrap_error(err)))
+}
+
+/// Burns the tracks `ids`, or the saved session when none are given, without asking
+/// anything. Progress is printed as JSON lines with `json`, or as the shell would.
+pub fn run_burn(ids: Vec<i64>, json: bool) -> Result<()> {
+    burn_playlist(
+        |state| {
+            if ids.is_empty() {
+                if let Some(err) = state.restore_session()?.into_iter().next() {
+                    return Err(err.context("failed to restore the saved session"));
+                }
+            } else {
+                for &id in &ids {
+                    state.playlist_add_by_id(id)?;
+                }
+            }
+
+            Ok(())
+        },
+        json,
+    )
+}
+
+/// Burns the entries of the M3U playlist at `path` in one go, titled after the file,
+/// for playlists curated in another player. Entries the library doesn't have are read
+/// straight from disk.
+pub fn run_burn_m3u(path: &Path, json: bool) -> Result<()> {
+    burn_playlist(
+        |state| {
+            for entry in read_m3u(path)? {
+                state
+                    .playlist_add_path(&entry)
+                    .with_context(|| format!("failed to add {}", entry.display()))?;
+            }
+            anyhow::ensure!(
+                !state.playlist.is_empty(),
+                "{} lists no tracks",
+                path.display()
+            );
+            if let Some(stem) = path.file_stem() {
+                state.burn_options.title = Some(stem.to_string_lossy().into_owned());
+            }
+
+            Ok(())
+        },
+        json,
+    )
+}
+
+/// The files an M3U playlist lists, with relative entries resolved against its folder
+/// and `file://` URLs decoded. Playlists that aren't UTF-8 are read as Latin-1, which
+/// older players write them in.
+fn read_m3u(path: &Path) -> Result<Vec<PathBuf>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("failed to read playlist {}", path.display()))?;
+    let contents = utf8_or_latin1(bytes);
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    contents
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.strip_prefix("file://") {
+            Some(url) => file_url_path(url).with_context(|| {
+                format!("unsupported file URL \"{}\" in {}", line, path.display())
+            }),
+            None => Ok(dir.join(line)),
+        })
+        .collect()
+}
+
+fn utf8_or_latin1(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|err| err.into_bytes().into_iter().map(char::from).collect())
+}
+
+/// The local path of a `file://` URL with the scheme taken off, e.g.
+/// `/music/My%20Song.flac` or `localhost/music/My%20Song.flac`.
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    let path = match url.strip_prefix("localhost") {
+        Some(path) => path,
+        None => url,
+    };
+    if !path.starts_with('/') {
+        // a file on another host
+        return None;
+    }
+
+    let mut bytes = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'%', [high, low, tail @ ..]) => {
+                let hex = [*high, *low];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    Some(PathBuf::from(utf8_or_latin1(bytes)))
+}
+
+/// Builds a playlist with `setup` and burns it, reporting progress as JSON lines or for
+/// a terminal.
+fn burn_playlist(setup: impl FnOnce(&mut AppState) -> Result<()>, json: bool) -> Result<()> {
+    let setup = || -> Result<AppState> {
+        let mut state = AppState::new()?;
+        setup(&mut state)?;
+
+        Ok(state)
+    };
+    let state = match setup() {
+        Ok(state) => state,
+        Err(err) => {
+            if json {
+                println!("{}", events::error_json(&err));
+            }
+            return Err(err);
+        }
+    };
+
+    let (handle, rx) = state.burn().context("failed to setup burning task")?;
+    if json {
+        print_json_events(handle, rx)
+    } else {
+        print_log_messages(
+            &state.disc_title()?,
+            handle,
+            rx,
+            &mut io::stdout(),
+            &mut io::stderr(),
+        )
+    }
+}
+
+/// Writes the progress of a background burn/mixdown task working on `task` to `out`,
+/// and the tools' errors to `err`, until it finishes, keeping the terminal's title on
+/// it too.
+pub fn print_log_messages(
+    task: &str,
+    handle: thread::JoinHandle<Result<()>>,
+    rx: mpsc::Receiver<Event>,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Result<()> {
+    let mut title = TaskTitle::new(task);
+    while let Ok(msg) = rx.recv() {
+        title.update(&msg);
+        match msg {
+            Event::Line(LogLine { is_stderr, line }) => {
+                if is_stderr {
+                    writeln!(err, "{}", line)?;
+                } else {
+                    writeln!(out, "{}", line)?;
+                }
+            }
+            // redrawn in place many times a second, which a screen reader can't follow
+            Event::Buffer(_) if style::accessible() => {}
+            Event::Buffer(BufferStatus {
+                fifo_percent,
+                drive_percent,
+            }) => {
+                write!(
+                    out,
+                    "\rfifo {:>3}%  drive buffer {:>3}%",
+                    fifo_percent, drive_percent
+                )?;
+                out.flush()?;
+            }
+            Event::TranscodeProgress { done, total, title } => {
+                writeln!(out, "[{}/{}] transcoding {}", done + 1, total, title)?;
+            }
+            Event::BurnStage(stage) => writeln!(out, "--- {:?} ---", stage)?,
+            Event::Eta {
+                stage,
+                remaining_secs,
+            } => writeln!(
+                out,
+                "{:?}: about {} left",
+                stage,
+                format_secs(remaining_secs)
+            )?,
+            Event::ScanProgress { .. } | Event::BurnProgress { .. } => {}
+            Event::Error(err) => return Err(unwrap_error(err)),
+            Event::Done(summary) => writeln!(out, "{}", style::success(&summary))?,
         }
     }
 
+    if handle.join().is_err() {
+        writeln!(err, "failed to join on background task thread")?;
+    }
+
     Ok(())
 }
 
-/// Prints the current playlist selection.
-fn playlist_print(playlist: &[Song]) {
-    println!(
+/// How the shell shows the current playlist selection.
+pub fn playlist_lines(playlist: &[Song], warnings: &[Option<String>]) -> Vec<String> {
+    if style::accessible() {
+        let mut lines = vec![format!(
+            "Playlist, {} tracks, {} in all.",
+            playlist.len(),
+            format_secs(playlist_duration_secs(playlist))
+        )];
+        for (i, song) in playlist.iter().enumerate() {
+            let mut notes = vec![];
+            if !song.edit.is_empty() {
+                notes.push(song.edit.describe());
+            }
+            notes.extend(warnings.get(i).cloned().flatten());
+            lines.push(format!(
+                "{}. {}{}",
+                i + 1,
+                spoken_song(song),
+                notes
+                    .iter()
+                    .map(|note| format!(" {}.", note))
+                    .collect::<String>()
+            ));
+        }
+        return lines;
+    }
+    let mut lines = vec![format!(
         "\n--- Current Playlist ({} Tracks, {} Total) ---",
         playlist.len(),
-        humantime_secs(playlist_duration_secs(playlist))
-    );
-    print_tracks(playlist);
-    println!("----------------------------------------------------\n");
+        format_secs(playlist_duration_secs(playlist))
+    )];
+    lines.extend(track_lines(playlist));
+    for (i, song) in playlist.iter().enumerate() {
+        if !song.edit.is_empty() {
+            lines.push(format!(
+                "#{} {}: {}",
+                i + 1,
+                song.title,
+                song.edit.describe()
+            ));
+        }
+    }
+    for (i, warning) in warnings.iter().enumerate() {
+        if let Some(warning) = warning {
+            lines.push(format!("#{} {}: {}", i + 1, playlist[i].title, warning));
+        }
+    }
+    lines.push(String::from(
+        "----------------------------------------------------\n",
+    ));
+
+    lines
+}
+
+// Display widths of the free-text columns in the shell track listing
+const ARTIST_COLUMN_WIDTH: usize = 24;
+const TITLE_COLUMN_WIDTH: usize = 32;
+pub const ALBUM_COLUMN_WIDTH: usize = 24;
+
+/// Truncates `text` to at most `width` terminal columns (ending in an ellipsis when cut)
+/// and pads it with spaces to exactly `width` columns. Wide (CJK) and zero-width
+/// (combining) characters are measured by their display width, not their char count.
+pub fn fit_width(text: &str, width: usize) -> String {
+    use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+    let mut fitted = String::with_capacity(text.len());
+    let mut used = 0;
+    if text.width() <= width {
+        fitted.push_str(text);
+        used = text.width();
+    } else {
+        // leave room for the ellipsis
+        let budget = width.saturating_sub(1);
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w > budget {
+                break;
+            }
+            fitted.push(c);
+            used += w;
+        }
+        if width > 0 {
+            fitted.push('…');
+            used += 1;
+        }
+    }
+
+    fitted.extend(std::iter::repeat_n(' ', width.saturating_sub(used)));
+    fitted
 }
 
-fn print_tracks(tracks: &[Song]) {
+/// The CD-TEXT each playlist entry will be burned with next to its tags.
+pub fn cdtext_lines(playlist: &[Song], mode: CdTextMode) -> Vec<String> {
+    let mut lines = vec![
+        format!("CD-TEXT mode: {:?}", mode),
+        format!(
+            "{}  {}",
+            fit_width("Tag", TITLE_COLUMN_WIDTH),
+            fit_width("CD-TEXT", TITLE_COLUMN_WIDTH)
+        ),
+    ];
+    for song in playlist {
+        for (tag, burned) in [
+            (&song.title, mode.apply(&song.title)),
+            (&song.artist, mode.apply(&song.artist)),
+        ] {
+            lines.push(format!(
+                "{}  {}",
+                fit_width(tag, TITLE_COLUMN_WIDTH),
+                fit_width(&burned, TITLE_COLUMN_WIDTH)
+            ));
+        }
+    }
+
+    lines
+}
+
+/// How the shell lists `tracks`: a table, or a sentence per track when accessible.
+pub fn track_lines(tracks: &[Song]) -> Vec<String> {
     use std::borrow::Cow;
-    println!("ID\tArtist\tTitle\tAlbum\tTrack Number\tFormat\tYear\tLength");
+    if style::accessible() {
+        return tracks
+            .iter()
+            .map(|song| format!("ID {}: {}", song.id, spoken_song(song)))
+            .collect();
+    }
+    let mut lines = vec![format!(
+        "ID\t{}  {}  {}  Track Number\tFormat\tYear\tLength",
+        fit_width("Artist", ARTIST_COLUMN_WIDTH),
+        fit_width("Title", TITLE_COLUMN_WIDTH),
+        fit_width("Album", ALBUM_COLUMN_WIDTH)
+    )];
     for s @ Song {
         id,
         artist,
@@ -443,172 +3203,80 @@ fn print_tracks(tracks: &[Song]) {
         album,
         track,
         year,
-        duration_sec,
         ..
     } in tracks
     {
-        let mut album = album.as_str();
-        if album == "" {
-            album = "\t";
-        }
+        let artist = fit_width(artist, ARTIST_COLUMN_WIDTH);
+        let title = fit_width(title, TITLE_COLUMN_WIDTH);
+        let album = fit_width(album, ALBUM_COLUMN_WIDTH);
         let track_no = if *track == 0 {
             Cow::Borrowed("\t")
         } else {
             Cow::Owned(track.to_string())
         };
         let format = s.format();
-        let length = humantime_secs(*duration_sec);
-        println!("{id}\t{artist}\t{title}\t{album}\t{track_no}\t{format}\t{year}\t{length}",);
+        let length = format_secs(s.played_duration_secs());
+        lines.push(format!(
+            "{id}\t{artist}  {title}  {album}  {track_no}\t{format}\t{year}\t{length}",
+        ));
     }
+    lines
 }
 
-// --- MAIN SHELL LOOP ---
-
-pub fn run_shell() -> anyhow::Result<()> {
-    let mut state = AppState::new()?;
-    let stdin = io::stdin();
-
-    println!("\n--- Audio Burner Shell ---");
-    println!("Type 'help' for commands.");
-
-    loop {
-        print!("audio_burner> ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        if stdin.read_line(&mut input)? == 0 {
-            // EOF detected (Ctrl+D)
-            break;
-        }
-
-        let input = input.trim();
-        let mut parts = input.split_whitespace().peekable();
-
-        let Some(command) = parts.next() else {
-            continue;
-        };
-
-        let result = handle_command(command, parts, &mut state);
-
-        match result {
-            Ok(true) => break,
-            Err(e) => eprintln!("❌ Error: {:?}", e),
-            _ => (),
-        }
+/// `song` as a sentence for a screen reader, e.g. "Title by Artist, from Album, track
+/// 3, FLAC, 1999, 3:45."
+pub fn spoken_song(song: &Song) -> String {
+    let mut parts = vec![format!("{} by {}", song.title, song.artist)];
+    if !song.album.is_empty() {
+        parts.push(format!("from {}", song.album));
+    }
+    if song.track != 0 {
+        parts.push(format!("track {}", song.track));
+    }
+    parts.push(song.format().to_uppercase());
+    if song.year != 0 {
+        parts.push(song.year.to_string());
     }
+    parts.push(format_secs(song.played_duration_secs()));
 
-    println!("\nGoodbye!");
-    Ok(())
+    parts.join(", ") + "."
 }
 
-// Immediate goals: Make it so I can build a playlist in memory.
-fn handle_command<'a, I: Iterator<Item = &'a str>>(
-    command: &'a str,
-    mut parts: Peekable<I>,
-    state: &mut AppState,
-) -> anyhow::Result<bool> {
-    const HELP_STR: &'static str = r#"
-Command:
-  playlist                               - show current playlist
-  playlist limit                         - show limit of playlist length
-  playlist add <ID>                      - add song by DB ID (transcodes and checks capacity)
-  playlist burn                          - burn your playlist to the CD
-  playlist clear                         - clears the existing playlist
-  artist-list <artist>                   - shows all tracks made by a given artist, or show all artists if none is supplied
-  album-list <album>                     - shows all tracks that belong to a given album
-  search <query>                         - search against artist / album track tags using full text search
-"#;
-    match command {
-        "quit" | "exit" => return Ok(true),
-        "help" => {
-            println!("{}", HELP_STR);
-        }
-        "playlist" => match parts.next() {
-            Some("add") => {
-                let id = parts
-                    .next()
-                    .context("expected an integer ID to be provided")?
-                    .parse()
-                    .context("failed to parse ID as integer")?;
-
-                state.playlist_add_by_id(id)?;
-            }
-            Some("clear") => {
-                state.playlist_clear();
-                println!("playlist has been cleared");
-            }
-            Some("burn") => {
-                let (handle, rx) = state.burn().context("failed to setup burning task")?;
-
-                while let Ok(msg) = rx.recv() {
-                    match msg {
-                        LogMessage::Line(LogLine { is_stderr, line }) => {
-                            if is_stderr {
-                                eprintln!("{}", line)
-                            } else {
-                                println!("{}", line)
-                            }
-                        }
-                        LogMessage::Complete(result) => {
-                            let output = result?;
-                            println!("{}", output);
-                        }
-                    }
-                }
+// --- MAIN SHELL LOOP ---
 
-                if let Err(_) = handle.join() {
-                    eprintln!("failed to join on burning playlist thread");
-                }
-            }
-            Option::None | Some("list") => {
-                playlist_print(&state.playlist[..]);
-            }
-            Some(unknown) => anyhow::bail!(
-                "unknown playlist command\"{}\": expected one of add / list / clear / burn",
-                unknown
-            ),
-        },
-        "search" => {
-            let tracks = queries::search_group(&state.conn, join_strings(parts).as_str())?;
+/// Runs the shell on `console` until it is quit or its input ends.
+pub fn run_shell(console: &mut dyn Console) -> anyhow::Result<()> {
+    let mut state = AppState::new()?;
 
-            print_tracks(&tracks[..]);
-        }
-        "artist-list" => {
-            if parts.peek().is_none() {
-                let artists = queries::list_artists(&state.conn)?;
-                println!("artists");
-                for artist in artists {
-                    println!("{}", artist);
-                }
-            } else {
-                let artist = join_strings(parts);
-                println!("tracks from artist \"{}\"", artist);
-                let tracks = queries::list_artist_tracks(&state.conn, &artist[..])?;
-                print_tracks(&tracks[..]);
-            }
-        }
-        "album-list" => {
-            if parts.peek().is_none() {
-                anyhow::bail!("need an album to list");
+    console.show("\n--- Audio Burner Shell ---");
+    console.show("Type 'help' for commands.");
+
+    let saved = state.saved_session_len()?;
+    if saved > 0 {
+        let question = format!("Restore {} tracks from the previous session? [y/N] ", saved);
+        if console.ask(&question)?.eq_ignore_ascii_case("y") {
+            for err in state.restore_session()? {
+                console.show_error(&format!("{} {:?}", style::error_label(), err));
             }
-            let album = join_strings(parts);
-            let tracks = queries::list_album(&state.conn, &album)?;
-            print_tracks(&tracks[..]);
+            console.show_all(&playlist_lines(
+                &state.playlist[..],
+                &state.playlist_warnings()?,
+            ));
         }
-        _ => anyhow::bail!("Unknown command\n{}\n", HELP_STR),
-    };
+    }
 
-    Ok(false)
-}
+    // Ctrl+D ends the input like quit
+    while let Some(input) = console.read_line("audio_burner> ")? {
+        // the last task's title stays up until the user is back
+        notify::restore_title();
 
-fn join_strings<'a, I: Iterator<Item = &'a str>>(mut iter: Peekable<I>) -> String {
-    let mut result = String::new();
-    while let Some(part) = iter.next() {
-        result += part;
-        if iter.peek().is_some() {
-            result += " ";
+        if command::run_command(&mut state, &input, console) {
+            break;
         }
     }
 
-    result
+    notify::restore_title();
+    state.save_session()?;
+    console.show("\nGoodbye!");
+    Ok(())
 }
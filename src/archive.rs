@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use crate::app::Song;
+use crate::events::json_string;
+use crate::naming::{self, NameContext};
+use crate::tags;
+
+/// Checksum manifest in the format `sha256sum -c` reads.
+pub const MANIFEST: &str = "sha256sums";
+/// Title, time and library metadata of every archived file.
+pub const METADATA: &str = "metadata.json";
+
+/// What [`export`] wrote.
+#[derive(Debug)]
+pub struct Archive {
+    pub dir: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Copies the untouched source files of `playlist` into a folder of `dir` named after
+/// disc `title`, named by `template` as disc number `disc`, and checks each copy
+/// against its source by SHA-256. Writes a [`MANIFEST`] of the checksums and a
+/// [`METADATA`] file describing the disc and every track as the library has it, or as
+/// its own tags have it for a file from outside the library.
+pub fn export(
+    conn: &Connection,
+    playlist: &[Song],
+    dir: &Path,
+    title: &str,
+    template: &str,
+    disc: u32,
+) -> Result<Archive> {
+    naming::ensure_numbered(template)?;
+    let dir = dir.join(naming::sanitize(title));
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create archive directory {}", dir.display()))?;
+
+    let mut manifest = String::new();
+    let mut tracks = vec![];
+    let mut bytes = 0;
+    for (i, song) in playlist.iter().enumerate() {
+        let name = naming::render(
+            template,
+            &NameContext {
+                disc,
+                track: i + 1,
+                song,
+                ext: song.format(),
+            },
+        )?;
+        let target = dir.join(&name);
+        let (digest, copied) = copy_hashed(Path::new(&song.path), &target)?;
+        let written = hash_file(&target)?;
+        anyhow::ensure!(
+            written == digest,
+            "checksum mismatch after copying {} to {}: the copy is damaged",
+            song.path,
+            target.display()
+        );
+        bytes += copied;
+
+        manifest += &format!("{}  {}\n", digest, name);
+        let fields = match song.id {
+            0 => file_fields(song),
+            id => tags::show(conn, id)?,
+        };
+        let library = fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(field, value)| {
+                format!(
+                    "{}: {}",
+                    json_string(&field.replace(' ', "_")),
+                    json_string(&value)
+                )
+            })
+            .collect::<Vec<_>>();
+        tracks.push(format!(
+            "    {{\"position\": {}, \"file\": {}, \"sha256\": {}, \"bytes\": {}, \"library\": {{{}}}}}",
+            i + 1,
+            json_string(&name),
+            json_string(&digest),
+            copied,
+            library.join(", ")
+        ));
+    }
+
+    let manifest_path = dir.join(MANIFEST);
+    std::fs::write(&manifest_path, manifest)
+        .with_context(|| format!("failed to write {}", manifest_path.display()))?;
+
+    let exported_at: String = conn
+        .query_row("SELECT datetime('now', 'localtime')", [], |row| row.get(0))
+        .context("failed to read the current time")?;
+    let metadata = format!(
+        "{{\n  \"title\": {},\n  \"exported_at\": {},\n  \"tracks\": [\n{}\n  ]\n}}\n",
+        json_string(title),
+        json_string(&exported_at),
+        tracks.join(",\n")
+    );
+    let metadata_path = dir.join(METADATA);
+    std::fs::write(&metadata_path, metadata)
+        .with_context(|| format!("failed to write {}", metadata_path.display()))?;
+
+    Ok(Archive {
+        dir,
+        files: playlist.len(),
+        bytes,
+    })
+}
+
+/// The fields of `song` read from its file's tags, labelled like [`tags::show`], for an
+/// entry the library doesn't hold.
+fn file_fields(song: &Song) -> Vec<(&'static str, String)> {
+    let number = |n: i64| if n > 0 { n.to_string() } else { String::new() };
+    vec![
+        ("path", song.path.clone()),
+        ("title", song.title.clone()),
+        ("artist", song.artist.clone()),
+        ("album", song.album.clone()),
+        ("track", number(song.track)),
+        ("year", number(i64::from(song.year))),
+        ("duration", song.duration_sec.to_string()),
+    ]
+}
+
+/// Copies `source` to `target`, hashing the bytes on the way. Returns the hex digest
+/// and the number of bytes copied.
+fn copy_hashed(source: &Path, target: &Path) -> Result<(String, u64)> {
+    let mut input =
+        File::open(source).with_context(|| format!("failed to open {}", source.display()))?;
+    let mut output =
+        File::create(target).with_context(|| format!("failed to create {}", target.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut copied = 0;
+    loop {
+        let read = input
+            .read(&mut buffer)
+            .with_context(|| format!("failed to read {}", source.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        output
+            .write_all(&buffer[..read])
+            .with_context(|| format!("failed to write {}", target.display()))?;
+        copied += read as u64;
+    }
+    // make sure what gets checked below is what is on disk
+    output
+        .sync_all()
+        .with_context(|| format!("failed to flush {}", target.display()))?;
+
+    Ok((format!("{:x}", hasher.finalize()), copied))
+}
+
+/// Hex SHA-256 digest of the file at `path`, read back from the disk rather than the
+/// page cache where the kernel lets go of it. The drive's own cache may still answer,
+/// so this catches what went wrong on the way to the drive, not on the platters.
+fn hash_file(path: &Path) -> Result<String> {
+    let mut input =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    // SAFETY: posix_fadvise only advises on the open descriptor; the pages are clean
+    // after sync_all, so the kernel drops them
+    unsafe {
+        libc::posix_fadvise(input.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = input
+            .read(&mut buffer)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
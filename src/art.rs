@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::base64;
+use crate::shutdown;
+
+/// Pictures looked for next to a track whose file has no art of its own.
+const FOLDER_ART: &[&str] = &[
+    "cover.jpg",
+    "cover.png",
+    "folder.jpg",
+    "folder.png",
+    "front.jpg",
+    "front.png",
+];
+
+/// Whether the album grid shows cover art, set as `album_art` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArtMode {
+    /// Art when the terminal looks like one that can show it.
+    #[default]
+    Auto,
+    Kitty,
+    Sixel,
+    Off,
+}
+
+impl std::str::FromStr for ArtMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ArtMode::Auto),
+            "kitty" => Ok(ArtMode::Kitty),
+            "sixel" => Ok(ArtMode::Sixel),
+            "off" => Ok(ArtMode::Off),
+            _ => anyhow::bail!(
+                "unknown album art mode \"{}\": expected auto / kitty / sixel / off",
+                s
+            ),
+        }
+    }
+}
+
+/// How pictures are sent to the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The kitty graphics protocol, also spoken by WezTerm, Ghostty and Konsole.
+    Kitty,
+    Sixel,
+}
+
+impl ArtMode {
+    /// The protocol to draw art with, if any. Terminals don't say what they support
+    /// without being queried, so `auto` goes by the names they set in the environment.
+    pub fn protocol(self) -> Option<Protocol> {
+        match self {
+            ArtMode::Kitty => Some(Protocol::Kitty),
+            ArtMode::Sixel => Some(Protocol::Sixel),
+            ArtMode::Off => None,
+            ArtMode::Auto => {
+                let var = |name: &str| std::env::var(name).unwrap_or_default().to_lowercase();
+                let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+                if std::env::var_os("KITTY_WINDOW_ID").is_some()
+                    || term.contains("kitty")
+                    || term.contains("ghostty")
+                    || matches!(program.as_str(), "wezterm" | "ghostty")
+                {
+                    Some(Protocol::Kitty)
+                } else if ["foot", "mlterm", "contour", "sixel"]
+                    .iter()
+                    .any(|name| term.contains(name))
+                {
+                    Some(Protocol::Sixel)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Pixels as RGB triples, row by row.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+/// The art of the track at `path`, scaled into `width` by `height` pixels and padded
+/// to fill them: the picture embedded in the file, else a cover image in its folder.
+/// `None` when there is neither, or ffmpeg can't read them.
+pub fn load(path: &str, width: u32, height: u32) -> Option<Image> {
+    let folder = Path::new(path).parent();
+    std::iter::once(Path::new(path).to_path_buf())
+        .chain(
+            FOLDER_ART
+                .iter()
+                .filter_map(|name| Some(folder?.join(name)))
+                .filter(|path| path.is_file()),
+        )
+        .find_map(|source| decode(&source, width, height).ok())
+}
+
+/// The first picture in `source`, which ffmpeg reads as a video stream whether it is
+/// an image file or art attached to audio.
+fn decode(source: &Path, width: u32, height: u32) -> Result<Image> {
+    let scale = format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2",
+        w = width,
+        h = height
+    );
+    let output = shutdown::output(
+        Command::new("ffmpeg")
+            .args(["-v", "error", "-i"])
+            .arg(source)
+            .args(["-an", "-frames:v", "1", "-vf", &scale])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgb24", "-"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+    )
+    .with_context(|| format!("failed to run ffmpeg to read art from {}", source.display()))?;
+
+    let len = width as usize * height as usize * 3;
+    anyhow::ensure!(
+        output.status.success() && output.stdout.len() == len,
+        "no art in {}",
+        source.display()
+    );
+
+    Ok(Image {
+        width,
+        height,
+        rgb: output.stdout,
+    })
+}
+
+/// Sends `image` to a kitty terminal under `id` without showing it yet. The data is
+/// base64 in chunks of at most 4096 bytes, as the protocol asks.
+pub fn kitty_transmit(id: u32, image: &Image) -> String {
+    let data = base64::encode(&image.rgb, base64::STANDARD, '=');
+    let chunks = data.as_bytes().chunks(4096).collect::<Vec<_>>();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let control = if i == 0 {
+            format!(
+                "a=t,q=2,f=24,i={},s={},v={},m={}",
+                id, image.width, image.height, more
+            )
+        } else {
+            format!("m={}", more)
+        };
+        // SAFETY: base64 is ASCII
+        let chunk = std::str::from_utf8(chunk).unwrap();
+        out += &format!("\x1b_G{};{}\x1b\\", control, chunk);
+    }
+    out
+}
+
+/// Shows image `id`, sent earlier, at the cursor scaled to `cols` by `rows` cells.
+pub fn kitty_place(id: u32, cols: u16, rows: u16) -> String {
+    format!("\x1b_Ga=p,q=2,C=1,i={},c={},r={}\x1b\\", id, cols, rows)
+}
+
+/// Takes every kitty image off the screen, keeping their data for placing again.
+pub const KITTY_CLEAR: &str = "\x1b_Ga=d,d=a,q=2\x1b\\";
+
+/// `image` as sixel graphics, in a palette of 6 levels each of red, green and blue.
+pub fn sixel(image: &Image) -> String {
+    let (width, height) = (image.width as usize, image.height as usize);
+    let colors = image
+        .rgb
+        .chunks(3)
+        .map(|p| {
+            let level = |v: u8| (usize::from(v) * 5 + 127) / 255;
+            (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as u8
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = format!("\x1bP0;1q\"1;1;{};{}", width, height);
+    for i in 0..216 {
+        out += &format!("#{};2;{};{};{}", i, i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20);
+    }
+    for top in (0..height).step_by(6) {
+        let rows = (height - top).min(6);
+        let mut used = [false; 216];
+        for &color in &colors[top * width..(top + rows) * width] {
+            used[usize::from(color)] = true;
+        }
+        for color in (0..216).filter(|&c| used[c]) {
+            out += &format!("#{}", color);
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|dy| usize::from(colors[(top + dy) * width + x]) == color)
+                    .fold(0, |bits, dy| bits | 1 << dy);
+                let c = char::from(63 + bits as u8);
+                run = match run {
+                    Some((prev, n)) if prev == c => Some((c, n + 1)),
+                    Some(prev) => {
+                        push_run(&mut out, prev);
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some(run) = run {
+                push_run(&mut out, run);
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out += "\x1b\\";
+    out
+}
+
+fn push_run(out: &mut String, (c, n): (char, usize)) {
+    if n > 3 {
+        out.push_str(&format!("!{}{}", n, c));
+    } else {
+        out.extend(std::iter::repeat_n(c, n));
+    }
+}
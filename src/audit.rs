@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::tags::{self, TagChange, TagField};
+
+/// What the tracks of an album disagree on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mismatch {
+    Year,
+    Artist,
+    Art,
+}
+
+impl Mismatch {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mismatch::Year => "year",
+            Mismatch::Artist => "album artist",
+            Mismatch::Art => "art",
+        }
+    }
+
+    /// The value a track has when its tag is missing, which never counts as the
+    /// majority: harmonizing fills it in rather than spreading it.
+    fn unset(self) -> &'static str {
+        match self {
+            Mismatch::Year => "0",
+            Mismatch::Artist => "",
+            Mismatch::Art => NO_ART,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditTrack {
+    pub id: i64,
+    pub path: String,
+    /// The year, the artist, or a fingerprint of the embedded art.
+    pub value: String,
+}
+
+/// An album whose tracks don't all agree on one tag.
+#[derive(Debug, Clone)]
+pub struct AlbumIssue {
+    pub album: String,
+    /// Directory the album's tracks are in, which tells apart albums sharing a name.
+    pub dir: String,
+    pub mismatch: Mismatch,
+    pub tracks: Vec<AuditTrack>,
+    /// The value most tracks have, which harmonizing gives the rest. Tracks missing
+    /// the tag, e.g. with year 0 or without art, never count as the majority.
+    pub majority: String,
+}
+
+/// Fingerprint standing in for a track without embedded art.
+const NO_ART: &str = "none";
+
+impl AlbumIssue {
+    /// Each value and how many tracks have it, most common first.
+    pub fn counts(&self) -> Vec<(&str, usize)> {
+        value_counts(self.tracks.iter().map(|track| track.value.as_str()))
+    }
+
+    /// How `value` is shown: art fingerprints are numbered by how common they are.
+    pub fn label(&self, value: &str) -> String {
+        match self.mismatch {
+            Mismatch::Art if value == NO_ART => String::from("no art"),
+            Mismatch::Art => {
+                let number = self
+                    .counts()
+                    .iter()
+                    .filter(|(value, _)| *value != NO_ART)
+                    .position(|(other, _)| *other == value)
+                    .map_or(0, |i| i + 1);
+                format!("art #{}", number)
+            }
+            Mismatch::Year | Mismatch::Artist => format!("\"{}\"", value),
+        }
+    }
+
+    /// Tracks that don't have the majority value.
+    pub fn outliers(&self) -> impl Iterator<Item = &AuditTrack> {
+        self.tracks
+            .iter()
+            .filter(|track| track.value != self.majority)
+    }
+
+    /// The tag changes giving every track the majority year or artist. Art isn't
+    /// stored in the library and a change to it couldn't be undone, so it has none.
+    pub fn changes(&self) -> Vec<TagChange> {
+        let field = match self.mismatch {
+            Mismatch::Year => TagField::Year,
+            Mismatch::Artist => TagField::Artist,
+            Mismatch::Art => return vec![],
+        };
+
+        self.outliers()
+            .map(|track| TagChange {
+                id: track.id,
+                field,
+                old: track.value.clone(),
+                new: self.majority.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Values in order of how many times they occur, ties in order of first appearance.
+fn value_counts<'a>(values: impl Iterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for value in values {
+        match counts.iter_mut().find(|(seen, _)| *seen == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    counts
+}
+
+/// Whether the album's tracks are by different artists on purpose: the artists don't
+/// have a clear majority, or a file names its album artist separately, in which case
+/// the library holds that and the track artists may well differ.
+fn is_compilation(tracks: &[AuditTrack]) -> bool {
+    let counts = value_counts(tracks.iter().map(|track| track.value.as_str()));
+    if counts
+        .first()
+        .is_none_or(|&(_, count)| count * 2 <= tracks.len())
+    {
+        return true;
+    }
+
+    // unreadable files are taken at their word in the library
+    tracks
+        .iter()
+        .any(|track| matches!(tags::album_artist(&track.path), Ok(Some(_))))
+}
+
+/// Finds albums whose tracks disagree on year, album artist or, when `check_art` is
+/// set, embedded art. Checking art reads every file, so it is much slower. Compilations
+/// are left out of the artist check.
+pub fn audit_albums(conn: &Connection, check_art: bool) -> Result<Vec<AlbumIssue>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, path, album, artist, year FROM tracks
+            WHERE album IS NOT NULL AND album != ''
+            ORDER BY album COLLATE NATSORT, path COLLATE NATSORT",
+        )
+        .context("failed to prepare album audit query")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                row.get::<_, Option<u32>>(4)?.unwrap_or_default(),
+            ))
+        })
+        .context("failed to query albums")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read albums")?;
+
+    let mut albums: BTreeMap<(String, String), Vec<_>> = BTreeMap::new();
+    for (id, path, album, artist, year) in rows {
+        let dir = Path::new(&path)
+            .parent()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        albums
+            .entry((album, dir))
+            .or_default()
+            .push((id, path, artist, year));
+    }
+
+    let mut issues = vec![];
+    for ((album, dir), tracks) in albums {
+        if tracks.len() < 2 {
+            continue;
+        }
+
+        let mut checks = vec![
+            (
+                Mismatch::Year,
+                tracks
+                    .iter()
+                    .map(|(id, path, _, year)| (*id, path.clone(), year.to_string()))
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                Mismatch::Artist,
+                tracks
+                    .iter()
+                    .map(|(id, path, artist, _)| (*id, path.clone(), artist.clone()))
+                    .collect(),
+            ),
+        ];
+        if check_art {
+            // unreadable files are left out rather than counted as having no art
+            let art = tracks
+                .iter()
+                .filter_map(|(id, path, _, _)| {
+                    let fingerprint = tags::art_fingerprint(path).ok()?;
+                    let value = fingerprint.map_or_else(
+                        || String::from(NO_ART),
+                        |fingerprint| format!("{:016x}", fingerprint),
+                    );
+                    Some((*id, path.clone(), value))
+                })
+                .collect();
+            checks.push((Mismatch::Art, art));
+        }
+
+        for (mismatch, values) in checks {
+            let tracks = values
+                .into_iter()
+                .map(|(id, path, value)| AuditTrack { id, path, value })
+                .collect::<Vec<_>>();
+            let counts = value_counts(tracks.iter().map(|track| track.value.as_str()));
+            if counts.len() < 2 || mismatch == Mismatch::Artist && is_compilation(&tracks) {
+                continue;
+            }
+            let majority = counts
+                .iter()
+                .find(|(value, _)| *value != mismatch.unset())
+                .map(|(value, _)| value.to_string())
+                .unwrap_or_default();
+
+            issues.push(AlbumIssue {
+                album: album.clone(),
+                dir: dir.clone(),
+                mismatch,
+                tracks,
+                majority,
+            });
+        }
+    }
+
+    Ok(issues)
+}
@@ -0,0 +1,502 @@
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::app::Song;
+use crate::config::Config;
+use crate::errors::{self, BurnError, TranscodeError};
+use crate::loudness::{self, Loudness};
+use crate::normalize::{NormalizeMode, NormalizeProfile};
+use crate::presets::Presets;
+use crate::shutdown;
+use crate::staging;
+use crate::throttle::Throttle;
+use crate::wodim;
+
+/// Set to any value to burn with [`MockTranscoder`], [`MockBurner`] and [`MockRipper`]
+/// instead of the real tools, as does building with the `mock-backend` feature.
+pub const MOCK_BACKEND_ENV: &str = "VOL28_MOCK_BACKEND";
+
+/// Turns source files into staged CD audio.
+pub trait Transcoder: Send + Sync {
+    /// Writes `song`, with its trim and fades applied, to `output` as 44.1 kHz 16-bit
+    /// stereo WAV.
+    fn transcode(&self, song: &Song, output: &Path) -> Result<(), TranscodeError>;
+
+    /// Normalizes the staged `wavs` in `dir` in place.
+    fn normalize(
+        &self,
+        profile: NormalizeProfile,
+        mode: NormalizeMode,
+        dir: &Path,
+        wavs: &[PathBuf],
+    ) -> Result<()>;
+
+    /// Measures the loudness of a staged `wav`.
+    fn loudness(&self, wav: &Path) -> Result<Loudness>;
+}
+
+/// Writes staged WAVs to a disc.
+pub trait Burner: Send + Sync {
+    /// Burns `wavs` from `dir` as an audio CD, passing each line of the burner's output
+    /// to `on_output` along with whether it was written to stderr.
+    fn burn(
+        &self,
+        dir: &Path,
+        wavs: &[PathBuf],
+        speed: Option<u32>,
+        on_output: &(dyn Fn(bool, String) -> Result<()> + Sync),
+    ) -> Result<()>;
+}
+
+/// Reads burned discs back to check them.
+pub trait Ripper: Send + Sync {
+    /// Rips every track of the disc in the verify drive into `dir` and returns the WAVs
+    /// in track order. `burned` are the WAVs the disc was burned from, which only a mock
+    /// drive reads.
+    fn rip(&self, dir: &Path, burned: &[PathBuf]) -> Result<Vec<PathBuf>>;
+}
+
+/// The tools a burn runs through.
+#[derive(Clone)]
+pub struct Backend {
+    pub transcoder: Arc<dyn Transcoder>,
+    pub burner: Arc<dyn Burner>,
+    pub ripper: Arc<dyn Ripper>,
+    /// Limits the transcoder runs under.
+    pub throttle: Throttle,
+    /// ffmpeg options for staging particular source formats.
+    pub presets: Presets,
+    /// Burner device passed to wodim.
+    pub device: String,
+    /// Second drive copies are verified on, if there is one.
+    pub verify_device: Option<String>,
+    /// Where staging directories are created.
+    pub staging_root: PathBuf,
+}
+
+impl Backend {
+    /// The real tools writing to the configured device, or the mocks when
+    /// [`MOCK_BACKEND_ENV`] is set or the `mock-backend` feature is enabled.
+    pub fn from_env(config: &Config) -> Self {
+        let (throttle, device) = (config.throttle, config.device.clone());
+        let verify_device = config.verify_device.clone();
+        let presets = config.presets.clone();
+        let (transcoder, burner, ripper): (Arc<dyn Transcoder>, Arc<dyn Burner>, Arc<dyn Ripper>) =
+            if cfg!(feature = "mock-backend") || std::env::var_os(MOCK_BACKEND_ENV).is_some() {
+                (
+                    Arc::new(MockTranscoder),
+                    Arc::new(MockBurner),
+                    Arc::new(MockRipper),
+                )
+            } else {
+                (
+                    Arc::new(Ffmpeg {
+                        throttle,
+                        presets: presets.clone(),
+                    }),
+                    Arc::new(Wodim {
+                        device: device.clone(),
+                    }),
+                    Arc::new(Cdparanoia {
+                        device: verify_device.clone().unwrap_or_else(|| device.clone()),
+                        read_offset: config.read_offset,
+                    }),
+                )
+            };
+
+        Backend {
+            transcoder,
+            burner,
+            ripper,
+            throttle,
+            presets,
+            device,
+            verify_device,
+            staging_root: config.staging_dir.clone(),
+        }
+    }
+}
+
+pub struct Ffmpeg {
+    pub throttle: Throttle,
+    pub presets: Presets,
+}
+
+/// The ffmpeg invocation that stages `song` at `output`, with the preset for its format
+/// from `presets` if there is one.
+pub fn transcode_command(song: &Song, presets: &Presets, output: &Path) -> Command {
+    let preset = presets.get(song.format());
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg
+        .args(song.edit.input_args())
+        .arg("-i")
+        .arg(&song.path);
+    // the preset's filter sees the source before it is faded
+    let filters = [
+        preset.and_then(|preset| preset.filter.clone()),
+        song.edit.fade_filter(song.duration_sec),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    if !filters.is_empty() {
+        ffmpeg.arg("-af").arg(filters.join(","));
+    }
+    if let Some(preset) = preset {
+        ffmpeg.args(&preset.args);
+    }
+    ffmpeg
+        .arg("-y")
+        .arg("-ar")
+        .arg("44100")
+        .arg("-ac")
+        .arg("2")
+        .arg("-sample_fmt")
+        .arg("s16")
+        .arg(output);
+
+    ffmpeg
+}
+
+impl Transcoder for Ffmpeg {
+    fn transcode(&self, song: &Song, output: &Path) -> Result<(), TranscodeError> {
+        TranscodeError::run(
+            "ffmpeg",
+            Path::new(&song.path),
+            &mut self
+                .throttle
+                .wrap(transcode_command(song, &self.presets, output)),
+        )
+    }
+
+    fn normalize(
+        &self,
+        profile: NormalizeProfile,
+        mode: NormalizeMode,
+        dir: &Path,
+        wavs: &[PathBuf],
+    ) -> Result<()> {
+        profile.run(mode, dir, wavs, &self.throttle)
+    }
+
+    fn loudness(&self, wav: &Path) -> Result<Loudness> {
+        loudness::measure(wav, &self.throttle)
+    }
+}
+
+pub struct Wodim {
+    pub device: String,
+}
+
+/// The wodim invocation that burns `wavs` from `dir` to `device`.
+pub fn wodim_command(device: &str, dir: &Path, wavs: &[PathBuf], speed: Option<u32>) -> Command {
+    let mut wodim = Command::new("wodim");
+    wodim
+        .current_dir(dir)
+        .arg("-v")
+        .arg("-eject")
+        .arg("-dao")
+        .arg("-pad")
+        .arg("-text")
+        .arg("-useinfo")
+        .arg("dev=")
+        .arg(device);
+    if let Some(speed) = speed {
+        wodim.arg(format!("speed={}", speed));
+    }
+    wodim.arg("-audio").args(wavs);
+
+    wodim
+}
+
+impl Burner for Wodim {
+    fn burn(
+        &self,
+        dir: &Path,
+        wavs: &[PathBuf],
+        speed: Option<u32>,
+        on_output: &(dyn Fn(bool, String) -> Result<()> + Sync),
+    ) -> Result<()> {
+        let mut wodim = wodim_command(&self.device, dir, wavs, speed)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(BurnError::Spawn)?;
+        let _wodim_guard = shutdown::track(&wodim);
+
+        let (stdout, stderr) = (
+            wodim
+                .stdout
+                .take()
+                .context("failed to get handle to stdout")?,
+            wodim
+                .stderr
+                .take()
+                .context("failed to get handle to stderr")?,
+        );
+        let stderr_lines = Mutex::new(Vec::<String>::new());
+
+        thread::scope(|scope| -> Result<()> {
+            let mut handles = vec![];
+            for (reader, is_stderr) in [
+                (
+                    Box::new(BufReader::new(stdout)) as Box<dyn BufRead + Send>,
+                    false,
+                ),
+                (Box::new(BufReader::new(stderr)), true),
+            ] {
+                let stderr_lines = &stderr_lines;
+                handles.push(scope.spawn(move || -> Result<()> {
+                    wodim::for_each_segment(reader, |line| {
+                        if is_stderr
+                            && wodim::parse_buffer_status(&line).is_none()
+                            && let Ok(mut lines) = stderr_lines.lock()
+                        {
+                            lines.push(line.clone());
+                        }
+                        on_output(is_stderr, line)
+                    })
+                    .context("failed to read wodim output")
+                }));
+            }
+
+            for handle in handles {
+                match handle.join() {
+                    Ok(result) => result?,
+                    Err(_) => anyhow::bail!("pipe failed"),
+                }
+            }
+
+            Ok(())
+        })?;
+
+        let status = wodim.wait().context("failed to wait for wodim to exit")?;
+        if !status.success() {
+            let stderr = stderr_lines
+                .lock()
+                .map(|lines| errors::stderr_tail(lines.iter().map(String::as_str)))
+                .unwrap_or_default();
+            return Err(BurnError::Failed { status, stderr }.into());
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Cdparanoia {
+    pub device: String,
+    /// Samples the drive reads off by, corrected so rips line up with what was burned.
+    pub read_offset: i32,
+}
+
+/// The cdparanoia invocation that rips every track of the disc in `device` into `dir`
+/// as `track01.cdda.wav` and so on.
+pub fn rip_command(device: &str, read_offset: i32, dir: &Path) -> Command {
+    let mut cdparanoia = Command::new("cdparanoia");
+    cdparanoia
+        .current_dir(dir)
+        .arg("-q")
+        .arg("-B")
+        .arg("-d")
+        .arg(device)
+        .arg("-O")
+        .arg(read_offset.to_string())
+        .arg("--")
+        .arg("1-");
+
+    cdparanoia
+}
+
+impl Ripper for Cdparanoia {
+    fn rip(&self, dir: &Path, _burned: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let output = shutdown::output(
+            rip_command(&self.device, self.read_offset, dir)
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped()),
+        )
+        .context("failed to run cdparanoia. Is it installed?")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!(
+                "cdparanoia failed to rip {} ({}):\n{}",
+                self.device,
+                output.status,
+                errors::stderr_tail(stderr.lines())
+            );
+        }
+
+        // what is on the disc, however many tracks were burned
+        let mut ripped = std::fs::read_dir(dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| Ok(entry?.path()))
+                    .collect::<io::Result<Vec<_>>>()
+            })
+            .with_context(|| format!("failed to list the ripped tracks in {}", dir.display()))?;
+        ripped.retain(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with(".cdda.wav"))
+        });
+        // track01.cdda.wav, track02.cdda.wav, ...
+        ripped.sort();
+
+        Ok(ripped)
+    }
+}
+
+/// Stages a short stretch of silence for every track without running ffmpeg, or no
+/// audio at all for tracks the library has as zero length.
+pub struct MockTranscoder;
+
+/// Length of the silence staged by [`MockTranscoder`].
+const MOCK_WAV_MILLIS: u32 = 500;
+
+impl Transcoder for MockTranscoder {
+    fn transcode(&self, song: &Song, output: &Path) -> Result<(), TranscodeError> {
+        let data_len = match song.duration_sec {
+            0 => 0,
+            _ => 44_100 * 4 * MOCK_WAV_MILLIS / 1000,
+        };
+        let wav = mock_wav(&vec![0; data_len as usize]);
+
+        std::fs::File::create(output)
+            .and_then(|mut file| file.write_all(&wav))
+            .map_err(|source| TranscodeError::Write {
+                path: output.to_path_buf(),
+                source,
+            })
+    }
+
+    fn normalize(
+        &self,
+        _profile: NormalizeProfile,
+        _mode: NormalizeMode,
+        _dir: &Path,
+        _wavs: &[PathBuf],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Made up from the file name, so tracks differ the same way on every run.
+    fn loudness(&self, wav: &Path) -> Result<Loudness> {
+        let seed = wav.file_name().map_or(0, |name| {
+            name.as_encoded_bytes().iter().map(|&b| b as u32).sum()
+        });
+        Ok(Loudness {
+            integrated: -6.0 - (seed % 13) as f64,
+            range: 3.0 + (seed % 7) as f64,
+            true_peak: -0.1 - (seed % 3) as f64,
+        })
+    }
+}
+
+/// Reports the progress wodim would for each track, always succeeding. It starts by
+/// naming the directory of the disc set, so tests can find what was burned.
+pub struct MockBurner;
+
+impl Burner for MockBurner {
+    fn burn(
+        &self,
+        _dir: &Path,
+        wavs: &[PathBuf],
+        speed: Option<u32>,
+        on_output: &(dyn Fn(bool, String) -> Result<()> + Sync),
+    ) -> Result<()> {
+        let speed = speed.unwrap_or(16);
+        if let Some(disc) = wavs.first().and_then(|wav| wav.parent()) {
+            on_output(false, format!("Burning the disc set in {}", disc.display()))?;
+        }
+        on_output(
+            false,
+            format!("Starting to write CD/DVD at speed {}.0", speed),
+        )?;
+        for (i, _) in wavs.iter().enumerate() {
+            for written in 0..=4 {
+                on_output(
+                    false,
+                    format!(
+                        "Track {:02}: {:>4} of {:>4} MB written (fifo 100%) [buf {:>3}%]  {}.0x.",
+                        i + 1,
+                        written,
+                        4,
+                        96 + written,
+                        speed
+                    ),
+                )?;
+            }
+        }
+        on_output(false, String::from("Fixating..."))?;
+        on_output(
+            false,
+            String::from("fifo was 0 times empty and 42 times full."),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A CD audio WAV holding `samples`.
+fn mock_wav(samples: &[u8]) -> Vec<u8> {
+    let data_len = samples.len() as u32;
+    let mut wav = Vec::with_capacity(44 + samples.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&2u16.to_le_bytes()); // channels
+    wav.extend_from_slice(&44_100u32.to_le_bytes());
+    wav.extend_from_slice(&(44_100u32 * 4).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&4u16.to_le_bytes()); // block align
+    wav.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    wav.extend_from_slice(samples);
+
+    wav
+}
+
+/// Reads back exactly what was burned, padded to whole frames, as a perfect disc would.
+pub struct MockRipper;
+
+impl Ripper for MockRipper {
+    fn rip(&self, dir: &Path, burned: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let mut ripped = vec![];
+        for (i, wav) in burned.iter().enumerate() {
+            let mut samples = staging::read_samples(wav)?;
+            samples.resize(samples.len().div_ceil(2352) * 2352, 0);
+            let track = dir.join(format!("track{:02}.cdda.wav", i + 1));
+            std::fs::write(&track, mock_wav(&samples))
+                .with_context(|| format!("failed to write {}", track.display()))?;
+            ripped.push(track);
+        }
+
+        Ok(ripped)
+    }
+}
+
+/// `command` as it would be typed into a shell, prefixed with a `cd` into its working
+/// directory when it has one.
+pub fn command_line(command: &Command) -> String {
+    let mut words = vec![quote(&command.get_program().to_string_lossy())];
+    words.extend(command.get_args().map(|arg| quote(&arg.to_string_lossy())));
+    let line = words.join(" ");
+
+    match command.get_current_dir() {
+        Some(dir) => format!("cd {} && {}", quote(&dir.to_string_lossy()), line),
+        None => line,
+    }
+}
+
+fn quote(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+%@".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
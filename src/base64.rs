@@ -0,0 +1,53 @@
+/// The alphabet of RFC 4648, as terminal graphics protocols expect it.
+pub const STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` as base64 in the 64 characters of `alphabet`, padding the last
+/// group with `pad`.
+pub fn encode(bytes: &[u8], alphabet: &[u8; 64], pad: char) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(alphabet[(n >> (18 - 6 * i) & 0x3f) as usize]));
+            } else {
+                encoded.push(pad);
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The test vectors of RFC 4648.
+    #[test]
+    fn rfc_4648_vectors() {
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(encode(text.as_bytes(), STANDARD, '='), expected);
+        }
+    }
+
+    #[test]
+    fn uses_the_given_alphabet_and_padding() {
+        let mut alphabet = *STANDARD;
+        alphabet[62..].copy_from_slice(b"._");
+        assert_eq!(encode(&[0xfb, 0xff], STANDARD, '='), "+/8=");
+        assert_eq!(encode(&[0xfb, 0xff], &alphabet, '-'), "._8-");
+    }
+}
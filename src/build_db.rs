@@ -1,10 +1,20 @@
 use anyhow::Context;
-use lofty::{file::TaggedFile, probe::Probe};
+use lofty::{error::LoftyError, file::TaggedFile, probe::Probe};
 use rusqlite::{Connection, Transaction};
+use std::collections::HashSet;
 use std::path::Path;
-use walkdir::WalkDir;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use walkdir::{DirEntry, WalkDir};
 
-use crate::DB_PATH;
+use crate::app::Song;
+use crate::edit::TrackEdit;
+use crate::errors::{DbError, ScanError};
+use crate::events::{Event, EventBus, LogLine};
+use crate::paths;
+use crate::tags;
 
 pub type CowStr<'a> = std::borrow::Cow<'a, str>;
 
@@ -21,6 +31,14 @@ pub struct InsertSong<'a> {
     pub bitrate_kbps: u32,
     pub sample_rate_hz: u32,
     pub bit_depth: u8,
+    pub genre: CowStr<'a>,
+    /// Camelot notation, from the initial key tag when it is present and parseable.
+    pub musical_key: Option<String>,
+    /// From the language tag; a language of [`tags::NO_LINGUISTIC_CONTENT`] marks an
+    /// instrumental instead.
+    pub language: Option<String>,
+    /// Whether the language tag says the track has lyrics, when there is one.
+    pub instrumental: Option<bool>,
 }
 
 const CREATE_TRACKS_SQL: &str = "
@@ -35,36 +53,505 @@ const CREATE_TRACKS_SQL: &str = "
         duration_sec INTEGER,
         bit_depth INTEGER,
         bitrate_kbps INTEGER,
-        sample_rate_hz INTEGER
+        sample_rate_hz INTEGER,
+        genre TEXT,
+        bpm REAL,
+        musical_key TEXT,
+        note TEXT,
+        loudness_db REAL,
+        unplayable TEXT,
+        language TEXT,
+        instrumental INTEGER
     );
 ";
-const INSERT_TRACK_SQL: &str = "
-    INSERT INTO tracks (path, title, artist, track, album, year, duration_sec, bit_depth, bitrate_kbps, sample_rate_hz)
-    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+/// How long past transcodes and burns took, for estimating the next one.
+const CREATE_THROUGHPUT_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS throughput (
+        id INTEGER PRIMARY KEY,
+        stage TEXT NOT NULL,
+        audio_secs INTEGER NOT NULL,
+        wall_secs REAL NOT NULL
+    );
 ";
-const CREATE_TRACKS_FTS_SQL: &str = "
-    CREATE VIRTUAL TABLE tracks_fts
-    USING fts5 (
-        id, title, artist, album
+/// Tracks auditioned with `playlist preview` and when, to pick curation back up from.
+const CREATE_PREVIEWS_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS previews (
+        id INTEGER PRIMARY KEY,
+        track_id INTEGER NOT NULL,
+        played_at INTEGER NOT NULL
+    );
+";
+/// Every burn attempt with the disc title it was given, the series and volume it
+/// belongs to if any, the IDs of the disc that came out, and the tracks it carried.
+const CREATE_BURNS_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS burns (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        series TEXT,
+        volume INTEGER,
+        burned_at INTEGER NOT NULL,
+        tracks INTEGER NOT NULL,
+        duration_secs INTEGER NOT NULL,
+        ok INTEGER NOT NULL,
+        log TEXT,
+        disc_id TEXT,
+        freedb_id TEXT,
+        toc TEXT
+    );
+    CREATE TABLE IF NOT EXISTS burn_tracks (
+        burn_id INTEGER NOT NULL REFERENCES burns (id),
+        position INTEGER NOT NULL,
+        track_id INTEGER NOT NULL,
+        PRIMARY KEY (burn_id, position)
+    );
+";
+/// A summary of every scan, and the tracks of the latest ones as they were then, for
+/// `db diff`.
+const CREATE_SCANS_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS scans (
+        id INTEGER PRIMARY KEY,
+        scanned_at INTEGER NOT NULL,
+        music_dir TEXT NOT NULL,
+        tracks INTEGER NOT NULL,
+        added INTEGER NOT NULL,
+        removed INTEGER NOT NULL,
+        changed INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS scan_tracks (
+        scan_id INTEGER NOT NULL REFERENCES scans (id),
+        path TEXT NOT NULL,
+        title TEXT,
+        artist TEXT,
+        album TEXT,
+        track INTEGER,
+        year INTEGER,
+        duration_sec INTEGER,
+        genre TEXT,
+        PRIMARY KEY (scan_id, path)
+    );
+";
+/// Artist spellings browsed under another name, e.g. "Bjork" under "Björk". The
+/// tracks keep their tags.
+const CREATE_ALIASES_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS artist_aliases (
+        alias TEXT PRIMARY KEY,
+        canonical TEXT NOT NULL
     );
 ";
+/// Tracks `playlist gate allow` lets through the quality gate whatever their quality.
+const CREATE_GATE_ALLOWED_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS gate_allowed (
+        track_id INTEGER PRIMARY KEY
+    );
+";
+/// The artist, track and duration totals shown under the artists pane, counted once
+/// and kept until the library changes.
+const CREATE_LIBRARY_TOTALS_SQL: &str = "
+    CREATE TABLE IF NOT EXISTS library_totals (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        artists INTEGER NOT NULL,
+        tracks INTEGER NOT NULL,
+        duration_secs INTEGER NOT NULL
+    );
+";
+const INSERT_TRACK_SQL: &str = "
+    INSERT INTO tracks (path, title, artist, track, album, year, duration_sec, bit_depth, bitrate_kbps, sample_rate_hz, genre, musical_key, language, instrumental)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+";
+/// Adds a scanned track, or rewrites the row already at its path so a track keeps its ID
+/// from one scan to the next. Rows whose file reads the same are left alone, so nothing
+/// changes for them. As with [`UPDATE_TRACK_SQL`], a detected key is kept, and so are a
+/// language and instrumental flag set by hand.
+const UPSERT_TRACK_SQL: &str = "
+    INSERT INTO tracks (path, title, artist, track, album, year, duration_sec, bit_depth, bitrate_kbps, sample_rate_hz, genre, musical_key, language, instrumental)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+    ON CONFLICT (path) DO UPDATE SET title = excluded.title, artist = excluded.artist,
+        track = excluded.track, album = excluded.album, year = excluded.year,
+        duration_sec = excluded.duration_sec, bit_depth = excluded.bit_depth,
+        bitrate_kbps = excluded.bitrate_kbps, sample_rate_hz = excluded.sample_rate_hz,
+        genre = excluded.genre, musical_key = COALESCE(excluded.musical_key, musical_key),
+        language = COALESCE(excluded.language, language),
+        instrumental = COALESCE(excluded.instrumental, instrumental)
+    WHERE title IS NOT excluded.title OR artist IS NOT excluded.artist
+        OR track IS NOT excluded.track OR album IS NOT excluded.album
+        OR year IS NOT excluded.year OR duration_sec IS NOT excluded.duration_sec
+        OR bit_depth IS NOT excluded.bit_depth OR bitrate_kbps IS NOT excluded.bitrate_kbps
+        OR sample_rate_hz IS NOT excluded.sample_rate_hz OR genre IS NOT excluded.genre
+        OR musical_key IS NOT COALESCE(excluded.musical_key, musical_key)
+        OR language IS NOT COALESCE(excluded.language, language)
+        OR instrumental IS NOT COALESCE(excluded.instrumental, instrumental)
+";
+/// Rewrites a track from its file. A key that was detected rather than tagged is kept
+/// when the file still has none, as are a language and instrumental flag set by hand.
+const UPDATE_TRACK_SQL: &str = "
+    UPDATE tracks SET title = ?2, artist = ?3, track = ?4, album = ?5, year = ?6,
+        duration_sec = ?7, bit_depth = ?8, bitrate_kbps = ?9, sample_rate_hz = ?10,
+        genre = ?11, musical_key = COALESCE(?12, musical_key),
+        language = COALESCE(?13, language), instrumental = COALESCE(?14, instrumental)
+    WHERE id = ?1
+";
 const INSERT_TRACKS_FTS_SQL: &str = "
-    INSERT INTO tracks_fts (id, title, artist, album)
-    SELECT id, title, artist, album
+    INSERT INTO tracks_fts (id, title, artist, album, path)
+    SELECT id, title, artist, album, path
     FROM tracks;
 ";
 
-pub fn build_db(music_dir: &Path) -> anyhow::Result<()> {
-    let mut conn = Connection::open(DB_PATH)
-        .with_context(|| format!("failed to open db at path \"{DB_PATH}\""))?;
+/// How the full-text search table is built, from the `fts_tokenizer` and `fts_prefix`
+/// config settings. Changes take effect on the next scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtsOptions {
+    /// FTS5 tokenizer and its arguments.
+    pub tokenizer: String,
+    /// Prefix lengths indexed to speed up `word*` searches.
+    pub prefix: Vec<u32>,
+}
+
+impl Default for FtsOptions {
+    fn default() -> Self {
+        FtsOptions {
+            tokenizer: String::from("unicode61 remove_diacritics 2"),
+            prefix: vec![2, 3],
+        }
+    }
+}
+
+impl FtsOptions {
+    /// Applies an `fts_tokenizer` or `fts_prefix` setting from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "fts_tokenizer" => {
+                anyhow::ensure!(
+                    !value.is_empty() && !value.contains(['\'', '"']),
+                    "expected a tokenizer such as \"unicode61 remove_diacritics 2\""
+                );
+                self.tokenizer = value.to_string();
+            }
+            "fts_prefix" => {
+                self.prefix = value
+                    .split_whitespace()
+                    .map(|length| length.parse::<u32>().ok().filter(|&length| length > 0))
+                    .collect::<Option<Vec<_>>>()
+                    .context("expected prefix lengths such as \"2 3 4\"")?;
+            }
+            _ => anyhow::bail!("unknown full-text search setting \"{}\"", key),
+        }
+
+        Ok(())
+    }
+
+    fn create_sql(&self) -> String {
+        let prefix = if self.prefix.is_empty() {
+            String::new()
+        } else {
+            let lengths = self.prefix.iter().map(u32::to_string).collect::<Vec<_>>();
+            format!(",\n        prefix = '{}'", lengths.join(" "))
+        };
+
+        format!(
+            "CREATE VIRTUAL TABLE tracks_fts
+    USING fts5 (
+        id, title, artist, album, path,
+        tokenize = '{}'{}
+    );",
+            self.tokenizer, prefix
+        )
+    }
+}
+
+/// How a scan copes with slow or flaky storage such as NFS and SMB mounts, from the
+/// `scan_timeout` and `scan_retries` config settings.
+#[derive(Debug, Clone, Copy)]
+pub struct ScanIo {
+    /// How long reading one file's tags, or listing the next part of the library, may
+    /// take before the attempt counts as failed.
+    pub timeout: Duration,
+    /// How often a file that timed out or hit an IO error is tried again.
+    pub retries: u32,
+}
+
+impl Default for ScanIo {
+    fn default() -> Self {
+        ScanIo {
+            timeout: Duration::from_secs(30),
+            retries: 2,
+        }
+    }
+}
+
+impl ScanIo {
+    /// Applies a `scan_timeout` (seconds) or `scan_retries` setting from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<()> {
+        match key {
+            "scan_timeout" => {
+                self.timeout = value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&secs| secs > 0)
+                    .map(Duration::from_secs)
+                    .context("expected a timeout of at least 1 second")?;
+            }
+            "scan_retries" => {
+                self.retries = value.parse().context("expected a number of retries")?;
+            }
+            _ => anyhow::bail!("unknown scan setting \"{}\"", key),
+        }
+
+        Ok(())
+    }
+}
+
+/// Wait before the first retry of a file; later retries wait longer.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+/// How often a paused scan checks whether the share is back.
+const SHARE_POLL: Duration = Duration::from_secs(5);
+/// Threads reading tags and listing folders for a scan. A read stuck on a dead mount
+/// holds one until the mount returns, so only this many are ever stuck.
+const IO_THREADS: usize = 4;
+
+/// Optional, slower passes run as part of a scan.
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// Estimate the tempo of tracks that don't have one stored yet.
+    pub detect_bpm: bool,
+    /// Estimate the key of tracks that neither have one stored nor tagged.
+    pub detect_key: bool,
+    /// Measure the average level of tracks that haven't been measured yet.
+    pub detect_loudness: bool,
+    pub fts: FtsOptions,
+    pub io: ScanIo,
+}
+
+/// Scans `music_dir` into the library, publishing [`Event::ScanProgress`] on `events`
+/// as files are examined along with the scan's log lines.
+pub fn build_db(music_dir: &Path, options: &ScanOptions, events: &EventBus) -> anyhow::Result<()> {
+    if !music_dir.is_dir() {
+        return Err(ScanError::NotADirectory(music_dir.to_path_buf()).into());
+    }
+
+    let db_path = paths::db();
+    let mut conn = Connection::open(&db_path).map_err(|source| DbError::Open {
+        path: db_path.display().to_string(),
+        source,
+    })?;
+
+    build_tracks_table(&mut conn, music_dir, options, events)
+        .context("failed to create table \"tracks\"")?;
+    crate::totals::invalidate(&conn)?;
+
+    match crate::snapshot::record(&mut conn, music_dir)? {
+        Some(diff) => log(
+            events,
+            false,
+            format!(
+                "since the last scan: {} added, {} removed, {} changed (see \"db diff\")",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            ),
+        ),
+        None => log(
+            events,
+            false,
+            String::from("took the first library snapshot"),
+        ),
+    }
+
+    if options.detect_bpm || options.detect_key || options.detect_loudness {
+        analyze_missing(&mut conn, options, events).context("failed to analyze tracks")?;
+    }
+
+    Ok(())
+}
+
+/// Reports a line of scan output to whoever is listening on `events`.
+fn log(events: &EventBus, is_stderr: bool, line: String) {
+    events.publish(LogLine { is_stderr, line }.into());
+}
+
+/// Runs the requested analyses for every track missing their results, decoding each
+/// track only once.
+fn analyze_missing(
+    conn: &mut Connection,
+    options: &ScanOptions,
+    events: &EventBus,
+) -> anyhow::Result<()> {
+    let pending = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, path, bpm IS NULL AND ?1, musical_key IS NULL AND ?2,
+                    loudness_db IS NULL AND ?3
+                FROM tracks
+                WHERE (bpm IS NULL AND ?1) OR (musical_key IS NULL AND ?2)
+                    OR (loudness_db IS NULL AND ?3)",
+            )
+            .context("failed to prepare query for tracks missing analysis")?;
+        stmt.query_map(
+            (
+                options.detect_bpm,
+                options.detect_key,
+                options.detect_loudness,
+            ),
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                    row.get::<_, bool>(3)?,
+                    row.get::<_, bool>(4)?,
+                ))
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read tracks missing analysis")?
+    };
+
+    log(
+        events,
+        false,
+        format!("analyzing {} tracks...", pending.len()),
+    );
+
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for storing analysis")?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "UPDATE tracks
+                SET bpm = COALESCE(bpm, ?1), musical_key = COALESCE(musical_key, ?2),
+                    loudness_db = COALESCE(loudness_db, ?3)
+                WHERE id = ?4",
+            )
+            .context("failed to obtain cached statement for storing analysis")?;
+        for (id, path, bpm, key, loudness) in pending {
+            match crate::analysis::analyze(&path, bpm, key, loudness) {
+                Ok(analysis) => {
+                    if analysis.bpm.is_none()
+                        && analysis.key.is_none()
+                        && analysis.loudness_db.is_none()
+                    {
+                        log(events, true, format!("could not analyze {}", path));
+                    }
+                    stmt.execute((
+                        analysis.bpm,
+                        analysis.key.map(|k| k.to_string()),
+                        analysis.loudness_db,
+                        id,
+                    ))
+                    .with_context(|| format!("failed to store analysis for {}", path))?;
+                }
+                Err(err) => log(
+                    events,
+                    true,
+                    format!("failed to analyze {}: {:?}", path, err),
+                ),
+            }
+        }
+    }
+    tx.commit().context("failed to commit analysis")?;
+
+    Ok(())
+}
+
+/// Columns added to `tracks` after its first release, with their SQL types. Databases
+/// scanned by older versions get them added (empty) instead of failing queries.
+const ADDED_TRACK_COLUMNS: &[(&str, &str)] = &[
+    ("genre", "TEXT"),
+    ("bpm", "REAL"),
+    ("musical_key", "TEXT"),
+    ("note", "TEXT"),
+    ("loudness_db", "REAL"),
+    ("unplayable", "TEXT"),
+    ("language", "TEXT"),
+    ("instrumental", "INTEGER"),
+];
+/// Columns added to `burns` after its first release, the same way.
+const ADDED_BURN_COLUMNS: &[(&str, &str)] = &[
+    ("series", "TEXT"),
+    ("volume", "INTEGER"),
+    ("log", "TEXT"),
+    ("disc_id", "TEXT"),
+    ("freedb_id", "TEXT"),
+    ("toc", "TEXT"),
+];
+
+/// Creates the bookkeeping tables and brings existing tables up to the current schema.
+/// The `tracks` table itself is left for a scan to create.
+pub fn migrate(conn: &Connection, fts: &FtsOptions) -> anyhow::Result<()> {
+    conn.execute(CREATE_THROUGHPUT_SQL, ())
+        .context("failed to create throughput table")?;
+    conn.execute(CREATE_PREVIEWS_SQL, ())
+        .context("failed to create previews table")?;
+    conn.execute_batch(CREATE_BURNS_SQL)
+        .context("failed to create burn history tables")?;
+    conn.execute_batch(CREATE_SCANS_SQL)
+        .context("failed to create scan snapshot tables")?;
+    conn.execute(CREATE_ALIASES_SQL, ())
+        .context("failed to create artist aliases table")?;
+    conn.execute(CREATE_LIBRARY_TOTALS_SQL, ())
+        .context("failed to create library totals table")?;
+    conn.execute(CREATE_GATE_ALLOWED_SQL, ())
+        .context("failed to create quality gate table")?;
+
+    add_missing_columns(conn, "tracks", ADDED_TRACK_COLUMNS)?;
+    add_missing_columns(conn, "burns", ADDED_BURN_COLUMNS)?;
+
+    // search indexes from before file paths were searchable
+    let fts_columns = table_columns(conn, "tracks_fts")?;
+    if !fts_columns.is_empty() && !fts_columns.iter().any(|c| c == "path") {
+        let tx = conn
+            .unchecked_transaction()
+            .context("failed to obtain transaction for rebuilding fts table")?;
+        rebuild_fts(&tx, fts)?;
+        tx.commit().context("failed to commit fts table")?;
+    }
+
+    Ok(())
+}
+
+fn table_columns(conn: &Connection, table: &str) -> anyhow::Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare("SELECT name FROM pragma_table_info(?1)")
+        .with_context(|| format!("failed to inspect {table} table"))?;
+    stmt.query_map([table], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("failed to read {table} table columns"))
+}
+
+/// Adds whichever of `added` table `table` lacks. A table that doesn't exist yet is left
+/// alone.
+fn add_missing_columns(
+    conn: &Connection,
+    table: &str,
+    added: &[(&str, &str)],
+) -> anyhow::Result<()> {
+    let columns = table_columns(conn, table)?;
+    if columns.is_empty() {
+        return Ok(());
+    }
 
-    build_tracks_table(&mut conn, music_dir).context("failed to create table \"tracks\"")?;
+    for (column, sql_type) in added {
+        if !columns.iter().any(|c| c == column) {
+            conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN {column} {sql_type}"),
+                (),
+            )
+            .with_context(|| format!("failed to add column \"{column}\" to {table}"))?;
+        }
+    }
 
     Ok(())
 }
 
-fn build_tracks_table(conn: &mut Connection, music_dir: &Path) -> anyhow::Result<()> {
+/// Scans `music_dir` into the tracks table and rebuilds the search table from it.
+fn build_tracks_table(
+    conn: &mut Connection,
+    music_dir: &Path,
+    options: &ScanOptions,
+    events: &EventBus,
+) -> anyhow::Result<()> {
     conn.execute(CREATE_TRACKS_SQL, ())?;
+    migrate(conn, &options.fts)?;
 
     // tracks table
     {
@@ -72,120 +559,594 @@ fn build_tracks_table(conn: &mut Connection, music_dir: &Path) -> anyhow::Result
             .transaction()
             .context("failed to obtain transaction for building tracks table")?;
 
-        let results = scan_and_insert_in_transaction(&tx, music_dir)?;
+        let results = scan_and_insert_in_transaction(&tx, music_dir, options.io, events)?;
 
         for error in results.read_errors {
-            println!("encountered an error when scanning the library: {}", error);
+            log(
+                events,
+                true,
+                format!("encountered an error when scanning the library: {}", error),
+            );
         }
-        println!("inserted {} tracks", results.inserted_count);
+        log(
+            events,
+            false,
+            format!(
+                "added {} tracks, updated {}, {} unchanged, {} removed",
+                results.added, results.updated, results.unchanged, results.removed
+            ),
+        );
 
         tx.commit()?;
     }
 
-    // full-text search table (fts)
+    // full-text search table (fts), rebuilt from scratch so tokenizer changes apply
     {
         let tx = conn
             .transaction()
             .context("failed to obtain transaction for building fts table")?;
 
-        tx.execute(CREATE_TRACKS_FTS_SQL, ())
-            .context("failed to execute creating fts table")?;
-
-        tx.execute(INSERT_TRACKS_FTS_SQL, ())
-            .context("failed to build fts table from tracks table")?;
-
+        rebuild_fts(&tx, &options.fts)?;
         tx.commit().context("failed to commit fts table")?;
     }
 
     Ok(())
 }
 
+/// Recreates the full-text search table from the tracks table.
+fn rebuild_fts(conn: &Connection, fts: &FtsOptions) -> anyhow::Result<()> {
+    conn.execute("DROP TABLE IF EXISTS tracks_fts", ())
+        .context("failed to drop old fts table")?;
+    conn.execute(&fts.create_sql(), ())
+        .context("failed to execute creating fts table")?;
+    conn.execute(INSERT_TRACKS_FTS_SQL, ())
+        .context("failed to build fts table from tracks table")?;
+
+    Ok(())
+}
+
 #[derive(Debug)]
 struct TracksResults {
-    inserted_count: usize,
-    read_errors: Vec<anyhow::Error>,
+    /// Tracks at paths no earlier scan found.
+    added: usize,
+    /// Tracks whose file now reads differently.
+    updated: usize,
+    unchanged: usize,
+    /// Tracks whose file is gone.
+    removed: usize,
+    read_errors: Vec<ScanError>,
 }
 
 /// Scans the directory, extracts metadata, and inserts into the database.
 fn scan_and_insert_in_transaction(
     tx: &Transaction,
     root_dir: &Path,
+    io: ScanIo,
+    events: &EventBus,
 ) -> anyhow::Result<TracksResults> {
     let mut stmt = tx
-        .prepare_cached(INSERT_TRACK_SQL)
+        .prepare_cached(UPSERT_TRACK_SQL)
         .context("failed to obtain cached statement for inserting track")?;
-    let mut inserted_count = 0;
+    let before = count_tracks(tx)?;
+    let mut changed = 0;
+    let mut stored = 0;
+    let mut scanned = 0;
     let mut read_errors = vec![];
+    let mut walked = HashSet::new();
 
-    println!("Scanning directory: {}...", root_dir.display());
+    log(
+        events,
+        false,
+        format!("Scanning directory: {}...", root_dir.display()),
+    );
 
-    for entry in WalkDir::new(root_dir).into_iter().filter_map(|e| e.ok()) {
+    let pool = IoPool::new(IO_THREADS);
+    let entries = walk(root_dir);
+    let mut walk_failed = false;
+    let mut stalls = 0;
+    loop {
+        let entry = match entries.recv_timeout(io.timeout) {
+            Ok(Ok(entry)) => entry,
+            Ok(Err(err)) => {
+                walk_failed = true;
+                read_errors.push(ScanError::Walk(err));
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                if !reachable(&pool, root_dir, Some(io.timeout)) {
+                    wait_for_share(&pool, root_dir, events);
+                    continue;
+                }
+                if stalls == io.retries {
+                    // the rest of the library is left unlisted, like a folder that failed
+                    walk_failed = true;
+                    read_errors.push(ScanError::TimedOut {
+                        path: root_dir.to_path_buf(),
+                        secs: io.timeout.as_secs(),
+                    });
+                    break;
+                }
+                stalls += 1;
+                log(
+                    events,
+                    true,
+                    format!(
+                        "still listing {} ({}/{})",
+                        root_dir.display(),
+                        stalls,
+                        io.retries
+                    ),
+                );
+                continue;
+            }
+        };
+        stalls = 0;
         let path = entry.path();
-        if path.is_file() {
+        // the directory listing already says what most entries are, saving a stat per
+        // file on slow network shares
+        if entry.file_type().is_file() || (entry.path_is_symlink() && path.is_file()) {
             // Check for common music extensions before probing
             let Some(ext) = path.extension().and_then(|s| s.to_str()) else {
                 continue;
             };
 
-            if !matches!(ext, "mp3" | "flac" | "ogg" | "m4a") {
+            if !matches!(ext, "mp3" | "flac" | "ogg" | "m4a" | "m4b") {
                 continue;
             }
+            scanned += 1;
+            events.publish(Event::ScanProgress { scanned });
+            walked.insert(path.to_string_lossy().into_owned());
 
             // Probe the file and extract metadata using lofty
-            match Probe::open(path).and_then(|p| p.read()) {
+            match read_tags(&pool, path, root_dir, io, events) {
                 Ok(tagged_file) => {
                     let track = match song_from_tags(&tagged_file, path) {
                         Ok(track) => track,
                         Err(e) => {
-                            read_errors.push(anyhow::format_err!(
-                                "failed to obtain tags/properties for {}: {}",
-                                path.display(),
-                                e
-                            ));
+                            read_errors.push(ScanError::MissingTags {
+                                path: path.to_path_buf(),
+                                reason: e.to_string(),
+                            });
                             continue;
                         }
                     };
 
-                    // Insert the track data into the prepared statement
-                    stmt.execute((
-                        &track.path,
-                        &track.title,
-                        &track.artist,
-                        &track.track,
-                        &track.album,
-                        &track.year,
-                        &track.duration_sec,
-                        &track.bit_depth,
-                        &track.bitrate_kbps,
-                        &track.sample_rate_hz,
-                    ))
-                    .with_context(|| {
-                        format!("failed to insert the following track: {:?}", &track)
-                    })?;
-                    inserted_count += 1;
-                }
-                Err(e) => {
-                    read_errors.push(anyhow::format_err!(
-                        "failed to read tags for {}: {}",
-                        path.display(),
-                        e
-                    ));
+                    // Insert the track data into the prepared statement, updating the
+                    // track at this path if an earlier scan found it
+                    changed += stmt
+                        .execute((
+                            &track.path,
+                            &track.title,
+                            &track.artist,
+                            &track.track,
+                            &track.album,
+                            &track.year,
+                            &track.duration_sec,
+                            &track.bit_depth,
+                            &track.bitrate_kbps,
+                            &track.sample_rate_hz,
+                            &track.genre,
+                            &track.musical_key,
+                            &track.language,
+                            &track.instrumental,
+                        ))
+                        .with_context(|| {
+                            format!("failed to insert the following track: {:?}", &track)
+                        })?;
+                    stored += 1;
                 }
+                Err(error) => read_errors.push(error),
             }
         }
     }
 
+    // a folder that couldn't be listed may well still hold its tracks
+    let removed = if walk_failed {
+        log(
+            events,
+            true,
+            String::from(
+                "kept the tracks of files not found, as parts of the library couldn't be listed",
+            ),
+        );
+        0
+    } else {
+        remove_vanished(tx, root_dir, &walked)?
+    };
+
+    let after = count_tracks(tx)?;
+    let added = after + removed - before;
     Ok(TracksResults {
-        inserted_count,
+        added,
+        updated: changed - added,
+        unchanged: stored - changed,
+        removed,
         read_errors,
     })
 }
 
+/// Deletes the tracks under `root_dir` whose file isn't among the `walked` ones, along
+/// with their previews and quality gate allowances. Returns how many went.
+fn remove_vanished(
+    tx: &Transaction,
+    root_dir: &Path,
+    walked: &HashSet<String>,
+) -> anyhow::Result<usize> {
+    let prefix = format!("{}/", root_dir.to_string_lossy().trim_end_matches('/'));
+    let vanished = {
+        let mut stmt = tx
+            .prepare("SELECT id, path FROM tracks WHERE substr(path, 1, length(?1)) = ?1")
+            .context("failed to prepare query for scanned tracks")?;
+        stmt.query_map([&prefix], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .filter(|row| !matches!(row, Ok((_, path)) if walked.contains(path)))
+        .map(|row| row.map(|(id, _)| id))
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read scanned tracks")?
+    };
+    for id in &vanished {
+        for sql in [
+            "DELETE FROM previews WHERE track_id = ?1",
+            "DELETE FROM gate_allowed WHERE track_id = ?1",
+            "DELETE FROM tracks WHERE id = ?1",
+        ] {
+            tx.execute(sql, [id])
+                .with_context(|| format!("failed to remove vanished track {}", id))?;
+        }
+    }
+
+    Ok(vanished.len())
+}
+
+fn count_tracks(conn: &Connection) -> anyhow::Result<usize> {
+    conn.query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))
+        .context("failed to count tracks")
+}
+
+/// Why one attempt at reading a file's tags failed.
+enum ReadFailure {
+    TimedOut,
+    Lofty(LoftyError),
+}
+
+/// A call on an [`IoPool`] thread.
+type IoJob = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of threads for file system calls that may never return on a dead
+/// network share. Such a call can't be cancelled, so the scan stops waiting for it and
+/// the thread stays stuck until the share is back, rather than leaking a new one for
+/// every file.
+struct IoPool {
+    jobs: mpsc::Sender<IoJob>,
+}
+
+impl IoPool {
+    fn new(threads: usize) -> IoPool {
+        let (jobs, queue) = mpsc::channel::<IoJob>();
+        let queue = Arc::new(Mutex::new(queue));
+        for _ in 0..threads {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                loop {
+                    // the lock is only held while waiting for the next job
+                    let job = match queue.lock() {
+                        Ok(queue) => queue.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        Ok(job) => job(),
+                        // the pool was dropped
+                        Err(_) => return,
+                    }
+                }
+            });
+        }
+
+        IoPool { jobs }
+    }
+
+    /// Runs `call` on one of the threads and returns what it did, or `None` when that
+    /// took longer than `timeout`. Without one, waits for as long as it takes.
+    fn run<T: Send + 'static>(
+        &self,
+        timeout: Option<Duration>,
+        call: impl FnOnce() -> T + Send + 'static,
+    ) -> Option<T> {
+        let (tx, rx) = mpsc::channel();
+        self.jobs
+            .send(Box::new(move || {
+                let _ = tx.send(call());
+            }))
+            .ok()?;
+
+        match timeout {
+            Some(timeout) => rx.recv_timeout(timeout).ok(),
+            None => rx.recv().ok(),
+        }
+    }
+}
+
+/// Lists `root` on a thread of its own, sending every entry as it is found, so the scan
+/// can give up on a listing stuck on a dead mount.
+fn walk(root: &Path) -> mpsc::Receiver<walkdir::Result<DirEntry>> {
+    let (tx, rx) = mpsc::channel();
+    let root = root.to_path_buf();
+    thread::spawn(move || {
+        for entry in WalkDir::new(root) {
+            if tx.send(entry).is_err() {
+                // the scan stopped listening
+                return;
+            }
+        }
+    });
+
+    rx
+}
+
+/// Reads the tags of `path` on the pool, giving up after `timeout`.
+fn probe_with_timeout(
+    pool: &IoPool,
+    path: &Path,
+    timeout: Duration,
+) -> Result<TaggedFile, ReadFailure> {
+    let path = path.to_path_buf();
+    match pool.run(Some(timeout), move || {
+        Probe::open(&path).and_then(|p| p.read())
+    }) {
+        Some(result) => result.map_err(ReadFailure::Lofty),
+        None => Err(ReadFailure::TimedOut),
+    }
+}
+
+/// Whether `dir` can be listed, within `timeout` when one is given.
+fn reachable(pool: &IoPool, dir: &Path, timeout: Option<Duration>) -> bool {
+    let dir = dir.to_path_buf();
+    pool.run(timeout, move || std::fs::read_dir(&dir).is_ok())
+        .unwrap_or(false)
+}
+
+/// Blocks until `root` can be listed again, e.g. once a dropped share is remounted.
+/// Interrupting the scan is left to Ctrl+C.
+fn wait_for_share(pool: &IoPool, root: &Path, events: &EventBus) {
+    log(
+        events,
+        true,
+        format!(
+            "lost access to {}: scan paused until it is back (Ctrl+C to give up)",
+            root.display()
+        ),
+    );
+    // a listing stuck on a hard mount returns once the share does
+    while !reachable(pool, root, None) {
+        thread::sleep(SHARE_POLL);
+    }
+    log(
+        events,
+        false,
+        format!("{} is back, resuming scan", root.display()),
+    );
+}
+
+/// Reads the tags of `path` below `root`, retrying reads that time out or hit IO errors
+/// and pausing while `root` itself can't be reached. Files that aren't valid audio fail
+/// straight away.
+fn read_tags(
+    pool: &IoPool,
+    path: &Path,
+    root: &Path,
+    io: ScanIo,
+    events: &EventBus,
+) -> Result<TaggedFile, ScanError> {
+    let mut attempt = 0;
+    loop {
+        let failure = match probe_with_timeout(pool, path, io.timeout) {
+            Ok(tagged_file) => return Ok(tagged_file),
+            Err(ReadFailure::Lofty(source))
+                if !matches!(source.kind(), lofty::error::ErrorKind::Io(_)) =>
+            {
+                return Err(ScanError::ReadTags {
+                    path: path.to_path_buf(),
+                    source,
+                });
+            }
+            Err(failure) => failure,
+        };
+
+        if !reachable(pool, root, Some(io.timeout)) {
+            wait_for_share(pool, root, events);
+            continue;
+        }
+        if attempt == io.retries {
+            return Err(match failure {
+                ReadFailure::TimedOut => ScanError::TimedOut {
+                    path: path.to_path_buf(),
+                    secs: io.timeout.as_secs(),
+                },
+                ReadFailure::Lofty(source) => ScanError::ReadTags {
+                    path: path.to_path_buf(),
+                    source,
+                },
+            });
+        }
+        attempt += 1;
+        log(
+            events,
+            true,
+            format!("retrying {} ({}/{})", path.display(), attempt, io.retries),
+        );
+        thread::sleep(RETRY_DELAY * attempt);
+    }
+}
+
+/// Reads track `id`'s file again and updates its row and search entry to match, e.g.
+/// after the file's tags were edited elsewhere.
+pub fn reprobe_track(conn: &mut Connection, id: i64) -> anyhow::Result<()> {
+    let path: String = conn
+        .query_row("SELECT path FROM tracks WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => DbError::TrackNotFound(id),
+            err => DbError::Query(err),
+        })?;
+    let path = Path::new(&path);
+    let tagged_file =
+        Probe::open(path)
+            .and_then(|p| p.read())
+            .map_err(|source| ScanError::ReadTags {
+                path: path.to_path_buf(),
+                source,
+            })?;
+    let track = song_from_tags(&tagged_file, path).map_err(|e| ScanError::MissingTags {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for updating track")?;
+    tx.execute(
+        UPDATE_TRACK_SQL,
+        (
+            id,
+            &track.title,
+            &track.artist,
+            &track.track,
+            &track.album,
+            &track.year,
+            &track.duration_sec,
+            &track.bit_depth,
+            &track.bitrate_kbps,
+            &track.sample_rate_hz,
+            &track.genre,
+            &track.musical_key,
+            &track.language,
+            &track.instrumental,
+        ),
+    )
+    .with_context(|| format!("failed to update the following track: {:?}", &track))?;
+    tx.execute(
+        "UPDATE tracks_fts SET title = ?2, artist = ?3, album = ?4 WHERE id = ?1",
+        (id, &track.title, &track.artist, &track.album),
+    )
+    .context("failed to update the search index")?;
+    crate::totals::invalidate(&tx)?;
+
+    tx.commit().context("failed to commit track update")
+}
+
+/// Reads a file that isn't in the library as a playlist entry with ID 0, titled after
+/// the file name when its tags are missing.
+pub fn song_from_file(path: &Path) -> anyhow::Result<Song> {
+    use lofty::file::AudioFile;
+
+    let tagged_file =
+        Probe::open(path)
+            .and_then(|p| p.read())
+            .map_err(|source| ScanError::ReadTags {
+                path: path.to_path_buf(),
+                source,
+            })?;
+    let song = match song_from_tags(&tagged_file, path) {
+        Ok(track) => Song {
+            id: 0,
+            path: track.path.into_owned(),
+            title: track.title.into_owned(),
+            artist: track.artist.into_owned(),
+            album: track.album.into_owned(),
+            track: track.track.into(),
+            year: track.year,
+            duration_sec: track.duration_sec,
+            edit: TrackEdit::default(),
+        },
+        Err(_) => Song {
+            id: 0,
+            path: path.to_string_lossy().into_owned(),
+            title: path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned()),
+            artist: String::new(),
+            album: String::new(),
+            track: 0,
+            year: 0,
+            duration_sec: tagged_file.properties().duration().as_secs(),
+            edit: TrackEdit::default(),
+        },
+    };
+
+    Ok(song)
+}
+
+/// Adds the file at `path` to the library the way a scan would and returns its track
+/// ID, or the ID it already has.
+pub fn import_file(conn: &mut Connection, path: &Path) -> anyhow::Result<i64> {
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("failed to find {}", path.display()))?;
+    let tagged_file = Probe::open(&path)
+        .and_then(|p| p.read())
+        .map_err(|source| ScanError::ReadTags {
+            path: path.clone(),
+            source,
+        })?;
+    let track = song_from_tags(&tagged_file, &path).map_err(|e| ScanError::MissingTags {
+        path: path.clone(),
+        reason: e.to_string(),
+    })?;
+
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for importing track")?;
+    let existing = tx.query_row(
+        "SELECT id FROM tracks WHERE path = ?1",
+        [&track.path],
+        |row| row.get(0),
+    );
+    match existing {
+        Ok(id) => return Ok(id),
+        Err(rusqlite::Error::QueryReturnedNoRows) => (),
+        Err(err) => return Err(DbError::Query(err).into()),
+    }
+    tx.execute(
+        INSERT_TRACK_SQL,
+        (
+            &track.path,
+            &track.title,
+            &track.artist,
+            &track.track,
+            &track.album,
+            &track.year,
+            &track.duration_sec,
+            &track.bit_depth,
+            &track.bitrate_kbps,
+            &track.sample_rate_hz,
+            &track.genre,
+            &track.musical_key,
+            &track.language,
+            &track.instrumental,
+        ),
+    )
+    .with_context(|| format!("failed to insert the following track: {:?}", &track))?;
+    let id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO tracks_fts (id, title, artist, album, path) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (id, &track.title, &track.artist, &track.album, &track.path),
+    )
+    .context("failed to add the track to the search index")?;
+    crate::totals::invalidate(&tx)?;
+
+    tx.commit().context("failed to commit imported track")?;
+    Ok(id)
+}
+
 /// Helper function to safely extract data from lofty's structures.
 fn song_from_tags<'a>(
     tagged_file: &'a TaggedFile,
     path: &'a Path,
 ) -> anyhow::Result<InsertSong<'a>> {
+    use crate::analysis::CamelotKey;
     use lofty::{
         file::{AudioFile, TaggedFileExt},
         tag::{Accessor, ItemKey, ItemValue},
@@ -206,7 +1167,23 @@ fn song_from_tags<'a>(
 
     let album = tag.album().unwrap_or(Borrowed(""));
     let year = tag.year().unwrap_or(0);
+    let genre = tag.genre().unwrap_or(Borrowed(""));
+    let musical_key = tag
+        .get_string(&ItemKey::InitialKey)
+        .and_then(|key| key.parse::<CamelotKey>().ok())
+        .map(|key| key.to_string());
     let track = tag.track().unwrap_or(0);
+    let language = tag
+        .get_string(&ItemKey::Language)
+        .map(str::trim)
+        .filter(|language| !language.is_empty());
+    // another language doesn't rule out an instrumental, so it leaves a flag set by hand
+    let instrumental = language
+        .is_some_and(|language| language.eq_ignore_ascii_case(tags::NO_LINGUISTIC_CONTENT))
+        .then_some(true);
+    let language = language
+        .filter(|_| instrumental != Some(true))
+        .map(str::to_string);
 
     let bitrate_kbps = properties
         .audio_bitrate()
@@ -227,5 +1204,9 @@ fn song_from_tags<'a>(
         bit_depth,
         bitrate_kbps,
         sample_rate_hz,
+        genre,
+        musical_key,
+        language,
+        instrumental,
     })
 }
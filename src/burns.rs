@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+
+use crate::app::Song;
+use crate::discid::DiscId;
+use crate::errors::DbError;
+
+/// One attempt at burning a disc, as kept in the library database.
+#[derive(Debug, Clone)]
+pub struct Burn {
+    pub id: i64,
+    pub title: String,
+    /// Series the disc belongs to and its volume number in it.
+    pub series: Option<(String, u32)>,
+    /// Local time the burn finished, e.g. `2024-06-01 21:14:03`.
+    pub burned_at: String,
+    pub tracks: usize,
+    pub duration_secs: u64,
+    /// Whether a disc came out of it.
+    pub ok: bool,
+    /// ID of the disc that came out, for burns recorded since they were kept.
+    pub disc_id: Option<DiscId>,
+}
+
+/// A named run of discs such as "Road Trips", as burned so far.
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub name: String,
+    /// Highest volume burned.
+    pub volumes: u32,
+    pub last_burned_at: String,
+}
+
+/// Keeps a burn of `playlist` titled `title` in the history, with its tracks in order,
+/// whether a disc came out (`ok`) and its ID if one could be worked out, and the `log`
+/// of everything its stages printed.
+pub fn record(
+    conn: &mut Connection,
+    title: &str,
+    series: Option<(&str, u32)>,
+    playlist: &[Song],
+    ok: bool,
+    disc_id: Option<&DiscId>,
+    log: &str,
+) -> Result<i64> {
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for burn history")?;
+    let duration_secs = playlist.iter().map(Song::played_duration_secs).sum::<u64>();
+    let (series, volume) = series.unzip();
+    tx.execute(
+        "INSERT INTO burns (title, series, volume, burned_at, tracks, duration_secs, ok, log,
+            disc_id, freedb_id, toc)
+        VALUES (?1, ?2, ?3, unixepoch(), ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            title,
+            series,
+            volume,
+            playlist.len() as i64,
+            duration_secs as i64,
+            ok,
+            log,
+            disc_id.map(|id| &id.musicbrainz),
+            disc_id.map(|id| &id.freedb),
+            disc_id.map(|id| &id.toc)
+        ],
+    )
+    .context("failed to record burn")?;
+    let id = tx.last_insert_rowid();
+    for (position, song) in playlist.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO burn_tracks (burn_id, position, track_id) VALUES (?1, ?2, ?3)",
+            params![id, position as i64 + 1, song.id],
+        )
+        .context("failed to record burned track")?;
+    }
+    tx.commit().context("failed to commit burn history")?;
+
+    Ok(id)
+}
+
+/// Track IDs of the last disc that came out, in order; empty before the first.
+pub fn last_burned_tracks(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT track_id FROM burn_tracks
+            WHERE burn_id = (SELECT MAX(id) FROM burns WHERE ok)
+            ORDER BY position",
+        )
+        .context("failed to prepare query for the last burned disc")?;
+    stmt.query_map([], |row| row.get(0))
+        .context("failed to query the last burned disc")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read the last burned disc")
+}
+
+const SELECT_BURNS_SQL: &str =
+    "SELECT id, title, series, volume, datetime(burned_at, 'unixepoch', 'localtime'),
+        tracks, duration_secs, ok, disc_id, freedb_id, toc
+    FROM burns";
+
+fn burn_from_row(row: &rusqlite::Row) -> rusqlite::Result<Burn> {
+    let series: Option<String> = row.get(2)?;
+    let volume: Option<u32> = row.get(3)?;
+    let disc_id: Option<String> = row.get(8)?;
+    let freedb_id: Option<String> = row.get(9)?;
+    let toc: Option<String> = row.get(10)?;
+    Ok(Burn {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        series: series.zip(volume),
+        burned_at: row.get(4)?,
+        tracks: row.get::<_, i64>(5)? as usize,
+        duration_secs: row.get::<_, i64>(6)? as u64,
+        ok: row.get(7)?,
+        disc_id: match (disc_id, freedb_id, toc) {
+            (Some(musicbrainz), Some(freedb), Some(toc)) => Some(DiscId {
+                musicbrainz,
+                freedb,
+                toc,
+            }),
+            _ => None,
+        },
+    })
+}
+
+/// Every burn, or only those of `series`, newest first.
+pub fn list(conn: &Connection, series: Option<&str>) -> Result<Vec<Burn>> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE ?1 IS NULL OR series = ?1 ORDER BY id DESC",
+            SELECT_BURNS_SQL
+        ))
+        .context("failed to prepare query for burn history")?;
+    stmt.query_map(params![series], burn_from_row)
+        .context("failed to query burn history")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to map burn history from database")
+}
+
+/// The burn with ID `id`.
+pub fn find(conn: &Connection, id: i64) -> Result<Burn> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", SELECT_BURNS_SQL),
+        params![id],
+        burn_from_row,
+    )
+    .map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => DbError::BurnNotFound(id),
+        err => DbError::Query(err),
+    })
+    .with_context(|| format!("failed to read burn {}", id))
+}
+
+/// Track IDs burn `id` was made of, in order.
+pub fn tracks(conn: &Connection, id: i64) -> Result<Vec<i64>> {
+    let mut stmt = conn
+        .prepare("SELECT track_id FROM burn_tracks WHERE burn_id = ?1 ORDER BY position")
+        .context("failed to prepare query for burned tracks")?;
+    stmt.query_map(params![id], |row| row.get(0))
+        .context("failed to query burned tracks")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read burned tracks")
+}
+
+/// What the stages of burn `id` printed, if it was kept; burns recorded before logs
+/// were kept have none.
+pub fn log(conn: &Connection, id: i64) -> Result<Option<String>> {
+    conn.query_row("SELECT log FROM burns WHERE id = ?1", params![id], |row| {
+        row.get(0)
+    })
+    .map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => DbError::BurnNotFound(id),
+        err => DbError::Query(err),
+    })
+    .with_context(|| format!("failed to read the log of burn {}", id))
+}
+
+/// Every series with a disc burned in it, most recently added to first.
+pub fn list_series(conn: &Connection) -> Result<Vec<Series>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT series, MAX(volume), datetime(MAX(burned_at), 'unixepoch', 'localtime')
+            FROM burns WHERE ok AND series IS NOT NULL
+            GROUP BY series ORDER BY MAX(id) DESC",
+        )
+        .context("failed to prepare query for series")?;
+    stmt.query_map([], |row| {
+        Ok(Series {
+            name: row.get(0)?,
+            volumes: row.get(1)?,
+            last_burned_at: row.get(2)?,
+        })
+    })
+    .context("failed to query series")?
+    .collect::<Result<Vec<_>, _>>()
+    .context("failed to map series from database")
+}
+
+/// The volume number the next disc of `series` gets. Failed burns don't use one up.
+pub fn next_volume(conn: &Connection, series: &str) -> Result<u32> {
+    let last: Option<u32> = conn
+        .query_row(
+            "SELECT MAX(volume) FROM burns WHERE ok AND series = ?1",
+            params![series],
+            |row| row.get(0),
+        )
+        .with_context(|| format!("failed to read the volumes of series \"{}\"", series))?;
+
+    Ok(last.unwrap_or(0) + 1)
+}
+
+/// Playlist entries whose artist and title were already on a disc of `series`, each with
+/// the first volume it appeared on. Other copies of a track count as the same track.
+pub fn series_repeats(
+    conn: &Connection,
+    series: &str,
+    playlist: &[Song],
+) -> Result<Vec<(Song, u32)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT lower(t.artist), lower(t.title), MIN(b.volume)
+            FROM burn_tracks bt
+                JOIN burns b ON b.id = bt.burn_id
+                JOIN tracks t ON t.id = bt.track_id
+            WHERE b.ok AND b.series = ?1
+            GROUP BY lower(t.artist), lower(t.title)",
+        )
+        .context("failed to prepare query for series tracks")?;
+    let burned = stmt
+        .query_map(params![series], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get(2)?,
+            ))
+        })
+        .context("failed to query series tracks")?
+        .collect::<Result<Vec<(String, String, u32)>, _>>()
+        .context("failed to map series tracks from database")?;
+
+    Ok(playlist
+        .iter()
+        .filter_map(|song| {
+            let (artist, title) = (song.artist.to_lowercase(), song.title.to_lowercase());
+            burned
+                .iter()
+                .find(|(a, t, _)| *a == artist && *t == title)
+                .map(|&(_, _, volume)| (song.clone(), volume))
+        })
+        .collect())
+}
+
+/// A title for the next disc, numbered after the discs already burned this month, e.g.
+/// `Mix 2024-06 Vol. 3`. Failed burns don't use up a volume.
+pub fn suggest_title(conn: &Connection) -> Result<String> {
+    let month: String = conn
+        .query_row("SELECT strftime('%Y-%m', 'now', 'localtime')", [], |row| {
+            row.get(0)
+        })
+        .context("failed to read the current month")?;
+    let prefix = format!("Mix {} Vol. ", month);
+    let burned: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM burns WHERE ok AND substr(title, 1, ?1) = ?2",
+            params![prefix.len() as i64, prefix],
+            |row| row.get(0),
+        )
+        .context("failed to count this month's burns")?;
+
+    Ok(format!("{}{}", prefix, burned + 1))
+}
@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::app::Song;
+
+/// How text is prepared before being written as CD-TEXT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CdTextMode {
+    /// Write tags as they are stored in the library.
+    #[default]
+    Raw,
+    /// Transliterate tags to plain ASCII, since many players only render ASCII/Latin-1.
+    Ascii,
+}
+
+impl CdTextMode {
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            CdTextMode::Raw => Cow::Borrowed(text),
+            CdTextMode::Ascii if text.is_ascii() => Cow::Borrowed(text),
+            CdTextMode::Ascii => Cow::Owned(deunicode::deunicode(text)),
+        }
+    }
+}
+
+impl std::str::FromStr for CdTextMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "raw" => Ok(CdTextMode::Raw),
+            "ascii" => Ok(CdTextMode::Ascii),
+            _ => anyhow::bail!(
+                "unknown CD-TEXT mode \"{}\": expected one of raw / ascii",
+                s
+            ),
+        }
+    }
+}
+
+/// Writes the `.inf` file wodim reads CD-TEXT from when given `-text -useinfo`, with
+/// `disc_title` as the album. The file sits next to `wav_path` and shares its stem.
+pub fn write_track_inf(
+    wav_path: &Path,
+    song: &Song,
+    disc_title: &str,
+    mode: CdTextMode,
+) -> Result<()> {
+    let inf_path = wav_path.with_extension("inf");
+    let contents = format!(
+        "Performer=\t'{}'\nTracktitle=\t'{}'\nAlbumtitle=\t'{}'\n",
+        inf_escape(&mode.apply(&song.artist)),
+        inf_escape(&mode.apply(&song.title)),
+        inf_escape(&mode.apply(disc_title)),
+    );
+
+    std::fs::write(&inf_path, contents)
+        .with_context(|| format!("failed to write CD-TEXT file {}", inf_path.display()))
+}
+
+/// The inf format wraps values in single quotes and has no escape for them.
+fn inf_escape(text: &str) -> String {
+    text.replace('\'', "`")
+}
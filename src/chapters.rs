@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::app::Song;
+use crate::edit::{FRAMES_PER_SEC, TrackEdit};
+use crate::shutdown;
+
+/// A chapter marked in an audiobook file, to the nearest CD frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    /// Counted from 1.
+    pub number: usize,
+    pub title: String,
+    pub start_frames: u64,
+    pub end_frames: u64,
+}
+
+impl Chapter {
+    /// A playlist entry playing just this chapter of `song`, titled after the chapter
+    /// so it gets its own CD-TEXT.
+    pub fn entry(&self, song: &Song) -> Song {
+        Song {
+            title: self.title.clone(),
+            edit: TrackEdit {
+                start_frames: self.start_frames,
+                // the last chapter runs to the end of the file
+                end_frames: (self.end_frames < song.duration_sec * FRAMES_PER_SEC)
+                    .then_some(self.end_frames),
+                ..TrackEdit::default()
+            },
+            ..song.clone()
+        }
+    }
+}
+
+/// The chapters of the m4b, m4a or mp3 file at `path`, read with ffprobe. Files without
+/// chapter marks have none.
+pub fn probe(path: &str) -> Result<Vec<Chapter>> {
+    let output = shutdown::output(
+        Command::new("ffprobe")
+            .arg("-v")
+            .arg("error")
+            .arg("-show_entries")
+            .arg("chapter=start_time,end_time:chapter_tags=title")
+            .arg("-of")
+            .arg("compact=p=0:nk=1")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+    )
+    .with_context(|| format!("failed to run ffprobe to read the chapters of {}", path))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "ffprobe failed to read the chapters of {}",
+        path
+    );
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            // start|end|title, where the title may be missing or hold a '|' itself
+            let mut fields = line.splitn(3, '|');
+            let mut frames = || -> Result<u64> {
+                let secs = fields
+                    .next()
+                    .unwrap_or_default()
+                    .parse::<f64>()
+                    .with_context(|| format!("unexpected chapter from ffprobe: {}", line))?;
+                Ok((secs * FRAMES_PER_SEC as f64).round() as u64)
+            };
+            let (start_frames, end_frames) = (frames()?, frames()?);
+            let title = fields
+                .next()
+                .map(str::trim)
+                .filter(|title| !title.is_empty())
+                .map_or_else(|| format!("Chapter {}", i + 1), str::to_string);
+
+            Ok(Chapter {
+                number: i + 1,
+                title,
+                start_frames,
+                end_frames,
+            })
+        })
+        .collect()
+}
+
+/// Whether `song` is in a format that can hold chapter marks.
+pub fn may_have_chapters(song: &Song) -> bool {
+    matches!(song.format().to_lowercase().as_str(), "m4b" | "m4a" | "mp3")
+}
+
+/// Gives an entry restored from the session, which only keeps its trim, back the title
+/// of the chapter it plays, if the trim is exactly one chapter of the file.
+pub fn retitle(song: &mut Song) {
+    if song.edit.is_empty() || !may_have_chapters(song) {
+        return;
+    }
+    let Ok(chapters) = probe(&song.path) else {
+        return;
+    };
+    if let Some(chapter) = chapters
+        .iter()
+        .find(|chapter| chapter.entry(song).edit == song.edit)
+    {
+        song.title = chapter.title.clone();
+    }
+}
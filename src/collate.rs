@@ -0,0 +1,139 @@
+use rusqlite::Connection;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+/// Name of the collation in SQL, as in `ORDER BY album COLLATE NATSORT`.
+pub const NAME: &str = "NATSORT";
+
+/// Makes [`compare`] available to `conn`'s queries as [`NAME`].
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_collation(NAME, compare)
+}
+
+/// Orders text the way a reader expects rather than by code point: runs of digits by
+/// their value, so "Track 2" comes before "Track 10", and letters regardless of case
+/// and accents, so "Édith" sorts among the E's. Text that only differs in those is
+/// then ordered by case and accents, and finally by code point, so the order is total.
+///
+/// Accents are folded the same way for every language, which isn't always how its
+/// readers alphabetize: Swedish puts å, ä and ö after z, but here they sort as a and o.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let folded = if a.is_ascii() && b.is_ascii() {
+        natural(
+            a.chars().map(|c| c.to_ascii_lowercase()),
+            b.chars().map(|c| c.to_ascii_lowercase()),
+        )
+    } else {
+        natural(fold(a).chars(), fold(b).chars())
+    };
+
+    folded
+        .then_with(|| natural(a.chars(), b.chars()))
+        .then_with(|| a.cmp(b))
+}
+
+/// `text` transliterated to ASCII and lowercased, e.g. "Björk" to "bjork".
+fn fold(text: &str) -> String {
+    deunicode::deunicode(text).to_lowercase()
+}
+
+/// Compares character by character, except that runs of digits are compared by value.
+fn natural(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> Ordering {
+    let (mut a, mut b) = (a.peekable(), b.peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let ordering = number(digits(&mut a), digits(&mut b));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// Takes the run of digits at the start of `chars`.
+fn digits(chars: &mut Peekable<impl Iterator<Item = char>>) -> String {
+    let mut run = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        run.push(digit);
+    }
+
+    run
+}
+
+/// Compares two runs of digits by value, however long they are; "007" and "7" are
+/// equal here.
+fn number(a: String, b: String) -> Ordering {
+    let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names = names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        names.sort_by(|a, b| compare(a, b));
+        names
+    }
+
+    #[test]
+    fn numbers_sort_by_value() {
+        assert_eq!(
+            sorted(&["Track 10", "Track 2", "Track 1", "Track 02b"]),
+            ["Track 1", "Track 2", "Track 02b", "Track 10"]
+        );
+        assert_eq!(
+            natural("disc 007".chars(), "disc 7".chars()),
+            Ordering::Equal
+        );
+        assert_eq!(
+            natural(
+                "99999999999999999999".chars(),
+                "100000000000000000000".chars()
+            ),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn case_and_accents_only_break_ties() {
+        assert_eq!(
+            sorted(&["Zebra", "édith", "Edith", "apple", "Eden"]),
+            ["apple", "Eden", "Edith", "édith", "Zebra"]
+        );
+        assert_eq!(compare("abc", "ABC"), Ordering::Greater);
+        assert_eq!(compare("abc", "abc"), Ordering::Equal);
+    }
+
+    /// Swedish alphabetizes å, ä and ö after z; the folding doesn't know that.
+    #[test]
+    fn swedish_letters_fold_to_latin() {
+        assert_eq!(
+            sorted(&["Öst", "Zorn", "Åsa", "Ask", "Oden"]),
+            ["Åsa", "Ask", "Oden", "Öst", "Zorn"]
+        );
+    }
+
+    #[test]
+    fn shorter_prefix_sorts_first() {
+        assert_eq!(natural("abc".chars(), "abcd".chars()), Ordering::Less);
+        assert_eq!(natural("".chars(), "".chars()), Ordering::Equal);
+        assert_eq!(natural("b".chars(), "a1".chars()), Ordering::Greater);
+    }
+}
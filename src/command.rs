@@ -0,0 +1,2515 @@
+use anyhow::{Context, Result};
+use std::io::{self, IsTerminal, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use crate::aliases;
+use crate::app::{self, AppState, ExportFormat, Song, queries};
+use crate::archive;
+use crate::audit::{self, Mismatch};
+use crate::build_db::ScanOptions;
+use crate::burns;
+use crate::cdtext::CdTextMode;
+use crate::chapters;
+use crate::duration;
+use crate::edit::FRAMES_PER_SEC;
+use crate::errors::BurnError;
+use crate::events::Event;
+use crate::external;
+use crate::history;
+use crate::image;
+use crate::journal;
+use crate::layout::DiscLayout;
+use crate::mix;
+use crate::mixdown;
+use crate::naming;
+use crate::normalize::{NormalizeMode, NormalizeProfile};
+use crate::pager;
+use crate::paths;
+use crate::preview::{self, Boundary};
+use crate::quality::GateAction;
+use crate::snapshot;
+use crate::style;
+use crate::tags::{self, Filter, TagField};
+
+/// Tracks listed by `recent` without a count.
+const DEFAULT_RECENT: usize = 20;
+
+const HELP_STR: &str = r#"
+Command:
+  playlist                               - show current playlist
+  playlist limit                         - show limit of playlist length
+  playlist add <ID> [--exact]            - add song by DB ID (transcodes and checks capacity);
+                                           --exact skips version picking and the lossless policy
+  playlist add <path> [--import]         - add an audio file by path, even one the library doesn't have;
+                                           --import adds it to the library as well
+  playlist add -                         - add IDs or paths read one per line until a blank line or EOF,
+                                           e.g. piped from fzf; lines of search output work too
+  playlist chapters <ID> [<N>[-<M>]]     - add the chapters of an audiobook (m4b, m4a or mp3) as tracks of their
+                                           own, all of them or chapters N to M
+  playlist prefer-lossless [on|off]      - show or set automatically picking lossless copies on add
+                                           (prefer_lossless in the config sets it at startup)
+  playlist layout                        - preview the disc: track numbers, start times, gaps and CD-TEXT
+  playlist burn [--yes]                  - show the disc layout and burn your playlist to the CD after confirming
+                                           (a retry resumes at the failed stage); --yes skips the confirmation
+  playlist burn --copies <n> [--yes]     - burn n copies one after another; with verify_device set, each copy is
+                                           read back and checked on that drive while the next one burns
+  playlist burn --print-commands         - print the ffmpeg / normalize / wodim commands a burn would run
+  playlist export-files <dir> [--format mp3|flac|original] [--template <template>]
+                                         - copy or transcode tracks into a folder of dir named after the disc
+                                           title, with an M3U, named by template
+                                           (default "{disc}.{track:02} - {artist} - {title}.{ext}")
+  playlist archive <dir> [--template <template>]
+                                         - copy the original files into a folder of dir named after the disc title,
+                                           verified by checksum, with a sha256sums manifest and metadata.json;
+                                           the template needs {track}, and {disc} is the volume in the series
+  playlist clear                         - clears the existing playlist
+  playlist save <file>                   - write the playlist as an M3U for other players (trims and fades aren't kept)
+  playlist load <file>                   - replace the playlist with the tracks an M3U lists, read the way
+                                           burn-m3u reads it; "playlist undo" brings the old one back
+  playlist undo | redo                   - take back the last playlist change, or make an undone one again;
+                                           kept with the session, so changes from before a restart count
+  playlist journal                       - list the changes undo takes back, most recent first
+  playlist title [<title> | --auto]      - show or set the disc title used for CD-TEXT, cue sheets, export
+                                           folders and the burn history; --auto suggests e.g. "Mix 2024-06 Vol. 3"
+  playlist series [<name> | --none]      - show or set the series the disc is the next volume of, e.g. Road Trips,
+                                           and list tracks that were already on an earlier volume
+  playlist smart <name> | --recent       - add the tracks of a saved search, or the recently previewed ones,
+                                           that aren't queued and still fit
+  playlist export-mix <file>             - join the normalized playlist into one FLAC/MP3 file with a cue sheet
+  playlist gate [off|warn <kbps>|block <kbps>|allow <ID>|disallow <ID>]
+                                         - show or configure the burn-time check for low bitrate sources;
+                                           gate_action and gate_min_kbps in the config set it at startup,
+                                           and allowed tracks are kept in the library
+  playlist normalize [cd|streaming|voice] [album|track]
+                                         - show or set the loudness profile used for burns and exports, and
+                                           whether it applies one gain to the whole disc or evens out every
+                                           track (by default album for cd, track for the others)
+  playlist loudness                      - stage the tracks and list their loudness and true peak (EBU R128)
+                                           before normalization, flagging the ones far from the rest
+  playlist trim <pos> <start> [<end>]    - cut the entry at position pos (from 1) to start..end (secs or m:ss)
+  playlist fade <pos> <in> <out>         - fade the entry in and out over the given lengths
+  playlist preview <pos> [start|end] [--ab]
+                                         - play the first 5s after the in-point or the last 10s before the out-point;
+                                           --ab first plays the untouched source around the cut, to compare
+  playlist transition <pos>              - play the last 10s of the entry at pos into the first 10s of the next
+  playlist gap [<secs>]                  - show or set the silence between tracks in export-mix
+  playlist crossfade [<secs>]            - show or set the crossfade between tracks in export-mix
+  playlist runs [join|gap]               - list runs of one album's consecutive tracks, which export-mix joins
+                                           without a gap or crossfade; gap treats them like other tracks
+  playlist speed [<N>|auto]              - show or set the burn speed passed to wodim
+  playlist cdtext [raw|ascii]            - preview CD-TEXT, or set whether it is transliterated to ASCII
+  artist-list <artist>                   - shows all tracks made by a given artist, or show all artists if none is supplied
+  album-list <album>                     - shows all tracks that belong to a given album
+                                           (listings longer than the terminal go through $PAGER, or a built-in pager)
+                                           (names sort with numbers by value and letters ignoring case and accents, so
+                                           Swedish å, ä and ö sort as a and o rather than after z)
+  artist-merge [<spelling> = <artist>]   - browse the tracks tagged with spelling under artist, e.g. Bjork = Björk,
+                                           keeping their tags; without arguments, list the merged spellings
+  artist-unmerge <artist>                - browse a merged spelling as its own artist again, or split every
+                                           spelling off the artist they were merged into
+  mix album-shuffle [N] [<filter> ...]   - fill the disc with N (default 3) random complete albums
+  mix balanced <bucket>:<pct> ... [<filter> ...]
+                                         - fill the disc by share of genre or decade, e.g. rock:50 soul:30 1970s:20
+                                           (a two-digit decade such as 80s is the 1900s one);
+                                           both take the language:<code> and instrumental:yes|no filters of search,
+                                           e.g. mix balanced jazz:60 classical:40 instrumental:yes for a study disc
+  mix tempo                              - order the playlist by rising BPM (needs "scan --bpm")
+  mix harmonic                           - order the playlist to avoid key clashes (needs "scan --key" or key tags)
+  mix optimize [--yes]                   - reorder for a rising tempo, even levels and no artist twice in a row;
+                                           shows the new order and asks first (needs "scan --bpm --loudness")
+  search <query>                         - search artist / title / album tags and file paths using full text search;
+                                           end a word with * to match prefixes, e.g. radi*; language:<code> and
+                                           instrumental:yes|no keep only tracks tagged so, e.g. for a saved search
+  search --last | --history              - repeat the previous search, or list recent ones
+  search --save <name> <query>           - save a search under a name for "playlist smart"
+  search --saved | --delete <name>       - list or delete saved searches
+  tag show <ID>                          - show the stored tags and properties of a track
+  tag set <ID> <field> <value> [--write] - fix the title / artist / album / track / year / genre in the library,
+                                           or set the language (e.g. eng) or instrumental (yes or no);
+                                           --write also saves it to the file's own tags, instrumentals as language zxx
+  tag bulk --filter <field:value ...> --set <field=value> ... [--dry-run] [--write]
+                                         - preview and apply the same edit to every matching track,
+                                           e.g. --filter artist:Beatles album:Revolver --set year=1966
+  tag undo                               - revert the last bulk edit
+  note <ID> [<text> | --clear]           - show, set or remove a free-text note on a track, e.g. why this master
+                                           was chosen; notes stay in the library and show in "tag show"
+  album-audit [--no-art] [--write]       - find albums whose tracks disagree on year, album artist or art and
+                                           offer to give every track the most common year or artist; --write
+                                           also updates the files. Compilations keep their artists, and art is
+                                           only reported since a change to it couldn't be undone
+  image inspect <file>                   - list the tracks of a disc image (a cue sheet, or the audio next to one)
+                                           with where they land on the disc and the total runtime
+  image burn <file> [--yes]              - burn a disc image made earlier, e.g. by playlist export-mix, as it is
+                                           after confirming; --yes skips the confirmation
+  history [--series <name>]              - list past burns, newest first, with their disc titles
+  history show <ID> [--log]              - list the tracks and disc ID of a past burn, or print everything its
+                                           stages printed, to find out why a burn failed
+  history submit <ID>                    - open the MusicBrainz page for the disc ID of a past burn and print its
+                                           tracks for the track parser and its freedb entry for gnudb, so CD players
+                                           that look discs up find them
+  series                                 - list disc series with how many volumes have been burned
+  recent [N]                             - list the N (default 20) tracks previewed most recently
+  audition [add <ID> | remove <pos> | clear]
+                                         - list, park or drop candidates kept apart from the playlist
+  audition preview <pos>                 - play 20s from a third of the way into the parked track
+  audition promote <pos> | demote <pos>  - move a parked track onto the playlist, or a playlist entry back
+  db rescan [--bpm] [--key] [--loudness] - scan music_dir from the config again, as "vol_28 scan" does
+  db scans                               - list past scans with how many tracks each added, removed or changed
+  db diff                                - list the tracks added, removed or changed between the last two scans
+  db reprobe <ID> | <path>               - reread one track's tags and audio properties from its file, e.g. after
+                                           fixing its tags in another program, without a full rescan
+  db export-user <file>                  - write just what was curated by hand (notes, languages and instrumental
+                                           flags, artist merges, saved searches, burned discs) to a file, keyed by
+                                           path under music_dir and a fingerprint of the tags
+  db import-user <file>                  - apply such a file, e.g. after rebuilding the library or on a new machine
+  chapters <ID>                          - list the chapters marked in an audiobook file
+  open <ID>                              - show the track's file in the file manager
+  edit-tags <ID>                         - edit the file's tags with tag_editor from the config, then reread it
+  paths                                  - show the profile, and where its library, config, session, undo journal,
+                                           burn progress and search history are stored
+
+The config file is `key = value` lines, not TOML: values are written without quotes.
+"#;
+
+/// A shell command, parsed from a line such as `playlist add 12`. Every frontend runs
+/// commands through [`execute`], so they all behave the same.
+#[derive(Debug, Clone)]
+pub enum Command {
+    Quit,
+    Help,
+    Paths,
+    Playlist(PlaylistCommand),
+    Mix(MixCommand),
+    Search(SearchCommand),
+    Tag(TagCommand),
+    AlbumAudit {
+        check_art: bool,
+        write_file: bool,
+    },
+    /// Every artist, or the tracks of one.
+    ArtistList(Option<String>),
+    /// Browse a spelling under another artist, or list the merged spellings.
+    ArtistMerge(Option<(String, String)>),
+    ArtistUnmerge(String),
+    AlbumList(String),
+    /// List the chapters of an audiobook file.
+    Chapters(i64),
+    /// Show a track's file in the file manager.
+    Open(i64),
+    /// Edit a track's file in the configured tag editor and reread it.
+    EditTags(i64),
+    /// Show a track's note, or set it first when given; an empty note removes it.
+    Note {
+        id: i64,
+        text: Option<String>,
+    },
+    /// The tracks previewed most recently, up to the given number.
+    Recent(usize),
+    Image(ImageCommand),
+    /// Past burns, newest first, of every series or only the given one.
+    History(Option<String>),
+    /// One past burn with its tracks, or with everything its stages printed.
+    HistoryShow {
+        id: i64,
+        log: bool,
+    },
+    /// Submit the disc ID of a past burn to MusicBrainz.
+    HistorySubmit(i64),
+    /// Every series with how far it has got.
+    Series,
+    Db(DbCommand),
+    Audition(AuditionCommand),
+}
+
+/// Works the list of candidates parked to be previewed before going on the playlist.
+#[derive(Debug, Clone)]
+pub enum AuditionCommand {
+    List,
+    Add(i64),
+    Remove(usize),
+    /// Move the entry to the end of the playlist.
+    Promote(usize),
+    /// Play a stretch of the entry.
+    Preview(usize),
+    /// Move a playlist entry back to the audition list.
+    Demote(usize),
+    Clear,
+}
+
+/// Works with disc images, a cue sheet and the audio it indexes.
+#[derive(Debug, Clone)]
+pub enum ImageCommand {
+    /// The image's track layout and runtime.
+    Inspect(PathBuf),
+    Burn {
+        path: PathBuf,
+        /// Skip showing the layout and asking first.
+        confirmed: bool,
+    },
+}
+
+/// Looks into the library's scan snapshots, or carries its curation to another library.
+#[derive(Debug, Clone)]
+pub enum DbCommand {
+    /// Scan the configured music directory again.
+    Rescan(ScanOptions),
+    /// Every scan, newest first.
+    Scans,
+    /// What the last scan changed.
+    Diff,
+    /// Write the user metadata to a file.
+    ExportUser(PathBuf),
+    /// Apply user metadata written by an export.
+    ImportUser(PathBuf),
+    /// Reread one track's file.
+    Reprobe(i64),
+    /// Reread the track at a path.
+    ReprobePath(PathBuf),
+}
+
+/// Where `playlist smart` takes its tracks from.
+#[derive(Debug, Clone)]
+pub enum SmartSource {
+    /// A search saved with `search --save`.
+    Saved(String),
+    /// The tracks previewed most recently, newest first.
+    Recent,
+}
+
+#[derive(Debug, Clone)]
+pub enum PlaylistCommand {
+    List,
+    Add {
+        id: i64,
+        /// Skip version picking and the lossless policy.
+        exact: bool,
+    },
+    /// Add a file by path, as the library's track if it has one, else read on the spot.
+    AddFile {
+        path: PathBuf,
+        /// Add the file to the library first.
+        import: bool,
+    },
+    /// Add IDs or paths read one per line until a blank line or EOF, e.g. from fzf.
+    AddBatch,
+    Clear,
+    /// Take back the last change to the playlist, even one made before a restart.
+    Undo,
+    Redo,
+    /// The changes that can be undone and redone.
+    Journal,
+    /// Fill up with tracks from a saved search or recent previews.
+    Smart(SmartSource),
+    /// Show the burn speed, setting it first when given: `Some(None)` leaves it to the
+    /// drive. The settings below show the current value the same way.
+    Speed(Option<Option<u32>>),
+    /// `Some(None)` goes back to a title suggested from the burn history.
+    Title(Option<Option<String>>),
+    /// `Some(None)` takes the disc out of its series.
+    Series(Option<Option<String>>),
+    CdText(Option<CdTextMode>),
+    ExportFiles {
+        dir: PathBuf,
+        format: ExportFormat,
+        template: String,
+    },
+    /// Write the playlist to an M3U file.
+    Save(PathBuf),
+    /// Replace the playlist with the entries of an M3U file.
+    Load(PathBuf),
+    /// Copy the original files with a checksum manifest and metadata.
+    Archive {
+        dir: PathBuf,
+        template: String,
+    },
+    PreferLossless(Option<bool>),
+    Gate(Option<GateSetting>),
+    Normalize {
+        profile: Option<NormalizeProfile>,
+        mode: Option<NormalizeMode>,
+    },
+    Trim {
+        index: usize,
+        start_secs: u64,
+        end_secs: Option<u64>,
+    },
+    Fade {
+        index: usize,
+        fade_in_secs: u64,
+        fade_out_secs: u64,
+    },
+    Preview {
+        index: usize,
+        boundary: Boundary,
+        /// Play the untouched source around the cut first, to hear what the edit changes.
+        compare: bool,
+    },
+    /// Add chapters of an audiobook file as entries of their own, all of them or the
+    /// numbered range.
+    Chapters {
+        id: i64,
+        range: Option<(usize, usize)>,
+    },
+    /// Play the end of an entry into the start of the one after it.
+    Transition(usize),
+    Gap(Option<u32>),
+    Crossfade(Option<u32>),
+    /// Show runs of consecutive album tracks, or set whether a mixdown gaps them.
+    Runs(Option<bool>),
+    ExportMix(PathBuf),
+    /// Measure every entry's loudness before normalization.
+    Loudness,
+    PrintCommands,
+    Layout,
+    Burn {
+        /// Skip showing the layout and asking first.
+        confirmed: bool,
+        /// Discs to burn, each checked on the verify drive while the next one burns.
+        copies: usize,
+    },
+}
+
+/// A change to the burn-time quality gate.
+#[derive(Debug, Clone, Copy)]
+pub enum GateSetting {
+    Off,
+    Warn(u32),
+    Block(u32),
+    Allow(i64),
+    Disallow(i64),
+}
+
+#[derive(Debug, Clone)]
+pub enum MixCommand {
+    AlbumShuffle {
+        count: usize,
+        /// `language:` and `instrumental:` filters the albums' tracks must pass.
+        filters: String,
+    },
+    /// Shares of genres and decades, and filters the tracks must pass.
+    Balanced(String),
+    Tempo,
+    Harmonic,
+    Optimize {
+        /// Apply without showing the new order and asking first.
+        confirmed: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum SearchCommand {
+    Query(String),
+    Last,
+    History,
+    Saved,
+    Save { name: String, query: String },
+    Delete(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum TagCommand {
+    Show(i64),
+    Set {
+        id: i64,
+        field: TagField,
+        value: String,
+        write_file: bool,
+    },
+    Bulk {
+        filter: Filter,
+        sets: Vec<(TagField, String)>,
+        dry_run: bool,
+        write_file: bool,
+    },
+    Undo,
+}
+
+impl std::str::FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self> {
+        let mut parts = line.split_whitespace().peekable();
+        let command = parts.next().context("expected a command")?;
+
+        Ok(match command {
+            "quit" | "exit" => Command::Quit,
+            "help" => Command::Help,
+            "paths" => Command::Paths,
+            "playlist" => Command::Playlist(parse_playlist(parts)?),
+            "mix" => Command::Mix(match parts.next() {
+                Some("album-shuffle") => MixCommand::AlbumShuffle {
+                    count: match parts.next_if(|arg| !arg.contains(':')) {
+                        Some(n) => n
+                            .parse()
+                            .context("failed to parse album count as integer")?,
+                        None => 3,
+                    },
+                    filters: join_strings(parts),
+                },
+                Some("balanced") => MixCommand::Balanced(join_strings(parts)),
+                Some("tempo") => MixCommand::Tempo,
+                Some("harmonic") => MixCommand::Harmonic,
+                Some("optimize") => MixCommand::Optimize {
+                    confirmed: parts.next() == Some("--yes"),
+                },
+                Some(unknown) => anyhow::bail!(
+                    "unknown mix \"{}\": expected one of album-shuffle / balanced / tempo / harmonic / optimize",
+                    unknown
+                ),
+                None => {
+                    anyhow::bail!(
+                        "expected a mix mode: album-shuffle / balanced / tempo / harmonic / optimize"
+                    )
+                }
+            }),
+            "search" => Command::Search(match parts.peek().copied() {
+                Some("--last") => SearchCommand::Last,
+                Some("--history") => SearchCommand::History,
+                Some("--saved") => SearchCommand::Saved,
+                Some("--save") => {
+                    parts.next();
+                    let name = parts.next().context("expected a name for the search")?;
+                    let query = join_strings(parts);
+                    anyhow::ensure!(!query.is_empty(), "expected a query to save");
+                    SearchCommand::Save {
+                        name: name.to_string(),
+                        query,
+                    }
+                }
+                Some("--delete") => {
+                    parts.next();
+                    let name = parts.next().context("expected a saved search name")?;
+                    SearchCommand::Delete(name.to_string())
+                }
+                _ => SearchCommand::Query(join_strings(parts)),
+            }),
+            "tag" => Command::Tag(parse_tag(parts)?),
+            "album-audit" => {
+                let (mut check_art, mut write_file) = (true, false);
+                for flag in parts {
+                    match flag {
+                        "--no-art" => check_art = false,
+                        "--write" => write_file = true,
+                        _ => anyhow::bail!("unknown album-audit option \"{}\"", flag),
+                    }
+                }
+                Command::AlbumAudit {
+                    check_art,
+                    write_file,
+                }
+            }
+            "artist-list" => {
+                Command::ArtistList(parts.peek().is_some().then(|| join_strings(parts)))
+            }
+            "artist-merge" => Command::ArtistMerge(match parts.peek() {
+                None => None,
+                Some(_) => {
+                    let names = join_strings(parts);
+                    let (spelling, artist) = names
+                        .split_once(" = ")
+                        .context("expected <spelling> = <artist>, e.g. Bjork = Björk")?;
+                    Some((spelling.to_string(), artist.to_string()))
+                }
+            }),
+            "artist-unmerge" => Command::ArtistUnmerge(
+                Some(join_strings(parts))
+                    .filter(|name| !name.is_empty())
+                    .context("expected the artist to unmerge")?,
+            ),
+            "chapters" => Command::Chapters(parse_id(parts.next())?),
+            "open" => Command::Open(parse_id(parts.next())?),
+            "edit-tags" => Command::EditTags(parse_id(parts.next())?),
+            "history" => match parts.next() {
+                Some("show") => Command::HistoryShow {
+                    id: parts
+                        .next()
+                        .context("expected the ID of a burn")?
+                        .parse()
+                        .context("failed to parse burn ID as integer")?,
+                    log: parts.next() == Some("--log"),
+                },
+                Some("submit") => Command::HistorySubmit(
+                    parts
+                        .next()
+                        .context("expected the ID of a burn")?
+                        .parse()
+                        .context("failed to parse burn ID as integer")?,
+                ),
+                Some("--series") => {
+                    anyhow::ensure!(parts.peek().is_some(), "expected a series name");
+                    Command::History(Some(join_strings(parts)))
+                }
+                Some(option) => anyhow::bail!("unknown history option \"{}\"", option),
+                None => Command::History(None),
+            },
+            "image" => {
+                let subcommand = parts
+                    .next()
+                    .context("expected an image command: inspect / burn")?;
+                let mut words = parts.collect::<Vec<_>>();
+                let confirmed = words.last() == Some(&"--yes");
+                if confirmed {
+                    words.pop();
+                }
+                anyhow::ensure!(!words.is_empty(), "expected an image file, e.g. mix.cue");
+                let path = PathBuf::from(words.join(" "));
+                Command::Image(match subcommand {
+                    "inspect" => ImageCommand::Inspect(path),
+                    "burn" => ImageCommand::Burn { path, confirmed },
+                    unknown => anyhow::bail!(
+                        "unknown image command \"{}\": expected inspect / burn",
+                        unknown
+                    ),
+                })
+            }
+            "series" => Command::Series,
+            "db" => Command::Db(match parts.next() {
+                Some("rescan") => {
+                    let mut options = ScanOptions::default();
+                    for flag in parts {
+                        match flag {
+                            "--bpm" => options.detect_bpm = true,
+                            "--key" => options.detect_key = true,
+                            "--loudness" => options.detect_loudness = true,
+                            _ => anyhow::bail!("unknown rescan option \"{}\"", flag),
+                        }
+                    }
+                    DbCommand::Rescan(options)
+                }
+                Some("scans") => DbCommand::Scans,
+                Some("diff") => DbCommand::Diff,
+                Some("reprobe") => match parts.peek() {
+                    Some(arg) if arg.parse::<i64>().is_err() => {
+                        DbCommand::ReprobePath(PathBuf::from(join_strings(parts)))
+                    }
+                    _ => DbCommand::Reprobe(parse_id(parts.next())?),
+                },
+                Some("export-user") => DbCommand::ExportUser(PathBuf::from(
+                    parts.next().context("expected a file to export to")?,
+                )),
+                Some("import-user") => DbCommand::ImportUser(PathBuf::from(
+                    parts.next().context("expected a file to import")?,
+                )),
+                Some(unknown) => anyhow::bail!(
+                    "unknown db command \"{}\": expected rescan / scans / diff / reprobe / export-user / import-user",
+                    unknown
+                ),
+                None => {
+                    anyhow::bail!(
+                        "expected a db command: rescan / scans / diff / reprobe / export-user / import-user"
+                    )
+                }
+            }),
+            "audition" => Command::Audition(match parts.next() {
+                None => AuditionCommand::List,
+                Some("add") => AuditionCommand::Add(parse_id(parts.next())?),
+                Some("remove") => AuditionCommand::Remove(parse_position(parts.next())?),
+                Some("promote") => AuditionCommand::Promote(parse_position(parts.next())?),
+                Some("preview") => AuditionCommand::Preview(parse_position(parts.next())?),
+                Some("demote") => AuditionCommand::Demote(parse_position(parts.next())?),
+                Some("clear") => AuditionCommand::Clear,
+                Some(unknown) => anyhow::bail!(
+                    "unknown audition command \"{}\": expected add / remove / promote / preview / demote / clear",
+                    unknown
+                ),
+            }),
+            "recent" => Command::Recent(match parts.next() {
+                Some(count) => count.parse().context("failed to parse count as integer")?,
+                None => DEFAULT_RECENT,
+            }),
+            "note" => {
+                let id = parse_id(parts.next())?;
+                let text = match parts.peek() {
+                    None => None,
+                    Some(&"--clear") => Some(String::new()),
+                    Some(_) => Some(join_strings(parts)),
+                };
+                Command::Note { id, text }
+            }
+            "album-list" => {
+                if parts.peek().is_none() {
+                    anyhow::bail!("need an album to list");
+                }
+                Command::AlbumList(join_strings(parts))
+            }
+            _ => anyhow::bail!("Unknown command\n{}\n", HELP_STR),
+        })
+    }
+}
+
+fn parse_playlist<'a, I: Iterator<Item = &'a str>>(
+    mut parts: Peekable<I>,
+) -> Result<PlaylistCommand> {
+    Ok(match parts.next() {
+        Some("add") => match parts.peek() {
+            Some(&"-") => PlaylistCommand::AddBatch,
+            Some(arg) if arg.parse::<i64>().is_err() => {
+                let mut words = parts.collect::<Vec<_>>();
+                let import = words.last() == Some(&"--import");
+                if import {
+                    words.pop();
+                }
+                PlaylistCommand::AddFile {
+                    path: PathBuf::from(words.join(" ")),
+                    import,
+                }
+            }
+            _ => PlaylistCommand::Add {
+                id: parse_id(parts.next())?,
+                exact: parts.next() == Some("--exact"),
+            },
+        },
+        Some("clear") => PlaylistCommand::Clear,
+        Some("save") => PlaylistCommand::Save(PathBuf::from(
+            parts
+                .next()
+                .context("expected a file to save the playlist to")?,
+        )),
+        Some("load") => PlaylistCommand::Load(PathBuf::from(
+            parts.next().context("expected a playlist file to load")?,
+        )),
+        Some("undo") => PlaylistCommand::Undo,
+        Some("redo") => PlaylistCommand::Redo,
+        Some("journal") => PlaylistCommand::Journal,
+        Some("title") => PlaylistCommand::Title(match parts.peek() {
+            None => None,
+            Some(&"--auto") => Some(None),
+            Some(_) => Some(Some(join_strings(parts))),
+        }),
+        Some("series") => PlaylistCommand::Series(match parts.peek() {
+            None => None,
+            Some(&"--none") => Some(None),
+            Some(_) => Some(Some(join_strings(parts))),
+        }),
+        Some("smart") => PlaylistCommand::Smart(
+            match parts
+                .next()
+                .context("expected the name of a saved search, or --recent")?
+            {
+                "--recent" => SmartSource::Recent,
+                name => SmartSource::Saved(name.to_string()),
+            },
+        ),
+        Some("speed") => PlaylistCommand::Speed(match parts.next() {
+            Some("auto") => Some(None),
+            Some(speed) => Some(Some(
+                speed.parse().context("failed to parse speed as integer")?,
+            )),
+            None => None,
+        }),
+        Some("cdtext") => PlaylistCommand::CdText(parts.next().map(str::parse).transpose()?),
+        Some("export-files" | "export") => {
+            let dir = parts
+                .next()
+                .context("expected a directory to export the playlist to")?;
+            let mut format = ExportFormat::Original;
+            let mut template = String::from(naming::DEFAULT_TEMPLATE);
+            while let Some(flag) = parts.next() {
+                match flag {
+                    "--format" => {
+                        format = parts
+                            .next()
+                            .context("expected a format after --format")?
+                            .parse()?;
+                    }
+                    "--template" => {
+                        // templates contain spaces, so the template takes the rest of the line
+                        template = join_strings(parts);
+                        break;
+                    }
+                    _ => anyhow::bail!("unknown export option \"{}\"", flag),
+                }
+            }
+            PlaylistCommand::ExportFiles {
+                dir: PathBuf::from(dir),
+                format,
+                template,
+            }
+        }
+        Some("archive") => {
+            let dir = parts
+                .next()
+                .context("expected a directory to archive the playlist to")?;
+            let template = match parts.next() {
+                None => String::from(naming::DEFAULT_TEMPLATE),
+                // templates contain spaces, so the template takes the rest of the line
+                Some("--template") => join_strings(parts),
+                Some(flag) => anyhow::bail!("unknown archive option \"{}\"", flag),
+            };
+            PlaylistCommand::Archive {
+                dir: PathBuf::from(dir),
+                template,
+            }
+        }
+        Some("prefer-lossless") => PlaylistCommand::PreferLossless(match parts.next() {
+            Some("on") => Some(true),
+            Some("off") => Some(false),
+            Some(other) => anyhow::bail!("expected on / off, got \"{}\"", other),
+            None => None,
+        }),
+        Some("gate") => PlaylistCommand::Gate(match parts.next() {
+            Some("off") => Some(GateSetting::Off),
+            Some(action @ ("warn" | "block")) => {
+                let kbps = parts
+                    .next()
+                    .context("expected a minimum bitrate in kbps")?
+                    .parse()
+                    .context("failed to parse bitrate as integer")?;
+                Some(if action == "warn" {
+                    GateSetting::Warn(kbps)
+                } else {
+                    GateSetting::Block(kbps)
+                })
+            }
+            Some("allow") => Some(GateSetting::Allow(
+                parts
+                    .next()
+                    .context("expected a track ID to allow")?
+                    .parse()
+                    .context("failed to parse ID as integer")?,
+            )),
+            Some("disallow") => Some(GateSetting::Disallow(
+                parts
+                    .next()
+                    .context("expected a track ID to disallow")?
+                    .parse()
+                    .context("failed to parse ID as integer")?,
+            )),
+            Some(other) => anyhow::bail!(
+                "unknown gate setting \"{}\": expected one of off / warn / block / allow / disallow",
+                other
+            ),
+            None => None,
+        }),
+        Some("normalize") => {
+            let mut profile = None;
+            let mut mode = None;
+            for arg in parts.by_ref() {
+                if let Ok(parsed) = arg.parse() {
+                    mode = Some(parsed);
+                } else {
+                    profile = Some(arg.parse()?);
+                }
+            }
+            PlaylistCommand::Normalize { profile, mode }
+        }
+        Some("trim") => PlaylistCommand::Trim {
+            index: parse_position(parts.next())?,
+            start_secs: parse_clock(parts.next().context("expected a start position")?)?,
+            end_secs: parts.next().map(parse_clock).transpose()?,
+        },
+        Some("fade") => PlaylistCommand::Fade {
+            index: parse_position(parts.next())?,
+            fade_in_secs: parse_clock(parts.next().context("expected a fade-in length")?)?,
+            fade_out_secs: parse_clock(parts.next().context("expected a fade-out length")?)?,
+        },
+        Some("preview") => {
+            let index = parse_position(parts.next())?;
+            let (mut boundary, mut compare) = (Boundary::End, false);
+            for word in parts {
+                match word {
+                    "start" => boundary = Boundary::Start,
+                    "end" => boundary = Boundary::End,
+                    "--ab" => compare = true,
+                    other => anyhow::bail!("expected start / end or --ab, got \"{}\"", other),
+                }
+            }
+            PlaylistCommand::Preview {
+                index,
+                boundary,
+                compare,
+            }
+        }
+        Some("chapters") => PlaylistCommand::Chapters {
+            id: parse_id(parts.next())?,
+            range: parts
+                .next()
+                .map(|range| {
+                    let (first, last) = range.split_once('-').unwrap_or((range, range));
+                    let number = |n: &str| {
+                        n.parse::<usize>().ok().filter(|&n| n > 0).with_context(|| {
+                            format!("expected chapter numbers from 1, got \"{}\"", range)
+                        })
+                    };
+                    Ok::<_, anyhow::Error>((number(first)?, number(last)?))
+                })
+                .transpose()?,
+        },
+        Some("transition") => PlaylistCommand::Transition(parse_position(parts.next())?),
+        Some("gap") => PlaylistCommand::Gap(
+            parts
+                .next()
+                .map(|secs| secs.parse())
+                .transpose()
+                .context("failed to parse gap as integer seconds")?,
+        ),
+        Some("crossfade") => PlaylistCommand::Crossfade(
+            parts
+                .next()
+                .map(|secs| secs.parse())
+                .transpose()
+                .context("failed to parse crossfade as integer seconds")?,
+        ),
+        Some("runs") => PlaylistCommand::Runs(match parts.next() {
+            None => None,
+            Some("join") => Some(false),
+            Some("gap") => Some(true),
+            Some(other) => anyhow::bail!("expected join or gap, got \"{}\"", other),
+        }),
+        Some("loudness") => PlaylistCommand::Loudness,
+        Some("export-mix") => PlaylistCommand::ExportMix(PathBuf::from(
+            parts
+                .next()
+                .context("expected an output file such as mix.flac or mix.mp3")?,
+        )),
+        Some("burn") if parts.peek() == Some(&"--print-commands") => PlaylistCommand::PrintCommands,
+        Some("layout") => PlaylistCommand::Layout,
+        Some("burn") => {
+            let (mut confirmed, mut copies) = (false, 1);
+            while let Some(flag) = parts.next() {
+                match flag {
+                    "--yes" => confirmed = true,
+                    "--copies" => {
+                        copies = parts
+                            .next()
+                            .and_then(|n| n.parse().ok())
+                            .filter(|&n| n > 0)
+                            .context("expected a number of copies after --copies")?;
+                    }
+                    _ => anyhow::bail!("unknown burn option \"{}\"", flag),
+                }
+            }
+            PlaylistCommand::Burn { confirmed, copies }
+        }
+        Option::None | Some("list") => PlaylistCommand::List,
+        Some(unknown) => anyhow::bail!(
+            "unknown playlist command\"{}\": expected one of add / list / clear / title / series / smart / prefer-lossless / gate / speed / cdtext / export-files / archive / normalize / trim / fade / preview / chapters / transition / gap / crossfade / runs / export-mix / layout / burn",
+            unknown
+        ),
+    })
+}
+
+fn parse_tag<'a, I: Iterator<Item = &'a str>>(mut parts: Peekable<I>) -> Result<TagCommand> {
+    Ok(match parts.next() {
+        Some("show") => TagCommand::Show(parse_id(parts.next())?),
+        Some("set") => {
+            let id = parse_id(parts.next())?;
+            let field: TagField = parts
+                .next()
+                .context(
+                    "expected a tag: title / artist / album / track / year / genre / language / instrumental",
+                )?
+                .parse()?;
+            let mut words = parts.collect::<Vec<_>>();
+            let write_file = words.last() == Some(&"--write");
+            if write_file {
+                words.pop();
+            }
+            anyhow::ensure!(!words.is_empty(), "expected a value for the tag");
+
+            TagCommand::Set {
+                id,
+                field,
+                value: words.join(" "),
+                write_file,
+            }
+        }
+        Some("bulk") => {
+            let mut filter = None;
+            let mut sets = vec![];
+            let (mut dry_run, mut write_file) = (false, false);
+            for (flag, value) in flag_groups(parts) {
+                match flag {
+                    "--filter" => filter = Some(value.parse::<Filter>()?),
+                    "--set" => {
+                        let (field, new) = value
+                            .split_once('=')
+                            .context("expected --set <field>=<value>")?;
+                        let field = field.trim().parse::<TagField>()?;
+                        if field == TagField::Instrumental {
+                            tags::parse_flag(new.trim())?;
+                        }
+                        sets.push((field, new.trim().to_string()));
+                    }
+                    "--dry-run" => dry_run = true,
+                    "--write" => write_file = true,
+                    _ => anyhow::bail!("unknown bulk tag option \"{}\"", flag),
+                }
+            }
+            let filter = filter.context("expected --filter <field:value ...>")?;
+            anyhow::ensure!(
+                !sets.is_empty(),
+                "expected at least one --set <field>=<value>"
+            );
+
+            TagCommand::Bulk {
+                filter,
+                sets,
+                dry_run,
+                write_file,
+            }
+        }
+        Some("undo") => TagCommand::Undo,
+        Some(unknown) => anyhow::bail!(
+            "unknown tag command \"{}\": expected one of show / set / bulk / undo",
+            unknown
+        ),
+        None => anyhow::bail!("expected a tag command: show / set / bulk / undo"),
+    })
+}
+
+/// What a command produced, for the frontend to show however suits it.
+#[derive(Debug, Default)]
+pub struct CommandOutput {
+    /// Messages, in the order they were produced.
+    pub lines: Vec<String>,
+    /// Tracks the command listed, shown as a table after the messages.
+    pub tracks: Option<Vec<Song>>,
+    /// Whether the messages and tracks are a listing to page when they fill the screen.
+    pub paged: bool,
+    /// Whether the command changed the playlist in a way worth showing.
+    pub show_playlist: bool,
+    /// Whether the frontend should stop.
+    pub quit: bool,
+}
+
+impl CommandOutput {
+    fn line(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+}
+
+/// Where a command shows progress and asks questions while it runs.
+pub trait Console {
+    /// Shows `line` straight away, e.g. what a question is about.
+    fn show(&mut self, line: &str);
+
+    /// Shows `lines` that may not fit on the screen.
+    fn show_all(&mut self, lines: &[String]) {
+        for line in lines {
+            self.show(line);
+        }
+    }
+
+    /// Shows `line` where errors go.
+    fn show_error(&mut self, line: &str);
+
+    /// Shows `prompt` and reads the line typed after it, or `None` once input has ended.
+    fn read_line(&mut self, prompt: &str) -> Result<Option<String>>;
+
+    /// Asks `question` and returns the answer, trimmed.
+    fn ask(&mut self, question: &str) -> Result<String> {
+        Ok(self
+            .read_line(question)?
+            .unwrap_or_default()
+            .trim()
+            .to_string())
+    }
+
+    /// Whether someone is there to answer questions. Questions that have a sensible
+    /// default aren't asked of scripted input, which would take the next command as
+    /// the answer.
+    fn interactive(&self) -> bool;
+
+    /// Follows a background task working on `task`, such as the disc being burned, to
+    /// the end, showing its output as it goes.
+    fn follow(
+        &mut self,
+        task: &str,
+        handle: JoinHandle<Result<()>>,
+        rx: mpsc::Receiver<Event>,
+    ) -> Result<()>;
+}
+
+/// A console on stdout and stdin, as the shell uses.
+pub struct StdConsole;
+
+impl Console for StdConsole {
+    fn show(&mut self, line: &str) {
+        println!("{}", line);
+    }
+
+    /// Goes through a pager when the lines won't fit on the terminal.
+    fn show_all(&mut self, lines: &[String]) {
+        pager::show(lines);
+    }
+
+    fn show_error(&mut self, line: &str) {
+        eprintln!("{}", line);
+    }
+
+    fn read_line(&mut self, prompt: &str) -> Result<Option<String>> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(line))
+    }
+
+    fn interactive(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+
+    fn follow(
+        &mut self,
+        task: &str,
+        handle: JoinHandle<Result<()>>,
+        rx: mpsc::Receiver<Event>,
+    ) -> Result<()> {
+        app::print_log_messages(task, handle, rx, &mut io::stdout(), &mut io::stderr())
+    }
+}
+
+/// A line per run of consecutive album tracks in the playlist, saying how a mixdown
+/// joins it.
+fn run_lines(state: &AppState) -> Vec<String> {
+    let playlist = state.playlist();
+    mixdown::runs(playlist)
+        .into_iter()
+        .map(|run| {
+            format!(
+                "tracks {}-{} run on from \"{}\": {}",
+                run.start + 1,
+                run.end,
+                playlist[run.start].album,
+                if state.burn_options().gap_runs {
+                    "joined like the rest (playlist runs join to play them straight on)"
+                } else {
+                    "joined without a gap (playlist runs gap to gap them too)"
+                }
+            )
+        })
+        .collect()
+}
+
+/// Shows what a command produced on `console` the way the shell shows it.
+pub fn print_output(state: &AppState, output: &CommandOutput, console: &mut dyn Console) {
+    if output.paged {
+        let mut lines = output.lines.clone();
+        lines.extend(
+            output
+                .tracks
+                .as_deref()
+                .map(app::track_lines)
+                .unwrap_or_default(),
+        );
+        console.show_all(&lines);
+    } else {
+        for line in &output.lines {
+            console.show(line);
+        }
+        for line in output
+            .tracks
+            .as_deref()
+            .map(app::track_lines)
+            .unwrap_or_default()
+        {
+            console.show(&line);
+        }
+    }
+    if output.show_playlist {
+        let warnings = state
+            .playlist_warnings()
+            .unwrap_or_else(|_| vec![None; state.playlist().len()]);
+        for line in app::playlist_lines(state.playlist(), &warnings) {
+            console.show(&line);
+        }
+    }
+}
+
+/// Parses and runs one line as typed at the shell prompt, shows what it produced on
+/// `console`, then saves the session. Errors are shown rather than returned; the
+/// result is whether the command asked to quit.
+pub fn run_command(state: &mut AppState, line: &str, console: &mut dyn Console) -> bool {
+    if line.trim().is_empty() {
+        return false;
+    }
+
+    let before = style::accessible().then(|| state.playlist().to_vec());
+    let mut result = line
+        .parse::<Command>()
+        .and_then(|command| execute(state, command, console));
+    if let Ok(output) = &mut result {
+        let change = before
+            .as_deref()
+            .and_then(|before| journal::describe(before, state.playlist()));
+        if change.is_some() {
+            // said in a sentence rather than listed again
+            output.show_playlist = false;
+        }
+        print_output(state, output, console);
+        if let Some(change) = change {
+            console.show(&format!(
+                "Playlist changed: {}. {} tracks, {} in all.",
+                change,
+                state.playlist().len(),
+                duration::format_secs(app::playlist_duration_secs(state.playlist()))
+            ));
+        }
+    }
+    if let Err(err) = state.save_session() {
+        console.show_error(&format!("{} {:?}", style::error_label(), err));
+    }
+
+    match result {
+        Ok(output) => output.quit,
+        Err(err) => {
+            for line in app::error_lines(&err) {
+                console.show_error(&line);
+            }
+            false
+        }
+    }
+}
+
+/// Runs one command against the saved session straight from the command line, e.g.
+/// `run playlist add 12`, saving the session afterwards. Unlike the shell, a failing
+/// command is an error so it sets the exit code.
+pub fn run_once(line: &str) -> Result<()> {
+    let mut state = AppState::new()?;
+    for err in state.restore_session()? {
+        eprintln!("{} {:?}", style::error_label(), err);
+    }
+
+    let command = line.parse::<Command>()?;
+    let output = execute(&mut state, command, &mut StdConsole)?;
+    print_output(&state, &output, &mut StdConsole);
+
+    state.save_session()
+}
+
+/// Every command in the help, with its subcommand where it has one, e.g. "playlist add".
+pub fn command_names() -> Vec<String> {
+    let mut names = vec![String::from("help"), String::from("quit")];
+    for line in HELP_STR.lines() {
+        // continuation lines are indented further
+        let Some(usage) = line
+            .strip_prefix("  ")
+            .filter(|usage| !usage.starts_with(' '))
+        else {
+            continue;
+        };
+        let name = usage
+            .split_whitespace()
+            .take_while(|word| {
+                !word.starts_with('-') && word.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+            })
+            .take(2)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Runs `command` against `state`, asking `console` anything it needs to know.
+pub fn execute(
+    state: &mut AppState,
+    command: Command,
+    console: &mut dyn Console,
+) -> Result<CommandOutput> {
+    let mut output = CommandOutput::default();
+    match command {
+        Command::Quit => {
+            let unburned = state.playlist_unburned()?;
+            let question = if unburned {
+                "The playlist hasn't been burned yet. Quit anyway? [y/N] "
+            } else {
+                "Quit? [y/N] "
+            };
+            output.quit = !state.config().confirm.quit(unburned)
+                || console.ask(question)?.eq_ignore_ascii_case("y");
+        }
+        Command::Help => output.line(HELP_STR),
+        Command::Paths => {
+            output.line(format!("{:<14} {}", "profile", paths::profile()));
+            for (label, path) in paths::all() {
+                output.line(format!("{:<14} {}", label, path.display()));
+            }
+        }
+        Command::Playlist(command) => execute_playlist(state, command, console, &mut output)?,
+        Command::Mix(command) => {
+            let songs = match command {
+                MixCommand::AlbumShuffle { count, filters } => {
+                    mix::album_shuffle(state.conn(), count, &filters, state.remaining_secs())?
+                }
+                MixCommand::Balanced(spec) => mix::balanced(
+                    state.conn(),
+                    &spec,
+                    state.remaining_secs(),
+                    state.free_track_slots(),
+                )?,
+                MixCommand::Tempo => {
+                    let missing = state.playlist_sort_by_tempo()?;
+                    if missing > 0 {
+                        output.line(format!(
+                            "{} tracks have no detected tempo and were left at the end",
+                            missing
+                        ));
+                    }
+                    vec![]
+                }
+                MixCommand::Harmonic => {
+                    let (clashes, missing) = state.playlist_sort_harmonic()?;
+                    output.line(format!("{} key clashes remaining", clashes));
+                    if missing > 0 {
+                        output.line(format!(
+                            "{} tracks have no known key and were left at the end",
+                            missing
+                        ));
+                    }
+                    vec![]
+                }
+                MixCommand::Optimize { confirmed } => {
+                    let plan = state.playlist_optimized_order()?;
+                    if plan.is_unchanged() {
+                        output.line("the playlist is already in the best order found");
+                        return Ok(output);
+                    }
+                    if !confirmed {
+                        for line in plan.lines(state.playlist()) {
+                            console.show(&line);
+                        }
+                        if !console
+                            .ask("Apply this order? [y/N] ")?
+                            .eq_ignore_ascii_case("y")
+                        {
+                            output.line("order left as it was");
+                            return Ok(output);
+                        }
+                    }
+                    state.playlist_apply_order(&plan);
+                    vec![]
+                }
+            };
+            // the whole mix goes in, or none of it does
+            state.playlist_add_all(songs)?;
+            output.show_playlist = true;
+        }
+        Command::Search(command) => {
+            let query = match command {
+                SearchCommand::Query(query) => query,
+                SearchCommand::Last => history::last()?.context("no searches yet")?,
+                SearchCommand::History => {
+                    for (i, query) in history::load()?.iter().rev().enumerate() {
+                        output.line(format!("{:>3}  {}", i + 1, query));
+                    }
+                    return Ok(output);
+                }
+                SearchCommand::Saved => {
+                    for (name, query) in history::saved()? {
+                        output.line(format!("{:<16}{}", name, query));
+                    }
+                    return Ok(output);
+                }
+                SearchCommand::Save { name, query } => {
+                    state.ensure_writable("saving searches")?;
+                    history::save(&name, &query)?;
+                    output.line(format!("saved \"{}\" as {}", query, name));
+                    return Ok(output);
+                }
+                SearchCommand::Delete(name) => {
+                    state.ensure_writable("deleting saved searches")?;
+                    history::delete(&name)?;
+                    output.line(format!("deleted saved search {}", name));
+                    return Ok(output);
+                }
+            };
+            if let Err(err) = history::record(&query) {
+                output.line(format!("failed to record search history: {:?}", err));
+            }
+            output.line(format!("searching for term \"{}\"", query));
+            output.tracks = Some(queries::search_group(state.conn(), &query)?);
+            output.paged = true;
+        }
+        Command::Tag(command) => execute_tag(state, command, console, &mut output)?,
+        Command::AlbumAudit {
+            check_art,
+            write_file,
+        } => album_audit(state, check_art, write_file, console, &mut output)?,
+        Command::ArtistList(None) => {
+            output.line("artists");
+            output.lines.extend(queries::list_artists(state.conn())?);
+            output.paged = true;
+        }
+        Command::ArtistList(Some(artist)) => {
+            output.line(format!("tracks from artist \"{}\"", artist));
+            output.tracks = Some(queries::list_artist_tracks(state.conn(), &artist)?);
+            output.paged = true;
+        }
+        Command::ArtistMerge(None) => {
+            let merged = aliases::list(state.conn())?;
+            if merged.is_empty() {
+                output.line("no artist spellings are merged");
+            }
+            for (spelling, artist) in merged {
+                output.line(format!("{} = {}", spelling, artist));
+            }
+        }
+        Command::ArtistMerge(Some((spelling, artist))) => {
+            let artist = state.merge_artist(&spelling, &artist)?;
+            output.line(format!(
+                "\"{}\" is browsed as \"{}\" (undo with artist-unmerge {})",
+                spelling.trim(),
+                artist,
+                spelling.trim()
+            ));
+        }
+        Command::ArtistUnmerge(name) => {
+            let spellings = state.unmerge_artist(&name)?;
+            if spellings.is_empty() {
+                output.line(format!("no spellings are merged with \"{}\"", name));
+            }
+            for spelling in spellings {
+                output.line(format!("\"{}\" is its own artist again", spelling));
+            }
+        }
+        Command::Image(ImageCommand::Inspect(path)) => {
+            let image = image::open(&path)?;
+            output.line(format!("{} ({})", image.title, image.cue_path.display()));
+            output
+                .lines
+                .extend(DiscLayout::new(&image.tracks, state.burn_options().cdtext).lines());
+        }
+        Command::Image(ImageCommand::Burn { path, confirmed }) => {
+            let image = image::open(&path)?;
+            if !confirmed && state.config().confirm.burn {
+                console.show(&image.title);
+                for line in DiscLayout::new(&image.tracks, state.burn_options().cdtext).lines() {
+                    console.show(&line);
+                }
+                if !console
+                    .ask("Burn this image? [y/N] ")?
+                    .eq_ignore_ascii_case("y")
+                {
+                    output.line("burn cancelled");
+                    return Ok(output);
+                }
+            }
+
+            let title = image.title.clone();
+            let (handle, rx) = state
+                .burn_image(image)
+                .context("failed to setup burning task")?;
+            console.follow(&title, handle, rx)?;
+        }
+        Command::AlbumList(album) => {
+            output.tracks = Some(queries::list_album(state.conn(), &album)?);
+            output.paged = true;
+        }
+        Command::Chapters(id) => {
+            let song = queries::track_from_id(state.conn(), id)?;
+            let chapters = chapters::probe(&song.path)?;
+            if chapters.is_empty() {
+                output.line(format!("\"{}\" has no chapters", song.title));
+            }
+            for chapter in chapters {
+                output.line(format!(
+                    "{:>3}  {:>8}  {:>8}  {}",
+                    chapter.number,
+                    duration::clock(chapter.start_frames / FRAMES_PER_SEC),
+                    duration::clock(
+                        chapter.end_frames.saturating_sub(chapter.start_frames) / FRAMES_PER_SEC
+                    ),
+                    chapter.title
+                ));
+            }
+            output.paged = true;
+        }
+        Command::Open(id) => {
+            let song = queries::track_from_id(state.conn(), id)?;
+            external::reveal(Path::new(&song.path))?;
+        }
+        Command::EditTags(id) => {
+            state.ensure_writable("editing tags")?;
+            let editor = state.config().tag_editor.clone().context(
+                "no tag editor configured: set tag_editor in the config, e.g. tag_editor = kid3",
+            )?;
+            let song = queries::track_from_id(state.conn(), id)?;
+            console.show(&format!("waiting for {} to close...", editor));
+            external::edit_tags(&editor, Path::new(&song.path))?;
+            state.reprobe(id)?;
+            output.line(format!("reread tags of track {}", id));
+            output.tracks = Some(vec![queries::track_from_id(state.conn(), id)?]);
+        }
+        Command::Recent(count) => {
+            let recent = preview::recent(state.conn(), count)?;
+            if recent.is_empty() {
+                output.line("nothing previewed yet: try \"playlist preview <pos>\"");
+            }
+            for (song, played_at) in recent {
+                output.line(format!(
+                    "{}  {:>5}  {} - {} ({})",
+                    played_at, song.id, song.artist, song.title, song.album
+                ));
+            }
+        }
+        Command::History(series) => {
+            let burns = burns::list(state.conn(), series.as_deref())?;
+            if burns.is_empty() {
+                output.line(match series {
+                    Some(series) => format!("nothing burned in series \"{}\" yet", series),
+                    None => String::from("no discs burned yet"),
+                });
+            }
+            for burn in burns {
+                output.line(burn_line(&burn));
+            }
+        }
+        Command::HistoryShow { id, log } => {
+            let burn = burns::find(state.conn(), id)?;
+            let kept = burns::log(state.conn(), id)?;
+            if log {
+                let kept = kept.with_context(|| {
+                    format!("burn {} was recorded before burn logs were kept", id)
+                })?;
+                for line in kept.lines() {
+                    output.line(line);
+                }
+                return Ok(output);
+            }
+
+            output.line(burn_line(&burn));
+            if let Some(disc_id) = &burn.disc_id {
+                output.line(format!(
+                    "  disc ID {} (freedb {})",
+                    disc_id.musicbrainz, disc_id.freedb
+                ));
+            }
+            for (i, track) in burns::tracks(state.conn(), id)?.into_iter().enumerate() {
+                output.line(match queries::track_from_id(state.conn(), track) {
+                    Ok(song) => format!(
+                        "  #{} [{}] {} - {}",
+                        i + 1,
+                        song.id,
+                        song.artist,
+                        song.title
+                    ),
+                    Err(_) => format!("  #{} [{}] (no longer in the library)", i + 1, track),
+                });
+            }
+            if kept.is_some() {
+                output.line(format!("\"history show {} --log\" shows its log", id));
+            }
+        }
+        Command::HistorySubmit(id) => {
+            let burn = burns::find(state.conn(), id)?;
+            let disc_id = burn.disc_id.with_context(|| {
+                format!(
+                    "burn {} has no disc ID: it failed, or was recorded before disc IDs were kept",
+                    id
+                )
+            })?;
+            output.line(format!(
+                "disc ID {} (freedb {}), tracks for the track parser:",
+                disc_id.musicbrainz, disc_id.freedb
+            ));
+            let mut tracks = vec![];
+            for (i, track) in burns::tracks(state.conn(), id)?.into_iter().enumerate() {
+                let song = queries::track_from_id(state.conn(), track)?;
+                output.line(format!(
+                    "{}. {} - {} ({})",
+                    i + 1,
+                    song.title,
+                    song.artist,
+                    duration::clock(song.played_duration_secs())
+                ));
+                tracks.push((song.artist, song.title));
+            }
+            let url = disc_id.submission_url();
+            output.line(format!("opening {}", url));
+            if let Err(err) = external::open_url(&url) {
+                output.line(format!("{:#}, open the page yourself", err));
+            }
+
+            // gnudb takes entries from CDDB clients, so the entry is only printed
+            let artist = match tracks.first() {
+                Some((first, _)) if tracks.iter().all(|(artist, _)| artist == first) => {
+                    first.clone()
+                }
+                _ => String::from("Various"),
+            };
+            output.line("freedb entry, for gnudb through a CDDB client:");
+            for line in disc_id.xmcd(&artist, &burn.title, &tracks).lines() {
+                output.line(line.to_string());
+            }
+        }
+        Command::Series => {
+            let series = burns::list_series(state.conn())?;
+            if series.is_empty() {
+                output.line("no series yet: start one with \"playlist series <name>\"");
+            }
+            for series in series {
+                output.line(format!(
+                    "{}: {} volume{}, last burned {}",
+                    series.name,
+                    series.volumes,
+                    if series.volumes == 1 { "" } else { "s" },
+                    series.last_burned_at
+                ));
+            }
+        }
+        Command::Audition(command) => execute_audition(state, command, console, &mut output)?,
+        Command::Db(DbCommand::Scans) => {
+            let scans = snapshot::list(state.conn())?;
+            if scans.is_empty() {
+                output.line("no scans recorded yet");
+            }
+            for scan in scans {
+                output.line(format!(
+                    "{:>4}  {}  {:>6} tracks  +{} -{} ~{}  {}",
+                    scan.id,
+                    scan.scanned_at,
+                    scan.tracks,
+                    scan.added,
+                    scan.removed,
+                    scan.changed,
+                    scan.music_dir
+                ));
+            }
+        }
+        Command::Db(DbCommand::Diff) => {
+            let diff = snapshot::last_diff(state.conn())?;
+            output.line(format!(
+                "scan {} ({}, {} tracks) -> scan {} ({}, {} tracks)",
+                diff.from.id,
+                diff.from.scanned_at,
+                diff.from.tracks,
+                diff.to.id,
+                diff.to.scanned_at,
+                diff.to.tracks
+            ));
+            if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+                output.line("no changes");
+            }
+            for entry in &diff.added {
+                output.line(format!(
+                    "+ {}  ({} - {})",
+                    entry.path, entry.artist, entry.title
+                ));
+            }
+            for entry in &diff.removed {
+                output.line(format!(
+                    "- {}  ({} - {})",
+                    entry.path, entry.artist, entry.title
+                ));
+            }
+            for (entry, changes) in &diff.changed {
+                output.line(format!("~ {}", entry.path));
+                for change in changes {
+                    output.line(format!(
+                        "    {}: \"{}\" -> \"{}\"",
+                        change.field, change.old, change.new
+                    ));
+                }
+            }
+        }
+        Command::Db(DbCommand::Reprobe(id)) => {
+            state.reprobe(id)?;
+            output.line(format!("reread tags of track {}", id));
+            output.tracks = Some(vec![queries::track_from_id(state.conn(), id)?]);
+        }
+        Command::Db(DbCommand::Rescan(options)) => {
+            let music_dir = state
+                .config()
+                .music_dir
+                .clone()
+                .context("set music_dir in the config to know what to scan")?;
+            crate::scan(&music_dir, &options, false)?;
+        }
+        Command::Db(DbCommand::ReprobePath(path)) => {
+            let id = state
+                .library_track_at(&path)?
+                .with_context(|| {
+                    format!(
+                        "{} is not in the library: add it with \"playlist add <path> --import\"",
+                        path.display()
+                    )
+                })?
+                .id;
+            state.reprobe(id)?;
+            output.line(format!("reread tags of track {}", id));
+            output.tracks = Some(vec![queries::track_from_id(state.conn(), id)?]);
+        }
+        Command::Db(DbCommand::ExportUser(path)) => {
+            let counts = state.export_user_data(&path)?;
+            output.line(format!("exported {} to {}", counts.label(), path.display()));
+        }
+        Command::Db(DbCommand::ImportUser(path)) => {
+            let counts = state.import_user_data(&path)?;
+            output.line(format!("imported {}", counts.label()));
+        }
+        Command::Note { id, text } => {
+            if let Some(text) = text {
+                state.set_note(id, &text)?;
+            }
+            output.line(match tags::note(state.conn(), id)? {
+                Some(note) => format!("note on track {}: {}", id, note),
+                None => format!("track {} has no note", id),
+            });
+        }
+    }
+
+    Ok(output)
+}
+
+fn execute_playlist(
+    state: &mut AppState,
+    command: PlaylistCommand,
+    console: &mut dyn Console,
+    output: &mut CommandOutput,
+) -> Result<()> {
+    match command {
+        PlaylistCommand::List => output.show_playlist = true,
+        PlaylistCommand::Add { id, exact } => {
+            let song = queries::track_from_id(state.conn(), id)?;
+            let song = if exact {
+                song
+            } else if state.prefer_lossless() {
+                let preferred = state.preferred_version(song)?;
+                if preferred.id != id {
+                    output.line(format!(
+                        "using lossless copy [{}] from \"{}\" (add {} --exact to override)",
+                        preferred.id, preferred.album, id
+                    ));
+                }
+                preferred
+            } else {
+                pick_version(state, song, console)?
+            };
+            let id = song.id;
+            match state.playlist_add_splitting(song)? {
+                Some(at) => output.line(format!(
+                    "added [{}] up to {}: {}; the rest is parked in the audition list",
+                    id,
+                    duration::clock(at),
+                    state.added_delta()
+                )),
+                None => output.line(format!("added [{}]: {}", id, state.added_delta())),
+            }
+            if let Some((_, volume)) = state
+                .series_repeats()?
+                .into_iter()
+                .find(|(song, _)| song.id == id)
+            {
+                output.line(format!(
+                    "warning: this track was already on volume {} of the series",
+                    volume
+                ));
+            }
+        }
+        PlaylistCommand::AddFile { path, import } => {
+            if import {
+                let id = state.import_file(&path)?;
+                state.playlist_add_by_id(id)?;
+                output.line(format!(
+                    "added {} to the library as [{}]: {}",
+                    path.display(),
+                    id,
+                    state.added_delta()
+                ));
+            } else {
+                state.playlist_add_path(&path)?;
+                output.line(format!("added {}: {}", path.display(), state.added_delta()));
+                if state.playlist().last().is_some_and(|song| song.id == 0) {
+                    output.line(format!(
+                        "{} is not in the library (add it with --import to keep it there)",
+                        path.display()
+                    ));
+                }
+            }
+        }
+        PlaylistCommand::AddBatch => {
+            let mut added = 0;
+            let mut failed = 0;
+            loop {
+                let line = console.ask("")?;
+                if line.is_empty() {
+                    break;
+                }
+                // lines of `search` output start with the ID
+                let id = line
+                    .split_whitespace()
+                    .next()
+                    .and_then(|id| id.parse::<i64>().ok());
+                let result = match id {
+                    Some(id) => queries::track_from_id(state.conn(), id)
+                        .and_then(|song| state.preferred_version(song))
+                        .and_then(|song| state.playlist_add(song)),
+                    None => state.playlist_add_path(Path::new(&line)),
+                };
+                match result {
+                    Ok(()) => added += 1,
+                    Err(err) => {
+                        failed += 1;
+                        output.line(format!("{} {}: {:#}", style::error_label(), line, err));
+                    }
+                }
+            }
+            output.line(format!(
+                "added {} tracks, {} failed, {} left",
+                added,
+                failed,
+                duration::format_secs(state.remaining_secs())
+            ));
+            output.show_playlist = added > 0;
+        }
+        PlaylistCommand::Clear => {
+            let len = state.playlist().len();
+            if state.config().confirm.clear(len)
+                && !console
+                    .ask(&format!("Clear all {} tracks? [y/N] ", len))?
+                    .eq_ignore_ascii_case("y")
+            {
+                output.line("clear cancelled");
+                return Ok(());
+            }
+            state.playlist_clear();
+            output.line("playlist has been cleared");
+        }
+        PlaylistCommand::Save(path) => {
+            state.playlist_save(&path)?;
+            output.line(format!(
+                "saved {} tracks to {}",
+                state.playlist().len(),
+                path.display()
+            ));
+        }
+        PlaylistCommand::Load(path) => {
+            state.playlist_load(&path)?;
+            output.line(format!(
+                "loaded {} tracks from {}",
+                state.playlist().len(),
+                path.display()
+            ));
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Undo => {
+            output.line(format!("undid {}", state.playlist_undo()?));
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Redo => {
+            output.line(format!("redid {}", state.playlist_redo()?));
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Journal => {
+            let journal = state.journal();
+            if journal.undo_steps().is_empty() && journal.redo_steps().is_empty() {
+                output.line("no playlist changes to undo");
+            }
+            for step in journal.redo_steps() {
+                output.line(format!("  redo: {}", step.label));
+            }
+            for (i, step) in journal.undo_steps().iter().rev().enumerate() {
+                output.line(format!("{:>4}. {}", i + 1, step.label));
+            }
+        }
+        PlaylistCommand::Smart(source) => {
+            let (songs, described) = match source {
+                SmartSource::Saved(name) => {
+                    let query = history::find_saved(&name)?;
+                    output.line(format!("searching for term \"{}\"", query));
+                    let songs = queries::search_group(state.conn(), &query)?;
+                    (songs, format!("saved search {}", name))
+                }
+                SmartSource::Recent => {
+                    let recent = preview::recent(state.conn(), preview::RECENT_LEN)?;
+                    let songs = recent.into_iter().map(|(song, _)| song).collect();
+                    (songs, String::from("recent previews"))
+                }
+            };
+            let mut added = 0;
+            for song in songs {
+                let queued = state.playlist().iter().any(|s| s.id == song.id);
+                if queued || song.played_duration_secs() > state.remaining_secs() {
+                    continue;
+                }
+                state.playlist_add(song)?;
+                added += 1;
+            }
+            output.line(format!("added {} tracks from {}", added, described));
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Speed(speed) => {
+            if let Some(speed) = speed {
+                state.burn_options_mut().speed = speed;
+            }
+            output.line(match state.burn_options().speed {
+                Some(speed) => format!("burn speed: {}x", speed),
+                None => String::from("burn speed: chosen by drive"),
+            });
+        }
+        PlaylistCommand::Title(title) => {
+            if let Some(title) = title {
+                state.burn_options_mut().title = title;
+            }
+            output.line(match &state.burn_options().title {
+                Some(title) => format!("disc title: {}", title),
+                None => format!("disc title: {} (suggested)", state.disc_title()?),
+            });
+        }
+        PlaylistCommand::Series(series) => {
+            if let Some(series) = series {
+                state.burn_options_mut().series = series;
+            }
+            match state.next_volume()? {
+                Some((series, volume)) => {
+                    output.line(format!("series: {}, burning volume {}", series, volume))
+                }
+                None => output.line("not part of a series"),
+            }
+            for (song, volume) in state.series_repeats()? {
+                output.line(format!(
+                    "already on volume {}: [{}] {} - {}",
+                    volume, song.id, song.artist, song.title
+                ));
+            }
+        }
+        PlaylistCommand::CdText(mode) => {
+            if let Some(mode) = mode {
+                state.burn_options_mut().cdtext = mode;
+            }
+            output.lines.extend(app::cdtext_lines(
+                state.playlist(),
+                state.burn_options().cdtext,
+            ));
+        }
+        PlaylistCommand::ExportFiles {
+            dir,
+            format,
+            template,
+        } => {
+            let title = state.disc_title()?;
+            let m3u = app::playlist_export(
+                state.playlist(),
+                &dir,
+                &title,
+                &template,
+                format,
+                state.disc_number()?,
+            )?;
+            output.line(format!("exported playlist to {}", m3u.display()));
+        }
+        PlaylistCommand::Archive { dir, template } => {
+            let title = state.disc_title()?;
+            let archive = archive::export(
+                state.conn(),
+                state.playlist(),
+                &dir,
+                &title,
+                &template,
+                state.disc_number()?,
+            )?;
+            output.line(format!(
+                "archived {} files ({} MB) to {}, checksums verified",
+                archive.files,
+                archive.bytes / 1_000_000,
+                archive.dir.display()
+            ));
+            output.line(format!(
+                "check them again any time with: cd \"{}\" && sha256sum -c {}",
+                archive.dir.display(),
+                archive::MANIFEST
+            ));
+        }
+        PlaylistCommand::PreferLossless(prefer) => {
+            if let Some(prefer) = prefer {
+                state.set_prefer_lossless(prefer);
+            }
+            output.line(format!(
+                "prefer lossless: {}",
+                if state.prefer_lossless() { "on" } else { "off" }
+            ));
+        }
+        PlaylistCommand::Gate(setting) => {
+            let gate = &mut state.burn_options_mut().quality_gate;
+            match setting {
+                Some(GateSetting::Off) => gate.action = GateAction::Off,
+                Some(GateSetting::Warn(kbps)) => {
+                    gate.action = GateAction::Warn;
+                    gate.min_lossy_kbps = kbps;
+                }
+                Some(GateSetting::Block(kbps)) => {
+                    gate.action = GateAction::Block;
+                    gate.min_lossy_kbps = kbps;
+                }
+                Some(GateSetting::Allow(id)) => state.set_gate_allowed(id, true)?,
+                Some(GateSetting::Disallow(id)) => state.set_gate_allowed(id, false)?,
+                None => (),
+            }
+
+            let gate = &state.burn_options().quality_gate;
+            output.line(format!(
+                "quality gate: {:?} below {} kbps, allowed IDs: {:?}",
+                gate.action, gate.min_lossy_kbps, gate.allowed
+            ));
+            for (song, quality) in state.quality_gate_failures()? {
+                output.line(format!(
+                    "  fails: [{}] {} - {}",
+                    song.id,
+                    song.title,
+                    quality.describe()
+                ));
+            }
+        }
+        PlaylistCommand::Normalize { profile, mode } => {
+            let options = state.burn_options_mut();
+            if let Some(profile) = profile {
+                options.normalize = profile;
+                // a new profile goes back to its usual mode unless one is given too
+                options.normalize_mode = None;
+            }
+            if mode.is_some() {
+                options.normalize_mode = mode;
+            }
+            let (profile, mode) = state.burn_options().normalization();
+            output.line(format!(
+                "normalization profile: {:?}, {} gain",
+                profile,
+                mode.name()
+            ));
+        }
+        PlaylistCommand::Trim {
+            index,
+            start_secs,
+            end_secs,
+        } => {
+            let mut edit = state
+                .playlist()
+                .get(index)
+                .map(|s| s.edit)
+                .unwrap_or_default();
+            edit.start_frames = start_secs * FRAMES_PER_SEC;
+            edit.end_frames = end_secs.map(|end| end * FRAMES_PER_SEC);
+            state.playlist_edit(index, edit)?;
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Fade {
+            index,
+            fade_in_secs,
+            fade_out_secs,
+        } => {
+            let mut edit = state
+                .playlist()
+                .get(index)
+                .map(|s| s.edit)
+                .unwrap_or_default();
+            edit.fade_in_secs = fade_in_secs;
+            edit.fade_out_secs = fade_out_secs;
+            state.playlist_edit(index, edit)?;
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Preview {
+            index,
+            boundary,
+            compare,
+        } => {
+            let song = state
+                .playlist()
+                .get(index)
+                .with_context(|| format!("no playlist entry at position {}", index + 1))?;
+            console.show(&format!(
+                "previewing {:?} of \"{}\" ({})",
+                boundary,
+                song.title,
+                song.edit.describe()
+            ));
+            if compare {
+                preview::play_source(song, boundary)?;
+            }
+            preview::play_boundary(song, boundary)?;
+            state.record_preview(song.id)?;
+        }
+        PlaylistCommand::Chapters { id, range } => {
+            let added = state.playlist_add_chapters(id, range)?;
+            output.line(format!(
+                "added {} chapters: {} left",
+                added,
+                duration::format_secs(state.remaining_secs())
+            ));
+            output.show_playlist = true;
+        }
+        PlaylistCommand::Transition(index) => {
+            let (from, to) = match state.playlist().get(index..index + 2) {
+                Some([from, to]) => (from, to),
+                _ => anyhow::bail!(
+                    "no playlist entry after position {} to transition into",
+                    index + 1
+                ),
+            };
+            console.show(&format!(
+                "previewing \"{}\" into \"{}\"",
+                from.title, to.title
+            ));
+            preview::play_transition(from, to)?;
+            state.record_preview(from.id)?;
+            state.record_preview(to.id)?;
+        }
+        PlaylistCommand::Gap(secs) => {
+            if let Some(secs) = secs {
+                state.burn_options_mut().gap_secs = secs;
+            }
+            output.line(format!("mixdown gap: {}s", state.burn_options().gap_secs));
+            output.lines.extend(run_lines(state));
+        }
+        PlaylistCommand::Crossfade(secs) => {
+            if let Some(secs) = secs {
+                state.burn_options_mut().crossfade_secs = secs;
+            }
+            output.line(format!(
+                "mixdown crossfade: {}s",
+                state.burn_options().crossfade_secs
+            ));
+            output.lines.extend(run_lines(state));
+        }
+        PlaylistCommand::Runs(gap_runs) => {
+            if let Some(gap_runs) = gap_runs {
+                state.burn_options_mut().gap_runs = gap_runs;
+            }
+            let lines = run_lines(state);
+            if lines.is_empty() {
+                output.line("no runs of consecutive tracks from one album");
+            }
+            output.lines.extend(lines);
+        }
+        PlaylistCommand::Loudness => {
+            let (handle, rx) = state
+                .loudness_report()
+                .context("failed to setup loudness task")?;
+            console.follow(&state.disc_title()?, handle, rx)?;
+        }
+        PlaylistCommand::ExportMix(path) => {
+            let (handle, rx) = state
+                .mixdown(path)
+                .context("failed to setup mixdown task")?;
+            console.follow(&state.disc_title()?, handle, rx)?;
+        }
+        PlaylistCommand::PrintCommands => output.lines.extend(state.burn_commands()?),
+        PlaylistCommand::Layout => output.lines.extend(state.disc_layout().lines()),
+        PlaylistCommand::Burn { confirmed, copies } => {
+            if state.playlist().is_empty() {
+                return Err(BurnError::EmptyPlaylist.into());
+            }
+            if !confirmed && state.config().confirm.burn {
+                for line in state.disc_layout().lines() {
+                    console.show(&line);
+                }
+                let question = match copies {
+                    1 => String::from("Burn this disc? [y/N] "),
+                    copies => format!("Burn {} copies of this disc? [y/N] ", copies),
+                };
+                if !console.ask(&question)?.eq_ignore_ascii_case("y") {
+                    output.line("burn cancelled");
+                    return Ok(());
+                }
+            }
+
+            if copies > 1 {
+                burn_copies(state, copies, console, output)?;
+            } else {
+                let (handle, rx) = state.burn().context("failed to setup burning task")?;
+                console.follow(&state.disc_title()?, handle, rx)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Burns `copies` of the playlist one after another, all as the same disc. With a
+/// `verify_device`, each copy is read back on that drive while the next one burns, so
+/// checking a batch takes hardly longer than burning it.
+fn burn_copies(
+    state: &AppState,
+    copies: usize,
+    console: &mut dyn Console,
+    output: &mut CommandOutput,
+) -> Result<()> {
+    let plan = state.burn_plan()?;
+    let device = &state.config().device;
+    let verify_device = state.config().verify_device.as_deref();
+    let (mut burned, mut verified) = (0, 0);
+    let mut mismatched = vec![];
+    for copy in 1..=copies {
+        let mut verify_task = None;
+        if copy > 1 {
+            let question = match verify_device {
+                Some(verify_device) => format!(
+                    "Move copy {} to {}, put a blank disc in {} and press Enter (s to stop) ",
+                    burned, verify_device, device
+                ),
+                None => format!(
+                    "Put a blank disc in {} for copy {} and press Enter (s to stop) ",
+                    device, copy
+                ),
+            };
+            if console.ask(&question)?.eq_ignore_ascii_case("s") {
+                break;
+            }
+            if verify_device.is_some() {
+                // the previous copy is read back while this one burns
+                verify_task = Some(state.verify(burned)?);
+            }
+        }
+
+        console.show(&format!("--- Copy {} of {} ---", copy, copies));
+        let result = state
+            .burn_as(plan.clone())
+            .context("failed to setup burning task")
+            .and_then(|(handle, rx)| {
+                let task = format!("{}, copy {} of {}", plan.title, copy, copies);
+                console.follow(&task, handle, rx)
+            });
+        if let Some((handle, rx)) = verify_task {
+            let task = format!("{}, copy {}", plan.title, burned);
+            if let Err(err) = console.follow(&task, handle, rx) {
+                console.show(&format!("{:#}", err));
+                mismatched.push(burned);
+            }
+            verified = burned;
+        }
+        result?;
+        burned = copy;
+    }
+
+    // the last copy has no next one to burn alongside
+    if let Some(verify_device) = verify_device
+        && burned > verified
+    {
+        console.ask(&format!(
+            "Move copy {} to {} and press Enter ",
+            burned, verify_device
+        ))?;
+        let (handle, rx) = state.verify(burned)?;
+        let task = format!("{}, copy {}", plan.title, burned);
+        if let Err(err) = console.follow(&task, handle, rx) {
+            console.show(&format!("{:#}", err));
+            mismatched.push(burned);
+        }
+    }
+
+    if !mismatched.is_empty() {
+        anyhow::bail!(
+            "{} of {} copies failed verification: {}",
+            mismatched.len(),
+            burned,
+            mismatched
+                .iter()
+                .map(|copy| format!("copy {}", copy))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    output.line(match verify_device {
+        Some(_) => format!("burned and verified {} copies", burned),
+        None => format!("burned {} copies", burned),
+    });
+
+    Ok(())
+}
+
+/// A past burn as one line of `history`.
+fn burn_line(burn: &burns::Burn) -> String {
+    let series = burn
+        .series
+        .as_ref()
+        .map(|(series, volume)| format!("  [{} #{}]", series, volume))
+        .unwrap_or_default();
+    format!(
+        "{:>4}  {}  {:<6}  {:>2} tracks  {:>6}  {}{}",
+        burn.id,
+        burn.burned_at,
+        if burn.ok { "ok" } else { "failed" },
+        burn.tracks,
+        duration::format_secs(burn.duration_secs),
+        burn.title,
+        series
+    )
+}
+
+fn execute_audition(
+    state: &mut AppState,
+    command: AuditionCommand,
+    console: &mut dyn Console,
+    output: &mut CommandOutput,
+) -> Result<()> {
+    let missing = |index: usize| format!("no audition entry at position {}", index + 1);
+    match command {
+        AuditionCommand::List => {
+            if state.audition().is_empty() {
+                output.line("nothing parked for audition");
+            }
+            for (i, song) in state.audition().iter().enumerate() {
+                output.line(format!(
+                    "#{} [{}] {} - {} ({})",
+                    i + 1,
+                    song.id,
+                    song.artist,
+                    song.title,
+                    duration::format_secs(song.played_duration_secs())
+                ));
+            }
+        }
+        AuditionCommand::Add(id) => {
+            let song = queries::track_from_id(state.conn(), id)?;
+            let title = song.title.clone();
+            if state.audition_add(song) {
+                output.line(format!("parked \"{}\" for audition", title));
+            } else {
+                output.line(format!("\"{}\" is already parked", title));
+            }
+        }
+        AuditionCommand::Remove(index) => {
+            anyhow::ensure!(state.audition_remove(index), missing(index));
+        }
+        AuditionCommand::Promote(index) => {
+            state.audition_promote(index)?;
+            output.show_playlist = true;
+        }
+        AuditionCommand::Preview(index) => {
+            let song = state
+                .audition()
+                .get(index)
+                .with_context(|| missing(index))?;
+            console.show(&format!("auditioning \"{}\"", song.title));
+            preview::play_sample(song)?;
+            state.record_preview(song.id)?;
+        }
+        AuditionCommand::Demote(index) => {
+            anyhow::ensure!(
+                state.playlist_demote(index),
+                "no playlist entry at position {}",
+                index + 1
+            );
+            output.show_playlist = true;
+        }
+        AuditionCommand::Clear => {
+            state.audition_clear();
+            output.line("audition list has been cleared");
+        }
+    }
+
+    Ok(())
+}
+
+fn execute_tag(
+    state: &mut AppState,
+    command: TagCommand,
+    console: &mut dyn Console,
+    output: &mut CommandOutput,
+) -> Result<()> {
+    match command {
+        TagCommand::Show(id) => {
+            for (label, value) in tags::show(state.conn(), id)? {
+                output.line(format!("{:<14}{}", label, value));
+            }
+        }
+        TagCommand::Set {
+            id,
+            field,
+            value,
+            write_file,
+        } => {
+            state.set_tag(id, field, &value, write_file)?;
+            output.line(format!("updated track {}", id));
+        }
+        TagCommand::Bulk {
+            filter,
+            sets,
+            dry_run,
+            write_file,
+        } => {
+            let changes = tags::plan_bulk(state.conn(), &filter, &sets)?;
+            if changes.is_empty() {
+                output.line("no tracks would change");
+                return Ok(());
+            }
+            for change in &changes {
+                console.show(&format!(
+                    "{}\t{}: \"{}\" -> \"{}\"",
+                    change.id,
+                    change.field.name(),
+                    change.old,
+                    change.new
+                ));
+            }
+            if dry_run {
+                return Ok(());
+            }
+
+            let question = format!("Apply {} changes? [y/N] ", changes.len());
+            if console.ask(&question)?.eq_ignore_ascii_case("y") {
+                state.apply_tag_changes(&changes, write_file)?;
+                output.line(format!(
+                    "applied {} changes; \"tag undo\" reverts them",
+                    changes.len()
+                ));
+            }
+        }
+        TagCommand::Undo => output.line(format!("reverted {} changes", state.undo_tags()?)),
+    }
+
+    Ok(())
+}
+
+fn album_audit(
+    state: &mut AppState,
+    check_art: bool,
+    write_file: bool,
+    console: &mut dyn Console,
+    output: &mut CommandOutput,
+) -> Result<()> {
+    let issues = audit::audit_albums(state.conn(), check_art)?;
+    if issues.is_empty() {
+        output.line("every album is consistent");
+        return Ok(());
+    }
+
+    let mut changes = vec![];
+    for issue in &issues {
+        let counts = issue
+            .counts()
+            .iter()
+            .map(|(value, count)| format!("{} x{}", issue.label(value), count))
+            .collect::<Vec<_>>();
+        console.show(&format!("\n{} ({})", issue.album, issue.dir));
+        console.show(&format!(
+            "  {} differs: {}",
+            issue.mismatch.name(),
+            counts.join(", ")
+        ));
+        if issue.mismatch == Mismatch::Art {
+            console.show("  copy the art by hand: \"tag undo\" couldn't revert it");
+            continue;
+        }
+
+        let question = format!(
+            "  Give every track {}? [y/N/q] ",
+            issue.label(&issue.majority)
+        );
+        match console.ask(&question)?.as_str() {
+            "y" | "Y" => changes.extend(issue.changes()),
+            "q" | "Q" => break,
+            _ => (),
+        }
+    }
+
+    if !changes.is_empty() {
+        state.apply_tag_changes(&changes, write_file)?;
+        output.line(format!(
+            "changed {} tags; \"tag undo\" reverts them",
+            changes.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Asks which copy to use when the library holds several files with the same artist and
+/// title, listing them best source first. Pressing enter keeps `song`, as does input
+/// that isn't from a terminal.
+fn pick_version(state: &AppState, song: Song, console: &mut dyn Console) -> Result<Song> {
+    if !console.interactive() {
+        return Ok(song);
+    }
+    let mut versions = queries::list_versions(state.conn(), &song.artist, &song.title)?;
+    if versions.len() < 2 {
+        return Ok(song);
+    }
+
+    console.show(&format!(
+        "\"{}\" by {} exists in {} versions:",
+        song.title,
+        song.artist,
+        versions.len()
+    ));
+    for (i, (version, quality)) in versions.iter().enumerate() {
+        let marker = if version.id == song.id { "*" } else { " " };
+        console.show(&format!(
+            "{marker}{}) [{}] {} ({}) - {}",
+            i + 1,
+            version.id,
+            app::fit_width(&version.album, app::ALBUM_COLUMN_WIDTH),
+            version.format(),
+            quality.describe()
+        ));
+    }
+
+    let answer = console.ask(&format!(
+        "Pick a version [1-{}] (enter keeps *): ",
+        versions.len()
+    ))?;
+    if answer.is_empty() {
+        return Ok(song);
+    }
+    let choice = answer
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=versions.len()).contains(n))
+        .with_context(|| format!("expected a number from 1 to {}", versions.len()))?;
+
+    Ok(versions.swap_remove(choice - 1).0)
+}
+
+fn parse_id(text: Option<&str>) -> Result<i64> {
+    text.context("expected an integer ID to be provided")?
+        .parse()
+        .context("failed to parse ID as integer")
+}
+
+/// Parses a position such as `95`, `1:35` or `1:02:03` into seconds.
+fn parse_clock(text: &str) -> Result<u64> {
+    text.split(':')
+        .try_fold(0u64, |acc, part| part.parse::<u64>().map(|n| acc * 60 + n))
+        .with_context(|| format!("failed to parse \"{}\" as seconds or m:ss", text))
+}
+
+/// Parses a 1-based playlist position into an index.
+fn parse_position(text: Option<&str>) -> Result<usize> {
+    text.context("expected a playlist position")?
+        .parse::<usize>()
+        .ok()
+        .and_then(|pos| pos.checked_sub(1))
+        .context("expected a playlist position starting at 1")
+}
+
+/// Groups words into a `--flag` and the words after it up to the next flag, with any
+/// quotes around the words removed.
+fn flag_groups<'a, I: Iterator<Item = &'a str>>(parts: I) -> Vec<(&'a str, String)> {
+    let mut groups: Vec<(&str, String)> = vec![];
+    for word in parts {
+        match groups.last_mut() {
+            _ if word.starts_with("--") => groups.push((word, String::new())),
+            Some((_, value)) => {
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(word.trim_matches(['"', '\'']));
+            }
+            None => groups.push(("", word.to_string())),
+        }
+    }
+
+    groups
+}
+
+fn join_strings<'a, I: Iterator<Item = &'a str>>(mut iter: Peekable<I>) -> String {
+    let mut result = String::new();
+    while let Some(part) = iter.next() {
+        result += part;
+        if iter.peek().is_some() {
+            result += " ";
+        }
+    }
+
+    result
+}
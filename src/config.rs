@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::art::ArtMode;
+use crate::build_db::{FtsOptions, ScanIo};
+use crate::confirm::Confirmations;
+use crate::duration::DurationStyle;
+use crate::hooks::Hooks;
+use crate::journal;
+use crate::notify::Alert;
+use crate::order::Etiquette;
+use crate::paths;
+use crate::presets::Presets;
+use crate::quality::{self, GateAction};
+use crate::throttle::Throttle;
+use crate::view::{BottomBar, PaneSplit};
+
+pub const DEFAULT_DEVICE: &str = "/dev/sr0";
+pub const DEFAULT_STAGING_DIR: &str = "/dev/shm";
+
+/// Settings read from the config file. Each line is `key = value`; blank lines and
+/// lines starting with `#` are ignored, and missing keys keep their defaults. The file
+/// isn't TOML: values are written bare, and quotes around one are dropped, see
+/// [`unquote`].
+///
+/// ```text
+/// nice = 10
+/// ionice = idle
+/// jobs = 2
+/// durations = long
+/// music_dir = /home/me/Music
+/// device = /dev/sr1
+/// verify_device = /dev/sr2
+/// read_offset = 6
+/// staging_dir = /tmp
+/// transcode_flac = -af aresample=resampler=soxr
+/// transcode_mp3 = -af aemphasis=mode=reproduction:type=cd
+/// fts_tokenizer = unicode61 remove_diacritics 2
+/// fts_prefix = 2 3 4
+/// scan_timeout = 60
+/// scan_retries = 3
+/// panes = 25 50 25
+/// collapse_empty_playlist = true
+/// show_help = true
+/// status_side = left
+/// bottom_lines = 2
+/// album_art = sixel
+/// finish_alert = bell
+/// accessible = true
+/// split_long_tracks = true
+/// tag_editor = kid3
+/// warn_same_artist = false
+/// max_album_tracks = 2
+/// confirm_burn = true
+/// confirm_clear = 5
+/// confirm_quit = unburned
+/// undo_depth = 100
+/// prefer_lossless = true
+/// gate_action = block
+/// gate_min_kbps = 256
+/// post_burn = notify-send "Burned $VOL28_TRACKS tracks: $VOL28_RESULT"
+/// ```
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub throttle: Throttle,
+    pub durations: DurationStyle,
+    /// Scanned when `scan` is run without a directory.
+    pub music_dir: Option<PathBuf>,
+    /// Burner device passed to wodim.
+    pub device: String,
+    /// Second drive burned copies are read back on while the next one burns.
+    pub verify_device: Option<String>,
+    /// Read offset of the verify drive in samples, as listed by AccurateRip.
+    pub read_offset: i32,
+    /// Where staged WAVs are written. Defaults to a RAM-backed directory since a
+    /// full disc is only about 700 MB.
+    pub staging_dir: PathBuf,
+    /// Extra ffmpeg options for staging each source format, as `transcode_<format>`.
+    pub presets: Presets,
+    pub fts: FtsOptions,
+    pub scan_io: ScanIo,
+    /// Widths of the TUI's artists, tracks and playlist panes.
+    pub panes: PaneSplit,
+    /// Hide the playlist pane while it has nothing in it, unless it has focus.
+    pub collapse_empty_playlist: bool,
+    /// Layout of the TUI's key help and status line.
+    pub bottom_bar: BottomBar,
+    /// Cover art in the TUI's album grid.
+    pub album_art: ArtMode,
+    /// How the end of a burn or mixdown is announced.
+    pub finish_alert: Alert,
+    /// Plain output for screen readers, as with `--accessible`.
+    pub accessible: bool,
+    /// Add as much of a track too long for the disc as fits, split at a quiet moment,
+    /// and park the rest for the next disc instead of refusing it.
+    pub split_long_tracks: bool,
+    pub hooks: Hooks,
+    /// Command `edit-tags` opens a track's file with.
+    pub tag_editor: Option<String>,
+    /// Rules the playlist is checked against, e.g. no artist twice in a row.
+    pub etiquette: Etiquette,
+    /// Which actions ask before going ahead.
+    pub confirm: Confirmations,
+    /// How many playlist changes the undo journal keeps.
+    pub undo_depth: usize,
+    /// Add the best lossless copy of a lossy track when the library has one, until
+    /// changed with `playlist prefer-lossless`.
+    pub prefer_lossless: bool,
+    /// What burns do with lossy sources below `gate_min_kbps`, until changed with
+    /// `playlist gate`.
+    pub gate_action: GateAction,
+    pub gate_min_kbps: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            throttle: Throttle::default(),
+            durations: DurationStyle::default(),
+            music_dir: None,
+            device: String::from(DEFAULT_DEVICE),
+            verify_device: None,
+            read_offset: 0,
+            staging_dir: PathBuf::from(DEFAULT_STAGING_DIR),
+            presets: Presets::default(),
+            fts: FtsOptions::default(),
+            scan_io: ScanIo::default(),
+            panes: PaneSplit::default(),
+            collapse_empty_playlist: false,
+            bottom_bar: BottomBar::default(),
+            album_art: ArtMode::default(),
+            finish_alert: Alert::default(),
+            accessible: false,
+            split_long_tracks: false,
+            hooks: Hooks::default(),
+            tag_editor: None,
+            etiquette: Etiquette::default(),
+            confirm: Confirmations::default(),
+            undo_depth: journal::DEFAULT_DEPTH,
+            prefer_lossless: false,
+            gate_action: GateAction::default(),
+            gate_min_kbps: quality::DEFAULT_MIN_KBPS,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, or the defaults when there isn't one.
+    pub fn load() -> Result<Config> {
+        let path = paths::config();
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                Config::parse(&text).with_context(|| format!("invalid config {}", path.display()))
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config> {
+        let mut config = Config::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .with_context(|| format!("line {}: expected key = value", number + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            let value = unquote(key, value).with_context(|| format!("line {}", number + 1))?;
+            match key {
+                "nice" | "ionice" | "jobs" => config.throttle.set(key, value),
+                "fts_tokenizer" | "fts_prefix" => config.fts.set(key, value),
+                "scan_timeout" | "scan_retries" => config.scan_io.set(key, value),
+                "pre_burn" | "post_burn" | "post_scan" => config.hooks.set(key, value),
+                "warn_same_artist" | "max_album_tracks" => config.etiquette.set(key, value),
+                "confirm_burn" | "confirm_clear" | "confirm_quit" => config.confirm.set(key, value),
+                "show_help" | "status_side" | "bottom_lines" => config.bottom_bar.set(key, value),
+                key if key.starts_with("transcode_") => {
+                    config.presets.set(&key["transcode_".len()..], value)
+                }
+                "durations" => value.parse().map(|style| config.durations = style),
+                "music_dir" => {
+                    config.music_dir = Some(PathBuf::from(value));
+                    Ok(())
+                }
+                "device" => {
+                    config.device = value.to_string();
+                    Ok(())
+                }
+                "verify_device" => {
+                    config.verify_device = Some(value.to_string());
+                    Ok(())
+                }
+                "read_offset" => value
+                    .parse()
+                    .map(|offset| config.read_offset = offset)
+                    .context("expected a number of samples"),
+                "tag_editor" => {
+                    config.tag_editor = Some(value.to_string());
+                    Ok(())
+                }
+                "staging_dir" => {
+                    config.staging_dir = PathBuf::from(value);
+                    Ok(())
+                }
+                "panes" => value.parse().map(|panes| config.panes = panes),
+                "collapse_empty_playlist" => value
+                    .parse()
+                    .map(|collapse| config.collapse_empty_playlist = collapse)
+                    .context("expected true or false"),
+                "album_art" => value.parse().map(|mode| config.album_art = mode),
+                "finish_alert" => value.parse().map(|alert| config.finish_alert = alert),
+                "accessible" => value
+                    .parse()
+                    .map(|accessible| config.accessible = accessible)
+                    .context("expected true or false"),
+                "split_long_tracks" => value
+                    .parse()
+                    .map(|split| config.split_long_tracks = split)
+                    .context("expected true or false"),
+                "undo_depth" => value
+                    .parse()
+                    .map(|depth| config.undo_depth = depth)
+                    .context("expected a number of changes"),
+                "prefer_lossless" => value
+                    .parse()
+                    .map(|prefer| config.prefer_lossless = prefer)
+                    .context("expected true or false"),
+                "gate_action" => value.parse().map(|action| config.gate_action = action),
+                "gate_min_kbps" => value
+                    .parse()
+                    .map(|kbps| config.gate_min_kbps = kbps)
+                    .context("expected a bitrate in kbps"),
+                _ => Err(anyhow::anyhow!("unknown setting \"{}\"", key)),
+            }
+            .with_context(|| format!("line {}", number + 1))?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// `value` without the quotes around it when it is wholly quoted, as it would be in
+/// TOML. Any other quote at its start is refused rather than becoming part of a path
+/// or name. Commands are left as written, since their quotes are for the shell.
+fn unquote<'a>(key: &str, value: &'a str) -> Result<&'a str> {
+    if matches!(key, "pre_burn" | "post_burn" | "post_scan" | "tag_editor")
+        || key.starts_with("transcode_")
+    {
+        return Ok(value);
+    }
+    let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+        return Ok(value);
+    };
+
+    value[1..]
+        .strip_suffix(quote)
+        .filter(|inner| !inner.contains(quote))
+        .with_context(|| {
+            format!(
+                "unbalanced quotes in {}: values don't need quoting, this isn't TOML",
+                value
+            )
+        })
+}
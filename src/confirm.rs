@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+
+/// When quitting asks first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuitConfirm {
+    #[default]
+    Never,
+    /// Only while the playlist isn't the disc burned last.
+    Unburned,
+    Always,
+}
+
+/// Which actions ask before going ahead, from the `confirm_burn`, `confirm_clear` and
+/// `confirm_quit` config settings.
+#[derive(Debug, Clone, Copy)]
+pub struct Confirmations {
+    /// Ask before burning, after showing the disc layout.
+    pub burn: bool,
+    /// Ask before clearing a playlist of more than this many tracks.
+    pub clear_over: Option<usize>,
+    pub quit: QuitConfirm,
+}
+
+impl Default for Confirmations {
+    fn default() -> Self {
+        Confirmations {
+            burn: true,
+            clear_over: None,
+            quit: QuitConfirm::Never,
+        }
+    }
+}
+
+impl Confirmations {
+    /// Applies a `confirm_burn` (true or false), `confirm_clear` (never, always or a
+    /// track count to ask above) or `confirm_quit` (never, unburned or always) setting
+    /// from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "confirm_burn" => {
+                self.burn = value.parse().context("expected true or false")?;
+            }
+            "confirm_clear" => {
+                self.clear_over = match value {
+                    "never" => None,
+                    "always" => Some(0),
+                    count => Some(
+                        count
+                            .parse()
+                            .context("expected never, always or a number of tracks")?,
+                    ),
+                };
+            }
+            "confirm_quit" => {
+                self.quit = match value {
+                    "never" => QuitConfirm::Never,
+                    "unburned" => QuitConfirm::Unburned,
+                    "always" => QuitConfirm::Always,
+                    _ => anyhow::bail!("expected never, unburned or always"),
+                };
+            }
+            _ => anyhow::bail!("unknown confirmation setting \"{}\"", key),
+        }
+
+        Ok(())
+    }
+
+    /// Whether clearing a playlist of `len` tracks asks first.
+    pub fn clear(&self, len: usize) -> bool {
+        self.clear_over.is_some_and(|over| len > over)
+    }
+
+    /// Whether quitting asks first, given whether the playlist has been burned as it is.
+    pub fn quit(&self, unburned: bool) -> bool {
+        match self.quit {
+            QuitConfirm::Never => false,
+            QuitConfirm::Unburned => unburned,
+            QuitConfirm::Always => true,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use sha1::{Digest, Sha1};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::app::CD_MAX_TRACKS;
+use crate::base64;
+use crate::staging;
+
+/// The base64 alphabet MusicBrainz uses in disc IDs, which are also URL parts: `.` and
+/// `_` in place of `+` and `/`. Padding is `-` rather than `=`.
+const MUSICBRAINZ_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789._";
+
+/// Frame offset of track 1 on every disc wodim writes: the two second pregap.
+const FIRST_OFFSET: u64 = 150;
+/// CD audio frames per second.
+const FRAMES_PER_SEC: u64 = 75;
+/// The number of offsets in a MusicBrainz disc ID, one for each track a CD can hold.
+const MAX_TRACKS: usize = CD_MAX_TRACKS;
+
+/// What identifies a burned disc to players looking up its titles: the MusicBrainz disc
+/// ID and the older freedb (CDDB) one, both worked out from where its tracks start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscId {
+    pub musicbrainz: String,
+    pub freedb: String,
+    /// The table of contents as MusicBrainz writes it: first and last track number, the
+    /// lead-out offset and every track's offset, in frames.
+    pub toc: String,
+}
+
+impl DiscId {
+    /// The ID of a disc of tracks `lengths` frames long, laid out back to back the way
+    /// wodim writes them with `-dao`.
+    pub fn from_lengths(lengths: &[u64]) -> Self {
+        let mut offsets = vec![];
+        let mut position = FIRST_OFFSET;
+        for length in lengths {
+            offsets.push(position);
+            position += length;
+        }
+        let leadout = position;
+        let tracks = offsets.len();
+
+        let mut toc = format!("{:02X}{:02X}{:08X}", 1, tracks, leadout);
+        for i in 0..MAX_TRACKS {
+            let _ = write!(toc, "{:08X}", offsets.get(i).copied().unwrap_or(0));
+        }
+        let musicbrainz = base64::encode(&Sha1::digest(toc.as_bytes()), MUSICBRAINZ_ALPHABET, '-');
+
+        let checksum = offsets
+            .iter()
+            .map(|offset| digit_sum(offset / FRAMES_PER_SEC))
+            .sum::<u64>();
+        let first = offsets.first().copied().unwrap_or(FIRST_OFFSET);
+        let secs = leadout / FRAMES_PER_SEC - first / FRAMES_PER_SEC;
+        let freedb = format!(
+            "{:08x}",
+            (checksum % 0xff) << 24 | secs << 8 | tracks as u64
+        );
+
+        let toc = [1, tracks as u64, leadout]
+            .into_iter()
+            .chain(offsets)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        DiscId {
+            musicbrainz,
+            freedb,
+            toc,
+        }
+    }
+
+    /// The ID of the disc burned from the staged `wavs`, each padded to whole frames.
+    pub fn from_wavs(wavs: &[PathBuf]) -> Result<Self> {
+        anyhow::ensure!(
+            wavs.len() <= MAX_TRACKS,
+            "a CD holds at most {} tracks",
+            MAX_TRACKS
+        );
+        let lengths = wavs
+            .iter()
+            .map(|wav| staging::disc_frames(wav))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(DiscId::from_lengths(&lengths))
+    }
+
+    /// The disc's entry in the xmcd format freedb took and gnudb still takes, for the
+    /// disc `title` by `artist` with one `(artist, title)` per track.
+    pub fn xmcd(&self, artist: &str, title: &str, tracks: &[(String, String)]) -> String {
+        let toc = self
+            .toc
+            .split(' ')
+            .filter_map(|n| n.parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        let leadout = toc.get(2).copied().unwrap_or(0);
+
+        let mut entry = String::from("# xmcd\n#\n# Track frame offsets:\n");
+        for offset in toc.iter().skip(3) {
+            let _ = writeln!(entry, "#\t{}", offset);
+        }
+        let _ = writeln!(
+            entry,
+            "#\n# Disc length: {} seconds\n#",
+            leadout / FRAMES_PER_SEC
+        );
+        let _ = writeln!(
+            entry,
+            "# Revision: 0\n# Submitted via: vol_28 {}\n#",
+            env!("CARGO_PKG_VERSION")
+        );
+        let _ = writeln!(entry, "DISCID={}", self.freedb);
+        let _ = writeln!(entry, "DTITLE={} / {}", artist, title);
+        entry.push_str("DYEAR=\nDGENRE=\n");
+        for (i, (track_artist, track_title)) in tracks.iter().enumerate() {
+            if track_artist == artist {
+                let _ = writeln!(entry, "TTITLE{}={}", i, track_title);
+            } else {
+                let _ = writeln!(entry, "TTITLE{}={} / {}", i, track_artist, track_title);
+            }
+        }
+        entry.push_str("EXTD=\n");
+        for i in 0..tracks.len() {
+            let _ = writeln!(entry, "EXTT{}=", i);
+        }
+        entry.push_str("PLAYORDER=\n");
+
+        entry
+    }
+
+    /// Page where MusicBrainz takes the disc ID, to attach it to a release or enter the
+    /// disc as a new one.
+    pub fn submission_url(&self) -> String {
+        let tracks = self.toc.split(' ').nth(1).unwrap_or("0");
+        format!(
+            "https://musicbrainz.org/cdtoc/attach?id={}&tracks={}&toc={}",
+            self.musicbrainz,
+            tracks,
+            self.toc.replace(' ', "+")
+        )
+    }
+}
+
+fn digit_sum(mut n: u64) -> u64 {
+    let mut sum = 0;
+    while n > 0 {
+        sum += n % 10;
+        n /= 10;
+    }
+
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example disc from MusicBrainz's disc ID documentation.
+    #[test]
+    fn musicbrainz_example() {
+        let disc_id = DiscId::from_lengths(&[15213, 16951, 14278, 16822, 17075, 14973]);
+        assert_eq!(disc_id.toc, "1 6 95462 150 15363 32314 46592 63414 80489");
+        assert_eq!(disc_id.musicbrainz, "49HHV7Eb8UKF3aQiNmu1GR8vKTY-");
+        assert_eq!(disc_id.freedb, "3404f606");
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How durations are written in tables, listings and messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DurationStyle {
+    /// `3:07` or `1:19:59`, which line up in table columns.
+    #[default]
+    Compact,
+    /// humantime's `3m 7s` or `1h 19m 59s`.
+    Long,
+}
+
+impl std::str::FromStr for DurationStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "compact" => Ok(DurationStyle::Compact),
+            "long" => Ok(DurationStyle::Long),
+            _ => anyhow::bail!("expected compact or long, got \"{}\"", s),
+        }
+    }
+}
+
+static LONG: AtomicBool = AtomicBool::new(false);
+
+/// Sets the style [`format_secs`] uses from now on.
+pub fn set_style(style: DurationStyle) {
+    LONG.store(style == DurationStyle::Long, Ordering::Relaxed);
+}
+
+/// `secs` in the configured style.
+pub fn format_secs(secs: u64) -> String {
+    if LONG.load(Ordering::Relaxed) {
+        humantime::format_duration(std::time::Duration::from_secs(secs)).to_string()
+    } else {
+        clock(secs)
+    }
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up.
+pub fn clock(secs: u64) -> String {
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{}:{:02}", minutes, secs)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::duration::clock;
+
+/// CD audio frames per second, the unit trims are kept in.
+pub const FRAMES_PER_SEC: u64 = 75;
+
+/// Trim and fade applied to a single playlist entry when it is staged. The source
+/// file is never modified.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TrackEdit {
+    /// Frames cut from the start of the track. Frames rather than seconds, so chapters
+    /// and cue sheet tracks are cut where they are marked.
+    pub start_frames: u64,
+    /// Position the track is cut off at, in frames, or the natural end when unset.
+    pub end_frames: Option<u64>,
+    pub fade_in_secs: u64,
+    pub fade_out_secs: u64,
+}
+
+impl TrackEdit {
+    pub fn is_empty(&self) -> bool {
+        *self == TrackEdit::default()
+    }
+
+    /// Where the kept region starts, in whole seconds.
+    pub fn start_secs(&self) -> u64 {
+        self.start_frames / FRAMES_PER_SEC
+    }
+
+    /// Length of the edited track in frames, given the length of the source.
+    pub fn duration_frames(&self, source_secs: u64) -> u64 {
+        let source_frames = source_secs * FRAMES_PER_SEC;
+        self.end_frames
+            .unwrap_or(source_frames)
+            .min(source_frames)
+            .saturating_sub(self.start_frames)
+    }
+
+    /// Length of the edited track to the nearest second, given the length of the source.
+    pub fn duration_secs(&self, source_secs: u64) -> u64 {
+        (self.duration_frames(source_secs) + FRAMES_PER_SEC / 2) / FRAMES_PER_SEC
+    }
+
+    /// ffmpeg input options (placed before `-i`) that select the kept region.
+    pub fn input_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.start_frames > 0 {
+            args.extend(["-ss".to_string(), timestamp(self.start_frames)]);
+        }
+        if let Some(end) = self.end_frames {
+            args.extend(["-to".to_string(), timestamp(end)]);
+        }
+
+        args
+    }
+
+    /// ffmpeg audio filter applying the fades, relative to the trimmed output.
+    pub fn fade_filter(&self, source_secs: u64) -> Option<String> {
+        let mut filters = vec![];
+        if self.fade_in_secs > 0 {
+            filters.push(format!("afade=t=in:st=0:d={}", self.fade_in_secs));
+        }
+        if self.fade_out_secs > 0 {
+            let start = self
+                .duration_frames(source_secs)
+                .saturating_sub(self.fade_out_secs * FRAMES_PER_SEC);
+            filters.push(format!(
+                "afade=t=out:st={}:d={}",
+                timestamp(start),
+                self.fade_out_secs
+            ));
+        }
+
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+
+    /// Short description for listings, e.g. `0:12-3:40, fade 2s/5s`.
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{}-{}",
+            clock(self.start_secs()),
+            self.end_frames
+                .map_or_else(|| String::from("end"), |end| clock(end / FRAMES_PER_SEC))
+        );
+        if self.fade_in_secs > 0 || self.fade_out_secs > 0 {
+            text += &format!(", fade {}s/{}s", self.fade_in_secs, self.fade_out_secs);
+        }
+
+        text
+    }
+}
+
+/// `frames` as seconds for ffmpeg, to the microsecond, e.g. `12.346667`.
+pub fn timestamp(frames: u64) -> String {
+    format!(
+        "{}.{:06}",
+        frames / FRAMES_PER_SEC,
+        frames % FRAMES_PER_SEC * 1_000_000 / FRAMES_PER_SEC
+    )
+}
@@ -0,0 +1,283 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use thiserror::Error;
+
+use crate::shutdown;
+
+/// How many trailing lines of a failed tool's stderr are kept for the error report.
+const STDERR_TAIL_LINES: usize = 5;
+/// Messages ffmpeg prints, lowercased, when the file itself can't be decoded, as opposed
+/// to the tool or the system failing.
+const UNDECODABLE_STDERR: &[&str] = &[
+    "invalid data found when processing input",
+    "moov atom not found",
+    "could not find codec parameters",
+    "does not contain any stream",
+    "output file is empty, nothing was encoded",
+    "error while decoding stream",
+];
+/// Messages ffmpeg prints, lowercased, about files it can't play because they are
+/// encrypted: DRM protected WMA and Audible AAX.
+const DRM_STDERR: &[&str] = &[
+    "drm protected stream detected",
+    "activation_bytes option is missing",
+];
+/// What ffmpeg's stderr holds, lowercased, when writing the output failed, which makes
+/// decoding errors next to it the system's fault rather than the file's.
+const OUTPUT_ERROR_STDERR: &[&str] = &[
+    "no space left on device",
+    "disk quota exceeded",
+    "read-only file system",
+    "input/output error",
+    "error writing trailer",
+    "av_interleaved_write_frame()",
+    "error submitting a packet to the muxer",
+];
+
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error("failed to open library database \"{path}\"")]
+    Open {
+        path: String,
+        #[source]
+        source: rusqlite::Error,
+    },
+    #[error("track {0} is not in the library")]
+    TrackNotFound(i64),
+    #[error("there is no burn {0} in the history")]
+    BurnNotFound(i64),
+    #[error("no track is tagged with artist \"{0}\"")]
+    ArtistNotFound(String),
+    #[error("library query failed")]
+    Query(#[from] rusqlite::Error),
+    #[error("{0} is disabled in read-only mode")]
+    ReadOnly(&'static str),
+}
+
+#[derive(Debug, Error)]
+pub enum ScanError {
+    #[error("\"{0}\" is not a directory")]
+    NotADirectory(PathBuf),
+    #[error("failed to read tags for {path}: {source}")]
+    ReadTags {
+        path: PathBuf,
+        #[source]
+        source: lofty::error::LoftyError,
+    },
+    #[error("{path} is missing required tags: {reason}")]
+    MissingTags { path: PathBuf, reason: String },
+    #[error("gave up on {path}: reading it took longer than {secs}s")]
+    TimedOut { path: PathBuf, secs: u64 },
+    #[error("failed to list part of the library: {0}")]
+    Walk(walkdir::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum TranscodeError {
+    #[error("failed to run {tool}. Is it installed?")]
+    Spawn {
+        tool: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("{tool} failed on {path} ({status}){}", stderr_suffix(stderr))]
+    Failed {
+        tool: &'static str,
+        path: PathBuf,
+        status: ExitStatus,
+        /// Last lines the tool wrote to stderr.
+        stderr: String,
+    },
+    #[error("failed to write {path}")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum BurnError {
+    #[error("playlist is empty. Add songs first")]
+    EmptyPlaylist,
+    #[error("failed to set up a staging directory in {dir}")]
+    Staging {
+        dir: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("quality gate blocked the burn: replace or allow the {0} tracks above")]
+    QualityGate(usize),
+    #[error("failed to spawn wodim. Check device path and permissions.")]
+    Spawn(#[source] std::io::Error),
+    #[error(
+        "wodim failed to burn the playlist ({status}){}",
+        stderr_suffix(stderr)
+    )]
+    Failed { status: ExitStatus, stderr: String },
+}
+
+fn stderr_suffix(stderr: &str) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(":\n{}", stderr)
+    }
+}
+
+/// Keeps the last few lines of a tool's stderr.
+pub fn stderr_tail<'a>(lines: impl DoubleEndedIterator<Item = &'a str>) -> String {
+    let mut tail = lines
+        .rev()
+        .filter(|line| !line.trim().is_empty())
+        .take(STDERR_TAIL_LINES)
+        .collect::<Vec<_>>();
+    tail.reverse();
+    tail.join("\n")
+}
+
+impl TranscodeError {
+    /// Why the source file can never be transcoded, e.g. "DRM protected", when the
+    /// failure is down to the file rather than to the tool or the system.
+    pub fn unplayable_reason(&self) -> Option<&'static str> {
+        let TranscodeError::Failed { stderr, status, .. } = self else {
+            return None;
+        };
+        let stderr = stderr.to_lowercase();
+        // killed, or failing to write, says nothing about the file
+        if status.code().is_none() || OUTPUT_ERROR_STDERR.iter().any(|text| stderr.contains(text)) {
+            return None;
+        }
+        let says = |messages: &[&str]| {
+            stderr
+                .lines()
+                .any(|line| messages.iter().any(|message| is_message(line, message)))
+        };
+        if says(DRM_STDERR) {
+            Some("DRM protected")
+        } else if says(UNDECODABLE_STDERR) {
+            Some("undecodable")
+        } else {
+            None
+        }
+    }
+
+    /// Runs `command` to completion, capturing its stderr for the error if it fails.
+    /// Its stdout is discarded.
+    pub fn run(tool: &'static str, path: &Path, command: &mut Command) -> Result<(), Self> {
+        let output = shutdown::output(command.stdout(Stdio::null()).stderr(Stdio::piped()))
+            .map_err(|source| TranscodeError::Spawn { tool, source })?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        Err(TranscodeError::Failed {
+            tool,
+            path: path.to_path_buf(),
+            status: output.status,
+            stderr: stderr_tail(String::from_utf8_lossy(&output.stderr).lines()),
+        })
+    }
+}
+
+/// Whether `line` of ffmpeg's stderr is `message`, as in `[mov,mp4 @ 0x55d1] moov atom
+/// not found` or `in.m4a: invalid data found when processing input`, rather than
+/// merely mentioning it in a path or tag.
+fn is_message(line: &str, message: &str) -> bool {
+    let mut line = line.trim();
+    // newer versions nest the context, e.g. `[aist#0:0/mp3 @ 0x55d1] [dec:mp3 @ 0x55d2]`
+    while let Some((_, text)) = line
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+    {
+        line = text;
+    }
+
+    line.starts_with(message) || line.ends_with(&format!(": {}", message))
+}
+
+/// Exit status for the process when `err` ends it, by the kind of failure.
+pub fn exit_code(err: &anyhow::Error) -> u8 {
+    for cause in err.chain() {
+        if cause.is::<DbError>() {
+            return 3;
+        } else if cause.is::<ScanError>() {
+            return 4;
+        } else if cause.is::<TranscodeError>() {
+            return 5;
+        } else if cause.is::<BurnError>() {
+            return 6;
+        }
+    }
+
+    1
+}
+
+/// Suggested fix for `err`, when it is one we know how to help with.
+pub fn hint(err: &anyhow::Error) -> Option<Cow<'static, str>> {
+    err.chain().find_map(|cause| {
+        if let Some(err) = cause.downcast_ref::<DbError>() {
+            match err {
+                DbError::Open { .. } => Some("run `scan <music dir>` to create the library".into()),
+                DbError::TrackNotFound(_) => {
+                    Some("list tracks with `search` or `artist-list`".into())
+                }
+                DbError::BurnNotFound(_) => {
+                    Some("list past burns and their IDs with `history`".into())
+                }
+                DbError::ArtistNotFound(_) => {
+                    Some("list artists with `artist-list`; spellings are case-sensitive".into())
+                }
+                DbError::Query(_) => {
+                    Some("rescan the library if it was created by an old version".into())
+                }
+                DbError::ReadOnly(_) => Some(
+                    "run without --read-only once no other session is using the library".into(),
+                ),
+            }
+        } else if let Some(err) = cause.downcast_ref::<ScanError>() {
+            match err {
+                ScanError::NotADirectory(_) => {
+                    Some("pass the top directory of your music library".into())
+                }
+                ScanError::ReadTags { .. } | ScanError::MissingTags { .. } => {
+                    Some("fix the file's tags with a tag editor and scan again".into())
+                }
+                ScanError::TimedOut { .. } => {
+                    Some("check the share is mounted, or raise scan_timeout in the config".into())
+                }
+                ScanError::Walk(_) => Some("check the folder's permissions and scan again".into()),
+            }
+        } else if let Some(err) = cause.downcast_ref::<TranscodeError>() {
+            match err {
+                TranscodeError::Spawn { .. } => Some("install ffmpeg and normalize-audio".into()),
+                TranscodeError::Failed { .. } => {
+                    Some("check the source file plays and is readable, or remove it".into())
+                }
+                TranscodeError::Write { .. } => {
+                    Some("check the staging directory is writable and has free space".into())
+                }
+            }
+        } else if let Some(err) = cause.downcast_ref::<BurnError>() {
+            match err {
+                BurnError::EmptyPlaylist => None,
+                BurnError::Staging { dir, .. } => Some(Cow::Owned(format!(
+                    "make sure {} exists and has free space, or set staging_dir in the config",
+                    dir.display()
+                ))),
+                BurnError::QualityGate(_) => {
+                    Some("use `playlist gate allow <ID>` to accept a track".into())
+                }
+                BurnError::Spawn(_) => {
+                    Some("install wodim and add yourself to the cdrom group".into())
+                }
+                BurnError::Failed { .. } => {
+                    Some("insert a blank CD-R, close the tray, or retry at a lower speed".into())
+                }
+            }
+        } else {
+            None
+        }
+    })
+}
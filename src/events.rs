@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use std::sync::{Arc, Mutex, mpsc};
+
+use crate::errors;
+use crate::wodim::BufferStatus;
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub is_stderr: bool,
+    pub line: String,
+}
+
+/// Stage of a burn or mixdown, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BurnStage {
+    Transcoding,
+    Normalizing,
+    Burning,
+    /// Reading a burned copy back on the second drive.
+    Verifying,
+    MixingDown,
+}
+
+impl BurnStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BurnStage::Transcoding => "transcoding",
+            BurnStage::Normalizing => "normalizing",
+            BurnStage::Burning => "burning",
+            BurnStage::Verifying => "verifying",
+            BurnStage::MixingDown => "mixing_down",
+        }
+    }
+}
+
+/// Progress reported by scans and background tasks. The TUI, the shell and any
+/// external frontend all consume this same stream.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Files examined so far while scanning a library.
+    ScanProgress {
+        scanned: usize,
+    },
+    /// `done` of `total` tracks have been staged; `title` is being transcoded next.
+    TranscodeProgress {
+        done: usize,
+        total: usize,
+        title: String,
+    },
+    BurnStage(BurnStage),
+    /// Share of the disc written so far while burning.
+    BurnProgress {
+        percent: u8,
+    },
+    /// Estimated wall-clock time left in `stage`, from the speed of past runs.
+    Eta {
+        stage: BurnStage,
+        remaining_secs: u64,
+    },
+    Buffer(BufferStatus),
+    Line(LogLine),
+    /// The task failed. Always the last event of a task.
+    Error(Arc<anyhow::Error>),
+    /// The task succeeded, with a summary. Always the last event of a task.
+    Done(String),
+}
+
+impl Event {
+    /// The event as a single line of JSON, for scripts following a headless run.
+    /// Errors carry the process exit code they map to and a hint when there is one.
+    pub fn to_json(&self) -> String {
+        match self {
+            Event::ScanProgress { scanned } => {
+                format!(r#"{{"event":"scan_progress","scanned":{}}}"#, scanned)
+            }
+            Event::TranscodeProgress { done, total, title } => format!(
+                r#"{{"event":"transcode_progress","done":{},"total":{},"title":{}}}"#,
+                done,
+                total,
+                json_string(title)
+            ),
+            Event::BurnStage(stage) => {
+                format!(r#"{{"event":"stage","stage":"{}"}}"#, stage.name())
+            }
+            Event::BurnProgress { percent } => {
+                format!(r#"{{"event":"burn_progress","percent":{}}}"#, percent)
+            }
+            Event::Eta {
+                stage,
+                remaining_secs,
+            } => format!(
+                r#"{{"event":"eta","stage":"{}","remaining_secs":{}}}"#,
+                stage.name(),
+                remaining_secs
+            ),
+            Event::Buffer(BufferStatus {
+                fifo_percent,
+                drive_percent,
+            }) => format!(
+                r#"{{"event":"buffer","fifo_percent":{},"drive_percent":{}}}"#,
+                fifo_percent, drive_percent
+            ),
+            Event::Line(LogLine { is_stderr, line }) => format!(
+                r#"{{"event":"log","stream":"{}","line":{}}}"#,
+                if *is_stderr { "stderr" } else { "stdout" },
+                json_string(line)
+            ),
+            Event::Error(err) => error_json(err),
+            Event::Done(summary) => {
+                format!(r#"{{"event":"done","summary":{}}}"#, json_string(summary))
+            }
+        }
+    }
+
+    /// The event as a line of a burn's transcript, for those worth keeping.
+    pub fn transcript_line(&self) -> Option<String> {
+        match self {
+            Event::TranscodeProgress { done, total, title } => {
+                Some(format!("transcoding {}/{}: {}", done + 1, total, title))
+            }
+            Event::BurnStage(stage) => Some(format!("--- {} ---", stage.name())),
+            Event::Line(LogLine { is_stderr, line }) => Some(if *is_stderr {
+                format!("[stderr] {}", line)
+            } else {
+                line.clone()
+            }),
+            Event::Error(err) => Some(format!("error: {:?}", err)),
+            Event::Done(summary) => Some(format!("done: {}", summary)),
+            Event::ScanProgress { .. }
+            | Event::BurnProgress { .. }
+            | Event::Eta { .. }
+            | Event::Buffer(_) => None,
+        }
+    }
+}
+
+/// The JSON line of an [`Event::Error`] carrying `err`.
+pub fn error_json(err: &anyhow::Error) -> String {
+    format!(
+        r#"{{"event":"error","message":{},"hint":{},"exit_code":{}}}"#,
+        json_string(&format!("{:#}", err)),
+        errors::hint(err).map_or_else(|| String::from("null"), |hint| json_string(&hint)),
+        errors::exit_code(err)
+    )
+}
+
+/// `text` as a quoted JSON string.
+pub fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str(r#"\""#),
+            '\\' => json.push_str(r"\\"),
+            '\n' => json.push_str(r"\n"),
+            '\r' => json.push_str(r"\r"),
+            '\t' => json.push_str(r"\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+
+    json
+}
+
+impl From<LogLine> for Event {
+    fn from(line: LogLine) -> Self {
+        Event::Line(line)
+    }
+}
+
+impl From<Result<String>> for Event {
+    fn from(result: Result<String>) -> Self {
+        match result {
+            Ok(summary) => Event::Done(summary),
+            Err(err) => Event::Error(Arc::new(err)),
+        }
+    }
+}
+
+/// Fans events out to every subscriber. Cloning the bus shares its subscribers.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<mpsc::Sender<Event>>>>,
+}
+
+impl EventBus {
+    /// Receives every event published from now on, across all tasks.
+    pub fn subscribe(&self) -> mpsc::Receiver<Event> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+
+        rx
+    }
+
+    /// Sends `event` to all subscribers, forgetting those that have hung up.
+    pub fn publish(&self, event: Event) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|tx| tx.send(event.clone()).is_ok());
+        }
+    }
+
+    /// Emitter for a single background task. The returned receiver sees only that
+    /// task's events and disconnects once the task drops its emitter.
+    pub fn task(&self) -> (Emitter, mpsc::Receiver<Event>) {
+        let (tx, rx) = mpsc::channel();
+        (
+            Emitter {
+                task: tx,
+                bus: self.clone(),
+                transcript: None,
+            },
+            rx,
+        )
+    }
+}
+
+/// Sending half handed to a background task; see [`EventBus::task`].
+#[derive(Clone)]
+pub struct Emitter {
+    task: mpsc::Sender<Event>,
+    bus: EventBus,
+    transcript: Option<Transcript>,
+}
+
+/// Lines of a task's output kept by a transcribing [`Emitter`].
+#[derive(Clone, Default)]
+pub struct Transcript(Arc<Mutex<Vec<String>>>);
+
+impl Transcript {
+    /// Everything kept so far, one line each.
+    pub fn text(&self) -> String {
+        self.0
+            .lock()
+            .map(|lines| lines.iter().map(|line| format!("{}\n", line)).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Emitter {
+    /// An emitter sending to the same places that also keeps a transcript of what is
+    /// sent through it, which the returned handle reads.
+    pub fn transcribed(&self) -> (Emitter, Transcript) {
+        let transcript = Transcript::default();
+        let emitter = Emitter {
+            transcript: Some(transcript.clone()),
+            ..self.clone()
+        };
+
+        (emitter, transcript)
+    }
+
+    /// Sends `event` to the task's receiver and publishes it on the bus. Fails if the
+    /// task's receiver is gone, which means nobody is waiting on the task any more.
+    pub fn send(&self, event: Event) -> Result<()> {
+        if let Some(Transcript(lines)) = &self.transcript
+            && let Some(line) = event.transcript_line()
+            && let Ok(mut lines) = lines.lock()
+        {
+            lines.push(line);
+        }
+        self.bus.publish(event.clone());
+        self.task
+            .send(event)
+            .ok()
+            .context("task event receiver hung up")
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::shutdown;
+
+/// Shows `path` in the system file manager: selected in Finder on macOS, or by opening
+/// its folder with `xdg-open` elsewhere.
+pub fn reveal(path: &Path) -> Result<()> {
+    anyhow::ensure!(path.exists(), "{} no longer exists", path.display());
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    };
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+
+    let status = shutdown::status(&mut command).context("failed to open the file manager")?;
+    anyhow::ensure!(status.success(), "file manager exited with {}", status);
+
+    Ok(())
+}
+
+/// Opens `url` in the default browser, with `open` on macOS and `xdg-open` elsewhere.
+pub fn open_url(url: &str) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let mut command = Command::new(opener);
+    command.arg(url).stdout(Stdio::null()).stderr(Stdio::null());
+
+    let status = shutdown::status(&mut command).context("failed to open the browser")?;
+    anyhow::ensure!(status.success(), "{} exited with {}", opener, status);
+
+    Ok(())
+}
+
+/// Runs tag editor `editor`, a shell command such as `kid3` or `puddletag`, on `path`
+/// and waits for it to be closed.
+pub fn edit_tags(editor: &str, path: &Path) -> Result<()> {
+    anyhow::ensure!(path.exists(), "{} no longer exists", path.display());
+
+    // the path goes in as $1 so it needs no quoting
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path);
+
+    let status = shutdown::status(&mut command)
+        .with_context(|| format!("failed to run tag editor \"{}\"", editor))?;
+    anyhow::ensure!(status.success(), "tag editor exited with {}", status);
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+/// Scores how well `query` matches `candidate` as a case-insensitive subsequence,
+/// skim-style: consecutive matches and matches at the start of words score higher,
+/// gaps score lower. Returns `None` when not every query character is found in order.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    if query.peek().is_none() {
+        return Some(0);
+    }
+
+    let mut score = 0;
+    let mut prev_matched = false;
+    let mut prev_char = None::<char>;
+    let mut gap = 0;
+
+    for c in candidate.chars() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+
+        let word_start = prev_char.is_none_or(|p| !p.is_alphanumeric());
+        if c.to_lowercase().eq(std::iter::once(wanted)) {
+            score += 16;
+            if prev_matched {
+                score += 24;
+            }
+            if word_start {
+                score += 32;
+            }
+            score -= gap.min(16);
+            gap = 0;
+            prev_matched = true;
+            query.next();
+        } else {
+            gap += 1;
+            prev_matched = false;
+        }
+        prev_char = Some(c);
+    }
+
+    if query.peek().is_some() {
+        return None;
+    }
+
+    Some(score)
+}
+
+/// Returns the indices of the `limit` best matching candidates, best first.
+pub fn rank<'a>(
+    query: &str,
+    candidates: impl Iterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<usize> {
+    let mut scored = candidates
+        .enumerate()
+        .filter_map(|(i, candidate)| score(query, candidate).map(|s| (s, i)))
+        .collect::<Vec<_>>();
+
+    // best score first, library order as a stable tie-breaker
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().take(limit).map(|(_, i)| i).collect()
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::paths;
+
+/// Searches kept in the history file.
+const HISTORY_LEN: usize = 100;
+
+fn read_lines(path: &Path) -> Result<Vec<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+/// Written to a sibling file first and renamed, like the session.
+fn write_lines(path: &Path, lines: &[String]) -> Result<()> {
+    let contents = lines
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, contents)
+        .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("failed to replace {}", path.display()))
+}
+
+/// Recent searches, oldest first.
+pub fn load() -> Result<Vec<String>> {
+    read_lines(&paths::history())
+}
+
+/// Adds `query` as the most recent search, moving it up if it was already there.
+pub fn record(query: &str) -> Result<()> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+
+    let mut history = load()?;
+    history.retain(|past| past != query);
+    history.push(query.to_string());
+    let excess = history.len().saturating_sub(HISTORY_LEN);
+    history.drain(..excess);
+
+    write_lines(&paths::history(), &history)
+}
+
+/// The most recent search.
+pub fn last() -> Result<Option<String>> {
+    Ok(load()?.pop())
+}
+
+/// Searches saved under a name, as `(name, query)` in the order they were saved.
+pub fn saved() -> Result<Vec<(String, String)>> {
+    Ok(read_lines(&paths::saved_searches())?
+        .into_iter()
+        .filter_map(|line| {
+            let (name, query) = line.split_once('\t')?;
+            Some((name.to_string(), query.to_string()))
+        })
+        .collect())
+}
+
+/// The query saved as `name`.
+pub fn find_saved(name: &str) -> Result<String> {
+    saved()?
+        .into_iter()
+        .find(|(saved, _)| saved == name)
+        .map(|(_, query)| query)
+        .with_context(|| format!("no saved search named \"{}\"", name))
+}
+
+/// Saves `query` as `name`, replacing any search already saved under it.
+pub fn save(name: &str, query: &str) -> Result<()> {
+    anyhow::ensure!(
+        !name.is_empty() && !name.contains(char::is_whitespace),
+        "a saved search name can't be empty or contain spaces"
+    );
+
+    let mut searches = saved()?;
+    searches.retain(|(saved, _)| saved != name);
+    searches.push((name.to_string(), query.trim().to_string()));
+    let lines = searches
+        .iter()
+        .map(|(name, query)| format!("{}\t{}", name, query))
+        .collect::<Vec<_>>();
+
+    write_lines(&paths::saved_searches(), &lines)
+}
+
+/// Deletes the search saved as `name`.
+pub fn delete(name: &str) -> Result<()> {
+    let mut searches = saved()?;
+    let before = searches.len();
+    searches.retain(|(saved, _)| saved != name);
+    anyhow::ensure!(
+        searches.len() < before,
+        "no saved search named \"{}\"",
+        name
+    );
+    let lines = searches
+        .iter()
+        .map(|(name, query)| format!("{}\t{}", name, query))
+        .collect::<Vec<_>>();
+
+    write_lines(&paths::saved_searches(), &lines)
+}
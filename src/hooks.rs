@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::NamedTempFile;
+
+use crate::app::Song;
+use crate::shutdown;
+
+/// Prefix of the environment variables a hook gets its context from.
+const ENV_PREFIX: &str = "VOL28_";
+
+/// A point where a user script can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Before anything is staged. A failing script stops the burn.
+    PreBurn,
+    /// Once the burn has finished, whether or not it worked.
+    PostBurn,
+    /// Once a scan has finished, whether or not it worked.
+    PostScan,
+}
+
+impl Hook {
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PreBurn => "pre_burn",
+            Hook::PostBurn => "post_burn",
+            Hook::PostScan => "post_scan",
+        }
+    }
+}
+
+/// Shell commands set in the config as `pre_burn`, `post_burn` and `post_scan`. Each is
+/// run with `sh -c` and told what happened through `VOL28_*` environment variables:
+///
+/// - `VOL28_PLAYLIST`: an M3U of the source files being burned
+/// - `VOL28_DEVICE`: the burner device
+/// - `VOL28_TRACKS`: how many tracks are on the disc
+/// - `VOL28_TITLE`: the disc title
+/// - `VOL28_MUSIC_DIR`: the directory that was scanned
+/// - `VOL28_RESULT`: `ok` or `failed`, after a burn or scan
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pub pre_burn: Option<String>,
+    pub post_burn: Option<String>,
+    pub post_scan: Option<String>,
+}
+
+impl Hooks {
+    /// Applies a `pre_burn`, `post_burn` or `post_scan` setting from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let command = Some(value.to_string()).filter(|command| !command.is_empty());
+        match key {
+            "pre_burn" => self.pre_burn = command,
+            "post_burn" => self.post_burn = command,
+            "post_scan" => self.post_scan = command,
+            _ => anyhow::bail!("unknown hook \"{}\"", key),
+        }
+
+        Ok(())
+    }
+
+    fn command(&self, hook: Hook) -> Option<&str> {
+        match hook {
+            Hook::PreBurn => self.pre_burn.as_deref(),
+            Hook::PostBurn => self.post_burn.as_deref(),
+            Hook::PostScan => self.post_scan.as_deref(),
+        }
+    }
+
+    /// Runs the script configured for `hook`, if any, with `vars` set as `VOL28_<name>`.
+    /// Returns the lines it printed, and fails when it exits unsuccessfully.
+    pub fn run(&self, hook: Hook, vars: &[(&str, &str)]) -> Result<Vec<String>> {
+        let Some(script) = self.command(hook) else {
+            return Ok(vec![]);
+        };
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        for (name, value) in vars {
+            command.env(format!("{}{}", ENV_PREFIX, name), value);
+        }
+        let output = shutdown::output(&mut command)
+            .with_context(|| format!("failed to run {} hook", hook.name()))?;
+
+        let lines = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .chain(String::from_utf8_lossy(&output.stderr).lines())
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if !output.status.success() {
+            let detail = lines
+                .last()
+                .map(|line| format!(": {}", line))
+                .unwrap_or_default();
+            anyhow::bail!("{} hook failed ({}){}", hook.name(), output.status, detail);
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Writes `playlist` as an M3U of its source files for a hook to read. The file is
+/// removed when the returned handle is dropped.
+pub fn write_playlist(playlist: &[Song], dir: &Path) -> Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .suffix(".m3u")
+        .tempfile_in(dir)
+        .context("failed to create playlist file for hooks")?;
+    writeln!(file, "#EXTM3U")?;
+    for song in playlist {
+        writeln!(
+            file,
+            "#EXTINF:{},{} - {}",
+            song.played_duration_secs(),
+            song.artist,
+            song.title
+        )?;
+        writeln!(file, "{}", song.path)?;
+    }
+    file.flush()?;
+
+    Ok(file)
+}
@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::app::Song;
+use crate::build_db;
+use crate::edit::{FRAMES_PER_SEC, TrackEdit};
+
+/// A disc image: a cue sheet and the audio files it indexes, such as the ones
+/// `playlist export-mix` writes.
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub cue_path: PathBuf,
+    pub title: String,
+    /// One entry per track, cut from its file with a trim at its cue position.
+    pub tracks: Vec<Song>,
+}
+
+/// A track of the cue sheet as read, before its file is looked at.
+struct CueTrack {
+    number: u32,
+    file: PathBuf,
+    title: Option<String>,
+    performer: Option<String>,
+    start_frames: Option<u64>,
+}
+
+/// Reads the image whose cue sheet is `path`, or sits next to it with a `.cue`
+/// extension when `path` is the audio.
+pub fn open(path: &Path) -> Result<Image> {
+    let cue_path = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+    {
+        path.to_path_buf()
+    } else {
+        path.with_extension("cue")
+    };
+    let contents = std::fs::read_to_string(&cue_path)
+        .with_context(|| format!("failed to read cue sheet {}", cue_path.display()))?;
+    let dir = cue_path.parent().unwrap_or(Path::new("."));
+
+    let (mut title, mut performer) = (None, None);
+    let mut file = None;
+    let mut cue_tracks: Vec<CueTrack> = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let context = || format!("line {} of {}", i + 1, cue_path.display());
+        let line = line.trim();
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            "FILE" => {
+                // the type comes last, after a possibly quoted name
+                let (name, _) = rest
+                    .rsplit_once(' ')
+                    .with_context(|| format!("expected FILE \"<name>\" <type> on {}", context()))?;
+                file = Some(dir.join(unquote(name)));
+            }
+            "TRACK" => {
+                let (number, kind) = rest.split_once(' ').unwrap_or((rest, ""));
+                anyhow::ensure!(
+                    kind == "AUDIO",
+                    "track {} is {}, only audio tracks can be burned ({})",
+                    number,
+                    kind,
+                    context()
+                );
+                cue_tracks.push(CueTrack {
+                    number: number
+                        .parse()
+                        .with_context(|| format!("invalid track number on {}", context()))?,
+                    file: file
+                        .clone()
+                        .with_context(|| format!("TRACK before any FILE on {}", context()))?,
+                    title: None,
+                    performer: None,
+                    start_frames: None,
+                });
+            }
+            "TITLE" | "PERFORMER" => {
+                let value = Some(unquote(rest).to_string());
+                match (cue_tracks.last_mut(), keyword) {
+                    (Some(track), "TITLE") => track.title = value,
+                    (Some(track), _) => track.performer = value,
+                    (None, "TITLE") => title = value,
+                    (None, _) => performer = value,
+                }
+            }
+            "INDEX" => {
+                let Some(track) = cue_tracks.last_mut() else {
+                    continue;
+                };
+                if let Some(("01", position)) = rest.split_once(' ') {
+                    track.start_frames = Some(
+                        frames(position)
+                            .with_context(|| format!("invalid INDEX 01 on {}", context()))?,
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+    anyhow::ensure!(
+        !cue_tracks.is_empty(),
+        "{} holds no tracks",
+        cue_path.display()
+    );
+
+    let mut tracks: Vec<Song> = vec![];
+    for (i, cue_track) in cue_tracks.iter().enumerate() {
+        let start_frames = cue_track
+            .start_frames
+            .with_context(|| format!("track {} has no INDEX 01", cue_track.number))?;
+        // a track runs to the next one in the same file, or to the end of the file
+        let end_frames = cue_tracks
+            .get(i + 1)
+            .filter(|next| next.file == cue_track.file)
+            .and_then(|next| next.start_frames);
+        let source = match tracks
+            .last()
+            .filter(|prev| Path::new(&prev.path) == cue_track.file)
+        {
+            Some(prev) => prev.clone(),
+            None => build_db::song_from_file(&cue_track.file)
+                .with_context(|| format!("failed to read {}", cue_track.file.display()))?,
+        };
+        tracks.push(Song {
+            title: cue_track
+                .title
+                .clone()
+                .unwrap_or_else(|| format!("Track {:02}", cue_track.number)),
+            artist: cue_track
+                .performer
+                .clone()
+                .or_else(|| performer.clone())
+                .unwrap_or_default(),
+            album: title.clone().unwrap_or_default(),
+            track: cue_track.number.into(),
+            edit: TrackEdit {
+                start_frames,
+                end_frames,
+                ..TrackEdit::default()
+            },
+            ..source
+        });
+    }
+
+    Ok(Image {
+        title: title.unwrap_or_else(|| {
+            cue_path
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        }),
+        cue_path,
+        tracks,
+    })
+}
+
+/// `value` without the double quotes around it, if it has them.
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Parses a cue position, `mm:ss:ff`, into frames.
+fn frames(position: &str) -> Option<u64> {
+    let mut fields = position
+        .trim()
+        .split(':')
+        .map(|field| field.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (fields.next()??, fields.next()??, fields.next()??);
+    (fields.next().is_none() && seconds < 60 && frames < FRAMES_PER_SEC)
+        .then_some((minutes * 60 + seconds) * FRAMES_PER_SEC + frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_keep_their_frames() {
+        assert_eq!(frames("01:02:03"), Some(62 * FRAMES_PER_SEC + 3));
+        assert_eq!(frames("00:60:00"), None);
+        assert_eq!(frames("00:00:75"), None);
+
+        let edit = TrackEdit {
+            start_frames: 62 * FRAMES_PER_SEC + 3,
+            end_frames: Some(90 * FRAMES_PER_SEC + 74),
+            ..TrackEdit::default()
+        };
+        assert_eq!(edit.input_args(), ["-ss", "62.040000", "-to", "90.986666"]);
+    }
+}
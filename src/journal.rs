@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+
+use crate::app::Song;
+use crate::paths;
+use crate::session::{self, Entry};
+use crate::shutdown;
+
+/// Mark the first line of a step, followed by the playlist entries it goes back to.
+const UNDO_PREFIX: &str = "undo ";
+const REDO_PREFIX: &str = "redo ";
+
+/// How many playlist changes can be undone unless `undo_depth` says otherwise.
+pub const DEFAULT_DEPTH: usize = 50;
+
+/// One playlist change as the journal keeps it: what it did, and the playlist's entries
+/// to go back to.
+#[derive(Debug, Clone)]
+pub struct Step {
+    pub label: String,
+    pub entries: Vec<Entry>,
+}
+
+/// Playlist changes that can be undone and undone changes that can be redone, newest
+/// last. Saved with the session so they can still be reviewed and reverted after a
+/// crash.
+#[derive(Debug, Default)]
+pub struct Journal {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    depth: usize,
+}
+
+impl Journal {
+    /// Reads the journal saved by a previous run, keeping the last `depth` changes.
+    pub fn load(depth: usize) -> Result<Journal> {
+        let path = paths::journal();
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read journal \"{}\"", path.display()));
+            }
+        };
+
+        let mut journal = Journal {
+            depth,
+            ..Journal::default()
+        };
+        let mut steps: Option<&mut Vec<Step>> = None;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let (list, label) = if let Some(label) = line.strip_prefix(UNDO_PREFIX) {
+                (&mut journal.undo, label)
+            } else if let Some(label) = line.strip_prefix(REDO_PREFIX) {
+                (&mut journal.redo, label)
+            } else {
+                let step = steps
+                    .as_mut()
+                    .and_then(|steps| steps.last_mut())
+                    .with_context(|| format!("entry \"{}\" in journal before any step", line))?;
+                let entry = session::parse_entry(line)
+                    .with_context(|| format!("invalid entry \"{}\" in journal", line))?;
+                step.entries.push(entry);
+                continue;
+            };
+            list.push(Step {
+                label: label.to_string(),
+                entries: vec![],
+            });
+            steps = Some(list);
+        }
+        journal.trim();
+
+        Ok(journal)
+    }
+
+    /// Writes the journal next to the session, the same way the session is written.
+    pub fn save(&self) -> Result<()> {
+        let contents = [(UNDO_PREFIX, &self.undo), (REDO_PREFIX, &self.redo)]
+            .into_iter()
+            .flat_map(|(prefix, steps)| steps.iter().map(move |step| (prefix, step)))
+            .map(|(prefix, step)| {
+                format!("{}{}\n", prefix, step.label)
+                    + &step
+                        .entries
+                        .iter()
+                        .map(|entry| session::format_entry(entry) + "\n")
+                        .collect::<String>()
+            })
+            .collect::<String>();
+
+        let path = paths::journal();
+        let tmp_path = path.with_extension("journal.tmp");
+        shutdown::critical(|| {
+            std::fs::write(&tmp_path, contents)
+                .with_context(|| format!("failed to write journal \"{}\"", tmp_path.display()))?;
+            std::fs::rename(&tmp_path, &path)
+                .with_context(|| format!("failed to replace journal \"{}\"", path.display()))
+        })
+    }
+
+    /// Records the change from `before` to `after`, unless there is none. A new change
+    /// can't be redone past, so it drops what could be.
+    pub fn record(&mut self, before: &[Song], after: &[Song]) {
+        let Some(label) = describe(before, after) else {
+            return;
+        };
+        self.undo.push(Step {
+            label,
+            entries: entries(before),
+        });
+        self.redo.clear();
+        self.trim();
+    }
+
+    /// Takes the last change off the journal so it can be redone from `current`.
+    pub fn undo(&mut self, current: &[Song]) -> Option<Step> {
+        let step = self.undo.pop()?;
+        self.redo.push(Step {
+            label: step.label.clone(),
+            entries: entries(current),
+        });
+
+        Some(step)
+    }
+
+    /// Takes the last undone change back so it can be undone again from `current`.
+    pub fn redo(&mut self, current: &[Song]) -> Option<Step> {
+        let step = self.redo.pop()?;
+        self.undo.push(Step {
+            label: step.label.clone(),
+            entries: entries(current),
+        });
+
+        Some(step)
+    }
+
+    /// Changes that can be undone, oldest first.
+    pub fn undo_steps(&self) -> &[Step] {
+        &self.undo
+    }
+
+    /// Undone changes that can be redone, the one to redo first last.
+    pub fn redo_steps(&self) -> &[Step] {
+        &self.redo
+    }
+
+    fn trim(&mut self) {
+        let excess = self.undo.len().saturating_sub(self.depth);
+        self.undo.drain(..excess);
+    }
+}
+
+fn entries(playlist: &[Song]) -> Vec<Entry> {
+    playlist.iter().map(Entry::of).collect()
+}
+
+/// Says what turned `before` into `after`, e.g. `add "Song"` or `reorder`, or nothing
+/// when they hold the same entries in the same order.
+pub fn describe(before: &[Song], after: &[Song]) -> Option<String> {
+    let quoted = |song: &Song| format!("\"{}\"", song.title);
+    let key = Song::key;
+    // entries on one side the other side doesn't have as many copies of
+    let unmatched = |of: &[Song], against: &[Song]| {
+        let mut against = against.iter().map(key).collect::<Vec<_>>();
+        of.iter()
+            .filter(|song| match against.iter().position(|k| *k == key(song)) {
+                Some(i) => {
+                    against.swap_remove(i);
+                    false
+                }
+                None => true,
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let (added, removed) = (unmatched(after, before), unmatched(before, after));
+
+    Some(match (added.as_slice(), removed.as_slice()) {
+        ([], []) => {
+            if before.iter().map(key).eq(after.iter().map(key)) {
+                let edited = before
+                    .iter()
+                    .zip(after)
+                    .find(|(before, after)| before.edit != after.edit)?;
+                format!("edit {}", quoted(edited.1))
+            } else {
+                String::from("reorder")
+            }
+        }
+        ([song], []) => format!("add {}", quoted(song)),
+        (added, []) => format!("add {} tracks", added.len()),
+        ([], removed) if after.is_empty() && removed.len() > 1 => {
+            format!("clear {} tracks", removed.len())
+        }
+        ([], [song]) => format!("remove {}", quoted(song)),
+        ([], removed) => format!("remove {} tracks", removed.len()),
+        ([new], [old]) => format!("replace {} with {}", quoted(old), quoted(new)),
+        (added, removed) => format!("add {}, remove {} tracks", added.len(), removed.len()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(id: i64) -> Song {
+        Song {
+            id,
+            path: format!("{}.flac", id),
+            title: format!("Song {}", id),
+            artist: String::from("Artist"),
+            album: String::from("Album"),
+            track: id,
+            year: 0,
+            duration_sec: 60,
+            edit: Default::default(),
+        }
+    }
+
+    fn songs(ids: &[i64]) -> Vec<Song> {
+        ids.iter().map(|&id| song(id)).collect()
+    }
+
+    fn describe_ids(before: &[i64], after: &[i64]) -> Option<String> {
+        describe(&songs(before), &songs(after))
+    }
+
+    #[test]
+    fn unchanged_playlist_is_not_a_change() {
+        assert_eq!(describe_ids(&[], &[]), None);
+        assert_eq!(describe_ids(&[1, 2], &[1, 2]), None);
+    }
+
+    #[test]
+    fn adds_and_removes() {
+        assert_eq!(describe_ids(&[1], &[1, 2]).unwrap(), "add \"Song 2\"");
+        assert_eq!(describe_ids(&[1], &[1, 2, 3]).unwrap(), "add 2 tracks");
+        assert_eq!(describe_ids(&[1, 2], &[1]).unwrap(), "remove \"Song 2\"");
+        assert_eq!(describe_ids(&[1, 2, 3], &[1]).unwrap(), "remove 2 tracks");
+        assert_eq!(describe_ids(&[1, 2, 3], &[]).unwrap(), "clear 3 tracks");
+        // a single track cleared away is just removed
+        assert_eq!(describe_ids(&[1], &[]).unwrap(), "remove \"Song 1\"");
+    }
+
+    #[test]
+    fn replacements() {
+        assert_eq!(
+            describe_ids(&[1, 2], &[1, 3]).unwrap(),
+            "replace \"Song 2\" with \"Song 3\""
+        );
+        assert_eq!(
+            describe_ids(&[1, 2, 3], &[1, 4]).unwrap(),
+            "add 1, remove 2 tracks"
+        );
+    }
+
+    #[test]
+    fn duplicates_are_counted() {
+        assert_eq!(describe_ids(&[1], &[1, 1]).unwrap(), "add \"Song 1\"");
+        assert_eq!(
+            describe_ids(&[1, 1, 2], &[1, 2]).unwrap(),
+            "remove \"Song 1\""
+        );
+    }
+
+    #[test]
+    fn reorders_and_edits() {
+        assert_eq!(describe_ids(&[1, 2, 3], &[3, 1, 2]).unwrap(), "reorder");
+
+        let before = songs(&[1, 2]);
+        let mut after = before.clone();
+        after[1].edit.fade_out_secs = 5;
+        assert_eq!(describe(&before, &after).unwrap(), "edit \"Song 2\"");
+    }
+
+    #[test]
+    fn outside_files_are_told_apart_by_path() {
+        let outside = |path: &str| Song {
+            id: 0,
+            path: path.to_string(),
+            title: path.to_string(),
+            ..song(0)
+        };
+        let before = vec![outside("a.mp3")];
+        let after = vec![outside("b.mp3")];
+        assert_eq!(
+            describe(&before, &after).unwrap(),
+            "replace \"a.mp3\" with \"b.mp3\""
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::app::{CD_MAX_DURATION_SECONDS, Song};
+use crate::cdtext::CdTextMode;
+
+/// CD audio frames (sectors) per second.
+const FRAMES_PER_SEC: u64 = 75;
+/// Silence every disc has before track 1: the 150-frame pregap that wodim writes.
+const FIRST_PREGAP_FRAMES: u64 = 2 * FRAMES_PER_SEC;
+
+/// Where one playlist entry lands on the disc.
+#[derive(Debug, Clone)]
+pub struct LayoutTrack {
+    pub number: usize,
+    /// Silence before the track starts.
+    pub pregap_frames: u64,
+    pub start_frames: u64,
+    pub length_frames: u64,
+    /// Performer and title as they will be written as CD-TEXT.
+    pub performer: String,
+    pub title: String,
+}
+
+impl LayoutTrack {
+    /// Where the track starts on the disc, in whole seconds.
+    pub fn start_secs(&self) -> u64 {
+        self.start_frames / FRAMES_PER_SEC
+    }
+
+    pub fn length_secs(&self) -> u64 {
+        self.length_frames / FRAMES_PER_SEC
+    }
+
+    /// Seconds left on an 80 minute disc once the track ends, or negative when it ends
+    /// past the end of the disc.
+    pub fn spare_secs(&self) -> i64 {
+        CD_MAX_DURATION_SECONDS as i64
+            - ((self.start_frames + self.length_frames) / FRAMES_PER_SEC) as i64
+    }
+}
+
+/// The disc a burn would write, as wodim lays it out with `-dao -pad`: tracks back to
+/// back after the first track's pregap, each padded to a whole frame. Untrimmed lengths
+/// are only known to the second, so positions may be a fraction of a second early.
+#[derive(Debug, Clone)]
+pub struct DiscLayout {
+    pub tracks: Vec<LayoutTrack>,
+    pub total_frames: u64,
+}
+
+impl DiscLayout {
+    pub fn new(playlist: &[Song], cdtext: CdTextMode) -> Self {
+        let mut position = 0;
+        let tracks = playlist
+            .iter()
+            .enumerate()
+            .map(|(i, song)| {
+                let pregap_frames = if i == 0 { FIRST_PREGAP_FRAMES } else { 0 };
+                let length_frames = song.edit.duration_frames(song.duration_sec);
+                let start_frames = position + pregap_frames;
+                position = start_frames + length_frames;
+
+                LayoutTrack {
+                    number: i + 1,
+                    pregap_frames,
+                    start_frames,
+                    length_frames,
+                    performer: cdtext.apply(&song.artist).into_owned(),
+                    title: cdtext.apply(&song.title).into_owned(),
+                }
+            })
+            .collect();
+
+        DiscLayout {
+            tracks,
+            total_frames: position,
+        }
+    }
+
+    /// Frames left on an 80 minute disc, or negative when the layout doesn't fit.
+    pub fn spare_frames(&self) -> i64 {
+        (CD_MAX_DURATION_SECONDS * FRAMES_PER_SEC) as i64 - self.total_frames as i64
+    }
+
+    /// The layout as a table followed by a summary, for the shell and the TUI alike.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:>2}  {:>8}  {:>8}  {:>5}  {}",
+            "#", "Start", "Length", "Gap", "CD-TEXT"
+        )];
+        for track in &self.tracks {
+            lines.push(format!(
+                "{:>2}  {:>8}  {:>8}  {:>5}  {} / {}",
+                track.number,
+                msf(track.start_frames),
+                msf(track.length_frames),
+                format!("{}s", track.pregap_frames / FRAMES_PER_SEC),
+                track.performer,
+                track.title
+            ));
+        }
+
+        let spare = self.spare_frames();
+        lines.push(String::new());
+        lines.push(format!(
+            "{} tracks, {} total, {} {}",
+            self.tracks.len(),
+            msf(self.total_frames),
+            msf(spare.unsigned_abs()),
+            if spare >= 0 { "to spare" } else { "over" }
+        ));
+
+        lines
+    }
+}
+
+/// `frames` as minutes, seconds and frames, the way disc positions are usually given.
+pub fn msf(frames: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / FRAMES_PER_SEC / 60,
+        frames / FRAMES_PER_SEC % 60,
+        frames % FRAMES_PER_SEC
+    )
+}
@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::app::{self, Song};
+use crate::normalize::{NormalizeMode, NormalizeProfile};
+use crate::shutdown;
+use crate::throttle::Throttle;
+
+/// Tracks this far from the playlist's median loudness stand out when played in a row.
+const MISMATCH_LU: f64 = 4.0;
+/// True peaks above this can clip once the disc is played back or converted.
+const PEAK_LIMIT_DBTP: f64 = -1.0;
+/// Spread in loudness beyond which album-wide normalization leaves quiet tracks quiet.
+const ALBUM_SPREAD_LU: f64 = 6.0;
+/// Columns the track titles of the report are cut to.
+const TITLE_WIDTH: usize = 32;
+
+/// EBU R128 measurements of a staged track.
+#[derive(Debug, Clone, Copy)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS.
+    pub integrated: f64,
+    /// Loudness range in LU.
+    pub range: f64,
+    /// True peak in dBTP.
+    pub true_peak: f64,
+}
+
+/// ffmpeg's ebur128 filter over `wav`, printing its summary to stderr.
+pub fn measure_command(wav: &Path) -> Command {
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg
+        .arg("-nostats")
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(wav)
+        .args(["-af", "ebur128=peak=true", "-f", "null", "-"]);
+
+    ffmpeg
+}
+
+/// Measures the integrated loudness, loudness range and true peak of `wav`.
+pub fn measure(wav: &Path, throttle: &Throttle) -> Result<Loudness> {
+    let output = shutdown::output(
+        throttle
+            .wrap(measure_command(wav))
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped()),
+    )
+    .context("failed to run ffmpeg. Is it installed?")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    anyhow::ensure!(
+        output.status.success(),
+        "ffmpeg failed to measure {} ({})",
+        wav.display(),
+        output.status
+    );
+
+    parse_summary(&stderr)
+        .with_context(|| format!("ffmpeg printed no loudness summary for {}", wav.display()))
+}
+
+/// Reads the `I:`, `LRA:` and `Peak:` values from the summary ebur128 prints last.
+fn parse_summary(stderr: &str) -> Option<Loudness> {
+    let summary = &stderr[stderr.rfind("Summary:")?..];
+    let value = |label: &str| {
+        summary
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(label))?
+            .split_whitespace()
+            .next()?
+            .parse::<f64>()
+            .ok()
+    };
+
+    Some(Loudness {
+        integrated: value("I:")?,
+        range: value("LRA:")?,
+        true_peak: value("Peak:")?,
+    })
+}
+
+/// A table of the `measured` loudness of every playlist entry, flagging tracks far from
+/// the rest or peaking too high, followed by what that means for the burn's profile and
+/// mode.
+pub fn report(
+    playlist: &[Song],
+    measured: &[Loudness],
+    (profile, mode): (NormalizeProfile, NormalizeMode),
+) -> Vec<String> {
+    let mut sorted = measured
+        .iter()
+        .map(|loudness| loudness.integrated)
+        .filter(|integrated| integrated.is_finite())
+        .collect::<Vec<_>>();
+    sorted.sort_by(f64::total_cmp);
+    let Some(median) = sorted.get(sorted.len() / 2).copied() else {
+        return vec![String::from("every track is silent")];
+    };
+    // SAFETY: not empty, or there would be no median
+    let spread = sorted.last().unwrap() - sorted.first().unwrap();
+
+    let mut lines = vec![format!(
+        "  #  {}    LUFS    LRA  dBTP",
+        app::fit_width("Title", TITLE_WIDTH)
+    )];
+    for (i, (song, loudness)) in playlist.iter().zip(measured).enumerate() {
+        let mut flags = vec![];
+        let offset = loudness.integrated - median;
+        if offset >= MISMATCH_LU {
+            flags.push(format!("{:.1} LU louder than the median", offset));
+        } else if offset <= -MISMATCH_LU {
+            flags.push(format!("{:.1} LU quieter than the median", -offset));
+        }
+        if loudness.true_peak > PEAK_LIMIT_DBTP {
+            flags.push(String::from("peaks near 0 dBTP"));
+        }
+        let row = format!(
+            "{:>3}  {}  {:>6.1}  {:>5.1}  {:>4.1}  {}",
+            i + 1,
+            app::fit_width(&song.title, TITLE_WIDTH),
+            loudness.integrated,
+            loudness.range,
+            loudness.true_peak,
+            flags.join(", ")
+        );
+        lines.push(row.trim_end().to_string());
+    }
+
+    lines.push(format!(
+        "median {:.1} LUFS, {:.1} LU between the quietest and the loudest track",
+        median, spread
+    ));
+    lines.push(match mode {
+        NormalizeMode::Album if spread > ALBUM_SPREAD_LU => format!(
+            "album gain keeps these differences; \"playlist normalize {} track\" evens them out",
+            profile.name()
+        ),
+        NormalizeMode::Album => String::from(
+            "the tracks are close, so album gain can keep their loudness as mastered",
+        ),
+        NormalizeMode::Track if spread > ALBUM_SPREAD_LU => String::from(
+            "the tracks differ a lot, which track gain evens out",
+        ),
+        NormalizeMode::Track => format!(
+            "the tracks are close: \"playlist normalize {} album\" would keep their loudness as mastered",
+            profile.name()
+        ),
+    });
+
+    lines
+}
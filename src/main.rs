@@ -1,35 +1,209 @@
+mod aliases;
+mod analysis;
 mod app;
+mod archive;
+mod art;
+mod audit;
+mod backend;
+mod base64;
 mod build_db;
+mod burns;
+mod cdtext;
+mod chapters;
+mod collate;
+mod command;
+mod config;
+mod confirm;
+mod discid;
+mod duration;
+mod edit;
+mod errors;
+mod events;
+mod external;
+mod fuzzy;
+mod history;
+mod hooks;
+mod image;
+mod journal;
+mod layout;
+mod loudness;
+mod mix;
+mod mixdown;
+mod naming;
+mod normalize;
+mod notify;
+mod order;
+mod pager;
+mod paths;
+mod presets;
+mod preview;
+mod quality;
+mod remote;
+mod session;
+mod setup;
+mod shutdown;
+mod snapshot;
+mod split;
+mod staging;
+mod style;
+mod tags;
+mod throttle;
+mod throughput;
+mod totals;
+mod userdata;
+mod verify;
 mod view;
+mod waveform;
+mod wodim;
 
 use anyhow::Context;
 use std::env;
-use std::path::PathBuf;
-
-pub const DB_PATH: &str = "library.db";
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 fn basic_mode() -> anyhow::Result<()> {
+    // output and profile flags can go anywhere on the command line
+    let mut no_color = false;
+    let mut no_emoji = false;
+    let mut accessible = false;
+    let mut remote = None;
+    let mut rest = vec![];
     let mut args = env::args();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-color" => no_color = true,
+            "--no-emoji" => no_emoji = true,
+            "--accessible" => accessible = true,
+            "--read-only" => app::set_read_only(),
+            "--profile" => {
+                let name = args
+                    .next()
+                    .context("expected a profile name after --profile")?;
+                paths::set_profile(&name)?;
+            }
+            "--remote" => {
+                remote = Some(args.next().context("expected host:port after --remote")?);
+            }
+            _ => rest.push(arg),
+        }
+    }
+    // the config can ask for it too; a broken config is reported once it is loaded
+    let config = config::Config::load().ok();
+    let accessible = accessible || config.as_ref().is_some_and(|config| config.accessible);
+    style::init(no_color, no_emoji, accessible);
+    notify::init(config.map(|config| config.finish_alert).unwrap_or_default());
+
+    shutdown::install()?;
+    paths::create_dirs()?;
+
+    let mut args = rest.into_iter().peekable();
+    let prog = args.next();
+    let mode = args.next();
+    // moving files is a change, and a remote session uses the other end's library
+    if !app::read_only() && remote.is_none() && mode.as_deref() != Some("paths") {
+        for moved in paths::migrate_legacy()? {
+            eprintln!("moved {} into place", moved.display());
+        }
+    }
+    if let Some(addr) = remote {
+        // everything but the terminal lives on the other end
+        return match mode.as_deref() {
+            Some("shell") => remote::connect(&addr),
+            _ => anyhow::bail!(
+                "only the shell can run against a remote library; the TUI needs the library on this machine"
+            ),
+        };
+    }
+    if matches!(mode.as_deref(), Some("tui" | "shell")) && setup::needed() && !app::read_only() {
+        first_run()?;
+    }
 
-    match (args.next(), args.next().as_deref()) {
+    match (prog, mode.as_deref()) {
         (Some(_), Some("tui")) => {
             crate::view::run_tui().context("error encountered when running TUI")?;
         }
         (Some(_), Some("shell")) => {
-            crate::app::run_shell().context("error encountered when running shell")?;
+            crate::app::run_shell(&mut command::StdConsole)
+                .context("error encountered when running shell")?;
         }
+        (Some(_), Some("serve")) => {
+            let addr = args
+                .next()
+                .context("expected an address to serve on, such as 127.0.0.1:7028")?;
+            let any_host = match args.next().as_deref() {
+                None => false,
+                Some("--insecure-any-host") => true,
+                Some(flag) => anyhow::bail!("unknown serve option \"{}\"", flag),
+            };
+            remote::serve(&addr, any_host)?;
+        }
+        (Some(_), Some("setup")) => first_run()?,
         (Some(_), Some("scan")) => {
-            let Some(music_dir) = args.next() else {
-                anyhow::bail!("expected path to a music directory to scan");
+            let music_dir = match args.next_if(|arg| !arg.starts_with("--")) {
+                Some(dir) => PathBuf::from(dir),
+                None => config::Config::load()?.music_dir.context(
+                    "expected path to a music directory to scan, or music_dir in the config",
+                )?,
             };
 
-            let music_dir = PathBuf::from(music_dir);
+            let mut options = build_db::ScanOptions::default();
+            let mut json = false;
+            for flag in args {
+                match flag.as_str() {
+                    "--bpm" => options.detect_bpm = true,
+                    "--key" => options.detect_key = true,
+                    "--loudness" => options.detect_loudness = true,
+                    "--json" => json = true,
+                    _ => anyhow::bail!("unknown scan option \"{}\"", flag),
+                }
+            }
+
+            scan(&music_dir, &options, json)?;
+        }
+        (Some(_), Some("burn")) => {
+            let mut json = false;
+            let mut ids = vec![];
+            for arg in args {
+                match arg.as_str() {
+                    "--json" => json = true,
+                    id => ids.push(
+                        id.parse()
+                            .with_context(|| format!("expected a track ID, got \"{}\"", id))?,
+                    ),
+                }
+            }
+
+            crate::app::run_burn(ids, json)?;
+        }
+        (Some(_), Some("burn-m3u")) => {
+            let mut json = false;
+            let mut playlist = None;
+            for arg in args {
+                match arg.as_str() {
+                    "--json" => json = true,
+                    path if playlist.is_none() => playlist = Some(PathBuf::from(path)),
+                    extra => anyhow::bail!("unexpected argument \"{}\"", extra),
+                }
+            }
+            let playlist = playlist.context("expected the path of an M3U playlist to burn")?;
 
-            build_db::build_db(&music_dir)?;
+            crate::app::run_burn_m3u(&playlist, json)?;
         }
+        (Some(_), Some("run")) => {
+            command::run_once(&args.collect::<Vec<_>>().join(" "))?;
+        }
+        (Some(_), Some("pick")) => {
+            let paths = match args.next().as_deref() {
+                None => false,
+                Some("--paths") => true,
+                Some(flag) => anyhow::bail!("unknown pick option \"{}\"", flag),
+            };
+            crate::view::run_pick(paths)?;
+        }
+        (Some(_), Some("paths")) => paths::print(),
         (Some(prog), _) => {
             eprintln!(
-                "Usage: {} <tui> | <shell> | <scan> <path_to_music_library>",
+                "Usage: {} [--no-color] [--no-emoji] [--accessible] [--profile <name>] [--read-only] [--remote <host:port>] <tui> | <shell> | <serve> <host:port> [--insecure-any-host] | <setup> | <paths> | <pick> [--paths] | <run> <shell command> | <scan> [<path_to_music_library>] [--bpm] [--key] [--loudness] [--json] | <burn> [--json] [<ID> ...] | <burn-m3u> <playlist.m3u> [--json]",
                 prog
             )
         }
@@ -39,6 +213,92 @@ fn basic_mode() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn main() -> anyhow::Result<()> {
-    basic_mode()
+/// Runs the setup wizard and scans the music directory it asked for.
+fn first_run() -> anyhow::Result<()> {
+    let music_dir = setup::run()?;
+    println!("Scanning {}...", music_dir.display());
+    scan(&music_dir, &build_db::ScanOptions::default(), false)
+}
+
+/// Scans `music_dir` into the library, printing progress as JSON lines or for a terminal.
+fn scan(music_dir: &Path, options: &build_db::ScanOptions, json: bool) -> anyhow::Result<()> {
+    if app::read_only() {
+        return Err(errors::DbError::ReadOnly("scanning").into());
+    }
+    let events = events::EventBus::default();
+    let progress = events.subscribe();
+    let printer = std::thread::spawn(move || print_scan_events(progress, json));
+
+    let config = config::Config::load()?;
+    let options = build_db::ScanOptions {
+        fts: config.fts.clone(),
+        io: config.scan_io,
+        ..*options
+    };
+    let result = build_db::build_db(music_dir, &options, &events);
+    if result.is_ok() {
+        events.publish(events::Event::Done(String::from("scan complete")));
+    }
+    drop(events);
+    let _ = printer.join();
+
+    let dir = music_dir.display().to_string();
+    let outcome = if result.is_ok() { "ok" } else { "failed" };
+    match config.hooks.run(
+        hooks::Hook::PostScan,
+        &[("MUSIC_DIR", &dir), ("RESULT", outcome)],
+    ) {
+        Ok(lines) => lines.iter().for_each(|line| eprintln!("{}", line)),
+        Err(err) => eprintln!("{} {:?}", style::error_label(), err),
+    }
+    if let (true, Err(err)) = (json, &result) {
+        println!("{}", events::error_json(err));
+    }
+
+    result
+}
+
+/// Prints scan output as it arrives, either as JSON lines or for a terminal.
+fn print_scan_events(events: std::sync::mpsc::Receiver<events::Event>, json: bool) {
+    let mut progress_shown = false;
+    for event in events {
+        if json {
+            println!("{}", event.to_json());
+            continue;
+        }
+
+        match event {
+            events::Event::ScanProgress { scanned } => {
+                eprint!("\rscanned {} files", scanned);
+                progress_shown = true;
+            }
+            events::Event::Line(events::LogLine { is_stderr, line }) => {
+                if std::mem::take(&mut progress_shown) {
+                    eprintln!();
+                }
+                if is_stderr {
+                    eprintln!("{}", line);
+                } else {
+                    println!("{}", line);
+                }
+            }
+            _ => (),
+        }
+    }
+    if progress_shown {
+        eprintln!();
+    }
+}
+
+fn main() -> ExitCode {
+    match basic_mode() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{} {:?}", style::error_label(), err);
+            if let Some(hint) = errors::hint(&err) {
+                eprintln!("{} {}", style::hint_label(), hint);
+            }
+            ExitCode::from(errors::exit_code(&err))
+        }
+    }
 }
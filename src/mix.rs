@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::collections::HashSet;
+
+use crate::app::{Song, queries};
+
+/// The tracks a mix may pick from under the `language:` and `instrumental:` filters in
+/// `words`, or `None` when there are none, along with the other words.
+fn kept_tracks(conn: &Connection, words: &str) -> Result<(Option<HashSet<i64>>, String)> {
+    let (filters, rest) = queries::search_filters(words)?;
+    if filters.is_empty() {
+        return Ok((None, rest));
+    }
+
+    Ok((Some(queries::filtered_ids(conn, &filters)?), rest))
+}
+
+/// Picks up to `count` random complete albums whose combined length fits in
+/// `budget_secs`, returned in album order so each plays through as released. With
+/// `filters` such as `instrumental:yes`, only albums every track of which passes them
+/// are picked.
+pub fn album_shuffle(
+    conn: &Connection,
+    count: usize,
+    filters: &str,
+    budget_secs: u64,
+) -> Result<Vec<Song>> {
+    let (kept, rest) = kept_tracks(conn, filters)?;
+    anyhow::ensure!(
+        rest.is_empty(),
+        "expected language:<code> or instrumental:yes|no, got \"{}\"",
+        rest
+    );
+    let mut albums = queries::list_album_durations(conn)?;
+    fastrand::shuffle(&mut albums);
+
+    let mut remaining = budget_secs;
+    let mut picked = 0;
+    let mut songs = vec![];
+    for (album, artist, duration_sec) in albums {
+        if picked == count {
+            break;
+        }
+        if duration_sec > remaining {
+            continue;
+        }
+        let tracks = queries::list_album(conn, &album)
+            .with_context(|| format!("failed to load album \"{}\"", album))?
+            .into_iter()
+            .filter(|s| s.artist == artist)
+            .collect::<Vec<_>>();
+        if let Some(kept) = &kept
+            && !tracks.iter().all(|s| kept.contains(&s.id))
+        {
+            continue;
+        }
+        remaining -= duration_sec;
+        picked += 1;
+        songs.extend(tracks);
+    }
+
+    Ok(songs)
+}
+
+/// One share of a balanced mix.
+#[derive(Debug)]
+enum Bucket {
+    Genre(String),
+    /// First year of the decade, e.g. 1970 for "1970s" or "70s".
+    Decade(u32),
+}
+
+/// Parses a spec like `rock:50 soul:30 1970s:20` into buckets and their percentages.
+fn parse_balance(spec: &str) -> Result<Vec<(Bucket, u64)>> {
+    let buckets = spec
+        .split_whitespace()
+        .map(|part| {
+            let (name, pct) = part
+                .rsplit_once(':')
+                .with_context(|| format!("expected <genre|decade>:<percent>, got \"{}\"", part))?;
+            let pct = pct
+                .trim_end_matches('%')
+                .parse::<u64>()
+                .with_context(|| format!("failed to parse percentage in \"{}\"", part))?;
+            let bucket = match name.strip_suffix('s') {
+                Some(digits)
+                    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) =>
+                {
+                    Bucket::Decade(parse_decade(digits).with_context(|| {
+                        format!("expected a decade like 1970s or 70s, got \"{}\"", name)
+                    })?)
+                }
+                _ => Bucket::Genre(name.to_string()),
+            };
+
+            Ok((bucket, pct))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if buckets.is_empty() {
+        anyhow::bail!("expected at least one <genre|decade>:<percent> share");
+    }
+    let total: u64 = buckets.iter().map(|(_, pct)| pct).sum();
+    if total > 100 {
+        anyhow::bail!("shares add up to {}%, expected at most 100%", total);
+    }
+
+    Ok(buckets)
+}
+
+/// The first year of the decade `digits` names: four digits as they are, and two as
+/// the 1900s, as in "80s".
+fn parse_decade(digits: &str) -> Option<u32> {
+    let year = digits.parse::<u32>().ok()?;
+    match digits.len() {
+        2 => Some(1900 + year - year % 10),
+        4 => Some(year - year % 10),
+        _ => None,
+    }
+}
+
+/// Builds a random selection where each genre/decade bucket takes its percentage of
+/// `budget_secs`, of at most `slots` tracks. A track is never picked twice, even if it
+/// falls in several buckets. Filters in `spec` such as `language:eng` leave out the
+/// tracks they don't keep.
+pub fn balanced(
+    conn: &Connection,
+    spec: &str,
+    budget_secs: u64,
+    slots: usize,
+) -> Result<Vec<Song>> {
+    let (kept, spec) = kept_tracks(conn, spec)?;
+    let mut picked: Vec<Song> = vec![];
+
+    for (bucket, pct) in parse_balance(&spec)? {
+        let mut candidates = match &bucket {
+            Bucket::Genre(genre) => queries::list_genre_tracks(conn, genre)?,
+            Bucket::Decade(decade) => queries::list_decade_tracks(conn, *decade)?,
+        };
+        if let Some(kept) = &kept {
+            candidates.retain(|song| kept.contains(&song.id));
+        }
+        fastrand::shuffle(&mut candidates);
+
+        let mut remaining = budget_secs * pct / 100;
+        for song in candidates {
+            if picked.len() == slots {
+                break;
+            }
+            if song.duration_sec <= remaining && !picked.iter().any(|p| p.id == song.id) {
+                remaining -= song.duration_sec;
+                picked.push(song);
+            }
+        }
+    }
+
+    Ok(picked)
+}
@@ -0,0 +1,228 @@
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::app::{BurnOptions, Song};
+
+/// How one track of a mix leads into the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Join {
+    /// Seconds of silence between them, none for a seamless join.
+    Gap(u32),
+    /// Seconds the two overlap.
+    Crossfade(u32),
+}
+
+/// How each entry of `playlist` leads into the next: overlapped by the crossfade when
+/// there is one, otherwise separated by the gap. Entries inside a [`runs`] run play
+/// straight on unless `options.gap_runs` is set.
+pub fn joins(playlist: &[Song], options: &BurnOptions) -> Vec<Join> {
+    playlist
+        .windows(2)
+        .map(|pair| {
+            if !options.gap_runs && continues(&pair[0], &pair[1]) {
+                Join::Gap(0)
+            } else if options.crossfade_secs > 0 {
+                Join::Crossfade(options.crossfade_secs)
+            } else {
+                Join::Gap(options.gap_secs)
+            }
+        })
+        .collect()
+}
+
+/// Whether `next` carries straight on from `song`, as tracks of a live album do: it is
+/// the following track of the same album, with nothing trimmed or faded where they meet.
+pub fn continues(song: &Song, next: &Song) -> bool {
+    !song.album.is_empty()
+        && song.track > 0
+        && next.track == song.track + 1
+        && song.album.to_lowercase() == next.album.to_lowercase()
+        && song.artist.to_lowercase() == next.artist.to_lowercase()
+        && song.edit.end_frames.is_none()
+        && song.edit.fade_out_secs == 0
+        && next.edit.start_frames == 0
+        && next.edit.fade_in_secs == 0
+}
+
+/// Positions of the runs of two or more entries that each carry on from the one before.
+pub fn runs(playlist: &[Song]) -> Vec<Range<usize>> {
+    let mut runs = vec![];
+    let mut start = 0;
+    for i in 1..=playlist.len() {
+        if i == playlist.len() || !continues(&playlist[i - 1], &playlist[i]) {
+            if i - start > 1 {
+                runs.push(start..i);
+            }
+            start = i;
+        }
+    }
+
+    runs
+}
+
+/// Builds the ffmpeg invocation that joins the staged WAVs into `output`, each leading
+/// into the next as `joins` says. The output codec follows from the file extension
+/// (e.g. `.flac`, `.mp3`).
+pub fn ffmpeg_command(wavs: &[PathBuf], joins: &[Join], output: &Path) -> Command {
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y").arg("-v").arg("error");
+    for wav in wavs {
+        command.arg("-i").arg(wav);
+    }
+
+    // each track is joined onto the mix of the ones before it
+    let mut filter = String::new();
+    let mut prev = String::from("[0]");
+    for (i, join) in (1..wavs.len()).zip(joins) {
+        let _ = match join {
+            Join::Crossfade(secs) => {
+                write!(filter, "{prev}[{i}]acrossfade=d={secs}[x{i}];")
+            }
+            Join::Gap(0) => write!(filter, "{prev}[{i}]concat=n=2:v=0:a=1[x{i}];"),
+            Join::Gap(secs) => write!(
+                filter,
+                "{prev}apad=pad_dur={secs}[p{i}];[p{i}][{i}]concat=n=2:v=0:a=1[x{i}];"
+            ),
+        };
+        prev = format!("[x{i}]");
+    }
+    if filter.is_empty() {
+        command.arg("-map").arg("0");
+    } else {
+        filter.pop();
+        command
+            .arg("-filter_complex")
+            .arg(filter)
+            .arg("-map")
+            .arg(prev);
+    }
+
+    command.arg(output);
+    command
+}
+
+/// Samples per second of the staged tracks.
+const SAMPLE_RATE: u64 = 44_100;
+/// Samples in a cue sheet frame, of which there are 75 a second.
+const SAMPLES_PER_FRAME: u64 = SAMPLE_RATE / 75;
+
+/// Writes a cue sheet titled `title` indexing each track of the mix, using the same
+/// `joins` as [`ffmpeg_command`] and the `samples` each staged track is long, so trims
+/// are accounted for.
+pub fn cue_sheet(
+    playlist: &[Song],
+    samples: &[u64],
+    title: &str,
+    joins: &[Join],
+    output: &Path,
+) -> String {
+    let file_name = output
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    // players take WAVE for any lossless file and MP3 for MP3s
+    let file_type = match output.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("mp3") => "MP3",
+        _ => "WAVE",
+    };
+
+    let mut cue = String::new();
+    let _ = writeln!(cue, "PERFORMER \"Various Artists\"");
+    let _ = writeln!(cue, "TITLE \"{}\"", cue_escape(title));
+    let _ = writeln!(cue, "FILE \"{}\" {}", cue_escape(&file_name), file_type);
+
+    let mut start = 0u64;
+    for (i, (song, length)) in playlist.iter().zip(samples).enumerate() {
+        let _ = writeln!(cue, "  TRACK {:02} AUDIO", i + 1);
+        let _ = writeln!(cue, "    TITLE \"{}\"", cue_escape(&song.title));
+        let _ = writeln!(cue, "    PERFORMER \"{}\"", cue_escape(&song.artist));
+        // cue times are mm:ss:ff with 75 frames per second
+        let frames = start / SAMPLES_PER_FRAME;
+        let _ = writeln!(
+            cue,
+            "    INDEX 01 {:02}:{:02}:{:02}",
+            frames / 75 / 60,
+            frames / 75 % 60,
+            frames % 75
+        );
+
+        start += length;
+        match joins.get(i) {
+            Some(Join::Crossfade(secs)) => start = start.saturating_sub(*secs as u64 * SAMPLE_RATE),
+            Some(Join::Gap(secs)) => start += *secs as u64 * SAMPLE_RATE,
+            None => (),
+        }
+    }
+
+    cue
+}
+
+fn cue_escape(text: &str) -> String {
+    text.replace('"', "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(album: &str, track: i64) -> Song {
+        Song {
+            id: track,
+            path: format!("{}.flac", track),
+            title: format!("Track {}", track),
+            artist: String::from("Artist"),
+            album: String::from(album),
+            track,
+            year: 0,
+            duration_sec: 60,
+            edit: Default::default(),
+        }
+    }
+
+    fn indexes(cue: &str) -> Vec<&str> {
+        cue.lines()
+            .filter_map(|line| line.trim().strip_prefix("INDEX 01 "))
+            .collect()
+    }
+
+    #[test]
+    fn runs_are_indexed_without_the_gap() {
+        let playlist = [song("Live", 1), song("Live", 2), song("Other", 7)];
+        let options = BurnOptions {
+            gap_secs: 2,
+            ..Default::default()
+        };
+        let joins = joins(&playlist, &options);
+        assert_eq!(joins, [Join::Gap(0), Join::Gap(2)]);
+
+        // a minute and half a frame, then a minute and ten frames
+        let samples = [
+            60 * SAMPLE_RATE + SAMPLES_PER_FRAME / 2,
+            60 * SAMPLE_RATE + 10 * 588,
+            0,
+        ];
+        let cue = cue_sheet(&playlist, &samples, "Mix", &joins, Path::new("mix.mp3"));
+        assert_eq!(indexes(&cue), ["00:00:00", "01:00:00", "02:02:10"]);
+        assert!(cue.contains("FILE \"mix.mp3\" MP3"), "{}", cue);
+    }
+
+    #[test]
+    fn crossfades_pull_the_next_track_in() {
+        let playlist = [song("", 1), song("", 2)];
+        let options = BurnOptions {
+            crossfade_secs: 5,
+            ..Default::default()
+        };
+        let joins = joins(&playlist, &options);
+        let cue = cue_sheet(
+            &playlist,
+            &[60 * SAMPLE_RATE, 60 * SAMPLE_RATE],
+            "Mix",
+            &joins,
+            Path::new("mix.flac"),
+        );
+        assert_eq!(indexes(&cue), ["00:00:00", "00:55:00"]);
+        assert!(cue.contains("FILE \"mix.flac\" WAVE"), "{}", cue);
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+
+use crate::app::Song;
+
+/// Default layout for exported files; zero-padded so dumb players sort correctly.
+pub const DEFAULT_TEMPLATE: &str = "{disc}.{track:02} - {artist} - {title}.{ext}";
+
+/// The values a filename template can refer to.
+pub struct NameContext<'a> {
+    pub disc: u32,
+    /// Position in the playlist (1-based), not the album track number.
+    pub track: usize,
+    pub song: &'a Song,
+    /// Extension of the file being written, which differs from the source when transcoding.
+    pub ext: &'a str,
+}
+
+/// Renders a template such as `{disc}.{track:02} - {artist} - {title}.{ext}`.
+///
+/// Placeholders are `disc`, `track`, `artist`, `title`, `album`, `year` and `ext`;
+/// numeric ones accept a `:0N` zero-padding width. Substituted values are sanitized so
+/// they can't introduce path separators or characters FAT-formatted players reject.
+pub fn render(template: &str, ctx: &NameContext) -> Result<String> {
+    let mut out = String::with_capacity(template.len() * 2);
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .map(|i| start + i)
+            .with_context(|| format!("unclosed '{{' in filename template \"{}\"", template))?;
+
+        let placeholder = &rest[start + 1..end];
+        let (name, pad) = match placeholder.split_once(':') {
+            Some((name, spec)) => {
+                let width = spec
+                    .strip_prefix('0')
+                    .unwrap_or(spec)
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid padding \"{}\" in template", spec))?;
+                (name, width)
+            }
+            None => (placeholder, 0),
+        };
+
+        let value = match name {
+            "disc" => format!("{:0pad$}", ctx.disc),
+            "track" => format!("{:0pad$}", ctx.track),
+            "year" => format!("{:0pad$}", ctx.song.year),
+            "artist" => sanitize(&ctx.song.artist),
+            "title" => sanitize(&ctx.song.title),
+            "album" => sanitize(&ctx.song.album),
+            "ext" => sanitize(ctx.ext),
+            _ => anyhow::bail!("unknown placeholder \"{{{}}}\" in filename template", name),
+        };
+        out.push_str(&value);
+
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Fails unless `template` has a `{track}` placeholder, without which tracks could get
+/// the same name and overwrite each other, and would lose their order.
+pub fn ensure_numbered(template: &str) -> Result<()> {
+    anyhow::ensure!(
+        template.contains("{track}") || template.contains("{track:"),
+        "filename template \"{}\" has no {{track}}: tracks would overwrite each other",
+        template
+    );
+
+    Ok(())
+}
+
+/// Replaces characters that are unsafe in file names on common filesystems.
+pub fn sanitize(text: &str) -> String {
+    let cleaned: String = text
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // leading dots would hide the file, trailing dots/spaces are stripped by FAT
+    cleaned
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
@@ -0,0 +1,271 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::errors::TranscodeError;
+use crate::loudness;
+use crate::throttle::Throttle;
+
+/// Stands in for the disc gain in printed commands, as it is only known once every
+/// track has been measured.
+const GAIN_PLACEHOLDER: &str = "<disc gain>";
+
+/// Loudness target applied to the staged WAVs before they are burned or exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalizeProfile {
+    /// `normalize` peaking at -9 dBFS, album-wide unless told otherwise; keeps the
+    /// relative loudness of tracks like a mastered CD.
+    #[default]
+    Cd,
+    /// EBU R128 loudness at -14 LUFS, per track unless told otherwise, matching
+    /// streaming services.
+    Streaming,
+    /// -16 LUFS with a narrow loudness range, per track unless told otherwise, for
+    /// spoken word.
+    Voice,
+}
+
+/// Whether a profile's target is met by the disc as a whole or by every track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizeMode {
+    /// One gain for every track, keeping the loudness differences within the album.
+    Album,
+    /// Each track brought to the target on its own, so they play equally loud.
+    Track,
+}
+
+/// What the loudnorm filter aims for, in LUFS, dBTP and LU.
+#[derive(Debug, Clone, Copy)]
+struct Target {
+    integrated: f64,
+    true_peak: f64,
+    range: f64,
+}
+
+impl NormalizeProfile {
+    /// The mode the profile is used in unless another one is picked.
+    pub fn default_mode(&self) -> NormalizeMode {
+        match self {
+            NormalizeProfile::Cd => NormalizeMode::Album,
+            NormalizeProfile::Streaming | NormalizeProfile::Voice => NormalizeMode::Track,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizeProfile::Cd => "cd",
+            NormalizeProfile::Streaming => "streaming",
+            NormalizeProfile::Voice => "voice",
+        }
+    }
+
+    /// Normalizes `wavs` in place in `mode`, running the tools under `throttle`.
+    pub fn run(
+        &self,
+        mode: NormalizeMode,
+        dir: &Path,
+        wavs: &[PathBuf],
+        throttle: &Throttle,
+    ) -> Result<()> {
+        match (self.target(), mode) {
+            (None, mode) => TranscodeError::run(
+                "normalize",
+                dir,
+                &mut throttle.wrap(normalize_command(dir, wavs, mode)),
+            )?,
+            (Some(target), NormalizeMode::Track) => {
+                for wav in wavs {
+                    replace_with(wav, throttle, |output| {
+                        loudnorm_command(wav, output, target)
+                    })?;
+                }
+            }
+            (Some(target), NormalizeMode::Album) => {
+                let gain = disc_gain(wavs, target, throttle)?;
+                for wav in wavs {
+                    replace_with(wav, throttle, |output| volume_command(wav, output, &gain))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The commands [`Self::run`] executes, without the renames that put ffmpeg's
+    /// output back in place. In album mode the loudness profiles measure every track
+    /// first, so the gain they then apply is shown as a placeholder.
+    pub fn commands(
+        &self,
+        mode: NormalizeMode,
+        dir: &Path,
+        wavs: &[PathBuf],
+        throttle: &Throttle,
+    ) -> Vec<Command> {
+        let normalized = |wav: &PathBuf| wav.with_extension("norm.wav");
+        match (self.target(), mode) {
+            (None, mode) => vec![throttle.wrap(normalize_command(dir, wavs, mode))],
+            (Some(target), NormalizeMode::Track) => wavs
+                .iter()
+                .map(|wav| throttle.wrap(loudnorm_command(wav, &normalized(wav), target)))
+                .collect(),
+            (Some(_), NormalizeMode::Album) => wavs
+                .iter()
+                .map(|wav| throttle.wrap(loudness::measure_command(wav)))
+                .chain(wavs.iter().map(|wav| {
+                    throttle.wrap(volume_command(wav, &normalized(wav), GAIN_PLACEHOLDER))
+                }))
+                .collect(),
+        }
+    }
+
+    /// What ffmpeg's loudnorm filter aims for, for the loudness profiles.
+    fn target(&self) -> Option<Target> {
+        match self {
+            NormalizeProfile::Cd => None,
+            NormalizeProfile::Streaming => Some(Target {
+                integrated: -14.0,
+                true_peak: -1.0,
+                range: 11.0,
+            }),
+            NormalizeProfile::Voice => Some(Target {
+                integrated: -16.0,
+                true_peak: -1.5,
+                range: 7.0,
+            }),
+        }
+    }
+}
+
+impl NormalizeMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizeMode::Album => "album",
+            NormalizeMode::Track => "track",
+        }
+    }
+}
+
+/// The gain, e.g. `-3.25dB`, that brings the disc as a whole to `target`: the
+/// loudness of all `wavs` played in a row, each weighted by its length, but never
+/// so much that the loudest peak goes over the target's.
+fn disc_gain(wavs: &[PathBuf], target: Target, throttle: &Throttle) -> Result<String> {
+    let mut energy = 0.0;
+    let mut weight = 0.0;
+    let mut peak = f64::NEG_INFINITY;
+    for wav in wavs {
+        let measured = loudness::measure(wav, throttle)?;
+        // staged WAVs share one format, so their size is their length
+        let len = std::fs::metadata(wav)
+            .with_context(|| format!("failed to read {}", wav.display()))?
+            .len() as f64;
+        if measured.integrated.is_finite() {
+            energy += len * 10f64.powf(measured.integrated / 10.0);
+            weight += len;
+        }
+        peak = peak.max(measured.true_peak);
+    }
+    if weight == 0.0 {
+        return Ok(String::from("0dB"));
+    }
+
+    let integrated = 10.0 * (energy / weight).log10();
+    let gain = (target.integrated - integrated).min(target.true_peak - peak);
+
+    Ok(format!("{:.2}dB", gain))
+}
+
+/// Runs the ffmpeg command `make` builds to write a normalized copy of `wav` next to
+/// it, then puts the copy in its place.
+fn replace_with(
+    wav: &Path,
+    throttle: &Throttle,
+    make: impl FnOnce(&Path) -> Command,
+) -> Result<()> {
+    let normalized = wav.with_extension("norm.wav");
+    TranscodeError::run("ffmpeg", wav, &mut throttle.wrap(make(&normalized)))?;
+
+    std::fs::rename(&normalized, wav).with_context(|| {
+        format!(
+            "failed to replace {} with its normalized copy",
+            wav.display()
+        )
+    })
+}
+
+/// Gain over `wavs` with the `normalize` tool: one for the whole batch in album mode,
+/// one per file otherwise.
+fn normalize_command(dir: &Path, wavs: &[PathBuf], mode: NormalizeMode) -> Command {
+    let mut normalize = Command::new("normalize");
+    normalize.current_dir(dir);
+    if mode == NormalizeMode::Album {
+        normalize.arg("-b");
+    }
+    normalize.arg("--peak").arg("-a").arg("-9dBFS").args(wavs);
+
+    normalize
+}
+
+/// ffmpeg's loudnorm filter over `wav`, keeping the CD audio format.
+fn loudnorm_command(wav: &Path, output: &Path, target: Target) -> Command {
+    filter_command(
+        wav,
+        output,
+        &format!(
+            "loudnorm=I={}:TP={}:LRA={}",
+            target.integrated, target.true_peak, target.range
+        ),
+    )
+}
+
+/// ffmpeg's volume filter over `wav`, changing its level by `gain`.
+fn volume_command(wav: &Path, output: &Path, gain: &str) -> Command {
+    filter_command(wav, output, &format!("volume={}", gain))
+}
+
+/// `filter` over `wav` with ffmpeg, keeping the CD audio format.
+fn filter_command(wav: &Path, output: &Path, filter: &str) -> Command {
+    let mut ffmpeg = Command::new("ffmpeg");
+    ffmpeg
+        .arg("-v")
+        .arg("error")
+        .arg("-y")
+        .arg("-i")
+        .arg(wav)
+        .arg("-af")
+        .arg(filter)
+        .args(["-ar", "44100", "-ac", "2", "-sample_fmt", "s16"])
+        .arg(output);
+
+    ffmpeg
+}
+
+impl std::str::FromStr for NormalizeProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cd" => Ok(NormalizeProfile::Cd),
+            "streaming" => Ok(NormalizeProfile::Streaming),
+            "voice" => Ok(NormalizeProfile::Voice),
+            _ => anyhow::bail!(
+                "unknown normalization profile \"{}\": expected one of cd / streaming / voice",
+                s
+            ),
+        }
+    }
+}
+
+impl std::str::FromStr for NormalizeMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "album" => Ok(NormalizeMode::Album),
+            "track" => Ok(NormalizeMode::Track),
+            _ => anyhow::bail!(
+                "unknown normalization mode \"{}\": expected album / track",
+                s
+            ),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
+
+use crate::events::{BurnStage, Event};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ALERT: AtomicU8 = AtomicU8::new(Alert::Off as u8);
+/// Whether the title the terminal had before ours was saved, to be put back.
+static SAVED: AtomicBool = AtomicBool::new(false);
+
+/// How long the screen stays inverted for [`Alert::Flash`].
+const FLASH: Duration = Duration::from_millis(150);
+
+/// What happens when a background task ends, set as `finish_alert` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alert {
+    #[default]
+    Off,
+    /// Rings the terminal bell, which most terminals also mark the tab or window for.
+    Bell,
+    /// Briefly inverts the screen.
+    Flash,
+}
+
+impl std::str::FromStr for Alert {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "off" => Ok(Alert::Off),
+            "bell" => Ok(Alert::Bell),
+            "flash" => Ok(Alert::Flash),
+            _ => anyhow::bail!(
+                "unknown finish alert \"{}\": expected off / bell / flash",
+                s
+            ),
+        }
+    }
+}
+
+/// Decides whether tasks show in the terminal's title and how their end is announced.
+/// Nothing is sent unless stdout is a terminal and `TERM` isn't `dumb`.
+pub fn init(alert: Alert) {
+    let terminal =
+        io::stdout().is_terminal() && std::env::var("TERM").is_ok_and(|term| term != "dumb");
+    ENABLED.store(terminal, Ordering::Relaxed);
+    ALERT.store(alert as u8, Ordering::Relaxed);
+}
+
+/// Sets the terminal's window title, saving the one it had the first time.
+fn set_title(title: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let title = title
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let mut stdout = io::stdout();
+    if !SAVED.swap(true, Ordering::Relaxed) {
+        let _ = write!(stdout, "\x1b[22;0t");
+    }
+    let _ = write!(stdout, "\x1b]0;{}\x07", title);
+    let _ = stdout.flush();
+}
+
+/// Puts back the title the terminal had before a task changed it. Terminals that
+/// don't keep titles leave the last one up.
+pub fn restore_title() {
+    if SAVED.swap(false, Ordering::Relaxed) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[23;0t");
+        let _ = stdout.flush();
+    }
+}
+
+fn alert() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut stdout = io::stdout();
+    match ALERT.load(Ordering::Relaxed) {
+        a if a == Alert::Bell as u8 => {
+            let _ = write!(stdout, "\x07");
+        }
+        a if a == Alert::Flash as u8 => {
+            let _ = write!(stdout, "\x1b[?5h");
+            let _ = stdout.flush();
+            std::thread::sleep(FLASH);
+            let _ = write!(stdout, "\x1b[?5l");
+        }
+        _ => {}
+    }
+    let _ = stdout.flush();
+}
+
+/// Keeps the terminal's title on what a background task is doing, e.g.
+/// `Burning 43% — Mix Vol 3`, so it can be followed from another tab.
+#[derive(Debug)]
+pub struct TaskTitle {
+    /// What the task works on, such as the disc's title.
+    name: String,
+    stage: Option<BurnStage>,
+    percent: Option<u8>,
+    shown: String,
+}
+
+impl TaskTitle {
+    pub fn new(name: &str) -> Self {
+        TaskTitle {
+            name: name.to_string(),
+            stage: None,
+            percent: None,
+            shown: String::new(),
+        }
+    }
+
+    /// Updates the title for `event` of the task, and gives the alert when it is the
+    /// last.
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::BurnStage(stage) => {
+                self.stage = Some(*stage);
+                self.percent = None;
+            }
+            Event::TranscodeProgress { done, total, .. } => {
+                self.stage = Some(BurnStage::Transcoding);
+                self.percent = Some((done * 100 / (*total).max(1)) as u8);
+            }
+            Event::BurnProgress { percent } => self.percent = Some(*percent),
+            Event::Done(_) | Event::Error(_) => {
+                let outcome = if matches!(event, Event::Done(_)) {
+                    "Done"
+                } else {
+                    "Failed"
+                };
+                set_title(&self.titled(outcome));
+                alert();
+                return;
+            }
+            _ => return,
+        }
+
+        let doing = match (self.stage, self.percent) {
+            (Some(stage), Some(percent)) => format!("{} {}%", stage_label(stage), percent),
+            (Some(stage), None) => stage_label(stage).to_string(),
+            (None, _) => return,
+        };
+        let title = self.titled(&doing);
+        if title != self.shown {
+            set_title(&title);
+            self.shown = title;
+        }
+    }
+
+    fn titled(&self, doing: &str) -> String {
+        if self.name.is_empty() {
+            doing.to_string()
+        } else {
+            format!("{} — {}", doing, self.name)
+        }
+    }
+}
+
+fn stage_label(stage: BurnStage) -> &'static str {
+    match stage {
+        BurnStage::Transcoding => "Transcoding",
+        BurnStage::Normalizing => "Normalizing",
+        BurnStage::Burning => "Burning",
+        BurnStage::Verifying => "Verifying",
+        BurnStage::MixingDown => "Mixing down",
+    }
+}
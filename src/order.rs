@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use crate::app::Song;
+
+/// Cost of two tracks by the same artist playing back to back.
+const SAME_ARTIST_COST: f64 = 10.0;
+/// Cost per dB of level difference between consecutive tracks.
+const LOUDNESS_COST_PER_DB: f64 = 0.5;
+/// Cost per BPM the tempo drops from one track to the next.
+const TEMPO_DROP_COST_PER_BPM: f64 = 0.2;
+/// Cost per BPM the tempo rises, kept small so the order ramps up gently.
+const TEMPO_RISE_COST_PER_BPM: f64 = 0.05;
+
+/// Albums may have this many tracks on a disc before they're flagged, by default.
+pub const DEFAULT_MAX_ALBUM_TRACKS: usize = 3;
+
+/// The mixtape rules playlist entries are checked against, as set in the config file
+/// with `warn_same_artist` and `max_album_tracks`.
+#[derive(Debug, Clone)]
+pub struct Etiquette {
+    /// Flag an artist following themselves.
+    pub same_artist: bool,
+    /// Flag albums with more tracks than this on the disc; 0 turns the check off.
+    pub max_album_tracks: usize,
+}
+
+impl Default for Etiquette {
+    fn default() -> Self {
+        Etiquette {
+            same_artist: true,
+            max_album_tracks: DEFAULT_MAX_ALBUM_TRACKS,
+        }
+    }
+}
+
+impl Etiquette {
+    /// Applies a `warn_same_artist` or `max_album_tracks` setting from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "warn_same_artist" => {
+                self.same_artist = value.parse().context("expected true or false")?;
+            }
+            "max_album_tracks" => {
+                self.max_album_tracks = value
+                    .parse()
+                    .context("expected a track count, or 0 for no limit")?;
+            }
+            _ => anyhow::bail!("unknown etiquette setting \"{}\"", key),
+        }
+
+        Ok(())
+    }
+
+    /// What's wrong with each playlist entry, if anything: following a track by the same
+    /// artist, or being one track too many from its album. Albums are told apart by
+    /// artist too, so two albums called "Greatest Hits" don't count together.
+    pub fn check(&self, playlist: &[Song]) -> Vec<Option<String>> {
+        let mut album_counts: HashMap<(String, String), usize> = HashMap::new();
+        playlist
+            .iter()
+            .enumerate()
+            .map(|(i, song)| {
+                let mut reasons = vec![];
+                if self.same_artist
+                    && !song.artist.is_empty()
+                    && i > 0
+                    && playlist[i - 1].artist.to_lowercase() == song.artist.to_lowercase()
+                {
+                    reasons.push(String::from("same artist as previous"));
+                }
+                if !song.album.is_empty() {
+                    let count = album_counts
+                        .entry((song.artist.to_lowercase(), song.album.to_lowercase()))
+                        .or_default();
+                    *count += 1;
+                    if self.max_album_tracks > 0 && *count > self.max_album_tracks {
+                        reasons.push(format!(
+                            "{} from \"{}\" (max {})",
+                            count, song.album, self.max_album_tracks
+                        ));
+                    }
+                }
+
+                (!reasons.is_empty()).then(|| reasons.join("; "))
+            })
+            .collect()
+    }
+}
+
+/// A proposed running order for a playlist.
+#[derive(Debug, Clone)]
+pub struct Plan {
+    /// Current playlist position of each entry, in the proposed order.
+    pub order: Vec<usize>,
+    /// Track ids of the playlist the plan was made for, in its order then.
+    ids: Vec<i64>,
+    pub cost_before: f64,
+    pub cost_after: f64,
+    /// Same-artist neighbours before and after.
+    pub adjacent_before: usize,
+    pub adjacent_after: usize,
+    /// Entries with neither a tempo nor a level to go by.
+    pub unanalyzed: usize,
+}
+
+impl Plan {
+    /// Whether the plan would change anything.
+    pub fn is_unchanged(&self) -> bool {
+        self.order.iter().enumerate().all(|(i, &from)| i == from)
+    }
+
+    /// Whether the plan still applies to a playlist holding `ids`.
+    pub fn fits(&self, ids: &[i64]) -> bool {
+        self.ids == ids
+    }
+
+    /// The proposed order as lines like `3. Artist - Title (was 7)`, followed by a summary.
+    pub fn lines(&self, playlist: &[Song]) -> Vec<String> {
+        let mut lines = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(i, &from)| {
+                let song = &playlist[from];
+                let moved = if i == from {
+                    String::new()
+                } else {
+                    format!(" (was {})", from + 1)
+                };
+                format!("{:>3}. {} - {}{}", i + 1, song.artist, song.title, moved)
+            })
+            .collect::<Vec<_>>();
+        lines.push(String::new());
+        lines.push(format!(
+            "same artist back to back: {} -> {}",
+            self.adjacent_before, self.adjacent_after
+        ));
+        lines.push(format!(
+            "transition cost: {:.1} -> {:.1}",
+            self.cost_before, self.cost_after
+        ));
+        if self.unanalyzed > 0 {
+            lines.push(format!(
+                "{} tracks have no tempo or level yet: run \"scan --bpm --loudness\"",
+                self.unanalyzed
+            ));
+        }
+
+        lines
+    }
+}
+
+/// What the optimizer knows of one playlist entry.
+struct Traits<'a> {
+    artist: String,
+    bpm: Option<&'a f64>,
+    loudness: Option<&'a f64>,
+}
+
+/// How rough going from `a` straight into `b` is. Unknown tempos and levels cost nothing.
+fn transition_cost(a: &Traits, b: &Traits) -> f64 {
+    let mut cost = 0.0;
+    if !a.artist.is_empty() && a.artist == b.artist {
+        cost += SAME_ARTIST_COST;
+    }
+    if let (Some(a), Some(b)) = (a.loudness, b.loudness) {
+        cost += (a - b).abs() * LOUDNESS_COST_PER_DB;
+    }
+    if let (Some(a), Some(b)) = (a.bpm, b.bpm) {
+        cost += if b < a {
+            (a - b) * TEMPO_DROP_COST_PER_BPM
+        } else {
+            (b - a) * TEMPO_RISE_COST_PER_BPM
+        };
+    }
+
+    cost
+}
+
+fn order_cost(traits: &[Traits], order: &[usize]) -> f64 {
+    order
+        .windows(2)
+        .map(|pair| transition_cost(&traits[pair[0]], &traits[pair[1]]))
+        .sum()
+}
+
+fn adjacent_artists(traits: &[Traits], order: &[usize]) -> usize {
+    order
+        .windows(2)
+        .filter(|pair| {
+            let (a, b) = (&traits[pair[0]], &traits[pair[1]]);
+            !a.artist.is_empty() && a.artist == b.artist
+        })
+        .count()
+}
+
+/// Walks from `start`, always taking the cheapest next track.
+fn greedy_from(traits: &[Traits], start: usize) -> Vec<usize> {
+    let mut remaining = (0..traits.len())
+        .filter(|&i| i != start)
+        .collect::<Vec<_>>();
+    let mut order = vec![start];
+    while let Some(&last) = order.last()
+        && !remaining.is_empty()
+    {
+        let next = (0..remaining.len())
+            .min_by(|&a, &b| {
+                transition_cost(&traits[last], &traits[remaining[a]])
+                    .total_cmp(&transition_cost(&traits[last], &traits[remaining[b]]))
+            })
+            .unwrap_or(0);
+        order.push(remaining.remove(next));
+    }
+
+    order
+}
+
+/// Swaps pairs of entries for as long as that makes the order cheaper.
+fn improve(traits: &[Traits], order: &mut [usize]) {
+    let mut cost = order_cost(traits, order);
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                order.swap(i, j);
+                let swapped = order_cost(traits, order);
+                if swapped + f64::EPSILON < cost {
+                    cost = swapped;
+                    improved = true;
+                } else {
+                    order.swap(i, j);
+                }
+            }
+        }
+    }
+}
+
+/// Orders `playlist` so tempos ramp up rather than lurch down, levels stay close from
+/// one track to the next, and an artist doesn't follow themselves. The best greedy walk
+/// over all starting tracks is then refined by swapping entries. The current order is kept
+/// if nothing beats it.
+pub fn optimize(playlist: &[Song], bpms: &HashMap<i64, f64>, loudness: &HashMap<i64, f64>) -> Plan {
+    let traits = playlist
+        .iter()
+        .map(|song| Traits {
+            artist: song.artist.to_lowercase(),
+            bpm: bpms.get(&song.id),
+            loudness: loudness.get(&song.id),
+        })
+        .collect::<Vec<_>>();
+    let current = (0..playlist.len()).collect::<Vec<_>>();
+    let cost_before = order_cost(&traits, &current);
+
+    let mut best = (0..traits.len())
+        .map(|start| greedy_from(&traits, start))
+        .min_by(|a, b| order_cost(&traits, a).total_cmp(&order_cost(&traits, b)))
+        .unwrap_or_default();
+    improve(&traits, &mut best);
+    let mut best_cost = order_cost(&traits, &best);
+    if best_cost + f64::EPSILON >= cost_before {
+        best = current.clone();
+        best_cost = cost_before;
+    }
+
+    Plan {
+        adjacent_before: adjacent_artists(&traits, &current),
+        adjacent_after: adjacent_artists(&traits, &best),
+        unanalyzed: traits
+            .iter()
+            .filter(|t| t.bpm.is_none() && t.loudness.is_none())
+            .count(),
+        ids: playlist.iter().map(|s| s.id).collect(),
+        order: best,
+        cost_before,
+        cost_after: best_cost,
+    }
+}
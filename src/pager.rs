@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, disable_raw_mode, enable_raw_mode};
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::shutdown;
+use crate::style;
+
+/// Prints `lines`, through `$PAGER` or the built-in pager when they won't fit on the
+/// terminal. Output that isn't going to a terminal is printed as it is.
+pub fn show(lines: &[String]) {
+    let rows = match terminal::size() {
+        Ok((_, rows)) if io::stdout().is_terminal() && io::stdin().is_terminal() => rows,
+        _ => u16::MAX,
+    };
+    // the prompt takes the last row
+    if lines.len() < usize::from(rows) {
+        print_all(lines);
+        return;
+    }
+
+    let paged = match std::env::var("PAGER") {
+        Ok(pager) if !pager.trim().is_empty() => external(&pager, lines).or_else(|err| {
+            eprintln!(
+                "{} {:#}, using the built-in pager",
+                style::error_label(),
+                err
+            );
+            internal(lines, rows)
+        }),
+        _ => internal(lines, rows),
+    };
+    if let Err(err) = paged {
+        eprintln!("{} {:#}", style::error_label(), err);
+        print_all(lines);
+    }
+}
+
+fn print_all(lines: &[String]) {
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+/// Pipes `lines` into shell command `pager`, e.g. `less -R`, and waits for it to exit.
+fn external(pager: &str, lines: &[String]) -> Result<()> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(pager).stdin(Stdio::piped());
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("failed to run pager \"{}\"", pager))?;
+    let _guard = shutdown::track(&child);
+
+    // SAFETY: stdin was piped above
+    let mut stdin = child.stdin.take().unwrap();
+    for line in lines {
+        // the pager was quit before the end
+        if writeln!(stdin, "{}", line).is_err() {
+            break;
+        }
+    }
+    drop(stdin);
+    child.wait().context("failed to wait for the pager")?;
+
+    Ok(())
+}
+
+/// Leaves raw mode when the built-in pager is done, however it ends.
+struct RawMode;
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+    }
+}
+
+/// Shows `lines` a screen of `rows` at a time: Space or f for the next screen, Enter or
+/// j for the next line, q or Esc to stop.
+fn internal(lines: &[String], rows: u16) -> Result<()> {
+    let page = usize::from(rows.saturating_sub(1)).max(1);
+    let mut stdout = io::stdout();
+    let mut shown = 0;
+    let mut next = page;
+    while shown < lines.len() {
+        for line in &lines[shown..next.min(lines.len())] {
+            writeln!(stdout, "{}", line)?;
+        }
+        shown = next.min(lines.len());
+        if shown == lines.len() {
+            break;
+        }
+
+        write!(
+            stdout,
+            "-- {}% (Space: more, Enter: one line, q: stop) --",
+            shown * 100 / lines.len()
+        )?;
+        stdout.flush()?;
+        let key = {
+            enable_raw_mode().context("failed to read keys for the pager")?;
+            let _raw = RawMode;
+            read_key()?
+        };
+        // clear the prompt for the lines that follow
+        write!(
+            stdout,
+            "\r{}\r",
+            " ".repeat(terminal::size().map_or(0, |(cols, _)| cols.into()))
+        )?;
+        match key {
+            KeyCode::Char(' ' | 'f') | KeyCode::PageDown => next = shown + page,
+            KeyCode::Enter | KeyCode::Char('j') | KeyCode::Down => next = shown + 1,
+            _ => break,
+        }
+    }
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// The next key pressed, with Ctrl+C as Esc since raw mode swallows the signal.
+fn read_key() -> Result<KeyCode> {
+    loop {
+        if let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read().context("failed to read a key")?
+        {
+            if modifiers.contains(KeyModifiers::CONTROL) && code == KeyCode::Char('c') {
+                return Ok(KeyCode::Esc);
+            }
+            return Ok(code);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Directory name used under each XDG base directory.
+const APP_DIR: &str = "vol_28";
+
+/// Profile used without `--profile`, stored straight in the base directories.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profile chosen with `--profile`. Each one has its own library, config and session
+/// under `vol_28/profiles/<name>` in every base directory.
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Points every path at profile `name`, e.g. `flac-archive`. Must be called before any
+/// path is used; later calls are ignored.
+pub fn set_profile(name: &str) -> Result<()> {
+    anyhow::ensure!(
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.')),
+        "invalid profile name \"{}\": use letters, digits, '-', '_' and '.'",
+        name
+    );
+    if name != DEFAULT_PROFILE {
+        let _ = PROFILE.set(name.to_string());
+    }
+
+    Ok(())
+}
+
+/// Name of the profile in use.
+pub fn profile() -> &'static str {
+    PROFILE.get().map_or(DEFAULT_PROFILE, String::as_str)
+}
+
+/// `$<var>/vol_28`, falling back to `$HOME/<fallback>/vol_28` when the variable is
+/// unset or not absolute, as the XDG base directory spec requires. Profiles other than
+/// the default get a directory of their own below that.
+fn base_dir(var: &str, fallback: &str) -> PathBuf {
+    let base = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(fallback)
+        });
+
+    match PROFILE.get() {
+        Some(profile) => base.join(APP_DIR).join("profiles").join(profile),
+        None => base.join(APP_DIR),
+    }
+}
+
+pub fn data_dir() -> PathBuf {
+    base_dir("XDG_DATA_HOME", ".local/share")
+}
+
+pub fn config_dir() -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", ".config")
+}
+
+pub fn state_dir() -> PathBuf {
+    base_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// The track library.
+pub fn db() -> PathBuf {
+    data_dir().join("library.db")
+}
+
+pub fn config() -> PathBuf {
+    config_dir().join("config")
+}
+
+/// Playlist autosaved between runs.
+pub fn session() -> PathBuf {
+    state_dir().join("session.playlist")
+}
+
+/// Playlist changes that can be undone, kept next to the session they belong to.
+pub fn journal() -> PathBuf {
+    state_dir().join("session.journal")
+}
+
+/// How far the last burn's staging got, for a retry to resume from.
+pub fn burn_progress() -> PathBuf {
+    state_dir().join("burn.progress")
+}
+
+/// Previously entered searches.
+pub fn history() -> PathBuf {
+    state_dir().join("history")
+}
+
+/// Searches saved under a name.
+pub fn saved_searches() -> PathBuf {
+    data_dir().join("saved_searches")
+}
+
+/// Everything the application stores, with a label for each. Burn logs and the
+/// burn history are kept in the library.
+pub fn all() -> Vec<(&'static str, PathBuf)> {
+    vec![
+        ("library", db()),
+        ("config", config()),
+        ("session", session()),
+        ("journal", journal()),
+        ("burn progress", burn_progress()),
+        ("search history", history()),
+        ("saved searches", saved_searches()),
+    ]
+}
+
+/// Creates the base directories so files can be written straight into them.
+pub fn create_dirs() -> Result<()> {
+    for dir in [data_dir(), config_dir(), state_dir()] {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Moves files that older versions dropped into the current directory to their XDG
+/// locations, unless something is already there. They belong to the default profile.
+/// Returns the files moved.
+pub fn migrate_legacy() -> Result<Vec<PathBuf>> {
+    let mut moved = vec![];
+    if PROFILE.get().is_some() {
+        return Ok(moved);
+    }
+    for (legacy, target) in [("library.db", db()), ("session.playlist", session())] {
+        let legacy = Path::new(legacy);
+        if !legacy.is_file() || target.exists() {
+            continue;
+        }
+
+        // rename fails across filesystems, so fall back to copying
+        if std::fs::rename(legacy, &target).is_err() {
+            std::fs::copy(legacy, &target).with_context(|| {
+                format!(
+                    "failed to move {} to {}",
+                    legacy.display(),
+                    target.display()
+                )
+            })?;
+            std::fs::remove_file(legacy)
+                .with_context(|| format!("failed to remove {}", legacy.display()))?;
+        }
+        moved.push(target);
+    }
+
+    Ok(moved)
+}
+
+/// Prints where everything lives.
+pub fn print() {
+    println!("{:<14} {}", "profile", profile());
+    for (label, path) in all() {
+        println!("{:<14} {}", label, path.display());
+    }
+}
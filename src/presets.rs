@@ -0,0 +1,55 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Extra ffmpeg options for staging one source format. An `-af` filter goes ahead of
+/// the entry's fades; everything else is passed before the CD audio output options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preset {
+    pub filter: Option<String>,
+    pub args: Vec<String>,
+}
+
+impl std::str::FromStr for Preset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut preset = Preset::default();
+        let mut words = s.split_whitespace();
+        while let Some(word) = words.next() {
+            if matches!(word, "-af" | "-filter:a") {
+                let filter = words
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("expected a filter after {}", word))?;
+                anyhow::ensure!(preset.filter.is_none(), "expected at most one {}", word);
+                preset.filter = Some(filter.to_string());
+            } else {
+                preset.args.push(word.to_string());
+            }
+        }
+
+        Ok(preset)
+    }
+}
+
+/// Presets set in the config as `transcode_<format> = <ffmpeg options>`, e.g.
+/// `transcode_flac = -af aresample=resampler=soxr`, keyed by file extension. Options are
+/// split on whitespace, so a filter can't contain spaces.
+#[derive(Debug, Clone, Default)]
+pub struct Presets(HashMap<String, Preset>);
+
+impl Presets {
+    /// Applies a `transcode_<format>` setting from the config file.
+    pub fn set(&mut self, format: &str, value: &str) -> Result<()> {
+        anyhow::ensure!(
+            !format.is_empty(),
+            "expected a format after transcode_, e.g. transcode_flac"
+        );
+        self.0.insert(format.to_lowercase(), value.parse()?);
+        Ok(())
+    }
+
+    /// The preset for files with extension `format`, if one is set.
+    pub fn get(&self, format: &str) -> Option<&Preset> {
+        self.0.get(&format.to_lowercase())
+    }
+}
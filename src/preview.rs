@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::process::{Command, Stdio};
+
+use crate::app::{Song, queries};
+use crate::edit::{self, FRAMES_PER_SEC};
+use crate::shutdown;
+
+/// Seconds heard before an out-point and after an in-point when previewing an edit.
+const BEFORE_CUT_SECS: u64 = 10;
+const AFTER_CUT_SECS: u64 = 5;
+/// Seconds heard of each side of a transition between two entries.
+const TRANSITION_SECS: u64 = 10;
+/// Seconds of a parked candidate heard when auditioning it.
+const SAMPLE_SECS: u64 = 20;
+/// Recent previews drawn on by `playlist smart --recent` and the TUI's list.
+pub const RECENT_LEN: usize = 50;
+
+/// Which edited boundary of a track to audition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    Start,
+    End,
+}
+
+/// Plays the edited region around `boundary` through ffplay, with the entry's trim and
+/// fades applied, and blocks until playback finishes: the first seconds after the
+/// in-point, or the last seconds before the out-point.
+pub fn play_boundary(song: &Song, boundary: Boundary) -> Result<()> {
+    match boundary {
+        Boundary::Start => play_region(song, 0, AFTER_CUT_SECS),
+        Boundary::End => {
+            let edited_secs = song.played_duration_secs();
+            play_region(
+                song,
+                edited_secs.saturating_sub(BEFORE_CUT_SECS),
+                BEFORE_CUT_SECS,
+            )
+        }
+    }
+}
+
+/// Plays the source around the cut at `boundary` as it is, without the entry's trim or
+/// fades, so [`play_boundary`] after it gives an A/B of the edit. Blocks until playback
+/// finishes.
+pub fn play_source(song: &Song, boundary: Boundary) -> Result<()> {
+    let edit = &song.edit;
+    let cut = match boundary {
+        Boundary::Start => edit.start_frames,
+        Boundary::End => edit.start_frames + edit.duration_frames(song.duration_sec),
+    };
+    let start = cut.saturating_sub(BEFORE_CUT_SECS * FRAMES_PER_SEC);
+    ffplay(
+        song,
+        &format!(
+            "atrim=start={}:end={}",
+            edit::timestamp(start),
+            edit::timestamp(cut + AFTER_CUT_SECS * FRAMES_PER_SEC)
+        ),
+    )
+}
+
+/// Plays the last seconds of `from` and then the first seconds of `to`, both as edited,
+/// to hear how one entry flows into the next. Blocks until playback finishes.
+pub fn play_transition(from: &Song, to: &Song) -> Result<()> {
+    let from_secs = from.played_duration_secs();
+    play_region(
+        from,
+        from_secs.saturating_sub(TRANSITION_SECS),
+        TRANSITION_SECS,
+    )?;
+    play_region(to, 0, TRANSITION_SECS)
+}
+
+/// Plays a stretch from a third of the way into `song`, long enough to judge it by,
+/// and blocks until playback finishes.
+pub fn play_sample(song: &Song) -> Result<()> {
+    let secs = song.played_duration_secs();
+    play_region(song, secs / 3, SAMPLE_SECS.min(secs))
+}
+
+/// Plays `length` seconds of the entry from `offset` seconds into its edited audio.
+fn play_region(song: &Song, offset: u64, length: u64) -> Result<()> {
+    let edit = &song.edit;
+    let edited_frames = edit.duration_frames(song.duration_sec);
+
+    let mut filters = vec![format!(
+        "atrim=start={}:end={}",
+        edit::timestamp(edit.start_frames),
+        edit::timestamp(edit.start_frames + edited_frames)
+    )];
+    filters.push(String::from("asetpts=PTS-STARTPTS"));
+    if let Some(fades) = edit.fade_filter(song.duration_sec) {
+        filters.push(fades);
+    }
+    filters.push(format!("atrim=start={}:end={}", offset, offset + length));
+
+    ffplay(song, &filters.join(","))
+}
+
+/// Plays the file of `song` through ffplay with the audio `filters`.
+fn ffplay(song: &Song, filters: &str) -> Result<()> {
+    let status = shutdown::status(
+        Command::new("ffplay")
+            .arg("-nodisp")
+            .arg("-autoexit")
+            .arg("-loglevel")
+            .arg("error")
+            .arg("-af")
+            .arg(filters)
+            .arg(&song.path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null()),
+    )
+    .context("failed to run ffplay. Is it installed?")?;
+
+    if !status.success() {
+        anyhow::bail!("ffplay failed to preview {}", song.path);
+    }
+
+    Ok(())
+}
+
+/// Notes that track `id` was just previewed.
+pub fn record(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO previews (track_id, played_at) VALUES (?1, unixepoch())",
+        params![id],
+    )
+    .context("failed to record preview")?;
+
+    Ok(())
+}
+
+/// Up to `limit` tracks previewed most recently, newest first, each with the local time
+/// it was last heard, e.g. `2024-05-01 21:14:03`.
+pub fn recent(conn: &Connection, limit: usize) -> Result<Vec<(Song, String)>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.id, t.path, t.title, t.artist, t.album, t.track, t.year, t.duration_sec,
+                datetime(MAX(p.played_at), 'unixepoch', 'localtime')
+            FROM previews p JOIN tracks t ON t.id = p.track_id
+            GROUP BY t.id
+            ORDER BY MAX(p.id) DESC
+            LIMIT ?1",
+        )
+        .context("failed to prepare query for recent previews")?;
+    stmt.query_map(params![limit as i64], |row| {
+        Ok((queries::track_from_row(row)?, row.get(8)?))
+    })
+    .context("failed to query recent previews")?
+    .collect::<Result<Vec<_>, _>>()
+    .context("failed to map recent previews from database")
+}
@@ -0,0 +1,103 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// Audio properties of a source file, used to rank copies of the same track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceQuality {
+    pub lossless: bool,
+    pub bit_depth: u8,
+    pub sample_rate_hz: u32,
+    pub bitrate_kbps: u32,
+}
+
+/// Whether a file extension is a lossless format.
+pub fn is_lossless(format: &str) -> bool {
+    matches!(
+        format.to_ascii_lowercase().as_str(),
+        "flac" | "wav" | "aiff" | "alac" | "ape" | "wv"
+    )
+}
+
+impl SourceQuality {
+    /// Short human readable summary, e.g. `lossless 24/96` or `320 kbps`.
+    pub fn describe(&self) -> String {
+        if self.lossless {
+            format!(
+                "lossless {}/{}",
+                self.bit_depth,
+                self.sample_rate_hz as f64 / 1000.0
+            )
+        } else {
+            format!("{} kbps", self.bitrate_kbps)
+        }
+    }
+}
+
+impl Ord for SourceQuality {
+    /// Lossless beats lossy; lossless copies are compared by resolution, lossy ones
+    /// by bitrate.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.lossless.cmp(&other.lossless).then_with(|| {
+            if self.lossless {
+                (self.bit_depth, self.sample_rate_hz).cmp(&(other.bit_depth, other.sample_rate_hz))
+            } else {
+                (self.bitrate_kbps, self.sample_rate_hz)
+                    .cmp(&(other.bitrate_kbps, other.sample_rate_hz))
+            }
+        })
+    }
+}
+
+impl PartialOrd for SourceQuality {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Bitrate lossy sources need to pass the quality gate unless `gate_min_kbps` says
+/// otherwise.
+pub const DEFAULT_MIN_KBPS: u32 = 192;
+
+/// What the quality gate does with sources below its threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GateAction {
+    #[default]
+    Off,
+    /// Report low-quality sources but burn anyway.
+    Warn,
+    /// Refuse to burn until the sources are replaced or allowed.
+    Block,
+}
+
+impl std::str::FromStr for GateAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "off" => Ok(GateAction::Off),
+            "warn" => Ok(GateAction::Warn),
+            "block" => Ok(GateAction::Block),
+            _ => anyhow::bail!("unknown gate action \"{}\": expected off / warn / block", s),
+        }
+    }
+}
+
+/// Burn-time check that keeps low bitrate rips off archival discs.
+#[derive(Debug, Clone, Default)]
+pub struct QualityGate {
+    pub action: GateAction,
+    /// Lossy sources below this bitrate fail the gate.
+    pub min_lossy_kbps: u32,
+    /// Track IDs let through regardless of their quality, kept in the library.
+    pub allowed: HashSet<i64>,
+}
+
+impl QualityGate {
+    /// Whether a track fails the gate. Lossless sources always pass.
+    pub fn rejects(&self, id: i64, quality: &SourceQuality) -> bool {
+        self.action != GateAction::Off
+            && !quality.lossless
+            && quality.bitrate_kbps < self.min_lossy_kbps
+            && !self.allowed.contains(&id)
+    }
+}
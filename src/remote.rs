@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use crate::app;
+use crate::command::Console;
+use crate::events::Event;
+use crate::style;
+
+/// Serves the shell on `addr` to one client at a time, so a laptop can browse the
+/// library and build the playlist while staging and burning run here, next to the
+/// burner. There is no authentication, so only loopback addresses are served unless
+/// `any_host` is set: reach it through an SSH tunnel
+/// (`ssh -L 7028:localhost:7028 burner`). Only the shell is served; the TUI needs a
+/// terminal of its own.
+pub fn serve(addr: &str, any_host: bool) -> Result<()> {
+    let addrs = addr
+        .to_socket_addrs()
+        .with_context(|| format!("failed to resolve {}", addr))?
+        .collect::<Vec<_>>();
+    if !any_host && let Some(open) = addrs.iter().find(|addr| !addr.ip().is_loopback()) {
+        anyhow::bail!(
+            "refusing to serve on {}: anyone who can reach it could burn discs, write tags and \
+             run hooks. Serve on 127.0.0.1 and use an SSH tunnel, or pass --insecure-any-host",
+            open
+        );
+    }
+    let listener =
+        TcpListener::bind(&addrs[..]).with_context(|| format!("failed to listen on {}", addr))?;
+    eprintln!(
+        "serving the library on {}",
+        listener
+            .local_addr()
+            .context("failed to read the listening address")?
+    );
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!(
+                    "{} failed to accept a client: {}",
+                    style::error_label(),
+                    err
+                );
+                continue;
+            }
+        };
+        let peer = stream
+            .peer_addr()
+            .map_or_else(|_| String::from("client"), |peer| peer.to_string());
+        eprintln!("{} connected", peer);
+
+        let result =
+            RemoteConsole::new(&stream).and_then(|mut console| app::run_shell(&mut console));
+        // drop anything the client sent after quitting
+        let _ = stream.shutdown(Shutdown::Both);
+        match result {
+            Ok(()) => eprintln!("{} disconnected", peer),
+            Err(err) => eprintln!("{} {} {:?}", style::error_label(), peer, err),
+        }
+    }
+
+    Ok(())
+}
+
+/// A shell session's console on a client's connection. Programs the shell runs keep
+/// the server's own stdio.
+struct RemoteConsole {
+    input: BufReader<TcpStream>,
+    output: Lossy,
+}
+
+impl RemoteConsole {
+    fn new(stream: &TcpStream) -> Result<Self> {
+        let clone = || {
+            stream
+                .try_clone()
+                .context("failed to set up the connection")
+        };
+
+        Ok(RemoteConsole {
+            input: BufReader::new(clone()?),
+            output: Lossy(Some(clone()?)),
+        })
+    }
+}
+
+impl Console for RemoteConsole {
+    fn show(&mut self, line: &str) {
+        let _ = writeln!(self.output, "{}", line);
+    }
+
+    fn show_error(&mut self, line: &str) {
+        self.show(line);
+    }
+
+    fn read_line(&mut self, prompt: &str) -> Result<Option<String>> {
+        let _ = write!(self.output, "{}", prompt);
+        let _ = self.output.flush();
+        if self.output.0.is_none() {
+            return Ok(None);
+        }
+        let mut line = String::new();
+        // a connection that breaks ends the input too
+        match self.input.read_line(&mut line) {
+            Ok(0) | Err(_) => Ok(None),
+            Ok(_) => Ok(Some(line)),
+        }
+    }
+
+    /// Clients are taken to be scripts: questions with a sensible default aren't asked.
+    fn interactive(&self) -> bool {
+        false
+    }
+
+    fn follow(
+        &mut self,
+        task: &str,
+        handle: JoinHandle<Result<()>>,
+        rx: mpsc::Receiver<Event>,
+    ) -> Result<()> {
+        let mut err = self.output.try_clone();
+        app::print_log_messages(task, handle, rx, &mut self.output, &mut err)
+    }
+}
+
+/// Writes to a client for as long as it is connected, then nowhere, so a client that
+/// goes away mid-command never fails the command.
+struct Lossy(Option<TcpStream>);
+
+impl Lossy {
+    fn try_clone(&self) -> Self {
+        Lossy(self.0.as_ref().and_then(|stream| stream.try_clone().ok()))
+    }
+}
+
+impl Write for Lossy {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(stream) = &mut self.0
+            && stream.write_all(buf).is_err()
+        {
+            self.0 = None;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(stream) = &mut self.0
+            && stream.flush().is_err()
+        {
+            self.0 = None;
+        }
+
+        Ok(())
+    }
+}
+
+/// Connects the terminal to a shell served by [`serve`] on `addr`.
+pub fn connect(addr: &str) -> Result<()> {
+    let stream = TcpStream::connect(addr)
+        .with_context(|| format!("failed to connect to the library at {}", addr))?;
+    let mut writer = stream
+        .try_clone()
+        .context("failed to set up the connection")?;
+    std::thread::spawn(move || {
+        let _ = io::copy(&mut io::stdin().lock(), &mut writer);
+        // let the server see EOF just like a local shell would
+        let _ = writer.shutdown(Shutdown::Write);
+    });
+
+    // copied by hand since stdout only flushes on newlines and prompts have none
+    let mut reader = stream;
+    let mut stdout = io::stdout().lock();
+    let mut buffer = [0; 4096];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .with_context(|| format!("lost the connection to {}", addr))?;
+        if read == 0 {
+            break;
+        }
+        stdout.write_all(&buffer[..read])?;
+        stdout.flush()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use anyhow::{Context, Result};
+use std::io::ErrorKind;
+
+use crate::app::Song;
+use crate::edit::{FRAMES_PER_SEC, TrackEdit};
+use crate::paths;
+use crate::shutdown;
+
+/// Mark the lines holding the disc title and series, which come before the tracks.
+const TITLE_PREFIX: &str = "title ";
+const SERIES_PREFIX: &str = "series ";
+/// Marks the entries parked in the audition list, which come after the tracks.
+const AUDITION_PREFIX: &str = "audition ";
+/// Marks an entry for a file from outside the library, which is saved by its path as
+/// it has no ID.
+const FILE_PREFIX: &str = "file ";
+/// Marks where the TUI was left, one `ui <key> <value>` line per setting.
+const UI_PREFIX: &str = "ui ";
+
+/// What a playlist is to be burned as, when the user has said.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiscLabels {
+    pub title: Option<String>,
+    pub series: Option<String>,
+}
+
+/// A playlist entry as saved: the track's ID, or for a file from outside the library
+/// its path, and the entry's trim and fades.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// 0 for a file from outside the library.
+    pub id: i64,
+    pub path: Option<String>,
+    pub edit: TrackEdit,
+}
+
+impl Entry {
+    pub fn of(song: &Song) -> Entry {
+        Entry {
+            id: song.id,
+            path: (song.id == 0).then(|| song.path.clone()),
+            edit: song.edit,
+        }
+    }
+}
+
+/// Where the TUI was left, so reopening it picks up there: the focused pane, the
+/// selected artist, and the selected row and scroll offset of each pane. There is no
+/// sort order to keep: the artists and tracks panes always list in the library's
+/// collation, and the playlist's order is that of its entries, saved above.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UiState {
+    /// `artists`, `tracks` or `playlist`.
+    pub pane: Option<String>,
+    pub artist: Option<String>,
+    pub artist_offset: usize,
+    /// Selected row and scroll offset.
+    pub track: (usize, usize),
+    pub playlist: (usize, usize),
+    /// Pane widths if they were resized, written `20 60 20`.
+    pub panes: Option<String>,
+}
+
+impl UiState {
+    fn format(&self) -> String {
+        let mut lines = vec![];
+        if let Some(pane) = &self.pane {
+            lines.push(format!("pane {}", pane));
+        }
+        if let Some(artist) = &self.artist {
+            lines.push(format!("artist {}", artist));
+        }
+        lines.push(format!("artist_offset {}", self.artist_offset));
+        lines.push(format!("track {} {}", self.track.0, self.track.1));
+        lines.push(format!("playlist {} {}", self.playlist.0, self.playlist.1));
+        if let Some(panes) = &self.panes {
+            lines.push(format!("panes {}", panes));
+        }
+
+        lines
+            .into_iter()
+            .map(|line| format!("{}{}\n", UI_PREFIX, line))
+            .collect()
+    }
+}
+
+/// Writes the disc labels that were set, the playlist's entries and edits, in order,
+/// the audition list and where the TUI was left to the session file. Written to a
+/// sibling file first and renamed so a crash mid-write never leaves a truncated session.
+pub fn save(playlist: &[Song], audition: &[Song], labels: &DiscLabels, ui: &UiState) -> Result<()> {
+    let tracks = playlist
+        .iter()
+        .map(|s| format_entry(&Entry::of(s)) + "\n")
+        .collect::<String>();
+    let contents = [
+        (TITLE_PREFIX, &labels.title),
+        (SERIES_PREFIX, &labels.series),
+    ]
+    .into_iter()
+    .filter_map(|(prefix, label)| Some(format!("{}{}\n", prefix, label.as_ref()?)))
+    .collect::<String>()
+        + &tracks
+        + &audition
+            .iter()
+            .map(|s| format!("{}{}\n", AUDITION_PREFIX, format_entry(&Entry::of(s))))
+            .collect::<String>()
+        + &ui.format();
+
+    let path = paths::session();
+    let tmp_path = path.with_extension("playlist.tmp");
+    shutdown::critical(|| {
+        std::fs::write(&tmp_path, contents)
+            .with_context(|| format!("failed to write session file \"{}\"", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("failed to replace session file \"{}\"", path.display()))
+    })
+}
+
+fn read() -> Result<String> {
+    let path = paths::session();
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => Ok(contents),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to read session file \"{}\"", path.display()))
+        }
+    }
+}
+
+/// Reads the playlist entries saved by a previous run, if there are any.
+pub fn load() -> Result<Vec<Entry>> {
+    read()?
+        .lines()
+        .filter(|line| {
+            !line.trim().is_empty()
+                && !line.starts_with(TITLE_PREFIX)
+                && !line.starts_with(SERIES_PREFIX)
+                && !line.starts_with(AUDITION_PREFIX)
+                && !line.starts_with(UI_PREFIX)
+        })
+        .map(|line| {
+            parse_entry(line).with_context(|| format!("invalid entry \"{}\" in session file", line))
+        })
+        .collect()
+}
+
+/// The entries a previous run left in the audition list, such as the rest of a split
+/// track with its trim.
+pub fn load_audition() -> Result<Vec<Entry>> {
+    read()?
+        .lines()
+        .filter_map(|line| line.strip_prefix(AUDITION_PREFIX))
+        .map(|entry| {
+            parse_entry(entry)
+                .with_context(|| format!("invalid audition entry \"{}\" in session file", entry))
+        })
+        .collect()
+}
+
+/// The disc labels saved by a previous run.
+pub fn load_labels() -> Result<DiscLabels> {
+    let contents = read()?;
+    let label = |prefix: &str| {
+        contents
+            .lines()
+            .find_map(|line| line.strip_prefix(prefix))
+            .map(String::from)
+    };
+
+    Ok(DiscLabels {
+        title: label(TITLE_PREFIX),
+        series: label(SERIES_PREFIX),
+    })
+}
+
+/// Where a previous run left the TUI. Settings this version doesn't know or can't read
+/// are left at their defaults rather than failing, as none of them is worth losing the
+/// session over.
+pub fn load_ui() -> Result<UiState> {
+    let mut ui = UiState::default();
+    let offsets = |value: &str| {
+        let (selected, offset) = value.split_once(' ')?;
+        Some((selected.parse().ok()?, offset.parse().ok()?))
+    };
+    for (key, value) in read()?
+        .lines()
+        .filter_map(|line| line.strip_prefix(UI_PREFIX)?.split_once(' '))
+    {
+        match key {
+            "pane" => ui.pane = Some(value.to_string()),
+            "artist" => ui.artist = Some(value.to_string()),
+            "artist_offset" => ui.artist_offset = value.parse().unwrap_or_default(),
+            "track" => ui.track = offsets(value).unwrap_or_default(),
+            "playlist" => ui.playlist = offsets(value).unwrap_or_default(),
+            "panes" => ui.panes = Some(value.to_string()),
+            _ => (),
+        }
+    }
+
+    Ok(ui)
+}
+
+/// Formats a playlist entry the way [`parse_entry`] reads it back.
+pub fn format_entry(entry: &Entry) -> String {
+    let edit = &entry.edit;
+    let end = edit
+        .end_frames
+        .map_or_else(|| "-".to_string(), |e| format!("{}f", e));
+    let fields = format!(
+        "{}f {} {} {}",
+        edit.start_frames, end, edit.fade_in_secs, edit.fade_out_secs
+    );
+    match &entry.path {
+        // the path goes last as it may have spaces in it
+        Some(path) => format!("{}{} {}", FILE_PREFIX, fields, path),
+        None if edit.is_empty() => entry.id.to_string(),
+        None => format!("{} {}", entry.id, fields),
+    }
+}
+
+/// Parses `<id>`, `<id> <start> <end|-> <fade in> <fade out>`, or
+/// `file <start> <end|-> <fade in> <fade out> <path>`, see [`parse_frames`] for the
+/// trim positions.
+pub fn parse_entry(line: &str) -> Result<Entry> {
+    if let Some(rest) = line.strip_prefix(FILE_PREFIX) {
+        let mut fields = rest.splitn(5, ' ');
+        let edit = parse_edit(&mut fields)?.context("missing trim")?;
+        let path = fields.next().filter(|path| !path.is_empty());
+        return Ok(Entry {
+            id: 0,
+            path: Some(path.context("missing path")?.to_string()),
+            edit,
+        });
+    }
+
+    let mut fields = line.split_whitespace();
+    let id = fields.next().context("missing track ID")?.parse()?;
+
+    Ok(Entry {
+        id,
+        path: None,
+        edit: parse_edit(&mut fields)?.unwrap_or_default(),
+    })
+}
+
+/// Parses `<start> <end|-> <fade in> <fade out>`, or nothing when there are no fields.
+fn parse_edit<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Result<Option<TrackEdit>> {
+    let Some(start) = fields.next() else {
+        return Ok(None);
+    };
+    Ok(Some(TrackEdit {
+        start_frames: parse_frames(start)?,
+        end_frames: match fields.next().context("missing end")? {
+            "-" => None,
+            end => Some(parse_frames(end)?),
+        },
+        fade_in_secs: fields.next().context("missing fade in")?.parse()?,
+        fade_out_secs: fields.next().context("missing fade out")?.parse()?,
+    }))
+}
+
+/// Parses a trim position: frames with an `f` after them, or whole seconds as sessions
+/// kept them before trims were cut to the frame.
+fn parse_frames(field: &str) -> Result<u64> {
+    Ok(match field.strip_suffix('f') {
+        Some(frames) => frames.parse()?,
+        None => field.parse::<u64>()? * FRAMES_PER_SEC,
+    })
+}
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, Config};
+use crate::paths;
+
+/// Whether this is the first launch: there is neither a library nor a config file, and
+/// someone is at the terminal to answer questions.
+pub fn needed() -> bool {
+    !paths::db().exists() && !paths::config().exists() && io::stdin().is_terminal()
+}
+
+/// Asks for the music directory, burner device and staging location, and writes them
+/// to the config file. Run again on an existing config, it offers the current values
+/// and changes only those three, keeping every other setting. Returns the music
+/// directory so it can be scanned.
+pub fn run() -> Result<PathBuf> {
+    println!("--- First-run setup ---");
+    println!("Press enter to accept the default in brackets.\n");
+
+    let path = paths::config();
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", path.display()));
+        }
+    };
+    let current = Config::parse(&existing)
+        .with_context(|| format!("fix {} before running setup again", path.display()))?;
+
+    let home = std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let music_dir = ask_dir(
+        "Music directory",
+        &current.music_dir.unwrap_or_else(|| home.join("Music")),
+    )?;
+    let device = ask(
+        "Burner device",
+        &if existing.is_empty() {
+            default_device()
+        } else {
+            current.device
+        },
+    )?;
+    let staging_dir = ask_dir(
+        "Staging directory for transcoded audio",
+        &current.staging_dir,
+    )?;
+
+    let text = if existing.is_empty() {
+        String::from(
+            "# written by the first-run setup; see `paths` for where everything else lives\n",
+        )
+    } else {
+        existing
+    };
+    let text = set_keys(
+        &text,
+        &[
+            ("music_dir", music_dir.display().to_string()),
+            ("device", device),
+            ("staging_dir", staging_dir.display().to_string()),
+        ],
+    );
+    // make sure the file we write is one we can read back
+    Config::parse(&text).context("setup produced an invalid config")?;
+
+    std::fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))?;
+    println!("\nWrote {}", path.display());
+
+    Ok(music_dir)
+}
+
+/// `text` with the lines setting each of `keys` replaced, and those not set yet added
+/// at the end. Comments and every other line are left as they are.
+fn set_keys(text: &str, keys: &[(&str, String)]) -> String {
+    let mut written = vec![false; keys.len()];
+    let mut out = String::new();
+    for line in text.lines() {
+        let key = line
+            .split_once('=')
+            .map(|(key, _)| key.trim())
+            .filter(|_| !line.trim_start().starts_with('#'));
+        match keys.iter().position(|(name, _)| Some(*name) == key) {
+            // a later duplicate would win over the new value, so it goes
+            Some(i) if written[i] => continue,
+            Some(i) => {
+                out += &format!("{} = {}\n", keys[i].0, keys[i].1);
+                written[i] = true;
+            }
+            None => {
+                out += line;
+                out.push('\n');
+            }
+        }
+    }
+    for ((name, value), _) in keys.iter().zip(&written).filter(|(_, written)| !**written) {
+        out += &format!("{} = {}\n", name, value);
+    }
+
+    out
+}
+
+/// The first optical drive present, or the usual Linux one when none are.
+fn default_device() -> String {
+    (0..4)
+        .map(|n| format!("/dev/sr{}", n))
+        .find(|device| Path::new(device).exists())
+        .unwrap_or_else(|| String::from(config::DEFAULT_DEVICE))
+}
+
+/// Prompts for a line of input, falling back to `default` on an empty answer.
+fn ask(question: &str, default: &str) -> Result<String> {
+    print!("{} [{}]: ", question, default);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        anyhow::bail!("setup cancelled");
+    }
+    let answer = answer.trim();
+
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Prompts until the answer names an existing directory.
+fn ask_dir(question: &str, default: &Path) -> Result<PathBuf> {
+    loop {
+        let dir = PathBuf::from(ask(question, &default.display().to_string())?);
+        if dir.is_dir() {
+            return Ok(dir);
+        }
+        println!("{} is not a directory", dir.display());
+    }
+}
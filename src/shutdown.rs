@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use crossterm::{
+    event::DisableMouseCapture,
+    execute,
+    terminal::{LeaveAlternateScreen, disable_raw_mode},
+};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+
+use crate::notify;
+
+/// What has to be undone if the process is interrupted.
+struct Cleanup {
+    /// Process IDs of running ffmpeg, normalize and wodim processes.
+    children: Vec<u32>,
+    /// Staging directories, removed on the way out since their destructors won't run.
+    dirs: Vec<PathBuf>,
+    /// Whether the TUI has the terminal in raw mode.
+    raw_terminal: bool,
+}
+
+static CLEANUP: Mutex<Cleanup> = Mutex::new(Cleanup {
+    children: Vec::new(),
+    dirs: Vec::new(),
+    raw_terminal: false,
+});
+
+/// Held while writing files that must not be left half written, such as the session.
+static CRITICAL: Mutex<()> = Mutex::new(());
+
+fn cleanup() -> std::sync::MutexGuard<'static, Cleanup> {
+    CLEANUP
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Handles SIGINT and SIGTERM on a background thread by stopping child processes,
+/// waiting for any session autosave in progress, removing staging directories and
+/// restoring the terminal before exiting with the usual `128 + signal` status.
+pub fn install() -> Result<()> {
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM]).context("failed to install signal handlers")?;
+    std::thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            shut_down();
+            std::process::exit(128 + signal);
+        }
+    });
+
+    Ok(())
+}
+
+fn shut_down() {
+    let mut cleanup = cleanup();
+    for &pid in &cleanup.children {
+        // SAFETY: kill has no memory safety requirements; at worst the process has
+        // already exited and this fails with ESRCH
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+
+    // autosaves are written as soon as the playlist changes, so flushing the session
+    // only means letting a save in progress finish
+    let _critical = CRITICAL.lock();
+
+    for dir in cleanup.dirs.drain(..) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    notify::restore_title();
+    if cleanup.raw_terminal {
+        let _ = disable_raw_mode();
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            crossterm::cursor::Show
+        );
+    } else {
+        eprintln!();
+    }
+}
+
+/// Runs `f` without being interrupted halfway by a signal.
+pub fn critical<T>(f: impl FnOnce() -> T) -> T {
+    let _critical = CRITICAL
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f()
+}
+
+/// Records whether the TUI has put the terminal in raw mode.
+pub fn set_raw_terminal(raw: bool) {
+    cleanup().raw_terminal = raw;
+}
+
+/// Keeps a directory registered to be removed on shutdown until dropped.
+pub struct DirGuard(PathBuf);
+
+/// Removes `dir` if the process is interrupted while the returned guard is alive.
+pub fn remove_on_exit(dir: &Path) -> DirGuard {
+    cleanup().dirs.push(dir.to_path_buf());
+    DirGuard(dir.to_path_buf())
+}
+
+impl Drop for DirGuard {
+    fn drop(&mut self) {
+        cleanup().dirs.retain(|dir| *dir != self.0);
+    }
+}
+
+/// Keeps a child process registered to be stopped on shutdown until dropped.
+pub struct ChildGuard(u32);
+
+pub fn track(child: &Child) -> ChildGuard {
+    cleanup().children.push(child.id());
+    ChildGuard(child.id())
+}
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        cleanup().children.retain(|&pid| pid != self.0);
+    }
+}
+
+/// [`Command::output`] for a command that is stopped on shutdown. Unlike it, stdout and
+/// stderr are only captured when the caller pipes them. Its stdin is closed.
+pub fn output(command: &mut Command) -> io::Result<Output> {
+    let child = command.stdin(Stdio::null()).spawn()?;
+    let _guard = track(&child);
+    child.wait_with_output()
+}
+
+/// [`Command::status`] for a command that is stopped on shutdown.
+pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+    let mut child = command.spawn()?;
+    let _guard = track(&child);
+    child.wait()
+}
@@ -0,0 +1,239 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Snapshots whose tracks are kept for diffing; older ones keep only their summary.
+const KEPT_SNAPSHOTS: i64 = 2;
+
+/// Summary of the library as a scan left it.
+#[derive(Debug, Clone)]
+pub struct Scan {
+    pub id: i64,
+    /// Local time the scan finished, e.g. `2024-06-01 21:14:03`.
+    pub scanned_at: String,
+    pub music_dir: String,
+    pub tracks: usize,
+    /// Differences from the scan before it.
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+/// A track as a snapshot saw it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub track: u32,
+    pub year: u32,
+    pub duration_sec: u64,
+    pub genre: String,
+}
+
+impl Entry {
+    fn fields(&self) -> [(&'static str, String); 7] {
+        [
+            ("title", self.title.clone()),
+            ("artist", self.artist.clone()),
+            ("album", self.album.clone()),
+            ("track", self.track.to_string()),
+            ("year", self.year.to_string()),
+            ("duration", crate::duration::format_secs(self.duration_sec)),
+            ("genre", self.genre.clone()),
+        ]
+    }
+}
+
+/// One field of a track that differs between two snapshots.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// What changed in the library from one scan to the next, by file path.
+#[derive(Debug, Clone)]
+pub struct Diff {
+    pub from: Scan,
+    pub to: Scan,
+    pub added: Vec<Entry>,
+    pub removed: Vec<Entry>,
+    pub changed: Vec<(Entry, Vec<Change>)>,
+}
+
+/// Takes a snapshot of the tracks table after a scan of `music_dir`, and returns how it
+/// differs from the previous one, if there was one.
+pub fn record(conn: &mut Connection, music_dir: &Path) -> Result<Option<Diff>> {
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for library snapshot")?;
+    tx.execute(
+        "INSERT INTO scans (scanned_at, music_dir, tracks, added, removed, changed)
+        SELECT unixepoch(), ?1, COUNT(*), 0, 0, 0 FROM tracks",
+        params![music_dir.display().to_string()],
+    )
+    .context("failed to record scan")?;
+    let id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO scan_tracks
+            (scan_id, path, title, artist, album, track, year, duration_sec, genre)
+        SELECT ?1, path, title, artist, album, track, year, duration_sec, genre FROM tracks",
+        params![id],
+    )
+    .context("failed to snapshot tracks")?;
+
+    let previous: Option<i64> = tx
+        .query_row(
+            "SELECT MAX(scan_id) FROM scan_tracks WHERE scan_id < ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .context("failed to find the previous snapshot")?;
+    let diff = match previous {
+        Some(previous) => {
+            let mut diff = diff_scans(&tx, previous, id)?;
+            tx.execute(
+                "UPDATE scans SET added = ?2, removed = ?3, changed = ?4 WHERE id = ?1",
+                params![
+                    id,
+                    diff.added.len() as i64,
+                    diff.removed.len() as i64,
+                    diff.changed.len() as i64
+                ],
+            )
+            .context("failed to record scan summary")?;
+            (diff.to.added, diff.to.removed, diff.to.changed) =
+                (diff.added.len(), diff.removed.len(), diff.changed.len());
+            Some(diff)
+        }
+        None => None,
+    };
+    tx.execute(
+        "DELETE FROM scan_tracks WHERE scan_id NOT IN
+            (SELECT id FROM scans ORDER BY id DESC LIMIT ?1)",
+        params![KEPT_SNAPSHOTS],
+    )
+    .context("failed to prune old snapshots")?;
+    tx.commit().context("failed to commit library snapshot")?;
+
+    Ok(diff)
+}
+
+/// Every scan, newest first.
+pub fn list(conn: &Connection) -> Result<Vec<Scan>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, datetime(scanned_at, 'unixepoch', 'localtime'), music_dir, tracks,
+                added, removed, changed
+            FROM scans ORDER BY id DESC",
+        )
+        .context("failed to prepare query for scans")?;
+    stmt.query_map([], scan_from_row)
+        .context("failed to query scans")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to map scans from database")
+}
+
+/// How the library changed between the last two scans.
+pub fn last_diff(conn: &Connection) -> Result<Diff> {
+    let ids = conn
+        .prepare("SELECT DISTINCT scan_id FROM scan_tracks ORDER BY scan_id DESC LIMIT 2")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, i64>(0))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .context("failed to find the last snapshots")?;
+    let [to, from] = ids[..] else {
+        anyhow::bail!("need two scans to compare: rescan the library first");
+    };
+
+    diff_scans(conn, from, to)
+}
+
+fn scan_from_row(row: &rusqlite::Row) -> rusqlite::Result<Scan> {
+    Ok(Scan {
+        id: row.get(0)?,
+        scanned_at: row.get(1)?,
+        music_dir: row.get(2)?,
+        tracks: row.get::<_, i64>(3)? as usize,
+        added: row.get::<_, i64>(4)? as usize,
+        removed: row.get::<_, i64>(5)? as usize,
+        changed: row.get::<_, i64>(6)? as usize,
+    })
+}
+
+fn scan(conn: &Connection, id: i64) -> Result<Scan> {
+    conn.query_row(
+        "SELECT id, datetime(scanned_at, 'unixepoch', 'localtime'), music_dir, tracks,
+            added, removed, changed
+        FROM scans WHERE id = ?1",
+        params![id],
+        scan_from_row,
+    )
+    .with_context(|| format!("failed to read scan {}", id))
+}
+
+fn entries(conn: &Connection, scan_id: i64) -> Result<BTreeMap<String, Entry>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, title, artist, album, track, year, duration_sec, genre
+            FROM scan_tracks WHERE scan_id = ?1",
+        )
+        .context("failed to prepare query for snapshot")?;
+    stmt.query_map(params![scan_id], |row| {
+        let entry = Entry {
+            path: row.get(0)?,
+            title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            artist: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            album: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            track: row.get::<_, Option<u32>>(4)?.unwrap_or_default(),
+            year: row.get::<_, Option<u32>>(5)?.unwrap_or_default(),
+            duration_sec: row.get::<_, Option<i64>>(6)?.unwrap_or_default() as u64,
+            genre: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+        };
+        Ok((entry.path.clone(), entry))
+    })
+    .context("failed to query snapshot")?
+    .collect::<Result<BTreeMap<_, _>, _>>()
+    .context("failed to map snapshot from database")
+}
+
+fn diff_scans(conn: &Connection, from: i64, to: i64) -> Result<Diff> {
+    let (old, new) = (entries(conn, from)?, entries(conn, to)?);
+    let added = new
+        .values()
+        .filter(|entry| !old.contains_key(&entry.path))
+        .cloned()
+        .collect();
+    let removed = old
+        .values()
+        .filter(|entry| !new.contains_key(&entry.path))
+        .cloned()
+        .collect();
+    let changed = new
+        .values()
+        .filter_map(|entry| {
+            let before = old.get(&entry.path).filter(|before| *before != entry)?;
+            let changes = before
+                .fields()
+                .into_iter()
+                .zip(entry.fields())
+                .filter(|((_, old), (_, new))| old != new)
+                .map(|((field, old), (_, new))| Change { field, old, new })
+                .collect();
+            Some((entry.clone(), changes))
+        })
+        .collect();
+
+    Ok(Diff {
+        from: scan(conn, from)?,
+        to: scan(conn, to)?,
+        added,
+        removed,
+        changed,
+    })
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+
+use crate::app::Song;
+use crate::duration::format_secs;
+use crate::edit::{FRAMES_PER_SEC, TrackEdit};
+use crate::waveform;
+
+/// How far before the end of the disc a quiet moment to split at is looked for.
+const SEARCH_SECS: u64 = 60;
+/// Sample rate the searched stretch is decoded at; only its level matters.
+const SAMPLE_RATE: u32 = 8000;
+/// Length of the windows whose levels are compared, in samples: half a second.
+const WINDOW: usize = SAMPLE_RATE as usize / 2;
+
+/// Splits `song`, which is longer than the `fits_secs` left on the disc, at the quietest
+/// moment of the last minute that still fits. Returns the part that fits and the rest,
+/// as entries trimmed from the same file, along with the second of the file the split is
+/// at. Fades stay with the ends they belong to.
+pub fn split(song: &Song, fits_secs: u64) -> Result<(Song, Song, u64)> {
+    anyhow::ensure!(
+        fits_secs >= SEARCH_SECS,
+        "only {} is left on the disc, too little to split \"{}\" onto",
+        format_secs(fits_secs),
+        song.title
+    );
+
+    let end = song.edit.start_secs() + fits_secs;
+    let search_start = end - SEARCH_SECS;
+    let samples = waveform::decode_mono_range(&song.path, SAMPLE_RATE, search_start, SEARCH_SECS)
+        .with_context(|| {
+        format!(
+            "failed to find a quiet moment to split \"{}\" at",
+            song.title
+        )
+    })?;
+    // the latest of the quietest windows, so the part that fits is as long as it can be
+    let quietest = samples
+        .chunks(WINDOW)
+        .map(|window| {
+            window.iter().map(|s| s.unsigned_abs() as u64).sum::<u64>() / window.len() as u64
+        })
+        .enumerate()
+        .fold(
+            None,
+            |quietest: Option<(usize, u64)>, (i, level)| match quietest {
+                Some((_, lowest)) if lowest < level => quietest,
+                _ => Some((i, level)),
+            },
+        )
+        .map(|(i, _)| i)
+        .context("decoded nothing to split at")?;
+    // the middle of the window, rounded down to the whole second trims are given in
+    let at = search_start + ((quietest * WINDOW + WINDOW / 2) / SAMPLE_RATE as usize) as u64;
+
+    let first = Song {
+        edit: TrackEdit {
+            end_frames: Some(at * FRAMES_PER_SEC),
+            fade_out_secs: 0,
+            ..song.edit
+        },
+        ..song.clone()
+    };
+    let rest = Song {
+        edit: TrackEdit {
+            start_frames: at * FRAMES_PER_SEC,
+            fade_in_secs: 0,
+            ..song.edit
+        },
+        ..song.clone()
+    };
+
+    Ok((first, rest, at))
+}
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use sha1::{Digest, Sha1};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::app::Song;
+
+/// Subdirectory of the staging directory holding the normalized copies that are burned.
+const DISC_DIR: &str = "disc";
+/// Burn order of the disc set, written next to it.
+const MANIFEST: &str = "manifest.txt";
+
+/// File name of the raw transcode of `song`: its ID plus a hash of everything the audio
+/// depends on, i.e. the source's path, size and modification time, and the edit. The
+/// same name means the same audio, so a transcode can be reused whenever it exists.
+/// SHA-1 rather than std's hasher, whose output may change between Rust releases.
+pub fn raw_name(song: &Song) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(song.path.as_bytes());
+    if let Ok(metadata) = std::fs::metadata(&song.path) {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        hasher.update(format!("\0{}\0{}", metadata.len(), modified.as_nanos()));
+    }
+    let edit = &song.edit;
+    hasher.update(format!(
+        "\0{}\0{:?}\0{}\0{}",
+        edit.start_frames, edit.end_frames, edit.fade_in_secs, edit.fade_out_secs
+    ));
+    let digest = hasher.finalize();
+
+    format!(
+        "{}-{:016x}.wav",
+        song.id,
+        u64::from_be_bytes(digest[..8].try_into().unwrap_or_default())
+    )
+}
+
+pub fn disc_dir(staging: &Path) -> PathBuf {
+    staging.join(DISC_DIR)
+}
+
+/// Where the copy of each raw transcode is burned from, in playlist order. Entries
+/// are numbered so the same track can appear more than once.
+pub fn disc_paths(staging: &Path, raws: &[PathBuf]) -> Vec<PathBuf> {
+    let dir = disc_dir(staging);
+    raws.iter()
+        .enumerate()
+        .map(|(i, raw)| {
+            let name = raw.file_name().unwrap_or_default().to_string_lossy();
+            dir.join(format!("{:02}-{}", i + 1, name))
+        })
+        .collect()
+}
+
+/// Replaces the disc set with fresh copies of `raws`, leaving the raw transcodes
+/// untouched by normalization.
+pub fn copy_disc_set(staging: &Path, raws: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let dir = disc_dir(staging);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .with_context(|| format!("failed to clear {}", dir.display()))?;
+    }
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let disc = disc_paths(staging, raws);
+    for (raw, copy) in raws.iter().zip(&disc) {
+        std::fs::copy(raw, copy)
+            .with_context(|| format!("failed to copy {} to {}", raw.display(), copy.display()))?;
+    }
+
+    Ok(disc)
+}
+
+/// Whether the WAV at `path` holds any samples. ffmpeg succeeds on some broken and
+/// zero-length sources, leaving just the headers.
+pub fn has_audio(path: &Path) -> Result<bool> {
+    Ok(data_bytes(path)?.is_some_and(|bytes| bytes > 0))
+}
+
+/// Length of the WAV at `path` on disc, in frames of 2352 bytes, the last one padded
+/// the way wodim's `-pad` does.
+pub fn disc_frames(path: &Path) -> Result<u64> {
+    Ok(audio_bytes(path)?.div_ceil(2352))
+}
+
+/// Length of the WAV at `path` in samples per channel, as staged at 44.1 kHz 16-bit
+/// stereo.
+pub fn samples(path: &Path) -> Result<u64> {
+    Ok(audio_bytes(path)? / 4)
+}
+
+/// Bytes of audio in the WAV at `path`, read from its headers.
+fn audio_bytes(path: &Path) -> Result<u64> {
+    data_bytes(path)?.with_context(|| format!("{} has no audio data", path.display()))
+}
+
+/// Bytes of audio in the WAV at `path`, or `None` when it has no data chunk. Only the
+/// headers are read, however long the file.
+fn data_bytes(path: &Path) -> Result<Option<u64>> {
+    use std::io::Read;
+
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let len = file
+        .metadata()
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .len();
+    // the chunks before the samples are small, so the headers are all in here
+    let mut header = vec![];
+    file.by_ref()
+        .take(64 * 1024)
+        .read_to_end(&mut header)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    let mut offset = 12;
+    while let (Some(id), Some(size)) = (
+        header.get(offset..offset + 4),
+        header.get(offset + 4..offset + 8),
+    ) {
+        // SAFETY: `get(offset + 4..offset + 8)` is four bytes long
+        let size = u32::from_le_bytes(size.try_into().unwrap()) as u64;
+        if id == b"data" {
+            // what is actually there, should the header overstate it
+            return Ok(Some(size.min(len.saturating_sub(offset as u64 + 8))));
+        }
+        offset += 8 + size as usize + size as usize % 2;
+    }
+
+    Ok(None)
+}
+
+/// The samples of the WAV at `path`, without its headers.
+pub fn read_samples(path: &Path) -> Result<Vec<u8>> {
+    let wav = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut offset = 12;
+    while let (Some(id), Some(size)) =
+        (wav.get(offset..offset + 4), wav.get(offset + 4..offset + 8))
+    {
+        // SAFETY: `get(offset + 4..offset + 8)` is four bytes long
+        let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+        if id == b"data" {
+            let end = (offset + 8).saturating_add(size).min(wav.len());
+            return Ok(wav[offset + 8..end].to_vec());
+        }
+        offset += 8 + size + size % 2;
+    }
+
+    anyhow::bail!("{} has no audio data", path.display())
+}
+
+/// Records which file holds which playlist entry, in burn order.
+pub fn write_manifest(staging: &Path, playlist: &[Song], disc: &[PathBuf]) -> Result<()> {
+    let manifest = playlist
+        .iter()
+        .zip(disc)
+        .enumerate()
+        .map(|(i, (song, wav))| {
+            format!(
+                "{:02}\t{}\t{}\t{}\n",
+                i + 1,
+                song.id,
+                wav.file_name().unwrap_or_default().to_string_lossy(),
+                song.title
+            )
+        })
+        .collect::<String>();
+
+    let path = disc_dir(staging).join(MANIFEST);
+    std::fs::write(&path, manifest).with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,62 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+static EMOJI: AtomicBool = AtomicBool::new(false);
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Decides how shell output is decorated. Color and emoji are only used when stdout
+/// and stderr are both terminals and `TERM` isn't `dumb`; color also honors `NO_COLOR`.
+/// `accessible` output has neither, see [`accessible`].
+pub fn init(no_color: bool, no_emoji: bool, accessible: bool) {
+    let terminal = std::io::stdout().is_terminal()
+        && std::io::stderr().is_terminal()
+        && std::env::var("TERM").is_ok_and(|term| term != "dumb")
+        && !accessible;
+    let color = terminal && !no_color && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty());
+    COLOR.store(color, Ordering::Relaxed);
+    EMOJI.store(terminal && !no_emoji, Ordering::Relaxed);
+    ACCESSIBLE.store(accessible, Ordering::Relaxed);
+}
+
+/// Whether output is meant for a screen reader: plain lines instead of aligned tables
+/// and progress redrawn in place, and changes to the playlist or the TUI's focus said
+/// in words.
+pub fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn mark(emoji: &str, text: &str) -> String {
+    if EMOJI.load(Ordering::Relaxed) {
+        format!("{} {}", emoji, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// `Error:` label in front of a failure.
+pub fn error_label() -> String {
+    mark("❌", &paint("1;31", "Error:"))
+}
+
+/// Label in front of a suggested fix.
+pub fn hint_label() -> String {
+    if EMOJI.load(Ordering::Relaxed) {
+        String::from("💡")
+    } else {
+        paint("1;33", "hint:")
+    }
+}
+
+/// `text` announcing that a task finished.
+pub fn success(text: &str) -> String {
+    mark("✅", &paint("32", text))
+}
@@ -0,0 +1,479 @@
+use anyhow::{Context, Result};
+use lofty::{
+    config::WriteOptions,
+    file::{AudioFile, TaggedFileExt},
+    probe::Probe,
+    tag::{Accessor, ItemKey, Tag},
+};
+use rusqlite::{Connection, params};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::errors::DbError;
+
+/// A tag that can be corrected with `tag set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    Track,
+    Year,
+    Genre,
+    /// Language of the lyrics, e.g. `eng`, as tagged.
+    Language,
+    /// `yes` for tracks without vocals, `no` for songs, unset when nobody has said.
+    Instrumental,
+}
+
+impl std::str::FromStr for TagField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "title" => Ok(TagField::Title),
+            "artist" => Ok(TagField::Artist),
+            "album" => Ok(TagField::Album),
+            "track" => Ok(TagField::Track),
+            "year" => Ok(TagField::Year),
+            "genre" => Ok(TagField::Genre),
+            "language" => Ok(TagField::Language),
+            "instrumental" => Ok(TagField::Instrumental),
+            _ => anyhow::bail!(
+                "unknown tag \"{}\": expected one of title / artist / album / track / year / genre / language / instrumental",
+                s
+            ),
+        }
+    }
+}
+
+impl TagField {
+    pub fn name(self) -> &'static str {
+        match self {
+            TagField::Title => "title",
+            TagField::Artist => "artist",
+            TagField::Album => "album",
+            TagField::Track => "track",
+            TagField::Year => "year",
+            TagField::Genre => "genre",
+            TagField::Language => "language",
+            TagField::Instrumental => "instrumental",
+        }
+    }
+
+    /// SQL reading the field as the text `tag set` takes, for comparing and showing.
+    pub fn text_sql(self) -> String {
+        match self {
+            TagField::Instrumental => {
+                String::from("CASE instrumental WHEN 1 THEN 'yes' WHEN 0 THEN 'no' END")
+            }
+            field => format!("CAST({} AS TEXT)", field.name()),
+        }
+    }
+
+    /// Whether the field is indexed for full-text search.
+    fn searchable(self) -> bool {
+        matches!(self, TagField::Title | TagField::Artist | TagField::Album)
+    }
+
+    fn is_number(self) -> bool {
+        matches!(self, TagField::Track | TagField::Year)
+    }
+}
+
+/// The language code taggers such as MusicBrainz Picard give tracks without lyrics.
+pub const NO_LINGUISTIC_CONTENT: &str = "zxx";
+
+/// The instrumental flag in `value`: `yes`, `no`, or empty for not known.
+pub fn parse_flag(value: &str) -> Result<Option<bool>> {
+    match value.to_lowercase().as_str() {
+        "yes" => Ok(Some(true)),
+        "no" => Ok(Some(false)),
+        "" => Ok(None),
+        _ => anyhow::bail!("expected yes or no for instrumental, got \"{}\"", value),
+    }
+}
+
+/// Tracks whose tags match every `field:value` term, ignoring case, e.g.
+/// `artist:The Beatles album:Revolver`. Words without a field continue the previous
+/// term's value.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    terms: Vec<(TagField, String)>,
+}
+
+impl std::str::FromStr for Filter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut terms: Vec<(TagField, String)> = vec![];
+        for word in s.split_whitespace() {
+            let field = word
+                .split_once(':')
+                .and_then(|(field, value)| Some((field.parse().ok()?, value)));
+            match (field, terms.last_mut()) {
+                (Some((field, value)), _) => terms.push((field, value.to_string())),
+                (None, Some((_, value))) => {
+                    value.push(' ');
+                    value.push_str(word);
+                }
+                (None, None) => anyhow::bail!("expected field:value, got \"{}\"", word),
+            }
+        }
+        anyhow::ensure!(!terms.is_empty(), "expected at least one field:value term");
+
+        Ok(Filter { terms })
+    }
+}
+
+/// One tag of one track going from `old` to `new`.
+#[derive(Debug, Clone)]
+pub struct TagChange {
+    pub id: i64,
+    pub field: TagField,
+    pub old: String,
+    pub new: String,
+}
+
+impl TagChange {
+    /// The change that undoes this one.
+    pub fn reversed(&self) -> TagChange {
+        TagChange {
+            old: self.new.clone(),
+            new: self.old.clone(),
+            ..*self
+        }
+    }
+}
+
+/// The changes setting each of `sets` on every track matching `filter` would make,
+/// leaving out tags that already have the value.
+pub fn plan_bulk(
+    conn: &Connection,
+    filter: &Filter,
+    sets: &[(TagField, String)],
+) -> Result<Vec<TagChange>> {
+    let columns = sets
+        .iter()
+        .map(|(field, _)| format!(", {}", field.text_sql()))
+        .collect::<String>();
+    let conditions = filter
+        .terms
+        .iter()
+        .enumerate()
+        .map(|(i, (field, _))| format!("{} = ?{} COLLATE NOCASE", field.text_sql(), i + 1))
+        .collect::<Vec<_>>()
+        .join(" AND ");
+    let sql = format!("SELECT id{columns} FROM tracks WHERE {conditions} ORDER BY id");
+
+    let mut stmt = conn
+        .prepare(&sql)
+        .context("failed to prepare bulk tag query")?;
+    let params = rusqlite::params_from_iter(filter.terms.iter().map(|(_, value)| value));
+    let rows = stmt
+        .query_map(params, |row| {
+            let id: i64 = row.get(0)?;
+            let old = (0..sets.len())
+                .map(|i| Ok(row.get::<_, Option<String>>(i + 1)?.unwrap_or_default()))
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok((id, old))
+        })
+        .context("failed to query tracks matching the filter")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read tracks matching the filter")?;
+
+    Ok(rows
+        .into_iter()
+        .flat_map(|(id, old)| {
+            sets.iter()
+                .zip(old)
+                .filter(|((_, new), old)| new != old)
+                .map(move |((field, new), old)| TagChange {
+                    id,
+                    field: *field,
+                    old,
+                    new: new.clone(),
+                })
+        })
+        .collect())
+}
+
+/// Every stored tag and property of track `id`, labelled, for `tag show`.
+pub fn show(conn: &Connection, id: i64) -> Result<Vec<(&'static str, String)>> {
+    let sql = "SELECT path, title, artist, album, track, year, genre, duration_sec,
+        bitrate_kbps, sample_rate_hz, bit_depth, bpm, musical_key, note, loudness_db,
+        language, CASE instrumental WHEN 1 THEN 'yes' WHEN 0 THEN 'no' END
+        FROM tracks WHERE id = ?1";
+    conn.query_row(sql, params![id], |row| {
+        let text = |i: usize| -> rusqlite::Result<String> {
+            Ok(row.get::<_, Option<String>>(i)?.unwrap_or_default())
+        };
+        let number = |i: usize| -> rusqlite::Result<String> {
+            Ok(row
+                .get::<_, Option<i64>>(i)?
+                .map_or_else(String::new, |n| n.to_string()))
+        };
+        Ok(vec![
+            ("path", text(0)?),
+            ("title", text(1)?),
+            ("artist", text(2)?),
+            ("album", text(3)?),
+            ("track", number(4)?),
+            ("year", number(5)?),
+            ("genre", text(6)?),
+            ("duration", number(7)?),
+            ("bitrate kbps", number(8)?),
+            ("sample rate", number(9)?),
+            ("bit depth", number(10)?),
+            (
+                "bpm",
+                row.get::<_, Option<f64>>(11)?
+                    .map_or_else(String::new, |bpm| format!("{:.1}", bpm)),
+            ),
+            ("key", text(12)?),
+            ("note", text(13)?),
+            (
+                "loudness",
+                row.get::<_, Option<f64>>(14)?
+                    .map_or_else(String::new, |db| format!("{:.1} dBFS", db)),
+            ),
+            ("language", text(15)?),
+            ("instrumental", text(16)?),
+        ])
+    })
+    .map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => DbError::TrackNotFound(id),
+        err => DbError::Query(err),
+    })
+    .map_err(Into::into)
+}
+
+/// Sets `field` of track `id` to `value` in the library, keeping the search index in
+/// step, and in the file's own tags as well when `write_file` is set. The file is
+/// written first so a failure there leaves the library untouched. An empty value
+/// clears a number, which is stored as 0 the way a scan stores a missing one.
+pub fn set(
+    conn: &mut Connection,
+    id: i64,
+    field: TagField,
+    value: &str,
+    write_file: bool,
+) -> Result<()> {
+    let number = parse_number(field, value)?;
+    let path = track_path(conn, id)?;
+    if write_file {
+        write_tag(&path, field, value, number)
+            .with_context(|| format!("failed to write tags to {}", path))?;
+    }
+
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for updating tags")?;
+    update(&tx, id, field, value, number)?;
+    tx.commit().context("failed to commit tag update")
+}
+
+/// Sets every change's `new` value as [`set`] does, in the library all at once or not
+/// at all. When a file can't be written, the files written before it get their `old`
+/// values back as far as they can, and the library is left untouched.
+pub fn set_batch(conn: &mut Connection, changes: &[TagChange], write_file: bool) -> Result<()> {
+    let mut planned = vec![];
+    for change in changes {
+        let number = parse_number(change.field, &change.new)?;
+        planned.push((change, number, track_path(conn, change.id)?));
+    }
+
+    if write_file {
+        for (i, (change, number, path)) in planned.iter().enumerate() {
+            let written = write_tag(path, change.field, &change.new, *number)
+                .with_context(|| format!("failed to write tags to {}", path));
+            if let Err(err) = written {
+                for (change, _, path) in planned[..i].iter().rev() {
+                    if let Ok(number) = parse_number(change.field, &change.old) {
+                        let _ = write_tag(path, change.field, &change.old, number);
+                    }
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for updating tags")?;
+    for (change, number, _) in &planned {
+        update(&tx, change.id, change.field, &change.new, *number)?;
+    }
+    tx.commit().context("failed to commit tag update")
+}
+
+/// `value` as the number `field` holds, if it holds one: 0 for an empty value.
+fn parse_number(field: TagField, value: &str) -> Result<Option<u32>> {
+    if !field.is_number() {
+        return Ok(None);
+    }
+    if value.is_empty() {
+        return Ok(Some(0));
+    }
+    value
+        .parse::<u32>()
+        .map(Some)
+        .with_context(|| format!("expected a number for {}", field.name()))
+}
+
+fn track_path(conn: &Connection, id: i64) -> Result<String> {
+    conn.query_row(
+        "SELECT path FROM tracks WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => DbError::TrackNotFound(id),
+        err => DbError::Query(err),
+    })
+    .map_err(Into::into)
+}
+
+/// Stores `value` (or its `number`) as `field` of track `id` within `tx`.
+fn update(
+    tx: &rusqlite::Transaction,
+    id: i64,
+    field: TagField,
+    value: &str,
+    number: Option<u32>,
+) -> Result<()> {
+    let column = field.name();
+    match field {
+        _ if field.is_number() => tx.execute(
+            &format!("UPDATE tracks SET {column} = ?1 WHERE id = ?2"),
+            params![number, id],
+        ),
+        TagField::Instrumental => tx.execute(
+            "UPDATE tracks SET instrumental = ?1 WHERE id = ?2",
+            params![parse_flag(value)?, id],
+        ),
+        _ => tx.execute(
+            &format!("UPDATE tracks SET {column} = ?1 WHERE id = ?2"),
+            params![value, id],
+        ),
+    }
+    .with_context(|| format!("failed to update {} of track {}", column, id))?;
+    if field.searchable() {
+        tx.execute(
+            &format!("UPDATE tracks_fts SET {column} = ?1 WHERE id = ?2"),
+            params![value, id],
+        )
+        .context("failed to update the search index")?;
+    }
+    if field == TagField::Artist {
+        crate::totals::invalidate(tx)?;
+    }
+
+    Ok(())
+}
+
+/// The free-text note kept on track `id`, if it has one.
+pub fn note(conn: &Connection, id: i64) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT note FROM tracks WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => DbError::TrackNotFound(id),
+        err => DbError::Query(err),
+    })
+    .map_err(Into::into)
+}
+
+/// Keeps `note` on track `id`, e.g. why this master was picked for an archival disc. The
+/// note lives only in the library, never in the file; an empty note removes it.
+pub fn set_note(conn: &Connection, id: i64, note: &str) -> Result<()> {
+    let note = Some(note.trim()).filter(|note| !note.is_empty());
+    let updated = conn
+        .execute(
+            "UPDATE tracks SET note = ?1 WHERE id = ?2",
+            params![note, id],
+        )
+        .with_context(|| format!("failed to update note of track {}", id))?;
+    anyhow::ensure!(updated > 0, DbError::TrackNotFound(id));
+
+    Ok(())
+}
+
+/// Hash of the pictures embedded in the file at `path`, or `None` when it has none.
+pub fn art_fingerprint(path: &str) -> Result<Option<u64>> {
+    let file = Probe::open(path)?.read()?;
+    let Some(tag) = file.primary_tag() else {
+        return Ok(None);
+    };
+    if tag.pictures().is_empty() {
+        return Ok(None);
+    }
+
+    let mut hasher = DefaultHasher::new();
+    for picture in tag.pictures() {
+        picture.data().hash(&mut hasher);
+    }
+
+    Ok(Some(hasher.finish()))
+}
+
+/// The album artist tagged in the file at `path`, if it names one.
+pub fn album_artist(path: &str) -> Result<Option<String>> {
+    let file = Probe::open(path)?.read()?;
+
+    Ok(file
+        .primary_tag()
+        .and_then(|tag| tag.get_string(&ItemKey::AlbumArtist))
+        .map(str::trim)
+        .filter(|artist| !artist.is_empty())
+        .map(str::to_string))
+}
+
+/// Writes the tag to the file's primary tag, creating one if it has none. The artist
+/// goes wherever the scan read it from: the album artist when the file has one. An
+/// instrumental is marked by [`NO_LINGUISTIC_CONTENT`] as its language.
+fn write_tag(path: &str, field: TagField, value: &str, number: Option<u32>) -> Result<()> {
+    let mut file = Probe::open(path)?.read()?;
+    if file.primary_tag().is_none() {
+        file.insert_tag(Tag::new(file.primary_tag_type()));
+    }
+    // SAFETY: inserted just above if missing
+    let tag = file.primary_tag_mut().unwrap();
+
+    let value = value.to_string();
+    match (field, number) {
+        (TagField::Title, _) => tag.set_title(value),
+        (TagField::Artist, _) if tag.get(&ItemKey::AlbumArtist).is_some() => {
+            tag.insert_text(ItemKey::AlbumArtist, value);
+        }
+        (TagField::Artist, _) => tag.set_artist(value),
+        (TagField::Album, _) => tag.set_album(value),
+        (TagField::Genre, _) => tag.set_genre(value),
+        (TagField::Language, _) if value.is_empty() => tag.remove_key(&ItemKey::Language),
+        (TagField::Language, _) => {
+            tag.insert_text(ItemKey::Language, value);
+        }
+        (TagField::Instrumental, _) if parse_flag(&value)? == Some(true) => {
+            tag.insert_text(ItemKey::Language, NO_LINGUISTIC_CONTENT.to_string());
+        }
+        (TagField::Instrumental, _) => {
+            let marked = tag
+                .get_string(&ItemKey::Language)
+                .is_some_and(|language| language.eq_ignore_ascii_case(NO_LINGUISTIC_CONTENT));
+            if marked {
+                tag.remove_key(&ItemKey::Language);
+            }
+        }
+        (TagField::Track, Some(0)) => tag.remove_track(),
+        (TagField::Track, Some(track)) => tag.set_track(track),
+        (TagField::Year, Some(0)) => tag.remove_year(),
+        (TagField::Year, Some(year)) => tag.set_year(year),
+        (TagField::Track | TagField::Year, None) => unreachable!("numbers are parsed first"),
+    }
+
+    file.save_to_path(path, WriteOptions::default())?;
+
+    Ok(())
+}
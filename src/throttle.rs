@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// I/O scheduling applied with `ionice`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriority {
+    /// Only touches the disk when nothing else wants it.
+    Idle,
+    /// Best-effort class at level 0 (highest) to 7 (lowest).
+    BestEffort(u8),
+}
+
+impl std::str::FromStr for IoPriority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "idle" => Ok(IoPriority::Idle),
+            level => match level.parse::<u8>() {
+                Ok(level) if level <= 7 => Ok(IoPriority::BestEffort(level)),
+                _ => anyhow::bail!("expected idle or a level from 0 to 7, got \"{}\"", s),
+            },
+        }
+    }
+}
+
+/// Limits on the ffmpeg and normalize processes run while staging a disc, so that
+/// staging doesn't make the rest of the machine unusable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    /// Niceness passed to `nice -n`.
+    pub nice: Option<i32>,
+    pub io: Option<IoPriority>,
+    /// Tracks transcoded at the same time.
+    pub jobs: usize,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Throttle {
+            nice: None,
+            io: None,
+            jobs: 1,
+        }
+    }
+}
+
+impl Throttle {
+    /// `command` run under `nice` and `ionice` as configured.
+    pub fn wrap(&self, command: Command) -> Command {
+        let mut prefix: Vec<String> = vec![];
+        if let Some(nice) = self.nice {
+            prefix.extend(["nice".into(), "-n".into(), nice.to_string()]);
+        }
+        match self.io {
+            Some(IoPriority::Idle) => prefix.extend(["ionice".into(), "-c".into(), "3".into()]),
+            Some(IoPriority::BestEffort(level)) => prefix.extend([
+                "ionice".into(),
+                "-c".into(),
+                "2".into(),
+                "-n".into(),
+                level.to_string(),
+            ]),
+            None => (),
+        }
+        if prefix.is_empty() {
+            return command;
+        }
+
+        let mut wrapped = Command::new(&prefix[0]);
+        wrapped
+            .args(&prefix[1..])
+            .arg(command.get_program())
+            .args(command.get_args());
+        if let Some(dir) = command.get_current_dir() {
+            wrapped.current_dir(dir);
+        }
+
+        wrapped
+    }
+
+    /// Applies a `nice`, `ionice` or `jobs` setting from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "nice" => {
+                let nice = value
+                    .parse::<i32>()
+                    .context("expected a niceness from -20 to 19")?;
+                anyhow::ensure!(
+                    (-20..=19).contains(&nice),
+                    "niceness must be from -20 to 19"
+                );
+                self.nice = Some(nice);
+            }
+            "ionice" => self.io = Some(value.parse()?),
+            "jobs" => {
+                self.jobs = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&jobs| jobs > 0)
+                    .context("expected a job count of at least 1")?;
+            }
+            _ => anyhow::bail!("unknown throttle setting \"{}\"", key),
+        }
+
+        Ok(())
+    }
+}
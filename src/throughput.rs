@@ -0,0 +1,63 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::time::Duration;
+
+use crate::events::BurnStage;
+use crate::paths;
+
+/// Past runs considered when estimating, most recent first.
+const HISTORY_LEN: u32 = 20;
+
+/// Timings of past transcodes and burns, kept in the library database.
+pub struct Throughput {
+    conn: Connection,
+}
+
+impl Throughput {
+    /// Opens its own connection, so it can be used from background tasks.
+    pub fn open() -> Result<Self> {
+        let conn = Connection::open(paths::db()).context("failed to open library database")?;
+        Ok(Throughput { conn })
+    }
+
+    /// Records that `stage` got through `audio_secs` of audio in `elapsed`.
+    pub fn record(&self, stage: BurnStage, audio_secs: u64, elapsed: Duration) -> Result<()> {
+        if audio_secs == 0 || elapsed.is_zero() {
+            return Ok(());
+        }
+
+        self.conn
+            .execute(
+                "INSERT INTO throughput (stage, audio_secs, wall_secs) VALUES (?1, ?2, ?3)",
+                params![format!("{:?}", stage), audio_secs, elapsed.as_secs_f64()],
+            )
+            .context("failed to record throughput")?;
+
+        Ok(())
+    }
+
+    /// Seconds of audio `stage` has recently processed per second, if it has run before.
+    pub fn rate(&self, stage: BurnStage) -> Result<Option<f64>> {
+        let (audio, wall): (Option<f64>, Option<f64>) = self
+            .conn
+            .query_row(
+                "SELECT SUM(audio_secs), SUM(wall_secs) FROM (
+                    SELECT audio_secs, wall_secs FROM throughput
+                    WHERE stage = ?1 ORDER BY id DESC LIMIT ?2
+                )",
+                params![format!("{:?}", stage), HISTORY_LEN],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .context("failed to read throughput")?;
+
+        Ok(audio
+            .zip(wall)
+            .filter(|(_, wall)| *wall > 0.0)
+            .map(|(audio, wall)| audio / wall))
+    }
+}
+
+/// Wall-clock seconds to get through `audio_secs` at `rate`.
+pub fn eta_secs(rate: Option<f64>, audio_secs: u64) -> Option<u64> {
+    rate.map(|rate| (audio_secs as f64 / rate).ceil() as u64)
+}
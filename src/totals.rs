@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::app;
+
+/// How big the library is, as shown under the artists pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LibraryTotals {
+    /// Artists as browsed, so merged spellings count once.
+    pub artists: u64,
+    pub tracks: u64,
+    pub duration_secs: u64,
+}
+
+impl LibraryTotals {
+    /// `N artists, M tracks, H:MM total`.
+    pub fn label(&self) -> String {
+        let minutes = self.duration_secs / 60;
+        format!(
+            "{} artist{}, {} track{}, {}:{:02} total",
+            self.artists,
+            if self.artists == 1 { "" } else { "s" },
+            self.tracks,
+            if self.tracks == 1 { "" } else { "s" },
+            minutes / 60,
+            minutes % 60
+        )
+    }
+}
+
+/// The library's totals, from the cache when it is there. Otherwise they are counted
+/// and cached until the library next changes, except in read-only mode.
+pub fn get(conn: &Connection) -> Result<LibraryTotals> {
+    let cached = conn
+        .query_row(
+            "SELECT artists, tracks, duration_secs FROM library_totals",
+            [],
+            |row| {
+                Ok(LibraryTotals {
+                    artists: row.get(0)?,
+                    tracks: row.get(1)?,
+                    duration_secs: row.get(2)?,
+                })
+            },
+        )
+        .optional();
+    // a read-only library may predate the table
+    if let Ok(Some(totals)) = cached {
+        return Ok(totals);
+    }
+
+    let totals = conn
+        .query_row(
+            "SELECT COUNT(DISTINCT COALESCE(canonical, artist)), COUNT(*),
+                COALESCE(SUM(duration_sec), 0)
+            FROM tracks LEFT JOIN artist_aliases ON alias = artist",
+            [],
+            |row| {
+                Ok(LibraryTotals {
+                    artists: row.get(0)?,
+                    tracks: row.get(1)?,
+                    duration_secs: row.get(2)?,
+                })
+            },
+        )
+        .context("failed to total the library")?;
+    if !app::read_only() {
+        conn.execute(
+            "INSERT INTO library_totals (id, artists, tracks, duration_secs)
+            VALUES (0, ?1, ?2, ?3)
+            ON CONFLICT (id) DO UPDATE SET artists = excluded.artists,
+                tracks = excluded.tracks, duration_secs = excluded.duration_secs",
+            params![totals.artists, totals.tracks, totals.duration_secs],
+        )
+        .context("failed to cache the library totals")?;
+    }
+
+    Ok(totals)
+}
+
+/// Drops the cached totals after the library's tracks or artists change.
+pub fn invalidate(conn: &Connection) -> Result<()> {
+    conn.execute("DELETE FROM library_totals", [])
+        .context("failed to clear the cached library totals")?;
+    Ok(())
+}
@@ -0,0 +1,426 @@
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::history;
+
+/// First line of an export, checked on import so other files aren't misread.
+const HEADER: &str = "# vol_28 user metadata 1";
+
+/// How many of each kind of entry an export wrote or an import applied.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counts {
+    pub notes: usize,
+    /// Languages and instrumental flags.
+    pub flags: usize,
+    pub aliases: usize,
+    pub searches: usize,
+    pub burns: usize,
+    /// Track entries of an import that matched no track in the library.
+    pub unmatched: usize,
+}
+
+impl Counts {
+    /// `N notes, M languages and flags, ...`, with the unmatched entries when there are any.
+    pub fn label(&self) -> String {
+        let mut label = format!(
+            "{} notes, {} languages and flags, {} artist merges, {} saved searches, {} burns",
+            self.notes, self.flags, self.aliases, self.searches, self.burns
+        );
+        if self.unmatched > 0 {
+            label += &format!(
+                "; {} track entries matched no track in the library",
+                self.unmatched
+            );
+        }
+        label
+    }
+}
+
+/// How an exported track is found again: by its path, relative to the music directory
+/// when it is inside it, or else by the fingerprint of its tags.
+struct TrackKey {
+    path: String,
+    fingerprint: String,
+}
+
+impl TrackKey {
+    fn new(path: &str, tags: &TrackTags, music_dir: Option<&Path>) -> TrackKey {
+        let path = music_dir
+            .and_then(|dir| Path::new(path).strip_prefix(dir).ok())
+            .map_or_else(
+                || path.to_string(),
+                |rel| rel.to_string_lossy().into_owned(),
+            );
+        TrackKey {
+            path,
+            fingerprint: tags.fingerprint(),
+        }
+    }
+}
+
+struct TrackTags {
+    artist: String,
+    album: String,
+    title: String,
+    track: u32,
+}
+
+impl TrackTags {
+    /// FNV-1a of the tags, ignoring case, so it comes out the same on any machine and
+    /// build, unlike the standard library's hasher.
+    fn fingerprint(&self) -> String {
+        let text = format!(
+            "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+            self.artist, self.album, self.title, self.track
+        )
+        .to_lowercase();
+        let hash = text.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+}
+
+/// Every track's ID with its path and tags.
+fn library_tracks(conn: &Connection) -> Result<Vec<(i64, String, TrackTags)>> {
+    let mut stmt = conn
+        .prepare("SELECT id, path, artist, album, title, track FROM tracks")
+        .context("failed to prepare query for the library's tracks")?;
+    stmt.query_map([], |row| {
+        Ok((
+            row.get(0)?,
+            row.get(1)?,
+            TrackTags {
+                artist: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                album: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                title: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                track: row.get::<_, Option<u32>>(5)?.unwrap_or_default(),
+            },
+        ))
+    })
+    .context("failed to query the library's tracks")?
+    .collect::<Result<Vec<_>, _>>()
+    .context("failed to read the library's tracks")
+}
+
+/// Fields are tab-separated, so tabs, newlines and backslashes in them are escaped.
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut text = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => text.push('\t'),
+            Some('n') => text.push('\n'),
+            Some(c) => text.push(c),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+fn line(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|field| escape(field))
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// Writes what was curated by hand to `path`: notes, languages and instrumental flags,
+/// artist merges, saved searches, and the discs burned with their tracks, leaving out
+/// their logs. Tracks are keyed so [`import`] finds them after a rebuild or under
+/// another music directory.
+pub fn export(conn: &Connection, music_dir: Option<&Path>, path: &Path) -> Result<Counts> {
+    let mut counts = Counts::default();
+    let mut lines = vec![HEADER.to_string()];
+
+    let keys = library_tracks(conn)?
+        .into_iter()
+        .map(|(id, path, tags)| (id, TrackKey::new(&path, &tags, music_dir)))
+        .collect::<HashMap<_, _>>();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, note, language, CASE instrumental WHEN 1 THEN 'yes' WHEN 0 THEN 'no' END
+            FROM tracks
+            WHERE note IS NOT NULL OR language IS NOT NULL OR instrumental IS NOT NULL
+            ORDER BY id",
+        )
+        .context("failed to prepare query for notes and flags")?;
+    let curated = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .context("failed to query notes and flags")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read notes and flags")?;
+    for (id, note, language, instrumental) in curated {
+        let key = &keys[&id];
+        for (kind, value) in [
+            ("note", note),
+            ("language", language),
+            ("instrumental", instrumental),
+        ] {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                lines.push(line(&[kind, &key.path, &key.fingerprint, &value]));
+                match kind {
+                    "note" => counts.notes += 1,
+                    _ => counts.flags += 1,
+                }
+            }
+        }
+    }
+
+    for (alias, canonical) in crate::aliases::list(conn)? {
+        lines.push(line(&["alias", &alias, &canonical]));
+        counts.aliases += 1;
+    }
+    for (name, query) in history::saved()? {
+        lines.push(line(&["search", &name, &query]));
+        counts.searches += 1;
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, burned_at, ok, tracks, duration_secs, title, series, volume, disc_id,
+                freedb_id, toc
+            FROM burns ORDER BY id",
+        )
+        .context("failed to prepare query for the burn history")?;
+    let burns = stmt
+        .query_map([], |row| {
+            let text = |i: usize| -> rusqlite::Result<String> {
+                Ok(row
+                    .get::<_, Option<rusqlite::types::Value>>(i)?
+                    .map_or_else(String::new, |value| match value {
+                        rusqlite::types::Value::Integer(n) => n.to_string(),
+                        rusqlite::types::Value::Text(text) => text,
+                        _ => String::new(),
+                    }))
+            };
+            Ok((
+                row.get::<_, i64>(0)?,
+                (1..=10).map(text).collect::<rusqlite::Result<Vec<_>>>()?,
+            ))
+        })
+        .context("failed to query the burn history")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to read the burn history")?;
+    for (id, fields) in burns {
+        let mut burn = vec!["burn"];
+        burn.extend(fields.iter().map(String::as_str));
+        lines.push(line(&burn));
+        for track_id in crate::burns::tracks(conn, id)? {
+            // a track removed from the library since can't be keyed any more
+            if let Some(key) = keys.get(&track_id) {
+                lines.push(line(&["burned", &key.path, &key.fingerprint]));
+            }
+        }
+        counts.burns += 1;
+    }
+
+    let contents = lines
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+    std::fs::write(path, contents)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    Ok(counts)
+}
+
+/// Finds the library's tracks for exported keys: by path first, then by fingerprint
+/// when only one track has it.
+struct Matcher {
+    by_path: HashMap<String, i64>,
+    by_fingerprint: HashMap<String, Option<i64>>,
+}
+
+impl Matcher {
+    fn new(conn: &Connection) -> Result<Matcher> {
+        let mut matcher = Matcher {
+            by_path: HashMap::new(),
+            by_fingerprint: HashMap::new(),
+        };
+        for (id, path, tags) in library_tracks(conn)? {
+            matcher.by_path.insert(path, id);
+            matcher
+                .by_fingerprint
+                .entry(tags.fingerprint())
+                .and_modify(|found| *found = None)
+                .or_insert(Some(id));
+        }
+        Ok(matcher)
+    }
+
+    fn find(&self, path: &str, fingerprint: &str, music_dir: Option<&Path>) -> Option<i64> {
+        let path = match music_dir {
+            Some(dir) if Path::new(path).is_relative() => {
+                dir.join(path).to_string_lossy().into_owned()
+            }
+            _ => path.to_string(),
+        };
+        self.by_path
+            .get(&path)
+            .copied()
+            .or_else(|| self.by_fingerprint.get(fingerprint).copied().flatten())
+    }
+}
+
+/// Applies an [`export`] read from `path` to the library, replacing notes, flags and
+/// artist merges already there. Burns already in the history, by time and title, are
+/// skipped, so importing the same file twice changes nothing.
+pub fn import(conn: &mut Connection, music_dir: Option<&Path>, path: &Path) -> Result<Counts> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let mut lines = text.lines();
+    anyhow::ensure!(
+        lines.next() == Some(HEADER),
+        "{} isn't a user metadata export",
+        path.display()
+    );
+
+    let matcher = Matcher::new(conn)?;
+    let mut counts = Counts::default();
+    let mut searches = vec![];
+    // the burn that "burned" lines add tracks to, none while skipping one
+    let mut burn: Option<(i64, i64)> = None;
+    let tx = conn
+        .transaction()
+        .context("failed to obtain transaction for importing user metadata")?;
+    for (number, entry) in lines.enumerate() {
+        let fields = entry.split('\t').map(unescape).collect::<Vec<_>>();
+        let field = |i: usize| fields.get(i).map_or("", String::as_str);
+        let track = || matcher.find(field(1), field(2), music_dir);
+        match field(0) {
+            "note" | "language" | "instrumental" => {
+                let Some(id) = track() else {
+                    counts.unmatched += 1;
+                    continue;
+                };
+                match field(0) {
+                    "note" => {
+                        tx.execute(
+                            "UPDATE tracks SET note = ?1 WHERE id = ?2",
+                            params![field(3), id],
+                        )
+                        .context("failed to import note")?;
+                        counts.notes += 1;
+                    }
+                    "language" => {
+                        tx.execute(
+                            "UPDATE tracks SET language = ?1 WHERE id = ?2",
+                            params![field(3), id],
+                        )
+                        .context("failed to import language")?;
+                        counts.flags += 1;
+                    }
+                    _ => {
+                        tx.execute(
+                            "UPDATE tracks SET instrumental = ?1 WHERE id = ?2",
+                            params![crate::tags::parse_flag(field(3))?, id],
+                        )
+                        .context("failed to import instrumental flag")?;
+                        counts.flags += 1;
+                    }
+                }
+            }
+            "alias" => {
+                tx.execute(
+                    "INSERT INTO artist_aliases (alias, canonical) VALUES (?1, ?2)
+                    ON CONFLICT (alias) DO UPDATE SET canonical = excluded.canonical",
+                    params![field(1), field(2)],
+                )
+                .context("failed to merge artist")?;
+                counts.aliases += 1;
+            }
+            "search" => searches.push((field(1).to_string(), field(2).to_string())),
+            "burn" => {
+                let burned_at: i64 = field(1)
+                    .parse()
+                    .with_context(|| format!("expected a burn time on line {}", number + 2))?;
+                let known = tx
+                    .query_row(
+                        "SELECT 1 FROM burns WHERE burned_at = ?1 AND title = ?2",
+                        params![burned_at, field(5)],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+                if known {
+                    burn = None;
+                    continue;
+                }
+                let optional = |i: usize| Some(field(i)).filter(|value| !value.is_empty());
+                tx.execute(
+                    "INSERT INTO burns (burned_at, ok, tracks, duration_secs, title, series,
+                        volume, disc_id, freedb_id, toc)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        burned_at,
+                        field(2) == "1",
+                        field(3).parse::<i64>().unwrap_or(0),
+                        field(4).parse::<i64>().unwrap_or(0),
+                        field(5),
+                        optional(6),
+                        optional(7).and_then(|volume| volume.parse::<u32>().ok()),
+                        optional(8),
+                        optional(9),
+                        optional(10)
+                    ],
+                )
+                .context("failed to record burn")?;
+                burn = Some((tx.last_insert_rowid(), 0));
+                counts.burns += 1;
+            }
+            "burned" => {
+                let Some((burn_id, position)) = &mut burn else {
+                    continue;
+                };
+                *position += 1;
+                match track() {
+                    Some(id) => {
+                        tx.execute(
+                            "INSERT INTO burn_tracks (burn_id, position, track_id)
+                            VALUES (?1, ?2, ?3)",
+                            params![*burn_id, *position, id],
+                        )
+                        .context("failed to record burned track")?;
+                    }
+                    None => counts.unmatched += 1,
+                }
+            }
+            kind => anyhow::bail!("unknown entry \"{}\" on line {}", kind, number + 2),
+        }
+    }
+    if counts.aliases > 0 {
+        crate::totals::invalidate(&tx)?;
+    }
+    tx.commit()
+        .context("failed to commit imported user metadata")?;
+
+    for (name, query) in &searches {
+        history::save(name, query)?;
+    }
+    counts.searches = searches.len();
+
+    Ok(counts)
+}
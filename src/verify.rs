@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::duration;
+use crate::staging;
+
+/// Bytes in a CD audio frame.
+const FRAME_BYTES: usize = 2352;
+/// Bytes in a second of CD audio.
+const BYTES_PER_SEC: usize = 44_100 * 4;
+/// Frames at either end of the disc a drive with a read offset can't reach, and that
+/// are left out of the comparison the way AccurateRip leaves them out.
+const EDGE_FRAMES: usize = 5;
+
+/// Compares the WAVs ripped back from a burned disc with the `burned` ones it was
+/// burned from, sample for sample. Returns what differs, one line per track, or
+/// nothing when the copy is good.
+pub fn compare(burned: &[PathBuf], ripped: &[PathBuf]) -> Result<Vec<String>> {
+    if burned.len() != ripped.len() {
+        return Ok(vec![format!(
+            "the disc holds {} tracks, {} were burned",
+            ripped.len(),
+            burned.len()
+        )]);
+    }
+
+    let mut problems = vec![];
+    let last = burned.len().saturating_sub(1);
+    for (i, (burned, ripped)) in burned.iter().zip(ripped).enumerate() {
+        let expected = staging::read_samples(burned)?;
+        let actual = staging::read_samples(ripped)?;
+        // wodim pads the last frame of every track with silence
+        let padded = expected.len().div_ceil(FRAME_BYTES) * FRAME_BYTES;
+        if actual.len() < padded {
+            problems.push(format!(
+                "track {:02}: {} frames short",
+                i + 1,
+                (padded - actual.len()).div_ceil(FRAME_BYTES)
+            ));
+            continue;
+        }
+
+        let start = if i == 0 { EDGE_FRAMES * FRAME_BYTES } else { 0 };
+        let end = if i == last {
+            padded.saturating_sub(EDGE_FRAMES * FRAME_BYTES)
+        } else {
+            padded
+        };
+        let differs =
+            (start..end).find(|&byte| actual[byte] != expected.get(byte).copied().unwrap_or(0));
+        if let Some(byte) = differs {
+            problems.push(format!(
+                "track {:02}: differs from {} on",
+                i + 1,
+                duration::clock((byte / BYTES_PER_SEC) as u64)
+            ));
+        }
+    }
+
+    Ok(problems)
+}
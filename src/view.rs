@@ -1,16 +1,193 @@
 use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
+        MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{prelude::*, widgets::*};
 use std::borrow::Cow;
-use std::io;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+use std::ops::Range;
+use std::rc::Rc;
 use std::sync::mpsc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::aliases;
+use crate::app::{self, Album, AppState, Song, queries};
+use crate::art::{self, Protocol};
+use crate::burns::{self, Burn};
+use crate::cdtext::CdTextMode;
+use crate::collate;
+use crate::command;
+use crate::duration;
+use crate::events::{BurnStage, Event as TaskEvent, LogLine};
+use crate::fuzzy;
+use crate::history;
+use crate::layout::LayoutTrack;
+use crate::mix;
+use crate::normalize::NormalizeMode;
+use crate::notify::{self, TaskTitle};
+use crate::order::Plan;
+use crate::preview;
+use crate::quality::SourceQuality;
+use crate::session;
+use crate::shutdown;
+use crate::style;
+use crate::tags;
+use crate::totals;
+use crate::waveform;
+use crate::wodim::BufferStatus;
+
+// Number of peaks sampled for the waveform popup
+const WAVEFORM_BUCKETS: usize = 200;
+// Number of results shown by the fuzzy finder
+const FINDER_RESULTS: usize = 50;
+// How long the artist selection has to stay put before its tracks load, so holding
+// Up or Down doesn't query every artist on the way
+const ARTIST_SETTLE: Duration = Duration::from_millis(150);
+// How long typing has to pause before the library search runs
+const SEARCH_SETTLE: Duration = Duration::from_millis(200);
+// How often the screen is refreshed while art or a waveform is read in the background
+const LOADING_POLL: Duration = Duration::from_millis(50);
+
+// Smallest share of the width a pane can be resized down to, and the step it resizes by
+const MIN_PANE_PERCENT: u16 = 10;
+const PANE_RESIZE_STEP: u16 = 5;
+
+// Lines a burn log scrolls by on Page Up / Page Down
+const BURN_LOG_PAGE: u16 = 20;
+
+// Width of a cell in the album grid, and the cells its cover art takes when art is shown
+const ALBUM_CELL_WIDTH: u16 = 26;
+const ALBUM_ART_COLS: u16 = 12;
+const ALBUM_ART_ROWS: u16 = 6;
+
+/// Percentages of the width taken by the artists, tracks and playlist panes, written
+/// `20 60 20` or `20/60/20` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneSplit(pub [u16; 3]);
+
+impl Default for PaneSplit {
+    fn default() -> Self {
+        PaneSplit([20, 60, 20])
+    }
+}
+
+impl std::str::FromStr for PaneSplit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let widths = s
+            .split(|c: char| c == '/' || c.is_whitespace())
+            .filter(|width| !width.is_empty())
+            .map(|width| {
+                width
+                    .parse::<u16>()
+                    .with_context(|| format!("invalid pane width \"{}\"", width))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let widths: [u16; 3] = widths
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("expected three pane widths, e.g. 20 60 20"))?;
+        anyhow::ensure!(
+            widths.iter().sum::<u16>() == 100,
+            "pane widths must add up to 100"
+        );
+        anyhow::ensure!(
+            widths.iter().all(|&width| width >= MIN_PANE_PERCENT),
+            "each pane must be at least {}% wide",
+            MIN_PANE_PERCENT
+        );
+
+        Ok(PaneSplit(widths))
+    }
+}
+
+/// How the bar under the panes is laid out, from the `show_help`, `status_side` and
+/// `bottom_lines` settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BottomBar {
+    /// Show the key help for the focused pane.
+    pub show_help: bool,
+    /// Put the status on the left and the key help on the right.
+    pub status_left: bool,
+    /// Drop the border for a line of help over a line of status.
+    pub two_line: bool,
+}
+
+impl Default for BottomBar {
+    fn default() -> Self {
+        BottomBar {
+            show_help: true,
+            status_left: false,
+            two_line: false,
+        }
+    }
+}
+
+impl BottomBar {
+    /// Applies a `show_help`, `status_side` or `bottom_lines` setting from the config file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "show_help" => {
+                self.show_help = value.parse().context("expected true or false")?;
+            }
+            "status_side" => {
+                self.status_left = match value {
+                    "left" => true,
+                    "right" => false,
+                    _ => anyhow::bail!("expected left or right, got \"{}\"", value),
+                };
+            }
+            "bottom_lines" => {
+                self.two_line = match value {
+                    "2" => true,
+                    "3" => false,
+                    _ => anyhow::bail!("expected 2 or 3 lines, got \"{}\"", value),
+                };
+            }
+            _ => anyhow::bail!("unknown bottom bar setting \"{}\"", key),
+        }
+
+        Ok(())
+    }
+
+    /// Rows the bar takes: a border around one line, or one line each for the help and
+    /// the status without one.
+    fn height(&self) -> u16 {
+        match (self.two_line, self.show_help) {
+            (false, _) => 3,
+            (true, true) => 2,
+            (true, false) => 1,
+        }
+    }
+}
+
+impl PaneSplit {
+    /// Widens pane `pane` at the expense of the widest other pane, or narrows it in favour
+    /// of the narrowest. Returns false when that would take a pane below the minimum.
+    fn resize(&mut self, pane: usize, grow: bool) -> bool {
+        let others = (0..3).filter(|&i| i != pane);
+        let (from, to) = if grow {
+            // SAFETY: there are always two other panes
+            (others.max_by_key(|&i| self.0[i]).unwrap(), pane)
+        } else {
+            (pane, others.min_by_key(|&i| self.0[i]).unwrap())
+        };
+        if self.0[from] < MIN_PANE_PERCENT + PANE_RESIZE_STEP {
+            return false;
+        }
+        self.0[from] -= PANE_RESIZE_STEP;
+        self.0[to] += PANE_RESIZE_STEP;
 
-use crate::app::{self, AppState, LogLine, LogMessage, Song, queries};
+        true
+    }
+}
 
 // --- TUI APP STATE ---
 
@@ -21,6 +198,26 @@ enum ActivePane {
     Playlist,
 }
 
+impl ActivePane {
+    /// How the pane is written in the session file.
+    fn name(&self) -> &'static str {
+        match self {
+            ActivePane::Artists => "artists",
+            ActivePane::ArtistTracks => "tracks",
+            ActivePane::Playlist => "playlist",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "artists" => Some(ActivePane::Artists),
+            "tracks" => Some(ActivePane::ArtistTracks),
+            "playlist" => Some(ActivePane::Playlist),
+            _ => None,
+        }
+    }
+}
+
 struct View {
     state: AppState,
 
@@ -28,16 +225,335 @@ struct View {
     active_pane: ActivePane,
 
     artists: WrappingList<String>,
+    // "Name (12 albums, 143 tracks)" for each artist, worked out once at startup
+    artist_labels: Vec<String>,
+    // "N artists, M tracks, H:MM total", under the artists
+    library_totals: String,
+    // Artist scrolled to and when, whose tracks load once scrolling pauses
+    pending_artist: Option<(usize, Instant)>,
 
     tracks: WrappingTable<Song>,
 
     playlist: WrappingTableState,
+    // Set while the previous session waits to be restored with R. Autosaving would
+    // overwrite it, so nothing is saved until it is restored or a new playlist started
+    session_pending: bool,
+    // Selection in the audition list, shown in place of the playlist while set
+    audition: Option<WrappingTableState>,
 
     // Help Tabs
     help: &'static [&'static str],
 
     // Feedback
     status_msg: Cow<'static, str>,
+    // What a burn targets, shown along the bottom
+    target_summary: String,
+    // Title the playlist would be burned under, shown under it; worked out again when
+    // the title, the series or the past burns change rather than on every frame
+    disc_title: String,
+    // Tracks a burn found unplayable, read again whenever the library may have changed
+    // rather than on every frame
+    unplayable: HashMap<i64, String>,
+
+    // Waveform popup for the selected track: (title, peaks), with no peaks until they
+    // have been read in the background
+    waveform: Option<(String, Vec<u64>)>,
+    waveform_loading: Option<mpsc::Receiver<Result<Vec<u64>>>>,
+
+    // Details popup for the focused track
+    details: Option<Details>,
+
+    // Ctrl+P fuzzy finder overlay
+    finder: Option<Finder>,
+    // What the finder searches, loaded on first use and kept until the library changes
+    finder_candidates: Option<Rc<Candidates>>,
+
+    // Transition preview playing in the background, so the TUI stays usable meanwhile
+    transition: Option<JoinHandle<Result<()>>>,
+
+    // Ctrl+R list of the tracks previewed most recently, with when each was last heard
+    recent: Option<WrappingList<(Song, String)>>,
+
+    // Ctrl+A grid of every album, and the protocol its cover art is drawn with, if any
+    album_grid: Option<AlbumGrid>,
+    art: Option<Protocol>,
+
+    // Ctrl+L list of past burns, and the log of the one opened from it with how far
+    // it is scrolled
+    burns: Option<WrappingList<Burn>>,
+    burn_log: Option<(String, Vec<String>, u16)>,
+
+    // Picker between copies of the same track, best source first
+    version_picker: Option<WrappingList<(Song, SourceQuality)>>,
+    // Playlist entry the picked version replaces; the version is added when there is none
+    version_target: Option<usize>,
+
+    // Single-line text prompt overlay
+    prompt: Option<Prompt>,
+
+    // Yes/no question asked before an action the config wants confirmed
+    confirm: Option<(String, ConfirmAction)>,
+
+    // '/' filter over the playlist pane, and whether it is still being typed
+    playlist_filter: String,
+    editing_filter: bool,
+
+    // '/' search over the whole library, shown in the tracks pane instead of the selected
+    // artist's tracks, whether it is still being typed, since when it has changed
+    // without running, and the past searches Up/Down bring back
+    library_search: Option<String>,
+    editing_search: bool,
+    pending_search: Option<Instant>,
+    search_history: Recall,
+
+    // ':' palette: every shell command for completion, and a submitted command line
+    // waiting for the terminal to be handed over to it
+    command_names: Vec<String>,
+    command: Option<String>,
+
+    // Disc layout shown before a burn starts (Enter: burn, Esc: cancel)
+    disc_preview: Option<Vec<String>>,
+
+    // Proposed running order and its diff lines (Enter: apply, Esc: cancel)
+    reorder: Option<(Plan, Vec<String>)>,
+
+    // Pane widths, and whether an empty playlist pane gives its width to the tracks
+    panes: PaneSplit,
+    collapse_empty_playlist: bool,
+
+    // Where the playlist pane was last drawn, and the entry being dragged with the mouse
+    playlist_area: Rect,
+    dragging: Option<usize>,
+
+    // What had focus when it was last put in the status line, in accessible mode
+    announced: String,
+}
+
+/// The album grid overlay: every album, the one selected, and where the last frame put
+/// them.
+struct AlbumGrid {
+    albums: Vec<Album>,
+    selected: usize,
+    // Columns and rows of cells that fit, and the first row shown, as last drawn
+    columns: usize,
+    rows: usize,
+    offset: usize,
+    // Where the last frame left room for the art of each album shown, and where art was
+    // drawn last, so it is only drawn again when that changes
+    slots: Vec<(usize, Rect)>,
+    drawn: Vec<(usize, Rect)>,
+    // Art read so far, none for albums that have none, and the images kitty was sent
+    art: HashMap<usize, Option<art::Image>>,
+    sent: HashSet<usize>,
+    // Reads the art, started once the size it is drawn at is known
+    loader: Option<ArtLoader>,
+}
+
+/// Reads album art on a worker thread, so opening or paging through the grid never
+/// waits for ffmpeg. The worker ends once the grid closes.
+struct ArtLoader {
+    /// The albums shown without art, with the path of a track of each. Only the latest
+    /// page sent is read.
+    pages: mpsc::Sender<Vec<(usize, String)>>,
+    loaded: mpsc::Receiver<(usize, Option<art::Image>)>,
+}
+
+impl ArtLoader {
+    /// Starts reading art scaled to `width` by `height` pixels.
+    fn spawn(width: u32, height: u32) -> Self {
+        let (pages, wanted_pages) = mpsc::channel::<Vec<(usize, String)>>();
+        let (send_loaded, loaded) = mpsc::channel();
+        thread::spawn(move || {
+            let mut wanted = VecDeque::new();
+            let mut done = HashSet::new();
+            loop {
+                // albums paged past since are no longer worth reading
+                let page = if wanted.is_empty() {
+                    match wanted_pages.recv() {
+                        Ok(page) => Some(page),
+                        Err(_) => return,
+                    }
+                } else {
+                    wanted_pages.try_iter().last()
+                };
+                if let Some(page) = page {
+                    wanted = page.into();
+                }
+                let Some((index, path)) = wanted.pop_front() else {
+                    continue;
+                };
+                if done.insert(index)
+                    && send_loaded
+                        .send((index, art::load(&path, width, height)))
+                        .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        ArtLoader { pages, loaded }
+    }
+}
+
+/// What answering yes to a confirmation does.
+enum ConfirmAction {
+    Clear,
+    Quit,
+}
+
+/// What submitting a text prompt does.
+enum PromptAction {
+    BalancedMix,
+    /// A shell command from the ':' palette, run with the TUI suspended.
+    Command,
+    /// The note kept on a track.
+    Note(i64),
+    /// The disc title; left empty, one is suggested again.
+    DiscTitle,
+    /// The artist to browse this spelling under.
+    MergeArtist(String),
+}
+
+/// A one-line input overlay, e.g. the balanced mix wizard.
+struct Prompt {
+    title: &'static str,
+    input: String,
+    action: PromptAction,
+}
+
+/// The details popup: the focused track's labelled tags, properties and note, and every
+/// copy of it in the library when there is more than one.
+struct Details {
+    id: i64,
+    title: String,
+    fields: Vec<(&'static str, String)>,
+    /// Best source first.
+    versions: Vec<(Song, SourceQuality)>,
+    /// Playlist entry the popup was opened on.
+    entry: Option<usize>,
+}
+
+/// Every track in the library with the "artist title album" text the finder matches on.
+struct Candidates {
+    library: Vec<Song>,
+    haystacks: Vec<String>,
+}
+
+impl Candidates {
+    fn new(library: Vec<Song>) -> Self {
+        let haystacks = library
+            .iter()
+            .map(|s| format!("{} {} {}", s.artist, s.title, s.album))
+            .collect();
+        Self { library, haystacks }
+    }
+}
+
+/// Fuzzy finder over every track in the library, matched on "artist title album".
+struct Finder {
+    query: String,
+    candidates: Rc<Candidates>,
+    matches: Vec<usize>,
+    state: ListState,
+    history: Recall,
+}
+
+impl Finder {
+    fn new(candidates: Rc<Candidates>, history: Vec<String>) -> Self {
+        let mut finder = Self {
+            query: String::new(),
+            candidates,
+            matches: vec![],
+            state: ListState::default(),
+            history: Recall::new(history),
+        };
+        finder.update();
+
+        finder
+    }
+
+    fn update(&mut self) {
+        self.matches = fuzzy::rank(
+            &self.query,
+            self.candidates.haystacks.iter().map(String::as_str),
+            FINDER_RESULTS,
+        );
+        self.state.select(if self.matches.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+    }
+
+    fn next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.matches.len()));
+        }
+    }
+
+    fn prev(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state
+                .select(Some(i.checked_sub(1).unwrap_or(self.matches.len() - 1)));
+        }
+    }
+
+    fn selected(&self) -> Option<&Song> {
+        Some(&self.candidates.library[self.selected_index()?])
+    }
+
+    /// Library index of the highlighted match.
+    fn selected_index(&self) -> Option<usize> {
+        Some(self.matches[self.state.selected()?])
+    }
+
+    /// Whether Up/Down step through past searches rather than the matches, which they do
+    /// from an empty query until something is typed.
+    fn browsing_history(&self) -> bool {
+        self.query.is_empty() || self.history.recalling()
+    }
+
+    fn recall(&mut self, older: bool) {
+        self.query = self.history.step(older);
+        self.update();
+    }
+}
+
+/// Past searches, oldest first, and which one Up/Down has recalled into a query.
+#[derive(Default)]
+struct Recall {
+    history: Vec<String>,
+    pos: Option<usize>,
+}
+
+impl Recall {
+    fn new(history: Vec<String>) -> Self {
+        Self { history, pos: None }
+    }
+
+    fn recalling(&self) -> bool {
+        self.pos.is_some()
+    }
+
+    /// Forgets the recalled search once the query is edited.
+    fn reset(&mut self) {
+        self.pos = None;
+    }
+
+    /// The search before the one recalled, or after it when `older` is false, going
+    /// back to an empty query past the newest.
+    fn step(&mut self, older: bool) -> String {
+        self.pos = match (self.pos, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) => Some(pos + 1).filter(|&pos| pos < self.history.len()),
+        };
+        self.pos
+            .map(|pos| self.history[pos].clone())
+            .unwrap_or_default()
+    }
 }
 
 struct WrappingList<T> {
@@ -84,6 +600,9 @@ impl WrappingTableState {
     }
 
     fn prev<T>(&mut self, items: &[T]) -> usize {
+        if items.is_empty() {
+            return 0;
+        }
         let i = self
             .0
             .selected()
@@ -116,12 +635,21 @@ impl<T> WrappingTable<T> {
 impl View {
     fn new(state: AppState) -> Result<Self> {
         // Initial Data Load
-        let artists = queries::list_artists(state.conn())
-            .context("failed to grab initial list of artists")?;
-        let mut artist_state = ListState::default();
-        if !artists.is_empty() {
-            artist_state.select(Some(0));
-        }
+        let (artists, artist_labels) =
+            artist_list(&state).context("failed to grab initial list of artists")?;
+        let library_totals = totals::get(state.conn())?.label();
+
+        let saved_session = state.saved_session_len();
+        let target_summary = state.target_summary();
+        let disc_title = state.disc_title()?;
+        let unplayable = queries::unplayable_tracks(state.conn())?;
+        let (panes, collapse_empty_playlist) =
+            (state.config().panes, state.config().collapse_empty_playlist);
+        let art = state
+            .config()
+            .album_art
+            .protocol()
+            .filter(|_| !style::accessible());
 
         Ok(Self {
             state,
@@ -130,214 +658,2240 @@ impl View {
                 items: artists,
                 state: ListState::default(),
             },
+            artist_labels,
+            library_totals,
+            pending_artist: None,
             tracks: WrappingTable {
                 items: vec![],
                 state: WrappingTableState::default(),
             },
             playlist: WrappingTableState::default(),
+            session_pending: matches!(saved_session, Ok(saved) if saved > 0),
             help: &ARTIST_HELP[..],
-            status_msg: Cow::Borrowed(
-                "Welcome. Use Left/Right to switch columns. Enter to select.",
-            ),
+            status_msg: match saved_session {
+                Ok(0) | Err(_) => {
+                    Cow::Borrowed("Welcome. Use Left/Right to switch columns. Enter to select.")
+                }
+                Ok(saved) => Cow::Owned(format!(
+                    "Previous session had {} tracks. Press R to restore them, or start a new playlist.",
+                    saved
+                )),
+            },
+            audition: None,
+            waveform: None,
+            waveform_loading: None,
+            details: None,
+            finder: None,
+            finder_candidates: None,
+            recent: None,
+            album_grid: None,
+            art,
+            burns: None,
+            burn_log: None,
+            transition: None,
+            version_picker: None,
+            version_target: None,
+            prompt: None,
+            confirm: None,
+            playlist_filter: String::new(),
+            editing_filter: false,
+            library_search: None,
+            search_history: Recall::default(),
+            editing_search: false,
+            pending_search: None,
+            command_names: command::command_names(),
+            command: None,
+            disc_preview: None,
+            reorder: None,
+            target_summary,
+            disc_title,
+            unplayable,
+            panes,
+            collapse_empty_playlist,
+            playlist_area: Rect::default(),
+            dragging: None,
+            announced: String::new(),
         })
     }
 
-    fn load_selected_artist(&mut self, index: usize) {
-        let selected_artist = &self.artists.items[index];
-        match queries::list_artist_tracks(self.state.conn(), selected_artist) {
-            Ok(tracks) => {
-                self.tracks.items = tracks;
-                self.tracks.state = WrappingTableState::default();
+    /// What adding `title` took and left, and where it was split if it was too long.
+    fn added_message(&self, title: &str, split_at: Option<u64>) -> String {
+        match split_at {
+            Some(at) => format!(
+                "Added \"{}\" up to {}: {}; the rest is parked in the audition list",
+                title,
+                duration::clock(at),
+                self.state.added_delta()
+            ),
+            None => format!("Added \"{}\": {}", title, self.state.added_delta()),
+        }
+    }
+
+    fn add_song(&mut self, song: Song) {
+        let (id, title) = (song.id, song.title.clone());
+        self.status_msg = match self.state.playlist_add_splitting(song) {
+            Ok(split_at) => match self.state.series_repeats() {
+                Ok(repeats) => match repeats.iter().find(|(song, _)| song.id == id) {
+                    Some((_, volume)) => Cow::Owned(format!(
+                        "{}, but it was already on volume {} of the series",
+                        self.added_message(&title, split_at),
+                        volume
+                    )),
+                    None => Cow::Owned(self.added_message(&title, split_at)),
+                },
+                Err(err) => Cow::Owned(format!("{:?}", err)),
+            },
+            Err(err) => Cow::Owned(format!("{:#}", err)),
+        };
+    }
+
+    /// Adds `song`, first opening the version picker if the library holds other copies
+    /// of it (same artist and title) so the best source can be chosen.
+    fn add_with_version_pick(&mut self, song: Song) {
+        if self.state.prefer_lossless() {
+            match self.state.preferred_version(song) {
+                Ok(song) => self.add_song(song),
+                Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
             }
-            Err(err) => {
-                self.status_msg = Cow::Owned(format!(
-                    "failed to load tracks for artist \"{}\": {:?}",
-                    selected_artist, err
-                ))
+            return;
+        }
+
+        match queries::list_versions(self.state.conn(), &song.artist, &song.title) {
+            Ok(versions) if versions.len() > 1 => {
+                let mut state = ListState::default();
+                state.select(Some(0));
+                self.version_picker = Some(WrappingList {
+                    items: versions,
+                    state,
+                });
             }
+            Ok(_) => self.add_song(song),
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
         }
     }
 
-    fn add_current_track(&mut self) {
-        let selected_track = self.tracks.state.selected();
-        let selected_track = &self.tracks.items[selected_track];
-        if let Err(err) = self.state.playlist_add(selected_track.clone()) {
-            self.status_msg = Cow::Owned(err.to_string());
+    /// Handles a key press while the version picker is open.
+    fn version_picker_key(&mut self, code: KeyCode) {
+        let Some(picker) = self.version_picker.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => {
+                self.version_picker = None;
+                self.version_target = None;
+            }
+            KeyCode::Up => {
+                picker.prev();
+            }
+            KeyCode::Down => {
+                picker.next();
+            }
+            KeyCode::Enter => {
+                let selected = picker.state.selected().unwrap_or(0);
+                let (song, _) = picker.items.swap_remove(selected);
+                self.version_picker = None;
+                match self.version_target.take() {
+                    Some(index) => {
+                        let album = song.album.clone();
+                        match self.state.playlist_swap_version(index, song) {
+                            Ok(()) => {
+                                self.status_msg =
+                                    Cow::Owned(format!("Using the copy from \"{}\"", album));
+                                self.save_session();
+                            }
+                            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+                        }
+                    }
+                    None => self.add_song(song),
+                }
+            }
+            _ => (),
         }
     }
 
-    fn clear_playlist(&mut self) {
-        self.state.playlist_clear();
-        self.playlist = WrappingTableState::default();
+    /// Handles a key press while a prompt is open.
+    fn prompt_key(&mut self, code: KeyCode) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            KeyCode::Tab if matches!(prompt.action, PromptAction::Command) => {
+                let input = prompt.input.trim_start();
+                let matches = self
+                    .command_names
+                    .iter()
+                    .filter(|name| name.starts_with(input))
+                    .collect::<Vec<_>>();
+                match matches.as_slice() {
+                    [] => self.status_msg = Cow::Borrowed("No matching command"),
+                    [name] => prompt.input = format!("{} ", name),
+                    [first, rest @ ..] => {
+                        // complete as far as every match agrees
+                        let common = rest.iter().fold(first.len(), |len, name| {
+                            first
+                                .bytes()
+                                .zip(name.bytes())
+                                .take(len)
+                                .take_while(|(a, b)| a == b)
+                                .count()
+                        });
+                        prompt.input = first[..common].to_string();
+                        self.status_msg = Cow::Owned(
+                            matches
+                                .iter()
+                                .map(|name| name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(" | "),
+                        );
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                // SAFETY: checked to be open above
+                let prompt = self.prompt.take().unwrap();
+                self.submit_prompt(prompt);
+            }
+            _ => (),
+        }
     }
-}
 
-// --- MAIN ENTRY ---
+    fn submit_prompt(&mut self, prompt: Prompt) {
+        match prompt.action {
+            PromptAction::BalancedMix => {
+                let songs = mix::balanced(
+                    self.state.conn(),
+                    &prompt.input,
+                    self.state.remaining_secs(),
+                    self.state.free_track_slots(),
+                );
+                self.status_msg = match songs.and_then(|songs| {
+                    let count = songs.len();
+                    self.state.playlist_add_all(songs).map(|_| count)
+                }) {
+                    Ok(count) => Cow::Owned(format!(
+                        "Added {} tracks from balanced mix, {} left",
+                        count,
+                        duration::format_secs(self.state.remaining_secs())
+                    )),
+                    Err(err) => Cow::Owned(format!("{:?}", err)),
+                };
+            }
+            PromptAction::Command => self.command = Some(prompt.input),
+            PromptAction::MergeArtist(spelling) => {
+                self.status_msg = match self.state.merge_artist(&spelling, &prompt.input) {
+                    Ok(artist) => {
+                        self.reload_artists(&artist);
+                        Cow::Owned(format!(
+                            "\"{}\" is browsed as \"{}\" (Ctrl+U on it to unmerge)",
+                            spelling, artist
+                        ))
+                    }
+                    Err(err) => Cow::Owned(format!("{:?}", err)),
+                };
+            }
+            PromptAction::DiscTitle => {
+                let title = prompt.input.trim();
+                self.state.burn_options_mut().title =
+                    Some(title.to_string()).filter(|title| !title.is_empty());
+                self.refresh_disc_title();
+                self.status_msg = Cow::Owned(format!("Disc title: {}", self.disc_title));
+            }
+            PromptAction::Note(id) => {
+                self.status_msg = match self.state.set_note(id, &prompt.input) {
+                    Ok(()) if prompt.input.trim().is_empty() => Cow::Borrowed("Note removed"),
+                    Ok(()) => Cow::Borrowed("Note saved"),
+                    Err(err) => Cow::Owned(format!("{:?}", err)),
+                };
+            }
+        }
+    }
 
-pub fn run_tui() -> Result<()> {
-    // Terminal Init
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    /// The track under the cursor in the tracks or playlist pane.
+    fn focused_song(&self) -> Option<&Song> {
+        match self.active_pane {
+            ActivePane::Artists => None,
+            ActivePane::ArtistTracks => self.tracks.items.get(self.tracks.state.selected()),
+            ActivePane::Playlist => self
+                .selected_playlist_index()
+                .and_then(|i| self.state.playlist().get(i)),
+        }
+    }
 
-    // App Init
-    let state = AppState::new()?;
-    let mut view = View::new(state)?;
+    fn show_focused_details(&mut self) {
+        let Some(song) = self.focused_song() else {
+            return;
+        };
+        let entry = match self.active_pane {
+            ActivePane::Playlist => self.selected_playlist_index(),
+            _ => None,
+        };
+        let details = tags::show(self.state.conn(), song.id).and_then(|fields| {
+            let versions = queries::list_versions(self.state.conn(), &song.artist, &song.title)?;
+            Ok(Details {
+                id: song.id,
+                title: song.title.clone(),
+                fields,
+                versions,
+                entry,
+            })
+        });
+        match details {
+            Ok(details) => self.details = Some(details),
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
 
-    // Initial load
-    view.load_selected_artist(0);
+    /// Opens the version picker on the copies listed in the details popup, to add the
+    /// chosen one or to swap it in for the playlist entry the popup was opened on.
+    fn pick_version_from_details(&mut self) {
+        let Some(details) = self.details.take() else {
+            return;
+        };
+        let current = details
+            .versions
+            .iter()
+            .position(|(song, _)| song.id == details.id);
+        let mut state = ListState::default();
+        state.select(Some(current.unwrap_or(0)));
+        self.version_picker = Some(WrappingList {
+            items: details.versions,
+            state,
+        });
+        self.version_target = details.entry;
+    }
 
-    let res = run_app(&mut terminal, &mut view);
+    fn edit_focused_note(&mut self) {
+        let Some(id) = self.focused_song().map(|song| song.id) else {
+            return;
+        };
+        match tags::note(self.state.conn(), id) {
+            Ok(note) => {
+                self.prompt = Some(Prompt {
+                    title: " Note on this track (Enter: save, empty to remove, Esc: cancel) ",
+                    input: note.unwrap_or_default(),
+                    action: PromptAction::Note(id),
+                })
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
 
-    // Terminal Restore
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    /// What has focus and what is selected in it, in words.
+    fn focus_description(&self) -> String {
+        fn position<T>(name: &str, items: &[T], selected: Option<usize>) -> String {
+            match selected.filter(|&i| i < items.len()) {
+                Some(i) => format!("{}, {} of {}", name, i + 1, items.len()),
+                None if items.is_empty() => format!("{}, empty", name),
+                None => format!("{}, {} items", name, items.len()),
+            }
+        }
+        let spoken = |song: &Song| {
+            format!(
+                "{} by {}, {}",
+                song.title,
+                song.artist,
+                duration::format_secs(song.played_duration_secs())
+            )
+        };
 
-    if let Err(err) = res {
-        println!("{:?}", err);
+        if let Some((question, _)) = &self.confirm {
+            return format!("{} y or n", question);
+        }
+        if let Some(prompt) = &self.prompt {
+            return prompt.title.trim().to_string();
+        }
+        if let Some((title, _)) = &self.waveform {
+            return format!("Waveform of {}, any key closes", title);
+        }
+        if let Some(details) = &self.details {
+            return format!("Details of {}, any key closes", details.title);
+        }
+        if self.disc_preview.is_some() {
+            return String::from("Disc layout, Enter burns, Escape cancels");
+        }
+        if self.reorder.is_some() {
+            return String::from("Proposed order, Enter applies, Escape cancels");
+        }
+        if let Some(finder) = &self.finder {
+            let at = position("Finder", &finder.matches, finder.state.selected());
+            return match finder.selected() {
+                Some(song) => format!("{}: {}", at, spoken(song)),
+                None => at,
+            };
+        }
+        if let Some(picker) = &self.version_picker {
+            let at = position("Versions", &picker.items, picker.state.selected());
+            return match picker.state.selected().and_then(|i| picker.items.get(i)) {
+                Some((song, quality)) => {
+                    format!("{}: {}, {}", at, spoken(song), quality.describe())
+                }
+                None => at,
+            };
+        }
+        if let Some(recent) = &self.recent {
+            let at = position("Recently previewed", &recent.items, recent.state.selected());
+            return match recent.state.selected().and_then(|i| recent.items.get(i)) {
+                Some((song, heard)) => format!("{}: {}, {}", at, spoken(song), heard),
+                None => at,
+            };
+        }
+        if let Some(grid) = &self.album_grid {
+            let at = position("Albums", &grid.albums, Some(grid.selected));
+            return match grid.albums.get(grid.selected) {
+                Some(album) => format!(
+                    "{}: {} by {}, {}, {} tracks",
+                    at, album.album, album.artist, album.year, album.tracks
+                ),
+                None => at,
+            };
+        }
+        if self.burn_log.is_some() {
+            return String::from("Burn log, Escape closes");
+        }
+        if let Some(burns) = &self.burns {
+            let at = position("Burn history", &burns.items, burns.state.selected());
+            return match burns.state.selected().and_then(|i| burns.items.get(i)) {
+                Some(burn) => format!("{}: {}, {}", at, burn.title, burn.burned_at),
+                None => at,
+            };
+        }
+        if self.editing_filter {
+            return String::from("Filter the playlist, Enter keeps it, Escape clears it");
+        }
+        if self.editing_search {
+            return String::from("Search the library, Enter shows the results, Escape closes");
+        }
+
+        match self.active_pane {
+            ActivePane::Artists => {
+                let selected = self.artists.state.selected();
+                let at = position("Artists", &self.artists.items, selected);
+                match selected.and_then(|i| self.artist_labels.get(i)) {
+                    Some(label) => format!("{}: {}", at, label),
+                    None => at,
+                }
+            }
+            ActivePane::ArtistTracks => {
+                let name = match &self.library_search {
+                    Some(query) => format!("Search results for {}", query),
+                    None => String::from("Tracks"),
+                };
+                let selected = self.tracks.state.selected();
+                let at = position(&name, &self.tracks.items, Some(selected));
+                match self.tracks.items.get(selected) {
+                    Some(song) => format!("{}: {}", at, spoken(song)),
+                    None => at,
+                }
+            }
+            ActivePane::Playlist => {
+                if let Some(audition) = &self.audition {
+                    let items = self.state.audition();
+                    let at = position("Audition", items, Some(audition.selected()));
+                    return match items.get(audition.selected()) {
+                        Some(song) => format!("{}: {}", at, spoken(song)),
+                        None => at,
+                    };
+                }
+                let visible = self.visible_playlist();
+                let name = if self.playlist_filter.is_empty() {
+                    String::from("Playlist")
+                } else {
+                    format!("Playlist filtered by {}", self.playlist_filter)
+                };
+                let at = position(&name, &visible, Some(self.playlist.selected()));
+                match self.focused_song() {
+                    Some(song) => format!("{}: {}", at, spoken(song)),
+                    None => at,
+                }
+            }
+        }
     }
 
-    Ok(())
-}
+    /// Puts what has focus in the status line whenever it changes, after any message the
+    /// last key left, so a screen reader reading out the line follows along.
+    fn announce_focus(&mut self) {
+        let focus = self.focus_description();
+        if focus == self.announced {
+            return;
+        }
+        let ours = !self.announced.is_empty() && self.status_msg.ends_with(&self.announced);
+        self.status_msg = if self.status_msg.is_empty() || ours {
+            Cow::Owned(focus.clone())
+        } else {
+            Cow::Owned(format!("{}. {}", self.status_msg, focus))
+        };
+        self.announced = focus;
+    }
 
-const ARTIST_HELP: [&str; 4] = [
-    "(ESC) Quit",
-    "(→ / Enter) Tracks Tab",
-    "(↑ / ↓) Navigate Artists",
-    "Jump To A Letter",
-];
-const TRACK_HELP: [&str; 4] = [
-    "(←) Artists Tab",
-    "(↑ / ↓) Navigate Tracks",
-    "(→) Playlist Tab",
-    "(Enter) Add Track",
-];
-const PLAYLIST_HELP: [&str; 4] = [
-    "(←) Tracks Tab",
-    "(Backspace) Remove Track",
-    "(B) Burn Playlist",
-    "(C) Clear Playlist",
-];
+    /// Playlist indices shown in the playlist pane, narrowed by the '/' filter.
+    fn visible_playlist(&self) -> Vec<usize> {
+        let filter = self.playlist_filter.to_lowercase();
+        self.state
+            .playlist()
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                filter.is_empty()
+                    || [&s.title, &s.artist, &s.album]
+                        .iter()
+                        .any(|tag| tag.to_lowercase().contains(&filter))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
 
-#[derive(Debug)]
-enum BurnPhase {
-    BuildingPlaylist,
-    Burning {
-        logs: Vec<ratatui::text::Line<'static>>,
-        completed: bool,
-        rx: mpsc::Receiver<LogMessage>,
-        handle: Option<JoinHandle<Result<()>>>,
-    },
-    Completed {
-        logs: Vec<ratatui::text::Line<'static>>,
-    },
-}
+    /// Playlist index of the selected row in the (possibly filtered) playlist pane.
+    fn selected_playlist_index(&self) -> Option<usize> {
+        self.visible_playlist()
+            .get(self.playlist.selected())
+            .copied()
+    }
+
+    /// Plays the end of the selected entry into the start of the next one, unless a
+    /// preview is still playing.
+    fn preview_transition(&mut self) {
+        if self
+            .transition
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            self.status_msg = Cow::Borrowed("Still playing the last transition");
+            return;
+        }
+        let Some(index) = self.selected_playlist_index() else {
+            return;
+        };
+        let Some([from, to]) = self.state.playlist().get(index..index + 2) else {
+            self.status_msg = Cow::Borrowed("The last entry has nothing to transition into");
+            return;
+        };
+        let (from, to) = (from.clone(), to.clone());
+        for id in [from.id, to.id] {
+            if let Err(err) = self.state.record_preview(id) {
+                self.status_msg = Cow::Owned(format!("{:?}", err));
+                return;
+            }
+        }
+
+        self.status_msg = Cow::Owned(format!(
+            "Playing the end of \"{}\" into \"{}\"",
+            from.title, to.title
+        ));
+        self.transition = Some(thread::spawn(move || preview::play_transition(&from, &to)));
+    }
+
+    /// Parks the selected track in the audition list.
+    fn park_current_track(&mut self) {
+        let Some(song) = self.tracks.items.get(self.tracks.state.selected()) else {
+            return;
+        };
+        let title = song.title.clone();
+        self.status_msg = if self.state.audition_add(song.clone()) {
+            Cow::Owned(format!("Parked \"{}\" for audition", title))
+        } else {
+            Cow::Owned(format!("\"{}\" is already parked", title))
+        };
+    }
+
+    /// Moves the selected playlist entry back to the audition list.
+    fn demote_selected_playlist_entry(&mut self) {
+        let Some(index) = self.selected_playlist_index() else {
+            return;
+        };
+        let title = self.state.playlist()[index].title.clone();
+        if self.state.playlist_demote(index) {
+            self.status_msg = Cow::Owned(format!("Moved \"{}\" back to audition", title));
+        }
+    }
+
+    /// Handles a key press in the playlist pane while it shows the audition list.
+    fn audition_key(&mut self, code: KeyCode) {
+        let Some(selection) = self.audition.as_mut() else {
+            return;
+        };
+        let index = selection.selected();
+        match code {
+            KeyCode::Tab | KeyCode::Esc => self.audition = None,
+            KeyCode::Left => self.active_pane = ActivePane::ArtistTracks,
+            KeyCode::Up => {
+                selection.prev(self.state.audition());
+            }
+            KeyCode::Down => {
+                selection.next(self.state.audition());
+            }
+            KeyCode::Enter => match self.state.audition_promote(index) {
+                Ok(()) => self.status_msg = Cow::Borrowed("Moved to the playlist"),
+                Err(err) => self.status_msg = Cow::Owned(err.to_string()),
+            },
+            KeyCode::Backspace => {
+                self.state.audition_remove(index);
+            }
+            KeyCode::Char('P' | ' ') => self.play_audition_sample(index),
+            _ => (),
+        }
+        if let Some(selection) = self.audition.as_mut() {
+            let last = self.state.audition().len().saturating_sub(1);
+            selection.0.select(Some(selection.selected().min(last)));
+        }
+    }
+
+    /// Plays a stretch of the audition entry at `index` in the background, unless a
+    /// preview is still playing.
+    fn play_audition_sample(&mut self, index: usize) {
+        if self
+            .transition
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            self.status_msg = Cow::Borrowed("Still playing the last preview");
+            return;
+        }
+        let Some(song) = self.state.audition().get(index).cloned() else {
+            return;
+        };
+        if let Err(err) = self.state.record_preview(song.id) {
+            self.status_msg = Cow::Owned(format!("{:?}", err));
+            return;
+        }
+
+        self.status_msg = Cow::Owned(format!("Auditioning \"{}\"", song.title));
+        self.transition = Some(thread::spawn(move || preview::play_sample(&song)));
+    }
+
+    /// Works out a better running order and shows how it differs before applying it.
+    fn optimize_order(&mut self) {
+        match self.state.playlist_optimized_order() {
+            Ok(plan) if plan.is_unchanged() => {
+                self.status_msg = Cow::Borrowed("The playlist is already in the best order found");
+            }
+            Ok(plan) => {
+                let lines = plan.lines(self.state.playlist());
+                self.reorder = Some((plan, lines));
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    /// Reports a transition preview that failed once it has finished.
+    fn check_transition(&mut self) {
+        if !self
+            .transition
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
+        {
+            return;
+        }
+        // SAFETY: checked to be there above
+        match self.transition.take().unwrap().join() {
+            Ok(Ok(())) => (),
+            Ok(Err(err)) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+            Err(_) => self.status_msg = Cow::Borrowed("Transition preview panicked"),
+        }
+    }
+
+    fn remove_selected_playlist_entry(&mut self) {
+        if let Some(index) = self.selected_playlist_index() {
+            self.state.playlist_remove(index);
+        }
+    }
+
+    /// Moves the selected entry past its neighbour in the pane, keeping it selected. With
+    /// a filter on, entries the filter hides stay where they are in between.
+    fn move_selected_playlist_entry(&mut self, up: bool) {
+        let visible = self.visible_playlist();
+        let row = self.playlist.selected();
+        let Some(&from) = visible.get(row) else {
+            return;
+        };
+        let neighbour = if up {
+            row.checked_sub(1)
+        } else {
+            Some(row + 1)
+        };
+        if let Some(neighbour) = neighbour
+            && let Some(&to) = visible.get(neighbour)
+            && self.state.playlist_move(from, to)
+        {
+            self.playlist.0.select(Some(neighbour));
+        }
+    }
+
+    /// Row of the playlist pane under the terminal cell at `column`, `row`, if any.
+    fn playlist_row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.playlist_area;
+        // rows start below the border, the header and the gap under it
+        let first_row = area.y + 3;
+        let inside = column > area.x
+            && column + 1 < area.right()
+            && row >= first_row
+            && row + 1 < area.bottom();
+        if !inside {
+            return None;
+        }
+        let index = self.playlist.0.offset() + (row - first_row) as usize;
+        (index < self.visible_playlist().len()).then_some(index)
+    }
+
+    /// Click-dragging a playlist row carries the entry along with the pointer; releasing
+    /// it saves the new order, which the staged files are numbered by at burn time.
+    fn playlist_mouse(&mut self, mouse: MouseEvent) {
+        let row = self.playlist_row_at(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(row) = row {
+                    self.active_pane = ActivePane::Playlist;
+                    self.playlist.0.select(Some(row));
+                    self.dragging = self.selected_playlist_index();
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let (Some(from), Some(row)) = (self.dragging, row) else {
+                    return;
+                };
+                let Some(&to) = self.visible_playlist().get(row) else {
+                    return;
+                };
+                if from != to && self.state.playlist_move(from, to) {
+                    self.dragging = Some(to);
+                    self.playlist.0.select(Some(row));
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.dragging.is_some() => {
+                self.dragging = None;
+                self.save_session();
+            }
+            _ => (),
+        }
+    }
+
+    /// Handles a key press while the library search is being typed, which runs once
+    /// typing pauses. Up/Down bring back past searches into an empty box, Enter keeps
+    /// the results and moves to them, Esc goes back to the selected artist's tracks.
+    fn search_key(&mut self, code: KeyCode) {
+        let Some(query) = self.library_search.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => {
+                self.close_search();
+                return;
+            }
+            KeyCode::Enter if query.trim().is_empty() => {
+                self.close_search();
+                return;
+            }
+            KeyCode::Enter => {
+                let _ = history::record(query);
+                self.editing_search = false;
+                self.settle_search();
+                self.active_pane = ActivePane::ArtistTracks;
+                return;
+            }
+            KeyCode::Up | KeyCode::Down if query.is_empty() || self.search_history.recalling() => {
+                *query = self.search_history.step(code == KeyCode::Up);
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                self.search_history.reset();
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                self.search_history.reset();
+            }
+            _ => return,
+        }
+        self.pending_search = Some(Instant::now());
+    }
+
+    /// Runs the library search typed since it last ran, showing the matches in the
+    /// tracks pane. The last word matches as a prefix, so results show while it is
+    /// still being typed.
+    fn settle_search(&mut self) {
+        if self.pending_search.take().is_none() {
+            return;
+        }
+        let Some(query) = self.library_search.as_deref() else {
+            return;
+        };
+        match queries::search_group(self.state.conn(), &prefix_query(query)) {
+            Ok(tracks) => {
+                self.status_msg = match tracks.len() {
+                    0 if query.trim().is_empty() => Cow::Borrowed(
+                        "Type to search titles, artists, albums and paths (Up: past searches, Esc: cancel)",
+                    ),
+                    0 => Cow::Owned(format!("Nothing matches \"{}\"", query)),
+                    n => Cow::Owned(format!("{} tracks match \"{}\"", n, query)),
+                };
+                self.tracks.state = WrappingTableState::default();
+                if !tracks.is_empty() {
+                    self.tracks.state.0.select(Some(0));
+                }
+                self.tracks.items = tracks;
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    /// Opens the library search box, or goes back to editing the search shown.
+    fn open_search(&mut self) {
+        self.settle_artist();
+        self.library_search.get_or_insert_with(String::new);
+        self.search_history = Recall::new(history::load().unwrap_or_default());
+        self.editing_search = true;
+        self.pending_search = Some(Instant::now());
+    }
+
+    /// Ends the library search, showing the selected artist's tracks again.
+    fn close_search(&mut self) {
+        self.library_search = None;
+        self.editing_search = false;
+        self.pending_search = None;
+        if let Some(index) = self.artists.state.selected() {
+            self.load_selected_artist(index);
+        }
+    }
+
+    /// Handles a key press while the playlist filter is being typed.
+    fn filter_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => {
+                self.playlist_filter.clear();
+                self.editing_filter = false;
+            }
+            KeyCode::Enter => self.editing_filter = false,
+            KeyCode::Backspace => {
+                self.playlist_filter.pop();
+            }
+            KeyCode::Char(c) => self.playlist_filter.push(c),
+            _ => (),
+        }
+        self.playlist = WrappingTableState::default();
+    }
+
+    fn open_finder(&mut self) {
+        let candidates = match &self.finder_candidates {
+            Some(candidates) => Rc::clone(candidates),
+            None => match queries::list_all_tracks(self.state.conn()) {
+                Ok(library) => Rc::clone(
+                    self.finder_candidates
+                        .insert(Rc::new(Candidates::new(library))),
+                ),
+                Err(err) => {
+                    self.status_msg = Cow::Owned(format!("{:?}", err));
+                    return;
+                }
+            },
+        };
+        // a missing or unreadable history just means nothing to recall
+        let history = history::load().unwrap_or_default();
+        self.finder = Some(Finder::new(candidates, history));
+    }
+
+    fn open_recent(&mut self) {
+        match preview::recent(self.state.conn(), preview::RECENT_LEN) {
+            Ok(items) if items.is_empty() => {
+                self.status_msg =
+                    Cow::Borrowed("Nothing previewed yet: use \"playlist preview\" in the shell")
+            }
+            Ok(items) => {
+                let mut state = ListState::default();
+                state.select(Some(0));
+                self.recent = Some(WrappingList { items, state });
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    fn open_burns(&mut self) {
+        match burns::list(self.state.conn(), None) {
+            Ok(items) if items.is_empty() => {
+                self.status_msg = Cow::Borrowed("No discs burned yet");
+            }
+            Ok(items) => {
+                let mut state = ListState::default();
+                state.select(Some(0));
+                self.burns = Some(WrappingList { items, state });
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    fn open_album_grid(&mut self) {
+        match queries::list_albums(self.state.conn()) {
+            Ok(albums) if albums.is_empty() => {
+                self.status_msg = Cow::Borrowed("No tracks have an album tag");
+            }
+            Ok(albums) => {
+                self.album_grid = Some(AlbumGrid {
+                    albums,
+                    selected: 0,
+                    columns: 1,
+                    rows: 1,
+                    offset: 0,
+                    slots: vec![],
+                    drawn: vec![],
+                    art: HashMap::new(),
+                    sent: HashSet::new(),
+                    loader: None,
+                });
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    /// Handles a key press while the album grid is open.
+    fn album_grid_key(&mut self, code: KeyCode) {
+        let Some(grid) = self.album_grid.as_mut() else {
+            return;
+        };
+        let last = grid.albums.len() - 1;
+        let page = grid.columns * grid.rows;
+        grid.selected = match code {
+            KeyCode::Left => grid.selected.saturating_sub(1),
+            KeyCode::Right => (grid.selected + 1).min(last),
+            KeyCode::Up => grid.selected.saturating_sub(grid.columns),
+            KeyCode::Down => (grid.selected + grid.columns).min(last),
+            KeyCode::PageUp => grid.selected.saturating_sub(page),
+            KeyCode::PageDown => (grid.selected + page).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Enter => {
+                let album = grid.albums[grid.selected].clone();
+                self.add_album(&album);
+                self.save_session();
+                return;
+            }
+            KeyCode::Tab => {
+                let album = grid.albums[grid.selected].clone();
+                self.album_grid = None;
+                match queries::list_album(self.state.conn(), &album.album) {
+                    Ok(songs) => {
+                        if let Some(song) = songs.iter().find(|song| song.artist == album.artist) {
+                            self.jump_to(song);
+                        }
+                    }
+                    Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+                }
+                return;
+            }
+            KeyCode::Esc => {
+                self.album_grid = None;
+                return;
+            }
+            _ => grid.selected,
+        };
+    }
+
+    /// Adds the tracks of `album` in order, as many as fit.
+    fn add_album(&mut self, album: &Album) {
+        let songs = match queries::list_album(self.state.conn(), &album.album) {
+            Ok(songs) => songs
+                .into_iter()
+                .filter(|song| song.artist == album.artist)
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                self.status_msg = Cow::Owned(format!("{:?}", err));
+                return;
+            }
+        };
+        let total = songs.len();
+        for (added, song) in songs.into_iter().enumerate() {
+            if let Err(err) = self.state.playlist_add(song) {
+                self.status_msg = Cow::Owned(format!(
+                    "Added {} of the {} tracks of \"{}\": {:#}",
+                    added, total, album.album, err
+                ));
+                return;
+            }
+        }
+        self.status_msg = Cow::Owned(format!(
+            "Added \"{}\", {} tracks: {} left",
+            album.album,
+            total,
+            duration::format_secs(self.state.remaining_secs())
+        ));
+    }
+
+    /// Draws the cover art of the albums the grid shows, in the room the last frame left
+    /// for it, and asks for the art not read yet. Art goes around ratatui straight to the
+    /// terminal, so it is only drawn when the albums shown, their places or their art
+    /// change.
+    fn draw_album_art(&mut self) -> Result<()> {
+        let (Some(protocol), Some(grid)) = (self.art, self.album_grid.as_mut()) else {
+            return Ok(());
+        };
+        if grid.slots == grid.drawn {
+            return Ok(());
+        }
+
+        // terminals that don't report their size in pixels mostly use cells about this big
+        let (cell_width, cell_height) = match crossterm::terminal::window_size() {
+            Ok(size) if size.width > 0 && size.columns > 0 && size.rows > 0 => (
+                u32::from(size.width / size.columns),
+                u32::from(size.height / size.rows),
+            ),
+            _ => (10, 20),
+        };
+        let width = u32::from(ALBUM_ART_COLS) * cell_width;
+        // sixel draws six rows of pixels at a time, so stay clear of the row below
+        let height = u32::from(ALBUM_ART_ROWS) * cell_height / 6 * 6;
+        let loader = grid
+            .loader
+            .get_or_insert_with(|| ArtLoader::spawn(width, height));
+        let missing = grid
+            .slots
+            .iter()
+            .filter(|(index, _)| !grid.art.contains_key(index))
+            .map(|&(index, _)| (index, grid.albums[index].path.clone()))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let _ = loader.pages.send(missing);
+        }
+
+        let mut out = String::new();
+        match protocol {
+            Protocol::Kitty => out.push_str(art::KITTY_CLEAR),
+            // sixel pixels stay until text is written over them
+            Protocol::Sixel => {
+                for (_, area) in &grid.drawn {
+                    for y in area.top()..area.bottom() {
+                        out += &format!(
+                            "\x1b[{};{}H{}",
+                            y + 1,
+                            area.x + 1,
+                            " ".repeat(area.width.into())
+                        );
+                    }
+                }
+            }
+        }
+        for &(index, area) in &grid.slots {
+            let Some(Some(image)) = grid.art.get(&index) else {
+                continue;
+            };
+            out += &format!("\x1b[{};{}H", area.y + 1, area.x + 1);
+            match protocol {
+                Protocol::Kitty => {
+                    let id = index as u32 + 1;
+                    if grid.sent.insert(index) {
+                        out += &art::kitty_transmit(id, image);
+                    }
+                    out += &art::kitty_place(id, area.width, area.height);
+                }
+                Protocol::Sixel => out += &art::sixel(image),
+            }
+        }
+        grid.drawn.clone_from(&grid.slots);
+
+        let mut stdout = io::stdout();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Handles a key press while the list of past burns is open.
+    fn burns_key(&mut self, code: KeyCode) {
+        let Some(burns) = self.burns.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.burns = None,
+            KeyCode::Up => {
+                burns.prev();
+            }
+            KeyCode::Down => {
+                burns.next();
+            }
+            KeyCode::Enter => {
+                let burn = &burns.items[burns.state.selected().unwrap_or(0)];
+                match burns::log(self.state.conn(), burn.id) {
+                    Ok(Some(log)) => {
+                        let title = format!(" Log of burn {}: {} ", burn.id, burn.title);
+                        let lines = log.lines().map(String::from).collect();
+                        self.burn_log = Some((title, lines, 0));
+                    }
+                    Ok(None) => {
+                        self.status_msg =
+                            Cow::Borrowed("That burn was recorded before burn logs were kept")
+                    }
+                    Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Scrolls the open burn log, going back to the list of burns on Esc.
+    fn burn_log_key(&mut self, code: KeyCode) {
+        let Some((_, lines, scroll)) = self.burn_log.as_mut() else {
+            return;
+        };
+        let last = lines.len().saturating_sub(1) as u16;
+        *scroll = match code {
+            KeyCode::Up => scroll.saturating_sub(1),
+            KeyCode::Down => (*scroll + 1).min(last),
+            KeyCode::PageUp => scroll.saturating_sub(BURN_LOG_PAGE),
+            KeyCode::PageDown => (*scroll + BURN_LOG_PAGE).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Esc => {
+                self.burn_log = None;
+                return;
+            }
+            _ => *scroll,
+        };
+    }
+
+    /// Handles a key press while the recently previewed list is open.
+    fn recent_key(&mut self, code: KeyCode) {
+        let Some(recent) = self.recent.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.recent = None,
+            KeyCode::Up => {
+                recent.prev();
+            }
+            KeyCode::Down => {
+                recent.next();
+            }
+            KeyCode::Enter => {
+                let selected = recent.state.selected().unwrap_or(0);
+                let (song, _) = recent.items.swap_remove(selected);
+                self.recent = None;
+                self.jump_to(&song);
+            }
+            KeyCode::Tab => {
+                let selected = recent.state.selected().unwrap_or(0);
+                let song = recent.items[selected].0.clone();
+                self.add_song(song);
+            }
+            _ => (),
+        }
+    }
+
+    /// Points the artist and track panes at `song` and focuses the tracks pane.
+    fn jump_to(&mut self, song: &Song) {
+        // a merged spelling is browsed under the artist it was merged into
+        let name = aliases::canonical_name(self.state.conn(), &song.artist)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| song.artist.clone());
+        let Some(artist) = self.artists.items.iter().position(|a| *a == name) else {
+            return;
+        };
+        self.artists.state.select(Some(artist));
+        self.load_selected_artist(artist);
+
+        let track = self.tracks.items.iter().position(|t| t.id == song.id);
+        self.tracks.state.0.select(track);
+        self.active_pane = ActivePane::ArtistTracks;
+    }
+
+    /// Handles a key press while the finder is open.
+    fn finder_key(&mut self, code: KeyCode) {
+        let Some(finder) = self.finder.as_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Esc => self.finder = None,
+            KeyCode::Up if finder.browsing_history() => finder.recall(true),
+            KeyCode::Down if finder.browsing_history() => finder.recall(false),
+            KeyCode::Up => finder.prev(),
+            KeyCode::Down => finder.next(),
+            KeyCode::Backspace => {
+                finder.query.pop();
+                finder.history.reset();
+                finder.update();
+            }
+            KeyCode::Char(c) => {
+                finder.query.push(c);
+                finder.history.reset();
+                finder.update();
+            }
+            KeyCode::Enter => {
+                if let Some(song) = finder.selected().cloned() {
+                    let _ = history::record(&finder.query);
+                    self.finder = None;
+                    self.jump_to(&song);
+                }
+            }
+            KeyCode::Tab => {
+                if let Some(song) = finder.selected().cloned() {
+                    let _ = history::record(&finder.query);
+                    self.add_with_version_pick(song);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn restore_session(&mut self) {
+        self.session_pending = false;
+        let picking_up = self.state.playlist().is_empty();
+        let restored = self.state.restore_session();
+        if picking_up {
+            self.restore_playlist_position();
+        }
+        // the session brings back the title and series too
+        self.refresh_disc_title();
+        match restored {
+            Ok(errors) if errors.is_empty() => {
+                self.status_msg = Cow::Borrowed("Restored previous session");
+            }
+            Ok(errors) => {
+                self.status_msg = Cow::Owned(format!(
+                    "Restored previous session, skipping {} tracks: {}",
+                    errors.len(),
+                    errors[0]
+                ));
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    fn save_session(&mut self) {
+        if self.session_pending {
+            if self.state.playlist().is_empty() && self.state.audition().is_empty() {
+                return;
+            }
+            self.session_pending = false;
+        }
+        self.state.set_ui(self.ui_state());
+        if let Err(err) = self.state.save_session() {
+            self.status_msg = Cow::Owned(format!("{:?}", err));
+        }
+    }
+
+    /// Where the TUI is now, for the session file. Positions in an empty playlist say
+    /// nothing, so until the previous session is restored its position is kept.
+    fn ui_state(&self) -> session::UiState {
+        let saved = self.state.ui();
+        let offsets = |state: &TableState| (state.selected().unwrap_or(0), state.offset());
+        session::UiState {
+            pane: Some(self.active_pane.name().to_string()),
+            artist: self.selected_artist(),
+            artist_offset: self.artists.state.offset(),
+            // the tracks shown are still the previous artist's while scrolling, and
+            // aren't the artist's at all while searching
+            track: if self.pending_artist.is_some() || self.library_search.is_some() {
+                (0, 0)
+            } else {
+                offsets(&self.tracks.state.0)
+            },
+            playlist: if self.state.playlist().is_empty() {
+                saved.playlist
+            } else {
+                offsets(&self.playlist.0)
+            },
+            panes: (self.panes != self.state.config().panes).then(|| {
+                let [artists, tracks, playlist] = self.panes.0;
+                format!("{} {} {}", artists, tracks, playlist)
+            }),
+        }
+    }
+
+    /// Puts the panes back where the previous session left them, starting on the first
+    /// artist when it left nothing to go back to.
+    fn restore_ui(&mut self) {
+        let ui = self.state.ui().clone();
+        if self.artists.items.is_empty() {
+            return;
+        }
+        let artist = ui
+            .artist
+            .and_then(|artist| self.artists.items.iter().position(|a| *a == artist))
+            .unwrap_or(0);
+        self.artists.state.select(Some(artist));
+        *self.artists.state.offset_mut() = ui.artist_offset;
+        self.load_selected_artist(artist);
+
+        let (track, offset) = ui.track;
+        if track < self.tracks.items.len() {
+            self.tracks.state.0.select(Some(track));
+            *self.tracks.state.0.offset_mut() = offset;
+        }
+        if let Some(pane) = ui.pane.as_deref().and_then(ActivePane::from_name) {
+            self.active_pane = pane;
+        }
+        if let Some(panes) = ui.panes.and_then(|panes| panes.parse().ok()) {
+            self.panes = panes;
+        }
+    }
+
+    /// Selects the playlist entry the previous session had selected, once its tracks are
+    /// back on an otherwise empty playlist.
+    fn restore_playlist_position(&mut self) {
+        let (selected, offset) = self.state.ui().playlist;
+        if selected < self.state.playlist().len() {
+            self.playlist.0.select(Some(selected));
+            *self.playlist.0.offset_mut() = offset;
+        }
+    }
+
+    /// Rereads what the view keeps of the library after something may have changed it,
+    /// e.g. a burn marking files unplayable or a command run from the palette.
+    fn library_changed(&mut self) {
+        match queries::unplayable_tracks(self.state.conn()) {
+            Ok(unplayable) => self.unplayable = unplayable,
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+        self.refresh_disc_title();
+        self.finder_candidates = None;
+
+        // a scan, merge or tag edit may have changed the artists and their tracks
+        let track = self
+            .tracks
+            .items
+            .get(self.tracks.state.selected())
+            .map(|song| song.id);
+        let search = self.library_search.take();
+        let artist = self.selected_artist();
+        self.reload_artists(artist.as_deref().unwrap_or_default());
+        if let Some(index) = self.artists.state.selected()
+            && self.selected_artist() != artist
+        {
+            // the selected artist is gone, so its neighbour takes its place
+            match self.artists.items.len() {
+                0 => {
+                    self.artists.state.select(None);
+                    self.tracks.items.clear();
+                }
+                len => {
+                    self.artists.state.select(Some(index.min(len - 1)));
+                    self.load_selected_artist(index.min(len - 1));
+                }
+            }
+        }
+        if search.is_some() {
+            self.library_search = search;
+            self.pending_search = Some(Instant::now());
+            self.settle_search();
+        }
+        if let Some(index) = track.and_then(|id| self.tracks.items.iter().position(|t| t.id == id))
+        {
+            self.tracks.state.0.select(Some(index));
+        }
+    }
+
+    /// Works out the disc title again after the title, the series or the past burns
+    /// changed.
+    fn refresh_disc_title(&mut self) {
+        match self.state.disc_title() {
+            Ok(title) => self.disc_title = title,
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    /// Rereads the artists after a merge or unmerge, selecting `artist` and showing
+    /// its tracks.
+    fn reload_artists(&mut self, artist: &str) {
+        let (artists, artist_labels) = match artist_list(&self.state) {
+            Ok(list) => list,
+            Err(err) => {
+                self.status_msg = Cow::Owned(format!("failed to reload artists: {:?}", err));
+                return;
+            }
+        };
+        self.artists.items = artists;
+        self.artist_labels = artist_labels;
+        match totals::get(self.state.conn()) {
+            Ok(totals) => self.library_totals = totals.label(),
+            Err(err) => {
+                self.status_msg = Cow::Owned(format!("failed to total the library: {:?}", err))
+            }
+        }
+        if let Some(index) = self.artists.items.iter().position(|a| a == artist) {
+            self.artists.state.select(Some(index));
+            self.pending_artist = None;
+            self.load_selected_artist(index);
+        }
+    }
+
+    fn unmerge_selected_artist(&mut self) {
+        let Some(artist) = self.selected_artist() else {
+            return;
+        };
+        match self.state.unmerge_artist(&artist) {
+            Ok(spellings) if spellings.is_empty() => {
+                self.status_msg = Cow::Owned(format!(
+                    "No spellings are merged with \"{}\" (Ctrl+G merges it into another)",
+                    artist
+                ));
+            }
+            Ok(spellings) => {
+                self.status_msg = Cow::Owned(format!(
+                    "Unmerged: {}",
+                    spellings
+                        .iter()
+                        .map(|s| format!("\"{}\"", s))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+                self.reload_artists(&artist);
+            }
+            Err(err) => self.status_msg = Cow::Owned(format!("{:?}", err)),
+        }
+    }
+
+    fn selected_artist(&self) -> Option<String> {
+        self.artists
+            .items
+            .get(self.artists.state.selected()?)
+            .cloned()
+    }
+
+    /// Loads the tracks of the artist scrolled to, if they haven't been yet.
+    fn settle_artist(&mut self) {
+        if let Some((index, _)) = self.pending_artist.take() {
+            self.load_selected_artist(index);
+        }
+    }
+
+    fn load_selected_artist(&mut self, index: usize) {
+        self.library_search = None;
+        let selected_artist = &self.artists.items[index];
+        match queries::list_artist_tracks(self.state.conn(), selected_artist) {
+            Ok(tracks) => {
+                self.tracks.items = tracks;
+                self.tracks.state = WrappingTableState::default();
+            }
+            Err(err) => {
+                self.status_msg = Cow::Owned(format!(
+                    "failed to load tracks for artist \"{}\": {:?}",
+                    selected_artist, err
+                ))
+            }
+        }
+    }
+
+    fn add_current_track(&mut self) {
+        let selected_track = self.tracks.state.selected();
+        let selected_track = self.tracks.items[selected_track].clone();
+        let id = selected_track.id;
+        let song = match self.state.preferred_version(selected_track) {
+            Ok(song) => song,
+            Err(err) => {
+                self.status_msg = Cow::Owned(format!("{:?}", err));
+                return;
+            }
+        };
+        let (title, album, lossless) = (song.title.clone(), song.album.clone(), song.id != id);
+        self.status_msg = match self.state.playlist_add_splitting(song) {
+            Ok(None) if lossless => Cow::Owned(format!(
+                "Added lossless copy from \"{}\": {} (E adds the exact file)",
+                album,
+                self.state.added_delta()
+            )),
+            Ok(split_at) => Cow::Owned(self.added_message(&title, split_at)),
+            Err(err) => Cow::Owned(format!("{:#}", err)),
+        };
+    }
+
+    /// Adds each chapter of the selected audiobook as an entry of its own.
+    fn add_current_chapters(&mut self) {
+        let Some(id) = self
+            .tracks
+            .items
+            .get(self.tracks.state.selected())
+            .map(|track| track.id)
+        else {
+            return;
+        };
+        self.status_msg = match self.state.playlist_add_chapters(id, None) {
+            Ok(added) => Cow::Owned(format!(
+                "Added {} chapters, {} left",
+                added,
+                duration::format_secs(self.state.remaining_secs())
+            )),
+            Err(err) => Cow::Owned(format!("{:#}", err)),
+        };
+    }
+
+    fn add_current_track_exact(&mut self) {
+        let selected_track = self.tracks.state.selected();
+        let selected_track = &self.tracks.items[selected_track];
+        let title = selected_track.title.clone();
+        self.status_msg = match self.state.playlist_add_splitting(selected_track.clone()) {
+            Ok(split_at) => Cow::Owned(self.added_message(&title, split_at)),
+            Err(err) => Cow::Owned(format!("{:#}", err)),
+        };
+    }
+
+    fn toggle_prefer_lossless(&mut self) {
+        let prefer_lossless = !self.state.prefer_lossless();
+        self.state.set_prefer_lossless(prefer_lossless);
+        self.status_msg = Cow::Borrowed(if prefer_lossless {
+            "Prefer lossless: on"
+        } else {
+            "Prefer lossless: off"
+        });
+    }
+
+    /// Opens the waveform popup for the selected track, reading its peaks in the
+    /// background.
+    fn show_current_waveform(&mut self) {
+        let Some(track) = self.tracks.items.get(self.tracks.state.selected()) else {
+            return;
+        };
+        let (send_peaks, peaks) = mpsc::channel();
+        let path = track.path.clone();
+        thread::spawn(move || {
+            let _ = send_peaks.send(waveform::track_peaks(&path, WAVEFORM_BUCKETS));
+        });
+        self.waveform = Some((track.title.clone(), vec![]));
+        self.waveform_loading = Some(peaks);
+    }
+
+    /// Whether art or a waveform is being read for what is on screen.
+    fn loading(&self) -> bool {
+        self.waveform_loading.is_some()
+            || self.album_grid.as_ref().is_some_and(|grid| {
+                grid.loader.is_some()
+                    && grid
+                        .slots
+                        .iter()
+                        .any(|(index, _)| !grid.art.contains_key(index))
+            })
+    }
+
+    /// Takes the album art and waveform read in the background since the last frame.
+    fn receive_loaded(&mut self) {
+        if let Some(grid) = &mut self.album_grid
+            && let Some(loader) = &grid.loader
+        {
+            for (index, image) in loader.loaded.try_iter() {
+                grid.art.insert(index, image);
+                // drawn again with the new art
+                grid.drawn.clear();
+            }
+        }
+
+        let Some(peaks) = &self.waveform_loading else {
+            return;
+        };
+        let Some((title, shown)) = &mut self.waveform else {
+            // closed before the peaks were read
+            self.waveform_loading = None;
+            return;
+        };
+        match peaks.try_recv() {
+            Ok(Ok(peaks)) => {
+                *shown = peaks;
+                self.waveform_loading = None;
+            }
+            Ok(Err(err)) => {
+                self.status_msg = Cow::Owned(format!(
+                    "failed to render waveform for \"{}\": {:?}",
+                    title, err
+                ));
+                self.waveform = None;
+                self.waveform_loading = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.waveform_loading = None,
+        }
+    }
+
+    fn toggle_cdtext_mode(&mut self) {
+        let options = self.state.burn_options_mut();
+        options.cdtext = match options.cdtext {
+            CdTextMode::Raw => CdTextMode::Ascii,
+            CdTextMode::Ascii => CdTextMode::Raw,
+        };
+        self.status_msg = Cow::Owned(format!(
+            "CD-TEXT mode: {:?} (playlist shows burned titles)",
+            options.cdtext
+        ));
+    }
+
+    fn toggle_normalize_mode(&mut self) {
+        let options = self.state.burn_options_mut();
+        let mode = match options.normalization().1 {
+            NormalizeMode::Album => NormalizeMode::Track,
+            NormalizeMode::Track => NormalizeMode::Album,
+        };
+        options.normalize_mode = Some(mode);
+        self.status_msg = Cow::Owned(format!(
+            "Normalization: {} gain ({:?} profile)",
+            mode.name(),
+            options.normalize
+        ));
+    }
+
+    fn clear_playlist(&mut self) {
+        self.state.playlist_clear();
+        self.playlist = WrappingTableState::default();
+    }
+
+    /// Undoes or redoes the last playlist change, keeping the selection on the playlist.
+    fn step_journal(&mut self, undo: bool) {
+        let stepped = if undo {
+            self.state
+                .playlist_undo()
+                .map(|label| format!("Undid {}", label))
+        } else {
+            self.state
+                .playlist_redo()
+                .map(|label| format!("Redid {}", label))
+        };
+        self.status_msg = match stepped {
+            Ok(message) => Cow::Owned(message),
+            Err(err) => Cow::Owned(err.to_string()),
+        };
+        let last = self.visible_playlist().len().saturating_sub(1);
+        self.playlist
+            .0
+            .select(Some(self.playlist.selected().min(last)));
+    }
+}
+
+// --- MAIN ENTRY ---
+
+pub fn run_tui() -> Result<()> {
+    // Terminal Init
+    enable_raw_mode()?;
+    shutdown::set_raw_terminal(true);
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    // App Init
+    let state = AppState::new()?;
+    let mut view = View::new(state)?;
+
+    // Initial load
+    view.restore_ui();
+
+    let res = run_app(&mut terminal, &mut view);
+
+    // Terminal Restore
+    notify::restore_title();
+    disable_raw_mode()?;
+    shutdown::set_raw_terminal(false);
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    if let Err(err) = res {
+        println!("{:?}", err);
+    }
+
+    Ok(())
+}
+
+/// Lets the user pick tracks with the fuzzy finder and prints their IDs, or their paths
+/// when `paths` is set, one per line for e.g. `playlist add -`. The finder is drawn on
+/// stderr to keep stdout for the picks. Tab marks several tracks; Enter picks the marked
+/// ones or else the highlighted one, and Esc picks nothing.
+pub fn run_pick(paths: bool) -> Result<()> {
+    let state = AppState::new()?;
+    let library = queries::list_all_tracks(state.conn())?;
+    let mut finder = Finder::new(Rc::new(Candidates::new(library)), vec![]);
+
+    enable_raw_mode().context("pick needs a terminal to show the finder on")?;
+    let mut stderr = io::stderr();
+    execute!(stderr, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stderr))?;
+
+    let res = pick_loop(&mut terminal, &mut finder);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    for i in res? {
+        let song = &finder.candidates.library[i];
+        if paths {
+            println!("{}", song.path);
+        } else {
+            println!("{}", song.id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the picker until the user is done, returning the picked library indices.
+fn pick_loop<B: Backend>(terminal: &mut Terminal<B>, finder: &mut Finder) -> Result<Vec<usize>> {
+    let mut marked: Vec<usize> = vec![];
+    loop {
+        terminal.draw(|f| pick_ui(f, finder, &marked))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        match key.code {
+            KeyCode::Esc => return Ok(vec![]),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(vec![]);
+            }
+            KeyCode::Enter if marked.is_empty() => {
+                return Ok(finder.selected_index().into_iter().collect());
+            }
+            KeyCode::Enter => return Ok(marked),
+            KeyCode::Tab => {
+                if let Some(i) = finder.selected_index() {
+                    match marked.iter().position(|&m| m == i) {
+                        Some(pos) => {
+                            marked.remove(pos);
+                        }
+                        None => marked.push(i),
+                    }
+                    finder.next();
+                }
+            }
+            KeyCode::Up => finder.prev(),
+            KeyCode::Down => finder.next(),
+            KeyCode::Backspace => {
+                finder.query.pop();
+                finder.update();
+            }
+            KeyCode::Char(c) => {
+                finder.query.push(c);
+                finder.update();
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Hands the terminal to shell command `line` so it can print and ask questions as it
+/// would in the shell, then takes it back once Enter is pressed. Returns whether the
+/// command asked to quit.
+fn run_suspended<B: Backend>(
+    terminal: &mut Terminal<B>,
+    state: &mut AppState,
+    line: &str,
+) -> Result<bool> {
+    disable_raw_mode()?;
+    shutdown::set_raw_terminal(false);
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+
+    println!(":{}", line);
+    let quit = command::run_command(state, line, &mut command::StdConsole);
+    if !quit {
+        print!("\nPress Enter to return");
+        io::stdout().flush()?;
+        io::stdin().read_line(&mut String::new())?;
+    }
+
+    enable_raw_mode()?;
+    shutdown::set_raw_terminal(true);
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+
+    Ok(quit)
+}
+
+const ARTIST_HELP: [&str; 12] = [
+    "(ESC) Quit",
+    "(Ctrl+P) Find",
+    "(Ctrl+R) Recently Previewed",
+    "(Ctrl+A) Album Grid",
+    "(Ctrl+L) Burn Logs",
+    "(:) Command",
+    "(→ / Enter) Tracks Tab",
+    "(↑ / ↓) Navigate Artists",
+    "Jump To A Letter",
+    "(/) Search Library",
+    "(Ctrl+G / Ctrl+U) Merge / Unmerge Spellings",
+    "(+ / -) Resize Pane",
+];
+const TRACK_HELP: [&str; 13] = [
+    "(←) Artists Tab",
+    "(↑ / ↓) Navigate Tracks",
+    "(→) Playlist Tab",
+    "(Enter) Add Track",
+    "(E) Add Exact File",
+    "(W) Waveform",
+    "(I) Details",
+    "(N) Note",
+    "(L) Prefer Lossless",
+    "(A) Park For Audition",
+    "(H) Add Chapters",
+    "(/) Search Library",
+    "(Esc) End Search",
+];
+const PLAYLIST_HELP: [&str; 19] = [
+    "(←) Tracks Tab",
+    "(Backspace) Remove Track",
+    "(/) Filter",
+    "(K / J) Move Up / Down",
+    "(Drag) Reorder",
+    "(B) Burn Playlist",
+    "(C) Clear Playlist",
+    "(T) Toggle ASCII CD-TEXT",
+    "(G) Album / Track Gain",
+    "(R) Restore Session",
+    "(M) Balanced Mix",
+    "(P) Preview Transition",
+    "(O) Optimize Order",
+    "(U / Y) Undo / Redo",
+    "(D) Disc Title",
+    "(I) Details",
+    "(N) Note",
+    "(A) Back To Audition",
+    "(Tab) Audition List",
+];
+const AUDITION_HELP: [&str; 6] = [
+    "(←) Tracks Tab",
+    "(↑ / ↓) Navigate",
+    "(Enter) Move To Playlist",
+    "(P / Space) Audition",
+    "(Backspace) Drop",
+    "(Tab / Esc) Playlist",
+];
+
+#[derive(Debug)]
+enum BurnPhase {
+    BuildingPlaylist,
+    Burning {
+        logs: Vec<ratatui::text::Line<'static>>,
+        buffer: Option<BufferStatus>,
+        /// Stage in progress and when it is expected to finish.
+        eta: Option<(BurnStage, Instant)>,
+        title: TaskTitle,
+        completed: bool,
+        rx: mpsc::Receiver<TaskEvent>,
+        handle: Option<JoinHandle<Result<()>>>,
+    },
+    Completed {
+        logs: Vec<ratatui::text::Line<'static>>,
+    },
+}
+
+/// `query` with its last word ending in `*` to match as a prefix, e.g. `radi` finding
+/// Radiohead. Filters such as `language:eng`, words already ending in `*` and a query
+/// ending in a space are left as they are.
+fn prefix_query(query: &str) -> Cow<'_, str> {
+    match query.split_whitespace().last() {
+        Some(word)
+            if !query.ends_with(char::is_whitespace)
+                && !word.ends_with('*')
+                && !word.contains(':') =>
+        {
+            Cow::Owned(format!("{}*", query))
+        }
+        _ => Cow::Borrowed(query),
+    }
+}
+
+/// Starts burning the playlist in the background.
+fn start_burn(state: &AppState) -> Result<BurnPhase> {
+    let (handle, rx) = state.burn().context("failed to setup burn task")?;
+
+    Ok(BurnPhase::Burning {
+        logs: vec![],
+        buffer: None,
+        eta: None,
+        title: TaskTitle::new(&state.disc_title()?),
+        completed: false,
+        rx,
+        handle: Some(handle),
+    })
+}
 
-fn to_ratatui_line(result: Result<String>) -> ratatui::text::Line<'static> {
-    let (line, style) = match result {
+/// The error's report, followed by a hint for fixing it when there is one.
+fn error_text(err: &anyhow::Error) -> String {
+    match crate::errors::hint(err) {
+        Some(hint) => format!("{:?}\nHint: {}", err, hint),
+        None => format!("{:?}", err),
+    }
+}
+
+/// The log lines for a task's outcome, one per line of its text, since a ratatui
+/// [`Line`] doesn't break on the newlines in e.g. a tool's stderr.
+fn to_ratatui_lines(result: Result<String>) -> Vec<Line<'static>> {
+    let (text, style) = match result {
         Ok(text) => (text, Style::default().fg(Color::Green)),
-        Err(err) => (format!("{:?}", err), Style::default().fg(Color::Red)),
+        Err(err) => (error_text(&err), Style::default().fg(Color::Red)),
     };
 
-    Line::from(vec![Span::styled(line, style)])
+    text.split('\n')
+        .map(|line| Line::from(vec![Span::styled(line.to_string(), style)]))
+        .collect()
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, view: &mut View) -> Result<()> {
-    use ratatui::text::{Line, Span};
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, view: &mut View) -> Result<()> {
+    use ratatui::text::{Line, Span};
+
+    let mut burn_view = BurnPhase::BuildingPlaylist;
+
+    loop {
+        {
+            use BurnPhase::*;
+            use crossterm::event::KeyEvent;
+
+            // render the specific burn view if we are in a burning phase
+            match &mut burn_view {
+                Burning {
+                    logs,
+                    buffer,
+                    eta,
+                    title,
+                    completed,
+                    rx,
+                    handle,
+                } => {
+                    // update our log lines
+                    while let Ok(log_msg) = rx.try_recv() {
+                        title.update(&log_msg);
+                        let result = match log_msg {
+                            TaskEvent::Done(summary) => Ok(format!("✅ {}", summary)),
+                            TaskEvent::Error(err) => Err(anyhow::anyhow!(error_text(&err))),
+                            TaskEvent::TranscodeProgress { done, total, title } => {
+                                logs.push(Line::from(vec![Span::styled(
+                                    format!("[{}/{}] transcoding {}...", done + 1, total, title),
+                                    Style::default().fg(Color::Green),
+                                )]));
+                                continue;
+                            }
+                            TaskEvent::BurnStage(stage) => {
+                                // a new stage makes the previous estimate meaningless
+                                *eta = None;
+                                logs.push(Line::from(vec![Span::styled(
+                                    format!("--- {:?} ---", stage),
+                                    Style::default().fg(Color::White),
+                                )]));
+                                continue;
+                            }
+                            TaskEvent::Eta {
+                                stage,
+                                remaining_secs,
+                            } => {
+                                *eta = Some((
+                                    stage,
+                                    Instant::now() + Duration::from_secs(remaining_secs),
+                                ));
+                                continue;
+                            }
+                            TaskEvent::ScanProgress { .. } | TaskEvent::BurnProgress { .. } => {
+                                continue;
+                            }
+                            TaskEvent::Buffer(status) => {
+                                *buffer = Some(status);
+                                continue;
+                            }
+                            TaskEvent::Line(LogLine { is_stderr, line }) => {
+                                let style = if is_stderr {
+                                    Style::default().fg(Color::Red)
+                                } else {
+                                    Style::default().fg(Color::Green)
+                                };
+                                let text = Line::from(vec![Span::styled(line, style)]);
+                                logs.push(text);
+                                continue;
+                            }
+                        };
+
+                        logs.extend(to_ratatui_lines(result));
+                        logs.push(Line::from(vec![Span::styled(
+                            "Press 'Q' to build a new playlist",
+                            Style::default().fg(Color::White),
+                        )]));
+
+                        // SAFETY: assuming that we are receiving messages, it means we have an open thread handle to clean up.
+                        let final_result = match handle.take().unwrap().join() {
+                            Ok(result) => result,
+                            Err(err) => {
+                                anyhow::bail!("failed to join background burn thread: {:?}", err)
+                            }
+                        };
+
+                        logs.extend(to_ratatui_lines(final_result.map(|_| String::from(""))));
+
+                        *completed = true;
+                    }
+
+                    let remaining = eta.map(|(stage, deadline)| {
+                        (stage, deadline.saturating_duration_since(Instant::now()))
+                    });
+                    terminal.draw(|f| burn_ui(f, logs, *buffer, remaining))?;
+                    if *completed {
+                        let mut old_lines = vec![];
+                        std::mem::swap(&mut old_lines, logs);
+                        burn_view = BurnPhase::Completed { logs: old_lines };
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                Completed { logs } => {
+                    terminal.draw(|f| burn_ui(f, logs, None, None))?;
+                    if let Event::Key(KeyEvent {
+                        code: KeyCode::Char('Q'),
+                        ..
+                    }) = event::read()?
+                    {
+                        notify::restore_title();
+                        view.library_changed();
+                        burn_view = BurnPhase::BuildingPlaylist;
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                BuildingPlaylist => (),
+            }
+        }
+
+        if style::accessible() {
+            view.announce_focus();
+        }
+        view.receive_loaded();
+        terminal.draw(|f| ui(f, view))?;
+        view.draw_album_art()?;
+
+        // load the tracks of an artist scrolled to, or run the search being typed, once
+        // no key follows for a moment, and show what is read in the background once it is
+        let settle = [
+            view.pending_artist.map(|(_, since)| since + ARTIST_SETTLE),
+            view.pending_search.map(|since| since + SEARCH_SETTLE),
+            view.loading().then(|| Instant::now() + LOADING_POLL),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        if let Some(deadline) = settle
+            && !event::poll(deadline.saturating_duration_since(Instant::now()))?
+        {
+            view.settle_artist();
+            view.settle_search();
+            continue;
+        }
+
+        let event = event::read()?;
+        view.check_transition();
+        let key = match event {
+            Event::Key(key) => key,
+            // the mouse only reorders the playlist, and not from under an overlay
+            Event::Mouse(mouse)
+                if view.waveform.is_none()
+                    && view.details.is_none()
+                    && view.finder.is_none()
+                    && view.recent.is_none()
+                    && view.album_grid.is_none()
+                    && view.burns.is_none()
+                    && view.burn_log.is_none()
+                    && view.version_picker.is_none()
+                    && view.prompt.is_none()
+                    && view.confirm.is_none()
+                    && view.disc_preview.is_none()
+                    && view.reorder.is_none()
+                    && view.audition.is_none()
+                    && !view.editing_filter
+                    && !view.editing_search =>
+            {
+                view.playlist_mouse(mouse);
+                continue;
+            }
+            _ => continue,
+        };
+
+        if key.code == KeyCode::Char('v')
+            && view
+                .details
+                .as_ref()
+                .is_some_and(|details| details.versions.len() > 1)
+        {
+            view.pick_version_from_details();
+            continue;
+        }
+        // any other key dismisses the waveform and details popups
+        if view.waveform.take().is_some() || view.details.take().is_some() {
+            continue;
+        }
+
+        if view.editing_filter {
+            view.filter_key(key.code);
+            continue;
+        }
+        if view.editing_search {
+            view.search_key(key.code);
+            continue;
+        }
+
+        if let Some((_, action)) = view.confirm.take() {
+            if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                match action {
+                    ConfirmAction::Clear => view.clear_playlist(),
+                    ConfirmAction::Quit => {
+                        view.save_session();
+                        return Ok(());
+                    }
+                }
+            }
+            view.save_session();
+            continue;
+        }
+
+        if view.disc_preview.is_some() {
+            match key.code {
+                KeyCode::Enter => {
+                    view.disc_preview = None;
+                    burn_view = start_burn(&view.state)?;
+                }
+                KeyCode::Esc => view.disc_preview = None,
+                _ => (),
+            }
+            continue;
+        }
+
+        if view.reorder.is_some() {
+            match key.code {
+                KeyCode::Enter => {
+                    if let Some((plan, _)) = view.reorder.take() {
+                        view.status_msg = if view.state.playlist_apply_order(&plan) {
+                            Cow::Borrowed("Playlist reordered")
+                        } else {
+                            Cow::Borrowed("The playlist changed, optimize it again")
+                        };
+                    }
+                }
+                KeyCode::Esc => view.reorder = None,
+                _ => (),
+            }
+            view.save_session();
+            continue;
+        }
+
+        if view.version_picker.is_some() {
+            view.version_picker_key(key.code);
+            view.save_session();
+            continue;
+        }
+
+        if view.prompt.is_some() {
+            view.prompt_key(key.code);
+            if let Some(line) = view.command.take() {
+                if run_suspended(terminal, &mut view.state, &line)? {
+                    view.save_session();
+                    return Ok(());
+                }
+                view.library_changed();
+            }
+            view.save_session();
+            continue;
+        }
+
+        if view.finder.is_some() {
+            view.finder_key(key.code);
+            view.save_session();
+            continue;
+        }
+
+        if view.recent.is_some() {
+            view.recent_key(key.code);
+            view.save_session();
+            continue;
+        }
+
+        if view.album_grid.is_some() {
+            view.album_grid_key(key.code);
+            // ratatui doesn't know about the art, so it is wiped by hand
+            if view.album_grid.is_none() {
+                match view.art {
+                    Some(Protocol::Kitty) => {
+                        let mut stdout = io::stdout();
+                        stdout.write_all(art::KITTY_CLEAR.as_bytes())?;
+                        stdout.flush()?;
+                    }
+                    Some(Protocol::Sixel) => terminal.clear()?,
+                    None => (),
+                }
+            }
+            continue;
+        }
 
-    let mut burn_view = BurnPhase::BuildingPlaylist;
+        if view.burn_log.is_some() {
+            view.burn_log_key(key.code);
+            continue;
+        }
 
-    loop {
-        {
-            use BurnPhase::*;
-            use crossterm::event::KeyEvent;
-            use std::time::Duration;
+        if view.burns.is_some() {
+            view.burns_key(key.code);
+            continue;
+        }
 
-            // render the specific burn view if we are in a burning phase
-            match &mut burn_view {
-                Burning {
-                    logs,
-                    completed,
-                    rx,
-                    handle,
-                } => {
-                    // update our log lines
-                    while let Ok(log_msg) = rx.try_recv() {
-                        match log_msg {
-                            LogMessage::Complete(result) => {
-                                logs.push(to_ratatui_line(result));
-                                logs.push(Line::from(vec![Span::styled(
-                                    "Press 'Q' to build a new playlist",
-                                    Style::default().fg(Color::White),
-                                )]));
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('p') {
+            view.open_finder();
+            continue;
+        }
 
-                                // SAFETY: assuming that we are receiving messages, it means we have an open thread handle to clean up.
-                                let final_result = match handle.take().unwrap().join() {
-                                    Ok(result) => result,
-                                    Err(err) => anyhow::bail!(
-                                        "failed to join background burn thread: {:?}",
-                                        err
-                                    ),
-                                };
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('r') {
+            view.open_recent();
+            continue;
+        }
 
-                                logs.push(to_ratatui_line(final_result.map(|_| String::from(""))));
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('l') {
+            view.open_burns();
+            continue;
+        }
 
-                                *completed = true;
-                            }
-                            LogMessage::Line(LogLine { is_stderr, line }) => {
-                                let style = if is_stderr {
-                                    Style::default().fg(Color::Red)
-                                } else {
-                                    Style::default().fg(Color::Green)
-                                };
-                                let text = Line::from(vec![Span::styled(line, style)]);
-                                logs.push(text);
-                            }
-                        }
-                    }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('a') {
+            view.open_album_grid();
+            continue;
+        }
 
-                    terminal.draw(|f| burn_ui(f, logs))?;
-                    if *completed {
-                        let mut old_lines = vec![];
-                        std::mem::swap(&mut old_lines, logs);
-                        burn_view = BurnPhase::Completed { logs: old_lines };
-                    }
-                    thread::sleep(Duration::from_millis(1));
-                    continue;
-                }
-                Completed { logs } => {
-                    terminal.draw(|f| burn_ui(f, logs))?;
-                    if let Event::Key(KeyEvent {
-                        code: KeyCode::Char('Q'),
-                        ..
-                    }) = event::read()?
-                    {
-                        burn_view = BurnPhase::BuildingPlaylist;
-                    }
-                    thread::sleep(Duration::from_millis(1));
-                    continue;
-                }
-                BuildingPlaylist => (),
-            }
+        if key.code == KeyCode::Char('R') {
+            view.restore_session();
+            view.save_session();
+            continue;
         }
 
-        terminal.draw(|f| ui(f, view))?;
+        if key.code == KeyCode::Char(':') {
+            view.prompt = Some(Prompt {
+                title: " Command, as in the shell (Tab: complete, Enter: run, Esc: cancel) ",
+                input: String::new(),
+                action: PromptAction::Command,
+            });
+            continue;
+        }
 
-        let Event::Key(key) = event::read()? else {
+        if let KeyCode::Char(c @ ('+' | '-')) = key.code {
+            let pane = match view.active_pane {
+                ActivePane::Artists => 0,
+                ActivePane::ArtistTracks => 1,
+                ActivePane::Playlist => 2,
+            };
+            if !view.panes.resize(pane, c == '+') {
+                view.status_msg = Cow::Borrowed("Pane can't be resized any further");
+            }
             continue;
-        };
+        }
 
         match view.active_pane {
             ActivePane::Artists => match key.code {
-                KeyCode::Esc => return Ok(()),
+                KeyCode::Esc => {
+                    let unburned = match view.state.playlist_unburned() {
+                        Ok(unburned) => unburned,
+                        Err(err) => {
+                            view.status_msg = Cow::Owned(format!("{:?}", err));
+                            true
+                        }
+                    };
+                    if view.state.config().confirm.quit(unburned) {
+                        let question = if unburned {
+                            "The playlist hasn't been burned yet. Quit anyway?"
+                        } else {
+                            "Quit?"
+                        };
+                        view.confirm = Some((question.to_string(), ConfirmAction::Quit));
+                    } else {
+                        view.save_session();
+                        return Ok(());
+                    }
+                }
                 KeyCode::Right | KeyCode::Enter => {
+                    view.settle_artist();
                     view.active_pane = ActivePane::ArtistTracks;
                     view.tracks
                         .state
@@ -346,17 +2900,34 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, view: &mut View) -> Result<()
                 }
                 KeyCode::Up => {
                     let i = view.artists.prev();
-                    view.load_selected_artist(i);
+                    view.pending_artist = Some((i, Instant::now()));
                 }
                 KeyCode::Down => {
                     let i = view.artists.next();
-                    view.load_selected_artist(i);
+                    view.pending_artist = Some((i, Instant::now()));
+                }
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    if let Some(artist) = view.selected_artist() {
+                        view.prompt = Some(Prompt {
+                            title: " Browse this artist under (Enter: merge, Esc: cancel) ",
+                            input: String::new(),
+                            action: PromptAction::MergeArtist(artist),
+                        });
+                    }
+                }
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    view.unmerge_selected_artist();
+                }
+                KeyCode::Char('/') => {
+                    view.open_search();
                 }
                 KeyCode::Char(c) => {
                     let s = String::from(c);
-                    let i = match view.artists.items.as_slice().binary_search(&s) {
-                        Ok(i) | Err(i) => i,
-                    };
+                    // artists come in the library's sort order, not byte order
+                    let i = view
+                        .artists
+                        .items
+                        .partition_point(|artist| collate::compare(artist, &s).is_lt());
                     view.artists.state.select(Some(i));
                 }
                 _ => (),
@@ -378,47 +2949,192 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, view: &mut View) -> Result<()
                 KeyCode::Enter => {
                     view.add_current_track();
                 }
+                // Shift+Enter only reaches us on terminals with enhanced key reporting
+                KeyCode::Char('E') => {
+                    view.add_current_track_exact();
+                }
+                KeyCode::Char('L') => {
+                    view.toggle_prefer_lossless();
+                }
+                KeyCode::Char('W') => {
+                    view.show_current_waveform();
+                }
+                KeyCode::Char('I') => {
+                    view.show_focused_details();
+                }
+                KeyCode::Char('N') => {
+                    view.edit_focused_note();
+                }
+                KeyCode::Char('A') => {
+                    view.park_current_track();
+                }
+                KeyCode::Char('H') => {
+                    view.add_current_chapters();
+                }
+                KeyCode::Char('/') => {
+                    view.open_search();
+                }
+                KeyCode::Esc if view.library_search.is_some() => {
+                    view.close_search();
+                }
                 _ => (),
             },
+            ActivePane::Playlist if view.audition.is_some() => {
+                view.audition_key(key.code);
+            }
             ActivePane::Playlist => match key.code {
                 KeyCode::Left => {
                     view.active_pane = ActivePane::ArtistTracks;
                 }
                 KeyCode::Up => {
-                    view.playlist.prev(view.state.playlist());
+                    view.playlist.prev(&view.visible_playlist());
                 }
                 KeyCode::Down => {
-                    view.playlist.next(view.state.playlist());
+                    view.playlist.next(&view.visible_playlist());
+                }
+                KeyCode::Char('K') => {
+                    view.move_selected_playlist_entry(true);
+                }
+                KeyCode::Char('J') => {
+                    view.move_selected_playlist_entry(false);
                 }
                 KeyCode::Backspace => {
-                    let index = view.playlist.selected();
-                    view.state.playlist_remove(index);
+                    view.remove_selected_playlist_entry();
+                }
+                KeyCode::Char('/') => {
+                    view.editing_filter = true;
+                }
+                KeyCode::Esc => {
+                    view.playlist_filter.clear();
+                    view.playlist = WrappingTableState::default();
                 }
                 KeyCode::Char('C') => {
-                    view.clear_playlist();
+                    let len = view.state.playlist().len();
+                    if view.state.config().confirm.clear(len) {
+                        view.confirm =
+                            Some((format!("Clear all {} tracks?", len), ConfirmAction::Clear));
+                    } else {
+                        view.clear_playlist();
+                    }
+                }
+                KeyCode::Char('T') => {
+                    view.toggle_cdtext_mode();
+                }
+                KeyCode::Char('G') => {
+                    view.toggle_normalize_mode();
+                }
+                KeyCode::Char('I') => {
+                    view.show_focused_details();
+                }
+                KeyCode::Char('N') => {
+                    view.edit_focused_note();
+                }
+                KeyCode::Char('P') => {
+                    view.preview_transition();
+                }
+                KeyCode::Char('O') => {
+                    view.optimize_order();
+                }
+                KeyCode::Char('U') => {
+                    view.step_journal(true);
+                }
+                KeyCode::Char('Y') => {
+                    view.step_journal(false);
+                }
+                KeyCode::Char('A') => {
+                    view.demote_selected_playlist_entry();
+                }
+                KeyCode::Tab => {
+                    view.audition = Some(WrappingTableState::default());
+                }
+                KeyCode::Char('D') => {
+                    view.prompt = Some(Prompt {
+                        title: " Disc title (Enter: set, empty for a suggestion, Esc: cancel) ",
+                        input: view.state.burn_options().title.clone().unwrap_or_default(),
+                        action: PromptAction::DiscTitle,
+                    });
+                }
+                KeyCode::Char('M') => {
+                    view.prompt = Some(Prompt {
+                        title: " Balanced mix, e.g. rock:50 soul:30 1970s:20 (Enter: build, Esc: cancel) ",
+                        input: String::new(),
+                        action: PromptAction::BalancedMix,
+                    });
                 }
                 KeyCode::Char('B') => {
-                    let (handle, rx) = view.state.burn().context("failed to setup burn task")?;
-                    burn_view = BurnPhase::Burning {
-                        logs: vec![],
-                        completed: false,
-                        rx,
-                        handle: Some(handle),
-                    };
+                    if view.state.playlist().is_empty() {
+                        view.status_msg = Cow::Borrowed("Nothing to burn: the playlist is empty");
+                    } else if app::read_only() {
+                        view.status_msg = Cow::Borrowed("Burning is disabled in read-only mode");
+                    } else if view.state.config().confirm.burn {
+                        view.disc_preview = Some(view.state.disc_layout().lines());
+                    } else {
+                        burn_view = start_burn(&view.state)?;
+                    }
                 }
 
                 _ => (),
             },
         }
+
+        view.save_session();
     }
 }
 
 // --- UI RENDERING ---
 
-fn playlist_song_to_row(s: &Song) -> Row<'_> {
+// The playlist column is narrow, so titles are cut by display width up front
+// rather than letting wide characters spill over the duration column.
+const PLAYLIST_TITLE_WIDTH: usize = 24;
+// Playlist pane width, borders included, from which it also shows where each entry
+// starts on the disc and how much of the disc is left after it
+const PLAYLIST_WIDE_WIDTH: u16 = 40;
+
+/// The rows of a `len` row list that fit in `height` lines, scrolled as little as
+/// possible from `offset` to keep `selected` in view, the way ratatui scrolls.
+fn window(len: usize, selected: Option<usize>, offset: usize, height: u16) -> Range<usize> {
+    let height = (height as usize).max(1);
+    let mut start = offset.min(len.saturating_sub(1));
+    if let Some(selected) = selected.filter(|&i| i < len) {
+        if selected < start {
+            start = selected;
+        } else if selected >= start + height {
+            start = selected + 1 - height;
+        }
+    }
+
+    start..(start + height).min(len)
+}
+
+/// Playlist entry `track` as a row: its number, title and how long it plays, and when
+/// the pane is `wide` enough, where it starts on the disc and how much of the disc is
+/// left after it.
+fn playlist_entry_to_row(track: &LayoutTrack, wide: bool) -> Row<'static> {
+    let mut cells = vec![
+        Cell::from(track.number.to_string()),
+        Cell::from(app::fit_width(&track.title, PLAYLIST_TITLE_WIDTH)),
+    ];
+    if wide {
+        cells.push(Cell::from(duration::format_secs(track.start_secs())));
+    }
+    cells.push(Cell::from(duration::format_secs(track.length_secs())));
+    if wide {
+        let spare = track.spare_secs();
+        let left = duration::format_secs(spare.unsigned_abs());
+        cells.push(Cell::from(if spare < 0 {
+            format!("-{}", left)
+        } else {
+            left
+        }));
+    }
+
+    Row::new(cells)
+}
+
+fn audition_song_to_row(s: &Song) -> Row<'_> {
     Row::new(vec![
-        Cell::from(s.title.clone()),
-        Cell::from(app::humantime_secs(s.duration_sec).to_string()),
+        Cell::from(app::fit_width(&s.title, PLAYLIST_TITLE_WIDTH)),
+        Cell::from(duration::format_secs(s.played_duration_secs())),
     ])
 }
 
@@ -427,7 +3143,7 @@ fn song_to_row(s: &Song) -> Row<'_> {
         Cell::from(s.title.clone()),
         Cell::from(s.album.clone()),
         Cell::from(s.year.to_string()),
-        Cell::from(app::humantime_secs(s.duration_sec).to_string()),
+        Cell::from(duration::format_secs(s.duration_sec)),
     ])
 }
 
@@ -450,28 +3166,47 @@ fn ui(f: &mut Frame, view: &mut View) {
         (artist, tracks, playlist)
     };
     // 1. Vertical Layout: Main Body vs Bottom Bar
+    let accessible = style::accessible();
+    let bottom_bar = view.state.config().bottom_bar;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(0),
-            Constraint::Length(3), // Bottom bar height
+            // Bottom bar height, with a line of its own for the status when accessible
+            Constraint::Length(if accessible { 4 } else { bottom_bar.height() }),
+            Constraint::Length(1), // Status bar
         ])
         .split(f.area());
 
     // 2. Horizontal Layout: Artist | Library | Playlist
+    let [artists_width, mut tracks_width, mut playlist_width] = view.panes.0;
+    if view.collapse_empty_playlist
+        && view.state.playlist().is_empty()
+        && view.active_pane != ActivePane::Playlist
+    {
+        tracks_width += playlist_width;
+        playlist_width = 0;
+    }
     let body_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(20), // Artists
-            Constraint::Percentage(60), // Tracks
-            Constraint::Percentage(20), // Playlist
+            Constraint::Percentage(artists_width),  // Artists
+            Constraint::Percentage(tracks_width),   // Tracks
+            Constraint::Percentage(playlist_width), // Playlist
         ])
         .split(chunks[0]);
 
+    // Only the rows that fit are built: each pane renders a window of its items with a
+    // throwaway state, and the window's start is kept as the pane's scroll offset.
+
     // --- ARTIST COLUMN ---
-    let artists: Vec<ListItem> = view
-        .artists
-        .items
+    let artist_window = window(
+        view.artist_labels.len(),
+        view.artists.state.selected(),
+        view.artists.state.offset(),
+        body_chunks[0].height.saturating_sub(2),
+    );
+    let artists: Vec<ListItem> = view.artist_labels[artist_window.clone()]
         .iter()
         .map(|a| ListItem::new(Line::from(a.as_str())))
         .collect();
@@ -479,15 +3214,33 @@ fn ui(f: &mut Frame, view: &mut View) {
     let artist_block = Block::default()
         .borders(Borders::ALL)
         .title(" Artists ")
+        .title_bottom(format!(" {} ", view.library_totals))
         .border_style(artist_border);
 
     let artist_list = List::new(artists)
         .block(artist_block)
         .highlight_style(highlight_item_style);
 
-    f.render_stateful_widget(artist_list, body_chunks[0], &mut view.artists.state);
-
-    let library_rows: Vec<Row> = view.tracks.items.iter().map(song_to_row).collect();
+    let mut artist_state = ListState::default().with_selected(
+        view.artists
+            .state
+            .selected()
+            .and_then(|i| i.checked_sub(artist_window.start)),
+    );
+    f.render_stateful_widget(artist_list, body_chunks[0], &mut artist_state);
+    *view.artists.state.offset_mut() = artist_window.start;
+
+    // borders, plus the header and the gap below it
+    let library_window = window(
+        view.tracks.items.len(),
+        view.tracks.state.0.selected(),
+        view.tracks.state.0.offset(),
+        body_chunks[1].height.saturating_sub(4),
+    );
+    let library_rows: Vec<Row> = view.tracks.items[library_window.clone()]
+        .iter()
+        .map(song_to_row)
+        .collect();
 
     let library_table = Table::new(
         library_rows,
@@ -510,97 +3263,773 @@ fn ui(f: &mut Frame, view: &mut View) {
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .title(" Tracks ")
+            .title(match &view.library_search {
+                Some(query) => format!(" Search /{} ", query),
+                None => String::from(" Tracks "),
+            })
             .border_style(tracks_border),
     )
     .row_highlight_style(highlight_item_style);
 
-    f.render_stateful_widget(library_table, body_chunks[1], &mut view.tracks.state.0);
+    let mut library_state = TableState::default().with_selected(
+        view.tracks
+            .state
+            .0
+            .selected()
+            .and_then(|i| i.checked_sub(library_window.start)),
+    );
+    f.render_stateful_widget(library_table, body_chunks[1], &mut library_state);
+    *view.tracks.state.0.offset_mut() = library_window.start;
 
     // --- PLAYLIST COLUMN ---
-    let playlist_rows: Vec<Row> = view
-        .state
-        .playlist()
+    let visible_playlist = view.visible_playlist();
+    // borders, plus the header and the gap below it
+    let playlist_window = window(
+        visible_playlist.len(),
+        view.playlist.0.selected(),
+        view.playlist.0.offset(),
+        body_chunks[2].height.saturating_sub(4),
+    );
+    let layout = view.state.disc_layout();
+    let wide_playlist = body_chunks[2].width >= PLAYLIST_WIDE_WIDTH;
+    let warnings = view.state.playlist_warnings_given(&view.unplayable);
+    let playlist_rows: Vec<Row> = visible_playlist[playlist_window.clone()]
         .iter()
-        .map(playlist_song_to_row)
+        .map(|&i| {
+            let row = playlist_entry_to_row(&layout.tracks[i], wide_playlist);
+            match warnings[i] {
+                Some(_) => row.style(Style::default().fg(Color::Yellow)),
+                None => row,
+            }
+        })
         .collect();
 
     // Calculate total time
-    let total_secs: u64 = view.state.playlist().iter().map(|s| s.duration_sec).sum();
-    let playlist_title = format!(" Playlist ({}/80m) ", app::humantime_secs(total_secs));
+    let total_secs: u64 = view
+        .state
+        .playlist()
+        .iter()
+        .map(|s| s.played_duration_secs())
+        .sum();
+    let mut playlist_title = format!(" Playlist ({}/80m) ", duration::format_secs(total_secs));
+    if view.editing_filter || !view.playlist_filter.is_empty() {
+        playlist_title += &format!("/{} ", view.playlist_filter);
+    }
+    // why the selected entry is flagged takes the place of the disc title
+    let playlist_footer = match view
+        .selected_playlist_index()
+        .and_then(|i| warnings[i].as_deref())
+    {
+        Some(warning) => Line::styled(
+            format!(" ! {} ", warning),
+            Style::default().fg(Color::Yellow),
+        ),
+        None => Line::from(format!(" {} ", view.disc_title)),
+    };
 
-    let playlist_table = Table::new(
-        playlist_rows,
-        [Constraint::Percentage(80), Constraint::Percentage(20)],
-    )
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(playlist_title)
-            .border_style(playlist_border),
-    )
-    .row_highlight_style(highlight_item_style);
-    f.render_stateful_widget(playlist_table, body_chunks[2], &mut view.playlist.0);
+    let (playlist_widths, playlist_header) = if wide_playlist {
+        (
+            vec![
+                Constraint::Length(2), // #
+                Constraint::Min(0),    // Title
+                Constraint::Length(7), // Start
+                Constraint::Length(5), // Time
+                Constraint::Length(8), // Left
+            ],
+            vec!["#", "Title", "Start", "Time", "Left"],
+        )
+    } else {
+        (
+            vec![
+                Constraint::Length(2), // #
+                Constraint::Min(0),    // Title
+                Constraint::Length(5), // Time
+            ],
+            vec!["#", "Title", "Time"],
+        )
+    };
+    let playlist_table = Table::new(playlist_rows, playlist_widths)
+        .header(
+            Row::new(playlist_header)
+                .style(
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .bottom_margin(1),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(playlist_title)
+                .title_bottom(playlist_footer)
+                .border_style(playlist_border),
+        )
+        .row_highlight_style(highlight_item_style);
+    let mut playlist_state = TableState::default().with_selected(
+        view.playlist
+            .0
+            .selected()
+            .and_then(|i| i.checked_sub(playlist_window.start)),
+    );
+    f.render_stateful_widget(playlist_table, body_chunks[2], &mut playlist_state);
+    *view.playlist.0.offset_mut() = playlist_window.start;
+    view.playlist_area = body_chunks[2];
+
+    // the audition list is drawn over the playlist it feeds
+    if let Some(selection) = view.audition.as_mut() {
+        let parked = view.state.audition();
+        let audition_window = window(
+            parked.len(),
+            selection.0.selected(),
+            selection.0.offset(),
+            body_chunks[2].height.saturating_sub(2),
+        );
+        let audition_rows: Vec<Row> = parked[audition_window.clone()]
+            .iter()
+            .map(audition_song_to_row)
+            .collect();
+        let audition_table = Table::new(
+            audition_rows,
+            [Constraint::Percentage(80), Constraint::Percentage(20)],
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Audition ({}) ", parked.len()))
+                .title_bottom(" Enter: onto the playlist ")
+                .border_style(playlist_border),
+        )
+        .row_highlight_style(highlight_item_style);
+        let mut audition_state = TableState::default().with_selected(
+            selection
+                .0
+                .selected()
+                .and_then(|i| i.checked_sub(audition_window.start)),
+        );
+        f.render_widget(Clear, body_chunks[2]);
+        f.render_stateful_widget(audition_table, body_chunks[2], &mut audition_state);
+        *selection.0.offset_mut() = audition_window.start;
+    }
 
     // --- BOTTOM BAR ---
     view.help = match view.active_pane {
         ActivePane::Artists => &ARTIST_HELP[..],
         ActivePane::ArtistTracks => &TRACK_HELP[..],
+        ActivePane::Playlist if view.audition.is_some() => &AUDITION_HELP[..],
         ActivePane::Playlist => &PLAYLIST_HELP[..],
     };
     let tabs = Tabs::new(view.help.iter().cloned())
-        .block(Block::default().borders(Borders::ALL).title(" Actions "))
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().fg(Color::White))
         .divider(Span::raw("|"));
 
-    f.render_widget(tabs, chunks[1]);
-
-    // Status Message Overlay (Right side of bottom bar, or specific line)
-    // We can render a paragraph over the tabs or just append it.
-    // Let's float it in the bottom right of the actions block
-    let status = Paragraph::new(Span::styled(
-        view.status_msg.clone(),
-        Style::default().fg(Color::LightCyan),
-    ))
-    .alignment(Alignment::Right);
-
-    // Render status inside the bottom chunk, but padded
-    let status_area = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(chunks[1].inner(Margin {
+    if accessible {
+        f.render_widget(
+            tabs.block(Block::default().borders(Borders::ALL).title(" Actions ")),
+            chunks[1],
+        );
+        // under the actions, read from the start of the line
+        let inner = chunks[1].inner(Margin {
             vertical: 1,
             horizontal: 1,
-        }));
+        });
+        let line = Rect {
+            y: inner.y + 1,
+            height: 1,
+            ..inner
+        };
+        f.render_widget(Paragraph::new(view.status_msg.clone()), line);
+    } else {
+        let status = Paragraph::new(Span::styled(
+            view.status_msg.clone(),
+            Style::default().fg(Color::LightCyan),
+        ))
+        .alignment(if bottom_bar.status_left {
+            Alignment::Left
+        } else {
+            Alignment::Right
+        });
+
+        if bottom_bar.two_line {
+            // help on the first line, the status on the whole of the second
+            let lines = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1); 2])
+                .split(chunks[1]);
+            if bottom_bar.show_help {
+                f.render_widget(tabs, lines[0]);
+                f.render_widget(status, lines[1]);
+            } else {
+                f.render_widget(status, lines[0]);
+            }
+        } else {
+            let title = if bottom_bar.show_help {
+                " Actions "
+            } else {
+                " Status "
+            };
+            f.render_widget(
+                Block::default().borders(Borders::ALL).title(title),
+                chunks[1],
+            );
+            let inner = chunks[1].inner(Margin {
+                vertical: 1,
+                horizontal: 1,
+            });
+            // the status takes half the line, floated over the help on its side
+            let halves = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(inner);
+            match (bottom_bar.show_help, bottom_bar.status_left) {
+                (false, _) => f.render_widget(status, inner),
+                (true, false) => {
+                    f.render_widget(tabs, inner);
+                    f.render_widget(status, halves[1]);
+                }
+                (true, true) => {
+                    f.render_widget(tabs, halves[1]);
+                    f.render_widget(status, halves[0]);
+                }
+            }
+        }
+    }
+
+    // --- STATUS BAR ---
+    let target = Paragraph::new(Span::styled(
+        view.target_summary.as_str(),
+        Style::default().fg(Color::DarkGray),
+    ));
+    f.render_widget(target, chunks[2]);
+
+    // --- WAVEFORM POPUP ---
+    if let Some((title, peaks)) = &view.waveform {
+        let area = centered_rect(f.area(), 80, 40);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Waveform: {} (any key to close) ", title));
+        f.render_widget(Clear, area);
+        if view.waveform_loading.is_some() {
+            let reading = Paragraph::new(Line::styled(
+                "Reading the track…",
+                Style::default().fg(Color::DarkGray),
+            ))
+            .block(block);
+            f.render_widget(reading, area);
+        } else {
+            let sparkline = Sparkline::default()
+                .block(block)
+                .data(peaks)
+                .style(Style::default().fg(Color::Cyan));
+            f.render_widget(sparkline, area);
+        }
+    }
+
+    // --- DETAILS POPUP ---
+    if let Some(details) = &view.details {
+        let area = centered_rect(f.area(), 70, 60);
+        let mut rows = details
+            .fields
+            .iter()
+            .map(|(label, value)| {
+                Row::new(vec![
+                    Cell::from(*label).style(Style::default().fg(Color::Cyan)),
+                    Cell::from(value.as_str()),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let title = if details.versions.len() > 1 {
+            rows.push(Row::new(vec![Cell::from("")]));
+            for (rank, (song, quality)) in details.versions.iter().enumerate() {
+                let this = if song.id == details.id {
+                    "  <- this copy"
+                } else {
+                    ""
+                };
+                rows.push(Row::new(vec![
+                    Cell::from(format!("version #{}", rank + 1))
+                        .style(Style::default().fg(Color::Cyan)),
+                    Cell::from(format!(
+                        "{} ({}, {}){}",
+                        song.album,
+                        song.format(),
+                        quality.describe(),
+                        this
+                    )),
+                ]));
+            }
+            format!(
+                " {} (v: choose from {} versions, any other key to close) ",
+                details.title,
+                details.versions.len()
+            )
+        } else {
+            format!(" {} (any key to close) ", details.title)
+        };
+        let table = Table::new(rows, [Constraint::Length(14), Constraint::Min(0)])
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(Clear, area);
+        f.render_widget(table, area);
+    }
+
+    // --- VERSION PICKER ---
+    if let Some(picker) = &mut view.version_picker {
+        let area = centered_rect(f.area(), 70, 40);
+        let versions: Vec<ListItem> = picker
+            .items
+            .iter()
+            .map(|(song, quality)| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} ", song.album)),
+                    Span::styled(
+                        format!("({}, {})", song.format(), quality.describe()),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                ]))
+            })
+            .collect();
+        let list = List::new(versions)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(match view.version_target {
+                        Some(_) => {
+                            " Versions, best first (Enter: use for this entry, Esc: cancel) "
+                        }
+                        None => " Several versions found, best first (Enter: add, Esc: cancel) ",
+                    })
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .highlight_style(highlight_item_style);
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut picker.state);
+    }
+
+    // --- RECENTLY PREVIEWED ---
+    if let Some(recent) = &mut view.recent {
+        let area = centered_rect(f.area(), 70, 60);
+        let items: Vec<ListItem> = recent
+            .items
+            .iter()
+            .map(|(song, played_at)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{}  ", played_at),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(song.artist.clone(), Style::default().fg(Color::Cyan)),
+                    Span::raw(" - "),
+                    Span::raw(song.title.clone()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Recently previewed (Enter: jump, Tab: add, Esc: close) ")
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .highlight_style(highlight_item_style);
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut recent.state);
+    }
+
+    // --- ALBUM GRID ---
+    if let Some(grid) = &mut view.album_grid {
+        let area = centered_rect(f.area(), 90, 85);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Albums (Enter: add, Tab: show tracks, Esc: close) ")
+            .border_style(Style::default().fg(Color::Yellow));
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let art_rows = if view.art.is_some() {
+            ALBUM_ART_ROWS
+        } else {
+            0
+        };
+        // a border around the art and three lines of text
+        let cell_height = art_rows + 5;
+        grid.columns = usize::from(inner.width / ALBUM_CELL_WIDTH).max(1);
+        grid.rows = usize::from(inner.height / cell_height).max(1);
+        let row = grid.selected / grid.columns;
+        if row < grid.offset {
+            grid.offset = row;
+        } else if row >= grid.offset + grid.rows {
+            grid.offset = row + 1 - grid.rows;
+        }
 
-    f.render_widget(status, status_area[1]);
+        grid.slots.clear();
+        let first = grid.offset * grid.columns;
+        let shown = first..(first + grid.columns * grid.rows).min(grid.albums.len());
+        for index in shown {
+            let at = index - first;
+            let cell = Rect {
+                x: inner.x + (at % grid.columns) as u16 * ALBUM_CELL_WIDTH,
+                y: inner.y + (at / grid.columns) as u16 * cell_height,
+                width: ALBUM_CELL_WIDTH.min(inner.width),
+                height: cell_height.min(inner.height),
+            };
+            let album = &grid.albums[index];
+            let border = if index == grid.selected {
+                Color::Yellow
+            } else {
+                Color::DarkGray
+            };
+            let mut lines = vec![Line::default(); art_rows.into()];
+            if art_rows > 0 && !grid.art.contains_key(&index) {
+                // until the art has been read
+                lines[usize::from(art_rows / 2)] =
+                    Line::styled("…", Style::default().fg(Color::DarkGray)).centered();
+            }
+            lines.push(Line::styled(
+                album.album.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            lines.push(Line::styled(
+                album.artist.clone(),
+                Style::default().fg(Color::Cyan),
+            ));
+            let year = match album.year {
+                0 => String::new(),
+                year => format!("{} · ", year),
+            };
+            lines.push(Line::styled(
+                format!(
+                    "{}{} track{} · {}",
+                    year,
+                    album.tracks,
+                    if album.tracks == 1 { "" } else { "s" },
+                    duration::format_secs(album.duration_secs)
+                ),
+                Style::default().fg(Color::DarkGray),
+            ));
+            let paragraph = Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(border)),
+            );
+            f.render_widget(paragraph, cell);
+            if art_rows > 0 {
+                grid.slots.push((
+                    index,
+                    Rect {
+                        x: cell.x + (cell.width.saturating_sub(ALBUM_ART_COLS)) / 2,
+                        y: cell.y + 1,
+                        width: ALBUM_ART_COLS.min(cell.width.saturating_sub(2)),
+                        height: art_rows,
+                    },
+                ));
+            }
+        }
+    }
+
+    // --- BURN HISTORY ---
+    if let Some(burns) = &mut view.burns {
+        let area = centered_rect(f.area(), 70, 60);
+        let items: Vec<ListItem> = burns
+            .items
+            .iter()
+            .map(|burn| {
+                let (result, color) = if burn.ok {
+                    ("ok    ", Color::Green)
+                } else {
+                    ("failed", Color::Red)
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{}  ", burn.burned_at),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(result, Style::default().fg(color)),
+                    Span::raw(format!("  {}", burn.title)),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" Past burns (Enter: show log, Esc: close) ")
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .highlight_style(highlight_item_style);
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut burns.state);
+    }
+    if let Some((title, lines, scroll)) = &view.burn_log {
+        let area = centered_rect(f.area(), 90, 80);
+        let text = lines
+            .iter()
+            .map(|line| match line.strip_prefix("[stderr] ") {
+                Some(line) => Line::styled(line, Style::default().fg(Color::LightRed)),
+                None => Line::from(line.as_str()),
+            })
+            .collect::<Vec<_>>();
+        let log = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title.as_str())
+                    .title_bottom(" ↑/↓ PgUp/PgDn Home/End: scroll, Esc: back ")
+                    .border_style(Style::default().fg(Color::Yellow)),
+            )
+            .scroll((*scroll, 0));
+        f.render_widget(Clear, area);
+        f.render_widget(log, area);
+    }
+
+    // --- PROMPT ---
+    if let Some(prompt) = &view.prompt {
+        let area = centered_rect(f.area(), 70, 20);
+        let area = Rect {
+            height: area.height.min(3),
+            ..area
+        };
+        let input = Paragraph::new(prompt.input.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(prompt.title)
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(input, area);
+    }
+
+    // --- CONFIRMATION ---
+    if let Some((question, _)) = &view.confirm {
+        let area = centered_rect(f.area(), 60, 20);
+        let area = Rect {
+            height: area.height.min(3),
+            ..area
+        };
+        let text = Paragraph::new(question.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Are you sure? (y: yes, any other key: no) ")
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(text, area);
+    }
+
+    // --- DISC PREVIEW ---
+    if let Some(lines) = &view.disc_preview {
+        let area = centered_rect(f.area(), 80, 70);
+        let text = lines
+            .iter()
+            .map(|line| Line::from(line.as_str()))
+            .collect::<Vec<_>>();
+        let preview = Paragraph::new(text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Disc layout (Enter: burn, Esc: cancel) ")
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(preview, area);
+    }
+
+    // --- REORDER PREVIEW ---
+    if let Some((_, lines)) = &view.reorder {
+        let area = centered_rect(f.area(), 80, 70);
+        let text = lines
+            .iter()
+            .map(|line| Line::from(line.as_str()))
+            .collect::<Vec<_>>();
+        let preview = Paragraph::new(text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Optimized order (Enter: apply, Esc: cancel) ")
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+        f.render_widget(Clear, area);
+        f.render_widget(preview, area);
+    }
+
+    // --- FUZZY FINDER ---
+    if let Some(finder) = &mut view.finder {
+        finder_ui(f, finder);
+    }
+}
+
+fn finder_ui(f: &mut Frame, finder: &mut Finder) {
+    let area = centered_rect(f.area(), 70, 60);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+
+    let input = Paragraph::new(finder.query.as_str()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Find (Enter: jump, Tab: add, Esc: close) ")
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+
+    let results: Vec<ListItem> = finder
+        .matches
+        .iter()
+        .map(|&i| ListItem::new(finder_line(&finder.candidates.library[i])))
+        .collect();
+    let results = List::new(results)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(highlight_item_style());
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, chunks[0]);
+    f.render_stateful_widget(results, chunks[1], &mut finder.state);
 }
 
-fn burn_ui<'a>(f: &mut Frame, logs: &mut Vec<ratatui::text::Line<'a>>) {
+fn finder_line(s: &Song) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(s.artist.clone(), Style::default().fg(Color::Cyan)),
+        Span::raw(" - "),
+        Span::raw(s.title.clone()),
+        Span::styled(
+            format!("  ({})", s.album),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ])
+}
+
+/// The `pick` screen: the finder across the whole terminal, marked tracks flagged.
+fn pick_ui(f: &mut Frame, finder: &mut Finder, marked: &[usize]) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(f.area());
+
+    let title = format!(
+        " Pick {} of {} (Tab: mark, Enter: pick, Esc: cancel) ",
+        marked.len(),
+        finder.candidates.library.len()
+    );
+    let input = Paragraph::new(finder.query.as_str()).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+
+    let results: Vec<ListItem> = finder
+        .matches
+        .iter()
+        .map(|&i| {
+            let mut line = finder_line(&finder.candidates.library[i]);
+            let mark = if marked.contains(&i) { "+ " } else { "  " };
+            line.spans
+                .insert(0, Span::styled(mark, Style::default().fg(Color::Green)));
+            ListItem::new(line)
+        })
+        .collect();
+    let results = List::new(results)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(highlight_item_style());
+
+    f.render_widget(input, chunks[0]);
+    f.render_stateful_widget(results, chunks[1], &mut finder.state);
+}
+
+/// Returns a rect centered in `area` taking up the given percentages of it.
+fn centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+fn burn_ui<'a>(
+    f: &mut Frame,
+    logs: &[ratatui::text::Line<'a>],
+    buffer: Option<BufferStatus>,
+    remaining: Option<(BurnStage, Duration)>,
+) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Header
-            Constraint::Min(1),    // Logs
+            Constraint::Length(3),                                    // Header
+            Constraint::Length(if buffer.is_some() { 3 } else { 0 }), // Buffer fill
+            Constraint::Min(1),                                       // Logs
         ])
         .split(f.area());
 
     // Header
-    let header = Paragraph::new("Process Monitor (Press 'q' to quit)")
-        .block(Block::default().borders(Borders::ALL));
+    let mut title = String::from("Process Monitor (Press 'q' to quit)");
+    if let Some((stage, remaining)) = remaining {
+        title += &format!(
+            " | {:?}: about {} left",
+            stage,
+            duration::format_secs(remaining.as_secs())
+        );
+    }
+    let header = Paragraph::new(title).block(Block::default().borders(Borders::ALL));
     f.render_widget(header, chunks[0]);
 
+    // Buffer fill: the drive buffer is what underruns, so it drives the gauge colour
+    if let Some(BufferStatus {
+        fifo_percent,
+        drive_percent,
+    }) = buffer
+    {
+        let color = match drive_percent {
+            0..=25 => Color::Red,
+            26..=60 => Color::Yellow,
+            _ => Color::Green,
+        };
+        let gauge = Gauge::default()
+            .block(Block::default().title(" Buffer ").borders(Borders::ALL))
+            .gauge_style(Style::default().fg(color))
+            .percent(drive_percent.min(100) as u16)
+            .label(format!("drive {}% | fifo {}%", drive_percent, fifo_percent));
+        f.render_widget(gauge, chunks[1]);
+    }
+
     // Calculate scroll to keep view at the bottom
-    let scroll_offset = if logs.len() as u16 > chunks[1].height - 2 {
-        (logs.len() as u16) - (chunks[1].height - 2)
+    let scroll_offset = if logs.len() as u16 > chunks[2].height - 2 {
+        (logs.len() as u16) - (chunks[2].height - 2)
     } else {
         0
     };
 
-    let logs_widget = Paragraph::new(logs.clone())
+    let logs_widget = Paragraph::new(logs.to_vec())
         .block(Block::default().title("Output Logs").borders(Borders::ALL))
         .scroll((scroll_offset, 0)); // Auto-scroll
 
-    f.render_widget(logs_widget, chunks[1]);
+    f.render_widget(logs_widget, chunks[2]);
+}
+
+/// Every artist as browsed, with a label counting their albums and tracks.
+fn artist_list(state: &AppState) -> Result<(Vec<String>, Vec<String>)> {
+    Ok(queries::list_artist_counts(state.conn())?
+        .into_iter()
+        .map(|(artist, albums, tracks)| {
+            let label = format!(
+                "{} ({} album{}, {} track{})",
+                artist,
+                albums,
+                if albums == 1 { "" } else { "s" },
+                tracks,
+                if tracks == 1 { "" } else { "s" }
+            );
+            (artist, label)
+        })
+        .unzip())
 }
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::shutdown;
+
+/// Sample rate the track is decoded at before being reduced to peaks. Low enough
+/// to keep decoding cheap, high enough to still show transients.
+const PEAK_SAMPLE_RATE: u32 = 8000;
+
+/// Decodes the track at `path` to mono 16-bit PCM at `sample_rate` through ffmpeg.
+pub fn decode_mono(path: &str, sample_rate: u32) -> Result<Vec<i16>> {
+    decode(path, sample_rate, &[])
+}
+
+/// Decodes `secs` seconds of the track at `path` from `start_secs` on, as
+/// [`decode_mono`] does the whole track.
+pub fn decode_mono_range(
+    path: &str,
+    sample_rate: u32,
+    start_secs: u64,
+    secs: u64,
+) -> Result<Vec<i16>> {
+    decode(
+        path,
+        sample_rate,
+        &[
+            String::from("-ss"),
+            start_secs.to_string(),
+            String::from("-t"),
+            secs.to_string(),
+        ],
+    )
+}
+
+fn decode(path: &str, sample_rate: u32, input_args: &[String]) -> Result<Vec<i16>> {
+    let output = shutdown::output(
+        Command::new("ffmpeg")
+            .arg("-v")
+            .arg("error")
+            .args(input_args)
+            .arg("-i")
+            .arg(path)
+            .arg("-ac")
+            .arg("1")
+            .arg("-ar")
+            .arg(sample_rate.to_string())
+            .arg("-f")
+            .arg("s16le")
+            .arg("-")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null()),
+    )
+    .with_context(|| format!("failed to run ffmpeg to decode {}", path))?;
+
+    if !output.status.success() {
+        anyhow::bail!("ffmpeg failed to decode {}", path);
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+/// Decodes the track at `path` and reduces it to `buckets` peak amplitudes, suitable
+/// for rendering as a sparkline.
+pub fn track_peaks(path: &str, buckets: usize) -> Result<Vec<u64>> {
+    let samples = decode_mono(path, PEAK_SAMPLE_RATE)
+        .context("failed to decode track for waveform")?
+        .into_iter()
+        .map(|sample| sample.unsigned_abs() as u64)
+        .collect::<Vec<_>>();
+
+    Ok(peaks(&samples, buckets))
+}
+
+/// Splits `samples` into `buckets` evenly sized windows and keeps the loudest
+/// sample of each.
+fn peaks(samples: &[u64], buckets: usize) -> Vec<u64> {
+    if samples.is_empty() || buckets == 0 {
+        return vec![];
+    }
+
+    let window = samples.len().div_ceil(buckets);
+    samples
+        .chunks(window)
+        .map(|chunk| chunk.iter().copied().max().unwrap_or(0))
+        .collect()
+}
@@ -0,0 +1,105 @@
+use std::io::{self, BufRead};
+
+/// Fill levels reported by wodim while writing a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferStatus {
+    /// Fill of wodim's own FIFO in RAM.
+    pub fifo_percent: u8,
+    /// Fill of the drive's hardware buffer.
+    pub drive_percent: u8,
+}
+
+/// Parses a progress line such as
+/// `Track 01:    3 of   45 MB written (fifo 100%) [buf  99%]  16.3x.`
+pub fn parse_buffer_status(line: &str) -> Option<BufferStatus> {
+    let fifo_percent = percent_after(line, "(fifo")?;
+    let drive_percent = percent_after(line, "[buf")?;
+
+    Some(BufferStatus {
+        fifo_percent,
+        drive_percent,
+    })
+}
+
+/// Parses the track number and megabytes written of a progress line such as
+/// `Track 01:    3 of   45 MB written (fifo 100%) [buf  99%]  16.3x.`
+pub fn parse_written(line: &str) -> Option<(usize, u64, u64)> {
+    let rest = line.trim_start().strip_prefix("Track ")?;
+    let (track, rest) = rest.split_once(':')?;
+    let (written, rest) = rest.split_once(" of ")?;
+    let (total, _) = rest.split_once(" MB written")?;
+
+    Some((
+        track.trim().parse().ok()?,
+        written.trim().parse().ok()?,
+        total.trim().parse().ok()?,
+    ))
+}
+
+fn percent_after(line: &str, marker: &str) -> Option<u8> {
+    let rest = &line[line.find(marker)? + marker.len()..];
+    let rest = rest.trim_start();
+    rest[..rest.find('%')?].trim().parse().ok()
+}
+
+/// Whether a log line indicates the drive ran dry during the write, either explicitly
+/// or through the end-of-burn summary (`fifo was 3 times empty ...`).
+pub fn is_underrun(line: &str) -> bool {
+    let lower = line.to_ascii_lowercase();
+    if lower.contains("buffer underrun") {
+        return true;
+    }
+
+    lower
+        .split_once("fifo was ")
+        .and_then(|(_, rest)| rest.split_once(" times empty"))
+        .and_then(|(count, _)| count.trim().parse::<u32>().ok())
+        .is_some_and(|count| count > 0)
+}
+
+/// Speed to recommend after an underrun at `speed`, or a conservative default when the
+/// drive picked its own speed.
+pub fn recommended_speed(speed: Option<u32>) -> u32 {
+    match speed {
+        Some(speed) => (speed / 2).max(1),
+        None => 4,
+    }
+}
+
+/// Splits wodim's output into segments on both `\n` and `\r`, since progress updates
+/// rewrite the same terminal line with carriage returns.
+pub fn for_each_segment<R: BufRead>(
+    mut reader: R,
+    mut f: impl FnMut(String) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut segment = Vec::new();
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if buf.is_empty() {
+            break;
+        }
+
+        let len = buf.len();
+        for &byte in buf {
+            if byte == b'\n' || byte == b'\r' {
+                if !segment.is_empty() {
+                    f(String::from_utf8_lossy(&segment).into_owned())?;
+                    segment.clear();
+                }
+            } else {
+                segment.push(byte);
+            }
+        }
+        reader.consume(len);
+    }
+
+    if !segment.is_empty() {
+        f(String::from_utf8_lossy(&segment).into_owned())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Scans a library and burns a playlist from it end to end against the mock backend,
+//! without ffmpeg or a drive.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// A FLAC file holding nothing but its stream info and tags, which is all a scan
+/// reads; the mock transcoder never decodes it.
+fn fixture_flac(title: &str, secs: u64) -> Vec<u8> {
+    let (rate, channels, bits) = (44_100u64, 2u64, 16u64);
+    let mut info = vec![];
+    info.extend_from_slice(&4096u16.to_be_bytes()); // min block size
+    info.extend_from_slice(&4096u16.to_be_bytes()); // max block size
+    info.extend_from_slice(&[0; 6]); // frame sizes, unknown
+    let packed = (rate << 44) | ((channels - 1) << 41) | ((bits - 1) << 36) | (rate * secs);
+    info.extend_from_slice(&packed.to_be_bytes());
+    info.extend_from_slice(&[0; 16]); // MD5, unknown
+
+    let mut comments = vec![];
+    let vendor = b"vol_28 tests";
+    comments.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    comments.extend_from_slice(vendor);
+    let tags = [format!("TITLE={}", title), String::from("ARTIST=Fixtures")];
+    comments.extend_from_slice(&(tags.len() as u32).to_le_bytes());
+    for tag in tags {
+        comments.extend_from_slice(&(tag.len() as u32).to_le_bytes());
+        comments.extend_from_slice(tag.as_bytes());
+    }
+
+    let mut flac = b"fLaC".to_vec();
+    for (header, block) in [(0u8, info), (0x84, comments)] {
+        flac.push(header);
+        flac.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+        flac.extend_from_slice(&block);
+    }
+
+    flac
+}
+
+/// Runs the binary with `args`, keeping everything it stores under `home` and burning
+/// with the mock backend.
+fn vol_28(home: &Path, args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_vol_28"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_STATE_HOME", home.join("state"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("VOL28_MOCK_BACKEND", "1")
+        .env("RUST_BACKTRACE", "0")
+        .output()
+        .expect("failed to run vol_28");
+    assert!(
+        output.status.success(),
+        "vol_28 {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    output
+}
+
+/// The value of `"field":"..."` in a JSON event line.
+fn field<'a>(event: &'a str, field: &str) -> Option<&'a str> {
+    let start = event.find(&format!(r#""{}":"#, field))? + field.len() + 3;
+    let value = &event[start..];
+    match value.strip_prefix('"') {
+        Some(text) => text.split('"').next(),
+        None => value.split([',', '}']).next(),
+    }
+}
+
+#[test]
+fn burns_a_scanned_playlist() {
+    let home = tempfile::tempdir().unwrap();
+    let home = home.path();
+    let music = home.join("music");
+    let staging = home.join("staging");
+    std::fs::create_dir_all(&music).unwrap();
+    std::fs::create_dir_all(&staging).unwrap();
+    std::fs::create_dir_all(home.join("config/vol_28")).unwrap();
+    for (title, secs) in [("Alpha", 60), ("Beta", 90), ("Gamma", 120)] {
+        std::fs::write(
+            music.join(format!("{}.flac", title)),
+            fixture_flac(title, secs),
+        )
+        .unwrap();
+    }
+    let m3u = music.join("Fixtures.m3u");
+    std::fs::write(&m3u, "Gamma.flac\nAlpha.flac\n").unwrap();
+    // the staging directory only lives as long as the burn, so the hook keeps a copy
+    let staged = home.join("staged");
+    std::fs::write(
+        home.join("config/vol_28/config"),
+        format!(
+            "music_dir = {}\nstaging_dir = {}\npost_burn = cp -R {} {}\n",
+            music.display(),
+            staging.display(),
+            staging.display(),
+            staged.display()
+        ),
+    )
+    .unwrap();
+
+    vol_28(home, &["scan"]);
+    let burn = vol_28(home, &["burn-m3u", m3u.to_str().unwrap(), "--json"]);
+    let stdout = String::from_utf8(burn.stdout).unwrap();
+    let events = stdout.lines().collect::<Vec<_>>();
+    let of_kind = |kind: &str, name: &str| {
+        events
+            .iter()
+            .filter(|event| field(event, "event") == Some(kind))
+            .filter_map(|event| field(event, name))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        of_kind("stage", "stage"),
+        ["transcoding", "normalizing", "burning"]
+    );
+    assert_eq!(of_kind("transcode_progress", "title"), ["Gamma", "Alpha"]);
+    let percents = of_kind("burn_progress", "percent")
+        .iter()
+        .map(|percent| percent.parse::<u8>().unwrap())
+        .collect::<Vec<_>>();
+    assert!(percents.is_sorted(), "{:?}", percents);
+    assert_eq!(percents.last(), Some(&100));
+    assert!(of_kind("error", "message").is_empty(), "{}", stdout);
+    assert_eq!(
+        events.last().and_then(|event| field(event, "event")),
+        Some("done"),
+        "{}",
+        stdout
+    );
+
+    // the mock burner names the directory it burned from
+    let disc = of_kind("log", "line")
+        .into_iter()
+        .find_map(|line| line.strip_prefix("Burning the disc set in "))
+        .expect("the mock burner should name the disc set");
+    let disc = Path::new(disc).strip_prefix(&staging).unwrap();
+    let manifest = std::fs::read_to_string(staged.join(disc).join("manifest.txt")).unwrap();
+    let entries = manifest
+        .lines()
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 2, "{}", manifest);
+    for (entry, (position, title)) in entries.iter().zip([("01", "Gamma"), ("02", "Alpha")]) {
+        assert_eq!(entry[0], position);
+        assert_ne!(entry[1], "0", "{} should be a library track", title);
+        assert!(entry[2].ends_with(".wav"), "{}", manifest);
+        assert_eq!(entry[3], title);
+    }
+}